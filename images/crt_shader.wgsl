@@ -0,0 +1,22 @@
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+@group(1) @binding(0)
+var t: texture_2d<f32>;
+
+@group(1) @binding(1)
+var s: sampler;
+
+// Darkens one line of pixels out of three and the corners of the screen, like an old CRT
+// monitor.
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(t, s, in.uv) * in.color;
+    let scanline = select(1.0, 0.7, fract(in.position.y / 3.0) < 0.34);
+    let centered = in.uv - vec2<f32>(0.5, 0.5);
+    let vignette = clamp(1.0 - dot(centered, centered) * 1.2, 0.0, 1.0);
+    return vec4<f32>(color.rgb * scanline * vignette, color.a);
+}
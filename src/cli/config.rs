@@ -0,0 +1,121 @@
+//! Configuration file, setting the environment variables read by the game.
+
+use std::{
+    env,
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Path of the configuration file, used if `--config` isn't given.
+const CONFIG_ENV_VAR: &str = "SOKOBAN_CONFIG";
+/// Path of the configuration file in the configuration directory of the user, used if there is
+/// one.
+const DEFAULT_CONFIG_PATH: &str = "sokoban/config";
+const ENV_VAR_PREFIX: &str = "SOKOBAN_";
+
+#[derive(Debug)]
+pub enum ConfigError {
+    IO(PathBuf, io::Error),
+    /// A line isn't a `key = value` one.
+    InvalidLine(PathBuf, usize),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::IO(path, e) => {
+                write!(f, "Could not read `{}`: {}", path.display(), e)
+            }
+            ConfigError::InvalidLine(path, line) => write!(
+                f,
+                "Invalid configuration at `{}:{}`, expected `key = value`.",
+                path.display(),
+                line
+            ),
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+/// Environment variables set by the `key = value` lines of `src`, the empty lines and those
+/// starting with `#` being skipped. Returns the number of the first invalid line.
+fn parse(src: &str) -> Result<Vec<(String, String)>, usize> {
+    src.lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty() && !l.trim_start().starts_with('#'))
+        .map(|(index, line)| {
+            let (key, value) = line.split_once('=').ok_or(index + 1)?;
+            let key = key.trim().to_uppercase().replace('-', "_");
+            if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                return Err(index + 1);
+            }
+            let name = if key.starts_with(ENV_VAR_PREFIX) {
+                key
+            } else {
+                format!("{}{}", ENV_VAR_PREFIX, key)
+            };
+            Ok((name, value.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Default configuration file, in `$XDG_CONFIG_HOME` or else `~/.config`.
+fn default_path() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        .map(|dir| dir.join(DEFAULT_CONFIG_PATH))
+}
+
+/// Sets the environment variables of the configuration file at `path`, or else of the one
+/// given by [`CONFIG_ENV_VAR`] or the default one, which may not exist. The variables already
+/// set are kept, so they can override the file.
+pub fn load(path: Option<&Path>) -> Result<(), ConfigError> {
+    let (path, required) = match path
+        .map(Path::to_path_buf)
+        .or_else(|| env::var_os(CONFIG_ENV_VAR).map(PathBuf::from))
+    {
+        Some(path) => (path, true),
+        None => match default_path() {
+            Some(path) => (path, false),
+            None => return Ok(()),
+        },
+    };
+
+    let src = match fs::read_to_string(&path) {
+        Ok(src) => src,
+        Err(e) if !required && e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(ConfigError::IO(path, e)),
+    };
+    let vars = parse(&src).map_err(|line| ConfigError::InvalidLine(path, line))?;
+    // Nothing else runs yet, so the environment can be changed safely.
+    for (name, value) in vars {
+        if env::var_os(&name).is_none() {
+            env::set_var(name, value);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+
+    #[test]
+    fn it_reads_keys_and_values() {
+        let vars =
+            parse("# Settings\nui = rich\n\ntui-theme=dark\nSOKOBAN_KEY_REPEAT_MS = 80\n").unwrap();
+        assert_eq!(
+            vars,
+            vec![
+                ("SOKOBAN_UI".to_string(), "rich".to_string()),
+                ("SOKOBAN_TUI_THEME".to_string(), "dark".to_string()),
+                ("SOKOBAN_KEY_REPEAT_MS".to_string(), "80".to_string()),
+            ]
+        );
+        assert_eq!(parse("ui = rich\ntheme dark\n"), Err(2));
+        assert_eq!(parse("a b = c\n"), Err(1));
+    }
+}
@@ -0,0 +1,512 @@
+//! Commands of the program, `sokoban <command> [options]`, with the options they all take:
+//!
+//! - `--pack <file or directory>`, the levels used by the command when it isn't given them,
+//! - `--level <number or title>`, the level of the pack to use, the others being left out,
+//! - `--ui <name>`, the interface to play or show the replays with,
+//! - `--config <file>`, the configuration file.
+//!
+//! The configuration file holds `key = value` lines, each setting the environment variable
+//! `SOKOBAN_<KEY>` if it isn't already set, such as `tui_theme = dark`. The `pack` and `ui` keys
+//! give the default of the options of the same name.
+
+use std::{env, fmt, path::Path, str::FromStr};
+
+use sokoban::{DisplayKind, LevelLibrary, LevelPack};
+
+mod config;
+mod play;
+mod tools;
+
+/// Levels played when none are given.
+const DEFAULT_LEVEL_FILENAME: &str = "./map.txt";
+/// Default of `--pack`.
+const PACK_ENV_VAR: &str = "SOKOBAN_PACK";
+/// Default of `--ui`.
+const UI_ENV_VAR: &str = "SOKOBAN_UI";
+
+/// Richest terminal interface enabled.
+#[cfg(feature = "ratatui")]
+const TERMINAL_DISPLAY: DisplayKind = DisplayKind::RichTUI;
+#[cfg(all(feature = "term_graphics", not(feature = "ratatui")))]
+const TERMINAL_DISPLAY: DisplayKind = DisplayKind::TermGraphics;
+#[cfg(all(
+    feature = "tui",
+    not(any(feature = "term_graphics", feature = "ratatui"))
+))]
+const TERMINAL_DISPLAY: DisplayKind = DisplayKind::TUI;
+#[cfg(not(feature = "tui"))]
+const TERMINAL_DISPLAY: DisplayKind = DisplayKind::CLI;
+
+/// The graphical interface if any, or else the richest terminal one.
+#[cfg(feature = "macroquad")]
+const DEFAULT_BACKEND: Backend = Backend::Macroquad;
+#[cfg(all(feature = "ggez", not(feature = "macroquad")))]
+const DEFAULT_BACKEND: Backend = Backend::Ggez;
+#[cfg(not(any(feature = "ggez", feature = "macroquad")))]
+const DEFAULT_BACKEND: Backend = Backend::Terminal(TERMINAL_DISPLAY);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Command {
+    Play,
+    #[cfg(feature = "tui")]
+    Edit,
+    Solve,
+    Validate,
+    Convert,
+    Gen,
+    Replay,
+    Bench,
+    #[cfg(feature = "thumbnail")]
+    Thumbnail,
+    #[cfg(feature = "net")]
+    Host,
+    #[cfg(feature = "net")]
+    Join,
+    #[cfg(feature = "remote")]
+    Remote,
+    #[cfg(feature = "chat")]
+    Chat,
+    #[cfg(feature = "serve")]
+    Serve,
+    #[cfg(feature = "repo")]
+    Packs,
+    Help,
+}
+
+impl Command {
+    /// Commands enabled, in the order of the help.
+    const ALL: &'static [Command] = &[
+        Command::Play,
+        #[cfg(feature = "tui")]
+        Command::Edit,
+        Command::Solve,
+        Command::Validate,
+        Command::Convert,
+        Command::Gen,
+        Command::Replay,
+        Command::Bench,
+        #[cfg(feature = "thumbnail")]
+        Command::Thumbnail,
+        #[cfg(feature = "net")]
+        Command::Host,
+        #[cfg(feature = "net")]
+        Command::Join,
+        #[cfg(feature = "remote")]
+        Command::Remote,
+        #[cfg(feature = "chat")]
+        Command::Chat,
+        #[cfg(feature = "serve")]
+        Command::Serve,
+        #[cfg(feature = "repo")]
+        Command::Packs,
+        Command::Help,
+    ];
+
+    /// Reads the name of a command, the ones which used to be options, such as `--edit`, being
+    /// still understood.
+    fn from_name(name: &str) -> Option<Self> {
+        let name = name.strip_prefix("--").unwrap_or(name);
+        Command::ALL.iter().copied().find(|c| c.name() == name)
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Command::Play => "play",
+            #[cfg(feature = "tui")]
+            Command::Edit => "edit",
+            Command::Solve => "solve",
+            Command::Validate => "validate",
+            Command::Convert => "convert",
+            Command::Gen => "gen",
+            Command::Replay => "replay",
+            Command::Bench => "bench",
+            #[cfg(feature = "thumbnail")]
+            Command::Thumbnail => "thumbnail",
+            #[cfg(feature = "net")]
+            Command::Host => "host",
+            #[cfg(feature = "net")]
+            Command::Join => "join",
+            #[cfg(feature = "remote")]
+            Command::Remote => "remote",
+            #[cfg(feature = "chat")]
+            Command::Chat => "chat",
+            #[cfg(feature = "serve")]
+            Command::Serve => "serve",
+            #[cfg(feature = "repo")]
+            Command::Packs => "packs",
+            Command::Help => "help",
+        }
+    }
+
+    /// Arguments of the command, after its name.
+    fn arguments(&self) -> &'static str {
+        match self {
+            Command::Play => "[--time-attack] [--hotseat] [--speedrun <splits file>] [<level file or directory>] [<replay file>] | --endless [--seed <n>]",
+            #[cfg(feature = "tui")]
+            Command::Edit => "<level file>",
+            Command::Solve => "[--max-states <positions>] [--max-time <seconds>] [--json] <level file>",
+            Command::Validate => "<level file or directory>...",
+            Command::Convert => "[--from <format>] [--to <format>] <level file or directory> [<output file or directory/>]",
+            Command::Gen => "[--seed <n>] [--size <width>x<height>] [--crates <n>] [--difficulty easy|medium|hard] [--count <n>] <output file or directory/>",
+            Command::Replay => "[--speed <1-32>] [--verify] <replay or solution file> [<level file>]",
+            Command::Bench => "[--max-states <positions>] [--max-time <seconds>] [--max-memory <MB>] [--csv|--json] [--output <file>] [--baseline <report.csv>] <level files or directories>...",
+            #[cfg(feature = "thumbnail")]
+            Command::Thumbnail => "[--cell <pixels>] [--sheet [--columns <n>]] <level file or directory> <output.png or directory/>",
+            #[cfg(feature = "net")]
+            Command::Host => "<level file> [<address>]",
+            #[cfg(feature = "net")]
+            Command::Join => "<address>",
+            #[cfg(feature = "remote")]
+            Command::Remote => "<stdin|udp:<address>|mqtt:<host>[:<port>]/<topic>>... [<level file>]",
+            #[cfg(feature = "chat")]
+            Command::Chat => "<channel> [--overlay <file>] [<level file>]",
+            #[cfg(feature = "serve")]
+            Command::Serve => "[--port <port>] [<level file>]",
+            #[cfg(feature = "repo")]
+            Command::Packs => "[<words>...] | install <id> | rate <id> <1-5>",
+            Command::Help => "[<command>]",
+        }
+    }
+
+    fn summary(&self) -> &'static str {
+        match self {
+            Command::Play => {
+                "Plays the levels, or watches a replay of the first one, or random levels."
+            }
+            #[cfg(feature = "tui")]
+            Command::Edit => "Edits a level in the terminal.",
+            Command::Solve => "Prints the solutions of the levels.",
+            Command::Validate => "Checks level files, for instance before sharing them.",
+            Command::Convert => "Writes levels in another format.",
+            Command::Gen => "Writes random levels and their solutions.",
+            Command::Replay => "Shows replays and solutions, or checks them.",
+            Command::Bench => "Runs the solver on the levels, to compare its changes.",
+            #[cfg(feature = "thumbnail")]
+            Command::Thumbnail => "Draws the levels in PNG pictures.",
+            #[cfg(feature = "net")]
+            Command::Host => "Hosts a game on a level for other players to join.",
+            #[cfg(feature = "net")]
+            Command::Join => "Plays in a hosted game.",
+            #[cfg(feature = "remote")]
+            Command::Remote => "Plays with the actions read from other programs.",
+            #[cfg(feature = "chat")]
+            Command::Chat => "Lets the chat of a Twitch channel play.",
+            #[cfg(feature = "serve")]
+            Command::Serve => "Serves game sessions over TCP and WebSocket.",
+            #[cfg(feature = "repo")]
+            Command::Packs => "Searches, installs and rates community packs.",
+            Command::Help => "Shows the help of the program or of a command.",
+        }
+    }
+
+    fn run(&self, options: &GlobalOptions, args: Vec<String>) {
+        match self {
+            Command::Play => play::play(options, args),
+            #[cfg(feature = "tui")]
+            Command::Edit => play::edit(options, args),
+            Command::Solve => tools::solve(options, args),
+            Command::Validate => tools::validate(options, args),
+            Command::Convert => tools::convert(options, args),
+            Command::Gen => tools::generate(args),
+            Command::Replay => tools::replay(options, args),
+            Command::Bench => tools::bench(options, args),
+            #[cfg(feature = "thumbnail")]
+            Command::Thumbnail => tools::thumbnail(options, args),
+            #[cfg(feature = "net")]
+            Command::Host => play::host(options, args),
+            #[cfg(feature = "net")]
+            Command::Join => play::join(options, args),
+            #[cfg(feature = "remote")]
+            Command::Remote => play::remote(options, args),
+            #[cfg(feature = "chat")]
+            Command::Chat => play::chat(options, args),
+            #[cfg(feature = "serve")]
+            Command::Serve => play::serve(options, args),
+            #[cfg(feature = "repo")]
+            Command::Packs => play::packs(args),
+            Command::Help => match args.first().map(|c| (c, Command::from_name(c))) {
+                Some((_, Some(command))) => println!("{}", command.help()),
+                Some((name, None)) => {
+                    eprintln!("Unknown command `{}`.\n\n{}", name, help());
+                    std::process::exit(2);
+                }
+                None => println!("{}", help()),
+            },
+        }
+    }
+
+    fn usage(&self) -> String {
+        format!("Usage: sokoban {} {}", self.name(), self.arguments())
+    }
+
+    fn help(&self) -> String {
+        format!(
+            "{}\n\n{}\n\n{}",
+            self.usage(),
+            self.summary(),
+            SHARED_OPTIONS
+        )
+    }
+}
+
+const SHARED_OPTIONS: &str = "Options of all the commands:
+  --pack <file or directory>  Levels used when the command isn't given them
+  --level <number or title>   Level of the pack to use
+  --ui <name>                 Interface to play with
+  --config <file>             Configuration file";
+
+/// Help of the program, listing the commands enabled.
+fn help() -> String {
+    let mut help = String::from(
+        "Usage: sokoban [<command>] [<options>]\n\nThe levels are played if no command is given.\n\nCommands:\n",
+    );
+    for command in Command::ALL {
+        help.push_str(&format!("  {:<10} {}\n", command.name(), command.summary()));
+    }
+    help.push('\n');
+    help.push_str(SHARED_OPTIONS);
+    help.push_str(&format!(
+        "\n\nInterfaces: {}",
+        Backend::ALL
+            .iter()
+            .map(|b| b.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    ));
+    help
+}
+
+/// Prints the usage of the `command` and exits.
+fn usage_error(command: Command) -> ! {
+    eprintln!("{}", command.usage());
+    std::process::exit(2);
+}
+
+/// Interface to play with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Backend {
+    Terminal(DisplayKind),
+    #[cfg(feature = "ggez")]
+    Ggez,
+    #[cfg(feature = "macroquad")]
+    Macroquad,
+}
+
+impl Backend {
+    const ALL: &'static [Backend] = &[
+        Backend::Terminal(DisplayKind::CLI),
+        #[cfg(feature = "tui")]
+        Backend::Terminal(DisplayKind::TUI),
+        #[cfg(feature = "term_graphics")]
+        Backend::Terminal(DisplayKind::TermGraphics),
+        #[cfg(feature = "ratatui")]
+        Backend::Terminal(DisplayKind::RichTUI),
+        #[cfg(feature = "ggez")]
+        Backend::Ggez,
+        #[cfg(feature = "macroquad")]
+        Backend::Macroquad,
+    ];
+
+    /// Terminal display to use for what only the terminal interfaces can do, such as joining a
+    /// game.
+    fn terminal(&self) -> DisplayKind {
+        match self {
+            Backend::Terminal(disp_kind) => *disp_kind,
+            #[allow(unreachable_patterns)]
+            _ => TERMINAL_DISPLAY,
+        }
+    }
+}
+
+impl Default for Backend {
+    fn default() -> Self {
+        DEFAULT_BACKEND
+    }
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Backend::ALL
+            .iter()
+            .copied()
+            .find(|b| b.to_string() == name.to_lowercase())
+            .ok_or_else(|| format!("Unknown interface `{}`.", name))
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Backend::Terminal(DisplayKind::CLI) => "cli",
+            #[cfg(feature = "tui")]
+            Backend::Terminal(DisplayKind::TUI) => "tui",
+            #[cfg(feature = "term_graphics")]
+            Backend::Terminal(DisplayKind::TermGraphics) => "term-graphics",
+            #[cfg(feature = "ratatui")]
+            Backend::Terminal(DisplayKind::RichTUI) => "rich",
+            #[cfg(feature = "ggez")]
+            Backend::Ggez => "ggez",
+            #[cfg(feature = "macroquad")]
+            Backend::Macroquad => "macroquad",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Options taken by all the commands, see the [module](self).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct GlobalOptions {
+    pack: Option<String>,
+    level: Option<String>,
+    ui: Backend,
+}
+
+impl GlobalOptions {
+    /// The `paths` given to the command, after the pack if any.
+    fn with_pack(&self, paths: Vec<String>) -> Vec<String> {
+        self.pack.iter().cloned().chain(paths).collect()
+    }
+
+    /// Library of the file or directory at `path`, or else of the pack or the default levels,
+    /// with the index of the selected level if any. Exits if they can't be read.
+    fn library(&self, path: Option<&str>) -> (LevelLibrary, Option<usize>) {
+        let path = path
+            .or(self.pack.as_deref())
+            .unwrap_or(DEFAULT_LEVEL_FILENAME);
+        let library = match LevelLibrary::open(Path::new(path)) {
+            Ok(l) => l,
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(2);
+            }
+        };
+        let index = self.level.as_ref().map(|selector| {
+            library.find(selector).unwrap_or_else(|| {
+                eprintln!("No level `{}` in `{}`.", selector, path);
+                std::process::exit(2);
+            })
+        });
+        (library, index)
+    }
+
+    /// Library with only the selected level, if any.
+    #[cfg(any(feature = "remote", feature = "chat", feature = "serve"))]
+    fn selected_library(&self, path: Option<&str>) -> LevelLibrary {
+        let (library, index) = self.library(path);
+        only_selected(library, index)
+    }
+
+    /// Leaves only the selected level in the `pack`, if any, or exits if it isn't found.
+    fn select_in_pack(&self, pack: &mut LevelPack) {
+        let Some(selector) = &self.level else {
+            return;
+        };
+        match pack.find(selector) {
+            Some(index) => pack.levels = vec![pack.levels.swap_remove(index)],
+            None => {
+                eprintln!("No level `{}` in the pack.", selector);
+                std::process::exit(2);
+            }
+        }
+    }
+}
+
+/// The `library`, or only its level at `index` if any.
+#[cfg(any(
+    feature = "ggez",
+    feature = "macroquad",
+    feature = "remote",
+    feature = "chat",
+    feature = "serve"
+))]
+fn only_selected(library: LevelLibrary, index: Option<usize>) -> LevelLibrary {
+    match index.and_then(|i| library.get(i)) {
+        Some(level) => LevelLibrary::from_level(level.clone()),
+        None => library,
+    }
+}
+
+/// Reads the command and the options given to the program, and runs it.
+pub fn run() {
+    let mut options = GlobalOptions::default();
+    let (mut config, mut ui, mut show_help) = (None, None, false);
+    let mut rest = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let option = match &arg[..] {
+            "--pack" => &mut options.pack,
+            "--level" => &mut options.level,
+            "--config" => &mut config,
+            "--ui" => &mut ui,
+            "--help" | "-h" => {
+                show_help = true;
+                continue;
+            }
+            _ => {
+                rest.push(arg);
+                continue;
+            }
+        };
+        match args.next() {
+            Some(value) => *option = Some(value),
+            None => {
+                eprintln!("{}", help());
+                std::process::exit(2);
+            }
+        }
+    }
+
+    if let Err(err) = config::load(config.as_deref().map(Path::new)) {
+        eprintln!("{}", err);
+        std::process::exit(2);
+    }
+    options.pack = options.pack.or_else(|| env::var(PACK_ENV_VAR).ok());
+    if let Some(ui) = ui.or_else(|| env::var(UI_ENV_VAR).ok()) {
+        options.ui = match ui.parse() {
+            Ok(ui) => ui,
+            Err(err) => {
+                eprintln!("{}\n\n{}", err, help());
+                std::process::exit(2);
+            }
+        };
+    }
+
+    let command = match rest.first().and_then(|c| Command::from_name(c)) {
+        Some(command) => {
+            rest.remove(0);
+            command
+        }
+        None => Command::Play,
+    };
+    if show_help {
+        match command {
+            Command::Play if rest.is_empty() => println!("{}", help()),
+            command => println!("{}", command.help()),
+        }
+        return;
+    }
+    command.run(&options, rest);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Backend, Command};
+
+    #[test]
+    fn it_reads_commands_and_interfaces() {
+        assert_eq!(Command::from_name("solve"), Some(Command::Solve));
+        assert_eq!(Command::from_name("--solve"), Some(Command::Solve));
+        assert_eq!(Command::from_name("map.txt"), None);
+        for command in Command::ALL {
+            assert_eq!(Command::from_name(command.name()), Some(*command));
+        }
+        for backend in Backend::ALL {
+            assert_eq!(backend.to_string().parse::<Backend>(), Ok(*backend));
+        }
+        assert!("CLI".parse::<Backend>().is_ok());
+        assert!("gui".parse::<Backend>().is_err());
+    }
+}
@@ -0,0 +1,300 @@
+//! Commands playing the levels, alone or with others.
+
+#[cfg(feature = "tui")]
+use std::path::Path;
+
+#[cfg(any(feature = "ggez", feature = "macroquad"))]
+use super::only_selected;
+use super::{usage_error, Backend, Command, GlobalOptions};
+
+/// Plays the levels given in `args`, or watches the replay given after them, or plays random
+/// levels in the endless mode.
+pub(super) fn play(options: &GlobalOptions, args: Vec<String>) {
+    let mut paths = Vec::new();
+    let mut game_options = sokoban::GameOptions::default();
+    let (mut endless, mut seed) = (false, None);
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "--time-attack" => game_options.time_attack = true,
+            "--hotseat" => game_options.hotseat = true,
+            "--speedrun" => match args.next() {
+                Some(path) => game_options.speedrun = Some(path.into()),
+                None => usage_error(Command::Play),
+            },
+            "--endless" => endless = true,
+            "--seed" => match args.next().and_then(|n| n.parse::<u64>().ok()) {
+                Some(n) => seed = Some(n),
+                None => usage_error(Command::Play),
+            },
+            _ if !arg.starts_with("--") => paths.push(arg),
+            _ => usage_error(Command::Play),
+        }
+    }
+    if game_options.hotseat {
+        // The players take turns on the levels of a pack, which they play one at a time.
+        if endless || game_options.speedrun.is_some() {
+            usage_error(Command::Play);
+        }
+        // Only the terminal interfaces let the players take turns.
+        if !matches!(options.ui, Backend::Terminal(_)) {
+            eprintln!("The hotseat mode is only available in the terminal interfaces.");
+            std::process::exit(2);
+        }
+    }
+    if endless {
+        // The random levels never end.
+        if !paths.is_empty() || game_options.speedrun.is_some() {
+            usage_error(Command::Play);
+        }
+        return play_endless(options, seed, &game_options);
+    }
+    // The seed only makes sense for random levels.
+    if seed.is_some() {
+        usage_error(Command::Play);
+    }
+    let (level_filename, replay_filename) = match &paths[..] {
+        [] => (None, None),
+        [level] => (Some(&level[..]), None),
+        [level, replay] => (Some(&level[..]), Some(replay)),
+        _ => usage_error(Command::Play),
+    };
+    let (mut library, index) = options.library(level_filename);
+
+    // A replay can be given after the level to watch it instead of playing.
+    if let Some(replay_filename) = replay_filename {
+        let replay = match std::fs::read_to_string(replay_filename)
+            .map_err(|e| e.to_string())
+            .and_then(|r| r.parse::<sokoban::Replay>().map_err(|e| e.to_string()))
+        {
+            Ok(r) => r,
+            Err(err) => {
+                eprintln!("Could not read replay `{}`: {}", replay_filename, err);
+                std::process::exit(2);
+            }
+        };
+        let level = library
+            .get(index.unwrap_or(0))
+            .expect("A library always contains at least one level.");
+
+        let res = match options.ui {
+            #[cfg(feature = "macroquad")]
+            Backend::Macroquad => {
+                let library = sokoban::LevelLibrary::from_level(level.clone());
+                sokoban::watch_replay_macroquad(library, replay);
+                Ok(())
+            }
+            ui => sokoban::watch_replay(ui.terminal(), level, &replay, None),
+        };
+        if let Err(err) = res {
+            eprintln!("Replay exited with following error :\n{}", err);
+        }
+        return;
+    }
+
+    let res = match (options.ui, index) {
+        (Backend::Terminal(disp_kind), None) => {
+            sokoban::game(disp_kind, &mut library, &game_options)
+        }
+        (Backend::Terminal(disp_kind), Some(index)) => {
+            sokoban::game_at(disp_kind, &mut library, index, &game_options)
+        }
+        // The graphical interfaces are only given the selected level.
+        #[cfg(feature = "ggez")]
+        (Backend::Ggez, index) => sokoban::game_ggez(only_selected(library, index), game_options)
+            .map_err(|e| sokoban::GameError::UiError(e.into())),
+        #[cfg(feature = "macroquad")]
+        (Backend::Macroquad, index) => {
+            sokoban::game_macroquad(only_selected(library, index), game_options);
+            Ok(())
+        }
+    };
+    if let Err(err) = res {
+        eprintln!("Game exited with following error :\n{}", err);
+    }
+}
+
+/// Plays random levels in the endless mode, from the `seed` or else one taken from the clock.
+fn play_endless(options: &GlobalOptions, seed: Option<u64>, game_options: &sokoban::GameOptions) {
+    let seed = seed.unwrap_or_else(|| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64)
+    });
+    // The graphical interfaces start it from their level select screen.
+    let res = match options.ui {
+        Backend::Terminal(disp_kind) => sokoban::game_endless(disp_kind, seed, game_options),
+        #[cfg(any(feature = "ggez", feature = "macroquad"))]
+        _ => {
+            eprintln!(
+                "The endless mode is started from the level select screen of this interface."
+            );
+            std::process::exit(2);
+        }
+    };
+    if let Err(err) = res {
+        eprintln!("Game exited with following error :\n{}", err);
+    }
+}
+
+/// Opens the level editor on the file given in `args`, or else the pack.
+#[cfg(feature = "tui")]
+pub(super) fn edit(options: &GlobalOptions, args: Vec<String>) {
+    let [level_filename] = &options.with_pack(args)[..] else {
+        usage_error(Command::Edit)
+    };
+    if let Err(err) = sokoban::edit_level(Path::new(level_filename)) {
+        eprintln!("Editor exited with following error :\n{}", err);
+    }
+}
+
+/// Hosts a game on the selected level, or the first one, of the file given in `args`.
+#[cfg(feature = "net")]
+pub(super) fn host(options: &GlobalOptions, args: Vec<String>) {
+    let (level_filename, addr) = match &options.with_pack(args)[..] {
+        [level] => (level.clone(), None),
+        [level, addr] => (level.clone(), Some(addr.clone())),
+        _ => usage_error(Command::Host),
+    };
+    let addr = addr.unwrap_or_else(|| format!("0.0.0.0:{}", sokoban::DEFAULT_PORT));
+
+    let (library, index) = options.library(Some(&level_filename));
+    let level = library
+        .get(index.unwrap_or(0))
+        .expect("A library always contains at least one level.");
+    if let Err(err) = sokoban::host_game(level, &addr) {
+        eprintln!("Host exited with following error :\n{}", err);
+    }
+}
+
+/// Plays in the game hosted at the address given in `args`.
+#[cfg(feature = "net")]
+pub(super) fn join(options: &GlobalOptions, args: Vec<String>) {
+    let [addr] = &args[..] else {
+        usage_error(Command::Join)
+    };
+    let mut addr = addr.clone();
+    if !addr.contains(':') {
+        addr = format!("{}:{}", addr, sokoban::DEFAULT_PORT);
+    }
+
+    if let Err(err) = sokoban::join_game(options.ui.terminal(), &addr) {
+        eprintln!("Game exited with following error :\n{}", err);
+    }
+}
+
+/// Plays with the actions of the sources given in `args`, such as `stdin`,
+/// `udp:0.0.0.0:7880` or `mqtt:localhost/sokoban`.
+#[cfg(feature = "remote")]
+pub(super) fn remote(options: &GlobalOptions, args: Vec<String>) {
+    let mut sources = Vec::new();
+    let mut level_filename = None;
+    for arg in args {
+        // Anything which doesn't look like a source is the level file.
+        if !(arg == "stdin" || arg.starts_with("udp:") || arg.starts_with("mqtt:")) {
+            level_filename = Some(arg);
+            continue;
+        }
+        match sokoban::open_source(&arg) {
+            Ok(source) => sources.push(source),
+            Err(err) => {
+                eprintln!("Could not open `{}`: {}", arg, err);
+                std::process::exit(2);
+            }
+        }
+    }
+    if sources.is_empty() {
+        usage_error(Command::Remote);
+    }
+
+    // The board is printed, so programs reading the standard output can follow the game.
+    let mut library = options.selected_library(level_filename.as_deref());
+    if let Err(err) = sokoban::game_remote(sokoban::DisplayKind::CLI, sources, &mut library) {
+        eprintln!("Game exited with following error :\n{}", err);
+    }
+}
+
+/// Lets the chat of the Twitch channel given in `args` play the levels.
+#[cfg(feature = "chat")]
+pub(super) fn chat(options: &GlobalOptions, args: Vec<String>) {
+    let mut args = args.into_iter();
+    let Some(channel) = args.next() else {
+        usage_error(Command::Chat)
+    };
+    let mut overlay = None;
+    let mut level_filename = None;
+    while let Some(arg) = args.next() {
+        if arg != "--overlay" {
+            level_filename = Some(arg);
+            continue;
+        }
+        match args.next() {
+            Some(file) => overlay = Some(file.into()),
+            None => usage_error(Command::Chat),
+        }
+    }
+
+    let mut library = options.selected_library(level_filename.as_deref());
+    if let Err(err) = sokoban::game_chat(&channel, overlay, &mut library) {
+        eprintln!("Game exited with following error :\n{}", err);
+    }
+}
+
+/// Serves game sessions on the levels of the file given in `args`.
+#[cfg(feature = "serve")]
+pub(super) fn serve(options: &GlobalOptions, args: Vec<String>) {
+    let mut port = sokoban::DEFAULT_SERVE_PORT;
+    let mut level_filename = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg != "--port" {
+            level_filename = Some(arg);
+            continue;
+        }
+        match args.next().map(|p| p.parse()) {
+            Some(Ok(p)) => port = p,
+            _ => usage_error(Command::Serve),
+        }
+    }
+
+    let library = options.selected_library(level_filename.as_deref());
+    if let Err(err) = sokoban::serve(library, port) {
+        eprintln!("Server exited with following error :\n{}", err);
+    }
+}
+
+/// Lists the packs of the level repository matching the words of `args`, or installs or rates
+/// one.
+#[cfg(feature = "repo")]
+pub(super) fn packs(args: Vec<String>) {
+    let Some(client) = sokoban::RepoClient::from_env() else {
+        eprintln!(
+            "Set {} to the address of a level repository.",
+            sokoban::REPO_URL_ENV_VAR
+        );
+        std::process::exit(2);
+    };
+
+    let res = match &args.iter().map(|a| &a[..]).collect::<Vec<_>>()[..] {
+        ["install", id] => client.install(id).map(|library| {
+            println!(
+                "Installed {} levels in `{}`.",
+                library.len(),
+                sokoban::pack_dir(id).display()
+            )
+        }),
+        ["rate", id, rating] => match rating.parse() {
+            Ok(rating) => client.rate(id, rating).map(|pack| println!("{}", pack)),
+            Err(_) => usage_error(Command::Packs),
+        },
+        words => client.search(&words.join(" ")).map(|packs| {
+            for pack in packs {
+                println!("{:<16} {}", pack.id, pack);
+            }
+        }),
+    };
+    if let Err(err) = res {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
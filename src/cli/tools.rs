@@ -0,0 +1,590 @@
+//! Commands working on the levels without playing them, for scripts and other programs.
+
+use std::path::Path;
+
+use super::{usage_error, Command, GlobalOptions};
+
+/// Prints the solution of each level of the file given in `args`, and exits with an error
+/// if some couldn't be solved.
+pub(super) fn solve(options: &GlobalOptions, args: Vec<String>) {
+    let mut limits = sokoban::SolverLimits::default();
+    let mut json = false;
+    let mut level_filename = None;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let valid = match &arg[..] {
+            "--json" => {
+                json = true;
+                true
+            }
+            "--max-states" => args
+                .next()
+                .and_then(|n| n.parse().ok())
+                .map(|n| limits.max_states = n)
+                .is_some(),
+            "--max-time" => args
+                .next()
+                .and_then(|s| s.parse().ok())
+                .and_then(|s| std::time::Duration::try_from_secs_f32(s).ok())
+                .map(|t| limits.max_time = Some(t))
+                .is_some(),
+            _ if !arg.starts_with("--") && level_filename.is_none() => {
+                level_filename = Some(arg);
+                true
+            }
+            _ => false,
+        };
+        if !valid {
+            usage_error(Command::Solve);
+        }
+    }
+    if level_filename.is_none() && options.pack.is_none() {
+        usage_error(Command::Solve);
+    }
+
+    let (library, index) = options.library(level_filename.as_deref());
+    let levels = match index {
+        Some(index) => &library.levels()[index..=index],
+        None => library.levels(),
+    };
+    let mut all_solved = true;
+    for level in levels {
+        let report = sokoban::solve_level(level, &limits);
+        all_solved &= report.is_solved();
+        if json {
+            println!("{}", report.to_json());
+        } else {
+            println!("{}", report);
+        }
+    }
+    if !all_solved {
+        std::process::exit(1);
+    }
+}
+
+/// Prints the problems of the level files and directories given in `args`, and exits with an
+/// error if some can't be played.
+pub(super) fn validate(options: &GlobalOptions, paths: Vec<String>) {
+    let paths = options.with_pack(paths);
+    if paths.is_empty() {
+        usage_error(Command::Validate);
+    }
+
+    let (mut files, mut errors, mut warnings) = (0, 0, 0);
+    for path in paths {
+        let reports = match sokoban::validate_path(Path::new(&path)) {
+            Ok(reports) => reports,
+            Err(err) => {
+                eprintln!("{}: error: {}", path, err);
+                errors += 1;
+                continue;
+            }
+        };
+        for report in reports {
+            print!("{}", report);
+            files += 1;
+            errors += report.count(sokoban::Severity::Error);
+            warnings += report.count(sokoban::Severity::Warning);
+        }
+    }
+    println!(
+        "{} files checked, {} errors, {} warnings",
+        files, errors, warnings
+    );
+    if errors > 0 {
+        std::process::exit(1);
+    }
+}
+
+/// Converts the levels given in `args`, printing them if no output is given, or writing each
+/// of them in its own file if the output is a directory.
+pub(super) fn convert(options: &GlobalOptions, args: Vec<String>) {
+    use sokoban::{Format, LevelPack};
+
+    let usage = |err: Option<sokoban::ConvertError>| -> ! {
+        if let Some(err) = err {
+            eprintln!("{}", err);
+        }
+        usage_error(Command::Convert)
+    };
+
+    let (mut from, mut to, mut paths) = (None, None, Vec::new());
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let format = match &arg[..] {
+            "--from" => &mut from,
+            "--to" => &mut to,
+            _ if !arg.starts_with("--") => {
+                paths.push(arg);
+                continue;
+            }
+            _ => usage(None),
+        };
+        match args.next().map(|f| f.parse::<Format>()) {
+            Some(Ok(f)) => *format = Some(f),
+            Some(Err(err)) => usage(Some(err)),
+            None => usage(None),
+        }
+    }
+    let paths = options.with_pack(paths);
+    let (input, output) = match &paths[..] {
+        [input] => (input, None),
+        [input, output] => (input, Some(output)),
+        _ => usage(None),
+    };
+
+    let res = LevelPack::open(Path::new(input), from).and_then(|mut pack| {
+        options.select_in_pack(&mut pack);
+        let Some(output) = output else {
+            print!("{}", pack.write(to.unwrap_or(Format::Xsb))?);
+            return Ok(());
+        };
+        let path = Path::new(output);
+        if path.is_dir() || output.ends_with(std::path::MAIN_SEPARATOR) {
+            let paths = pack.write_dir(path, to.unwrap_or(Format::Xsb))?;
+            println!("{} levels written to `{}`.", paths.len(), path.display());
+        } else {
+            let format = to
+                .or_else(|| Format::from_extension(path))
+                .unwrap_or(Format::Xsb);
+            std::fs::write(path, pack.write(format)?)
+                .map_err(|e| sokoban::ConvertError::IO(path.to_path_buf(), e))?;
+        }
+        Ok(())
+    });
+    if let Err(err) = res {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
+
+/// Generates the levels described by `args`, and writes them with their solutions, in `.sol`
+/// files next to them.
+pub(super) fn generate(args: Vec<String>) {
+    use sokoban::{Difficulty, Format, Generator, GeneratorOptions};
+
+    let seed = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64);
+    let mut options = GeneratorOptions {
+        seed,
+        ..GeneratorOptions::default()
+    };
+    let mut count = 10;
+    let mut output = None;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let value = if arg.starts_with("--") {
+            args.next()
+        } else {
+            None
+        };
+        let valid = match (&arg[..], value) {
+            ("--seed", Some(n)) => n.parse().map(|n| options.seed = n).is_ok(),
+            ("--size", Some(size)) => size
+                .split_once('x')
+                .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+                .map(|(w, h)| (options.width, options.height) = (w, h))
+                .is_some(),
+            ("--crates", Some(n)) => n.parse().map(|n| options.crates = Some(n)).is_ok(),
+            ("--difficulty", Some(d)) => {
+                options.difficulty = match &d.to_lowercase()[..] {
+                    "easy" => Difficulty::Easy,
+                    "medium" => Difficulty::Medium,
+                    "hard" => Difficulty::Hard,
+                    _ => usage_error(Command::Gen),
+                };
+                true
+            }
+            ("--count", Some(n)) => n.parse().map(|n| count = n).is_ok(),
+            (_, None) if !arg.starts_with("--") && output.is_none() => {
+                output = Some(arg);
+                true
+            }
+            _ => false,
+        };
+        if !valid {
+            usage_error(Command::Gen);
+        }
+    }
+    let Some(output) = output else {
+        usage_error(Command::Gen)
+    };
+
+    let mut generator = match Generator::new(options) {
+        Ok(g) => g,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+    let (pack, solutions) = match generator.pack(count) {
+        Ok(pack) => pack,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let path = Path::new(&output);
+    let res = if path.is_dir() || output.ends_with(std::path::MAIN_SEPARATOR) {
+        // A solution file alone is a replay, which isn't taken as a level.
+        pack.write_dir(path, Format::Xsb).and_then(|paths| {
+            for (level_path, solution) in paths.iter().zip(solutions.iter()) {
+                let sol_path = level_path.with_extension("sol");
+                std::fs::write(&sol_path, format!("{}\n", solution))
+                    .map_err(|e| sokoban::ConvertError::IO(sol_path, e))?;
+            }
+            Ok(())
+        })
+    } else {
+        let format = Format::from_extension(path).unwrap_or(Format::Xsb);
+        let sol_path = path.with_extension("sol");
+        let sols: String = pack
+            .levels
+            .iter()
+            .zip(solutions.iter())
+            .map(|(level, solution)| format!("{}\t{}\n", level.title, solution))
+            .collect();
+        pack.write(format).and_then(|text| {
+            std::fs::write(path, text)
+                .map_err(|e| sokoban::ConvertError::IO(path.to_path_buf(), e))?;
+            std::fs::write(&sol_path, sols).map_err(|e| sokoban::ConvertError::IO(sol_path, e))
+        })
+    };
+    match res {
+        Ok(()) => println!(
+            "{} levels generated with the seed {} in `{}`.",
+            count,
+            options.seed,
+            path.display()
+        ),
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Plays the replay given in `args` on its level, found next to it if not given, and shows it
+/// or checks that it solves the level.
+pub(super) fn replay(options: &GlobalOptions, args: Vec<String>) {
+    use sokoban::LevelPack;
+
+    let (mut verify, mut speed) = (false, None);
+    let mut paths = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let valid = match &arg[..] {
+            "--verify" => {
+                verify = true;
+                true
+            }
+            "--speed" => args
+                .next()
+                .and_then(|x| x.parse::<u32>().ok())
+                .filter(|x| (1..=32).contains(x))
+                .map(|x| speed = Some(x))
+                .is_some(),
+            _ if !arg.starts_with("--") => {
+                paths.push(arg);
+                true
+            }
+            _ => false,
+        };
+        if !valid {
+            usage_error(Command::Replay);
+        }
+    }
+    let (replay_path, level_path) = match &paths[..] {
+        [replay] => (Path::new(replay), options.pack.as_deref().map(Path::new)),
+        [replay, level] => (Path::new(replay), Some(Path::new(level))),
+        _ => usage_error(Command::Replay),
+    };
+
+    let solutions = match std::fs::read_to_string(replay_path)
+        .map_err(|e| e.to_string())
+        .and_then(|r| sokoban::parse_solutions(&r).map_err(|e| e.to_string()))
+    {
+        Ok(s) => s,
+        Err(err) => {
+            eprintln!("Could not read replay `{}`: {}", replay_path.display(), err);
+            std::process::exit(2);
+        }
+    };
+    let pack = match level_path {
+        Some(path) => LevelPack::open(path, None),
+        None => find_replay_level(replay_path),
+    };
+    let pack = match pack {
+        Ok(p) => p,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+
+    // A replay without title is the one of the selected level, or else of the first one.
+    let mut pack = pack;
+    options.select_in_pack(&mut pack);
+    let mut levels = Vec::with_capacity(solutions.len());
+    for (title, replay) in solutions {
+        let level = match &title {
+            Some(title) if options.level.is_some() && pack.levels[0].title != *title => continue,
+            Some(title) => pack.levels.iter().find(|l| &l.title == title),
+            None => pack.levels.first(),
+        };
+        let Some(level) = level else {
+            eprintln!("No level `{}` in the pack.", title.unwrap_or_default());
+            std::process::exit(2);
+        };
+        levels.push((level, replay));
+    }
+
+    if verify {
+        let mut all_solved = true;
+        for (level, replay) in levels {
+            let report = sokoban::check_replay(&level.title, &level.board, &replay);
+            all_solved &= report.is_solved();
+            println!("{}", report);
+        }
+        if !all_solved {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    for (level, replay) in levels {
+        let level = sokoban::Level::from(level.clone());
+        if let Err(err) = sokoban::watch_replay(options.ui.terminal(), &level, &replay, speed) {
+            eprintln!("Replay exited with following error :\n{}", err);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Levels of the first file next to `replay_path` with the same name, such as `level.xsb` for
+/// `level.sol`.
+fn find_replay_level(replay_path: &Path) -> Result<sokoban::LevelPack, sokoban::ConvertError> {
+    let dir = match replay_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let mut paths: Vec<_> = std::fs::read_dir(dir)
+        .map_err(|e| sokoban::ConvertError::IO(dir.to_path_buf(), e))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| {
+            p.file_stem() == replay_path.file_stem() && p.file_name() != replay_path.file_name()
+        })
+        .collect();
+    paths.sort();
+    paths
+        .iter()
+        .find_map(|path| sokoban::LevelPack::open(path, None).ok())
+        .ok_or(sokoban::ConvertError::NoLevel)
+}
+
+/// Runs the solver on the levels given in `args` and writes a report, compared with an earlier
+/// one if given, exiting with an error if some levels aren't solved anymore.
+pub(super) fn bench(global: &GlobalOptions, args: Vec<String>) {
+    use sokoban::{BenchOptions, BenchReport, LevelPack};
+
+    let mut options = BenchOptions::default();
+    let (mut csv, mut json) = (false, false);
+    let (mut output, mut baseline) = (None, None);
+    let mut paths = Vec::new();
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let valid = match &arg[..] {
+            "--csv" => {
+                csv = true;
+                true
+            }
+            "--json" => {
+                json = true;
+                true
+            }
+            "--max-states" => args
+                .next()
+                .and_then(|n| n.parse().ok())
+                .map(|n| options.limits.max_states = n)
+                .is_some(),
+            "--max-time" => args
+                .next()
+                .and_then(|s| s.parse().ok())
+                .and_then(|s| std::time::Duration::try_from_secs_f32(s).ok())
+                .map(|t| options.limits.max_time = Some(t))
+                .is_some(),
+            "--max-memory" => args
+                .next()
+                .and_then(|m| m.parse::<usize>().ok())
+                .map(|m| options.max_memory = Some(m * 1024 * 1024))
+                .is_some(),
+            "--output" => args.next().map(|f| output = Some(f)).is_some(),
+            "--baseline" => args.next().map(|f| baseline = Some(f)).is_some(),
+            _ if !arg.starts_with("--") => {
+                paths.push(arg);
+                true
+            }
+            _ => false,
+        };
+        if !valid || (csv && json) {
+            usage_error(Command::Bench);
+        }
+    }
+    let paths = global.with_pack(paths);
+    if paths.is_empty() {
+        usage_error(Command::Bench);
+    }
+
+    // Read first, so a wrong path doesn't waste a long run.
+    let baseline = baseline.map(|path| {
+        match std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|src| BenchReport::from_csv(&src).map_err(|e| e.to_string()))
+        {
+            Ok(report) => report,
+            Err(err) => {
+                eprintln!("Could not read baseline `{}`: {}", path, err);
+                std::process::exit(2);
+            }
+        }
+    });
+    let mut packs = Vec::with_capacity(paths.len());
+    for path in paths {
+        match LevelPack::open(Path::new(&path), None) {
+            Ok(mut pack) => {
+                global.select_in_pack(&mut pack);
+                packs.push((path, pack));
+            }
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    let mut report = BenchReport::default();
+    for (path, pack) in packs.iter() {
+        for level in pack.levels.iter() {
+            let row = sokoban::bench_level(path, level, &options);
+            // Shows the progress, as the whole run may be long.
+            eprintln!(
+                "{}: {} ({} ms)",
+                level.title,
+                row.status,
+                row.time.as_millis()
+            );
+            report.rows.push(row);
+        }
+    }
+
+    let text = if csv {
+        report.to_csv()
+    } else if json {
+        report.to_json(&options)
+    } else {
+        format!("{}\n", report)
+    };
+    match &output {
+        Some(path) => {
+            if let Err(err) = std::fs::write(path, text) {
+                eprintln!("Could not write `{}`: {}", path, err);
+                std::process::exit(1);
+            }
+            println!(
+                "{}/{} levels solved, report written to `{}`.",
+                report.solved(),
+                report.rows.len(),
+                path
+            );
+        }
+        None => print!("{}", text),
+    }
+
+    if let Some(baseline) = baseline {
+        let comparison = report.compare(&baseline);
+        // Kept out of the reports printed for other programs.
+        if output.is_none() && (csv || json) {
+            eprintln!("{}", comparison);
+        } else {
+            println!("{}", comparison);
+        }
+        if comparison.is_regression() {
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Draws the levels given in `args` in a PNG file, or in a file per level if the output is a
+/// directory, or all of them in a sheet with `--sheet`.
+#[cfg(feature = "thumbnail")]
+pub(super) fn thumbnail(global: &GlobalOptions, args: Vec<String>) {
+    use sokoban::{LevelPack, ThumbnailOptions};
+
+    let mut options = ThumbnailOptions::default();
+    let mut sheet = false;
+    let mut columns = None;
+    let mut paths = Vec::new();
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let valid = match &arg[..] {
+            "--sheet" => {
+                sheet = true;
+                true
+            }
+            "--cell" => args
+                .next()
+                .and_then(|n| n.parse().ok())
+                .filter(|n| *n > 0)
+                .map(|n| options.cell_size = n)
+                .is_some(),
+            "--columns" => args
+                .next()
+                .and_then(|n| n.parse().ok())
+                .filter(|n| *n > 0)
+                .map(|n| columns = Some(n))
+                .is_some(),
+            _ if !arg.starts_with("--") => {
+                paths.push(arg);
+                true
+            }
+            _ => false,
+        };
+        if !valid {
+            usage_error(Command::Thumbnail);
+        }
+    }
+    let [input, output] = &global.with_pack(paths)[..] else {
+        usage_error(Command::Thumbnail)
+    };
+
+    let mut pack = match LevelPack::open(Path::new(input), None) {
+        Ok(pack) => pack,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+    global.select_in_pack(&mut pack);
+    // Close to a square by default.
+    if sheet {
+        let default = (pack.levels.len() as f64).sqrt().ceil() as usize;
+        options.sheet_columns = Some(columns.unwrap_or(default));
+    }
+
+    let path = Path::new(output);
+    let dir = path.is_dir() || output.ends_with(std::path::MAIN_SEPARATOR);
+    match sokoban::write_thumbnails(&pack, path, dir, &options) {
+        Ok(paths) if paths.len() == 1 => println!("Written to `{}`.", paths[0].display()),
+        Ok(paths) => println!("{} pictures written to `{}`.", paths.len(), path.display()),
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    }
+}
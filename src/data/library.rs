@@ -0,0 +1,443 @@
+//! Collections of levels the player can choose from.
+
+use std::{
+    error::Error,
+    fmt,
+    fs::{self, read_to_string},
+    io,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use super::{Board, LevelParseError, Replay};
+use crate::tools::{ConvertError, Format, LevelPack, PackLevel};
+
+/// Name of the file listing the titles of the solved levels, inside a level directory.
+/// Each line contains the title, optionally followed by the best score and its replay, separated
+/// by tabs.
+const SOLVED_FILENAME: &str = ".solved";
+const SOLVED_SEPARATOR: char = '\t';
+
+/// Rough estimation of how hard a level is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    /// Estimates the difficulty based on the number of crates to place.
+    pub fn estimate(board: &Board) -> Self {
+        match board.crates.len() {
+            0..=2 => Difficulty::Easy,
+            3..=5 => Difficulty::Medium,
+            _ => Difficulty::Hard,
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Difficulty::*;
+        write!(
+            f,
+            "{}",
+            match self {
+                Easy => "Easy",
+                Medium => "Medium",
+                Hard => "Hard",
+            }
+        )
+    }
+}
+
+/// How well a level was solved.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Score {
+    pub moves: u32,
+    pub pushes: u32,
+    pub time: Duration,
+}
+
+impl Score {
+    /// Score of a `board` won in `time`.
+    pub fn new(board: &Board, time: Duration) -> Self {
+        Score {
+            moves: board.moves(),
+            pushes: board.pushes(),
+            time,
+        }
+    }
+
+    /// Less moves is better, then less pushes, then less time.
+    pub fn is_better_than(&self, other: &Score) -> bool {
+        (self.moves, self.pushes, self.time) < (other.moves, other.pushes, other.time)
+    }
+
+    fn parse(fields: &[&str]) -> Option<Self> {
+        match fields {
+            [moves, pushes, millis] => Some(Score {
+                moves: moves.parse().ok()?,
+                pushes: pushes.parse().ok()?,
+                time: Duration::from_millis(millis.parse().ok()?),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// A level of the library, with its state at the start.
+#[derive(Clone, Debug)]
+pub struct Level {
+    title: String,
+    source: String,
+    board: Board,
+    solved: bool,
+    /// Best score, if the level was solved with a known score.
+    best: Option<Score>,
+    /// Moves which achieved the best score.
+    best_replay: Option<Replay>,
+    /// If the level is played in the move-limit challenge, see [`MoveLimit`](`super::MoveLimit`).
+    challenge: bool,
+}
+
+impl Level {
+    /// Parses the level from the file content in `source`.
+    pub fn new(title: String, source: String) -> Result<Self, LevelParseError> {
+        let board = Board::from_str(&source[..])?;
+        Ok(Level {
+            title,
+            source,
+            board,
+            solved: false,
+            best: None,
+            best_replay: None,
+            challenge: false,
+        })
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// Content of the file the level was loaded from, or the level alone in XSB with its
+    /// metadata if it was read in a pack.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Board as it is when starting the level.
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn difficulty(&self) -> Difficulty {
+        Difficulty::estimate(&self.board)
+    }
+
+    pub fn is_solved(&self) -> bool {
+        self.solved
+    }
+
+    pub fn best(&self) -> Option<Score> {
+        self.best
+    }
+
+    pub fn best_replay(&self) -> Option<&Replay> {
+        self.best_replay.as_ref()
+    }
+
+    /// If the level must be won within its [par](`super::MoveLimit::par`) moves and pushes.
+    pub fn is_challenge(&self) -> bool {
+        self.challenge
+    }
+}
+
+impl From<PackLevel> for Level {
+    fn from(level: PackLevel) -> Self {
+        let (title, board) = (level.title.clone(), level.board.clone());
+        let pack = LevelPack {
+            levels: vec![level],
+            ..LevelPack::default()
+        };
+        Level {
+            title,
+            source: pack
+                .write(Format::Xsb)
+                .expect("A level can always be written in XSB."),
+            board,
+            solved: false,
+            best: None,
+            best_replay: None,
+            challenge: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum LibraryError {
+    IO(PathBuf, io::Error),
+    /// The levels can't be read, see [`LevelPack::open`].
+    Pack(ConvertError),
+    NoLevel(PathBuf),
+}
+
+impl fmt::Display for LibraryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use LibraryError::*;
+        match self {
+            IO(path, err) => write!(f, "Could not access `{}`: {}", path.display(), err),
+            Pack(err) => err.fmt(f),
+            NoLevel(path) => write!(f, "No level could be found in `{}`.", path.display()),
+        }
+    }
+}
+
+impl Error for LibraryError {}
+
+/// List of levels, loaded either from a level or pack file or from all the level files of a
+/// directory.
+#[derive(Clone, Debug)]
+pub struct LevelLibrary {
+    levels: Vec<Level>,
+    /// Where to save which levels were solved, if the library comes from a directory.
+    solved_path: Option<PathBuf>,
+}
+
+impl LevelLibrary {
+    /// Library containing only the given level.
+    pub fn from_level(level: Level) -> Self {
+        LevelLibrary {
+            levels: vec![level],
+            solved_path: None,
+        }
+    }
+
+    /// Library of the levels of the `pack`, in order.
+    pub fn from_pack(pack: LevelPack) -> Self {
+        LevelLibrary {
+            levels: pack.levels.into_iter().map(Level::from).collect(),
+            solved_path: None,
+        }
+    }
+
+    /// Loads the levels of the file at `path`, in any of the formats of [`Format`], or of all
+    /// the level files if it is a directory (see [`LevelPack::open`]). The levels solved in a
+    /// directory are remembered in it.
+    pub fn open(path: &Path) -> Result<Self, LibraryError> {
+        let pack = LevelPack::open(path, None).map_err(|e| match e {
+            ConvertError::IO(path, e) => LibraryError::IO(path, e),
+            ConvertError::NoLevel => LibraryError::NoLevel(path.to_path_buf()),
+            e => LibraryError::Pack(e),
+        })?;
+        let mut library = LevelLibrary::from_pack(pack);
+        if !path.is_dir() {
+            return Ok(library);
+        }
+
+        let solved_path = path.join(SOLVED_FILENAME);
+        if let Ok(solved) = read_to_string(&solved_path) {
+            for line in solved.lines() {
+                let fields: Vec<&str> = line.split(SOLVED_SEPARATOR).collect();
+                if let Some(l) = library.levels.iter_mut().find(|l| l.title == fields[0]) {
+                    l.solved = true;
+                    l.best = fields.get(1..4).and_then(Score::parse);
+                    l.best_replay = fields.get(4).and_then(|r| r.parse().ok());
+                }
+            }
+        }
+        library.solved_path = Some(solved_path);
+        Ok(library)
+    }
+
+    pub fn levels(&self) -> &[Level] {
+        &self.levels
+    }
+
+    pub fn len(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Level> {
+        self.levels.get(index)
+    }
+
+    /// Index of the level given by its number, from 1, or by its title.
+    pub fn find(&self, selector: &str) -> Option<usize> {
+        find_level(self.levels.iter().map(|l| &l.title[..]), selector)
+    }
+
+    /// Switches the move-limit challenge of the level at `index` on or off, for this session
+    /// only.
+    pub fn toggle_challenge(&mut self, index: usize) {
+        if let Some(level) = self.levels.get_mut(index) {
+            level.challenge = !level.challenge;
+        }
+    }
+
+    /// Index of the first level which wasn't solved yet.
+    pub fn first_unsolved(&self) -> Option<usize> {
+        self.levels.iter().position(|l| !l.solved)
+    }
+
+    /// Marks the level as solved, keeps the `score` and its `replay` if it is the best one and
+    /// saves it if the library comes from a directory.
+    pub fn set_solved(
+        &mut self,
+        index: usize,
+        score: Score,
+        replay: Replay,
+    ) -> Result<(), LibraryError> {
+        let Some(level) = self.levels.get_mut(index) else {
+            return Ok(());
+        };
+        level.solved = true;
+        if level.best.is_none_or(|best| score.is_better_than(&best)) {
+            level.best = Some(score);
+            level.best_replay = Some(replay);
+        }
+
+        if let Some(path) = &self.solved_path {
+            let solved = self
+                .levels
+                .iter()
+                .filter(|l| l.solved)
+                .map(|l| match (l.best, &l.best_replay) {
+                    (Some(best), replay) => format!(
+                        "{1}{0}{2}{0}{3}{0}{4}{0}{5}\n",
+                        SOLVED_SEPARATOR,
+                        l.title,
+                        best.moves,
+                        best.pushes,
+                        best.time.as_millis(),
+                        replay.as_ref().map(Replay::to_string).unwrap_or_default()
+                    ),
+                    (None, _) => format!("{}\n", l.title),
+                })
+                .collect::<String>();
+            fs::write(path, solved).map_err(|e| LibraryError::IO(path.clone(), e))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Index of the level given by its number, from 1, or else by its title ignoring the case,
+/// among the levels with the `titles`.
+pub(crate) fn find_level<'a>(
+    mut titles: impl ExactSizeIterator<Item = &'a str>,
+    selector: &str,
+) -> Option<usize> {
+    match selector.trim().parse::<usize>() {
+        Ok(number) if (1..=titles.len()).contains(&number) => Some(number - 1),
+        _ => titles.position(|t| t.eq_ignore_ascii_case(selector.trim())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{find_level, Difficulty, Level, LevelLibrary, Replay, Score};
+    use crate::data::Tutorial;
+
+    const EASY_LEVEL_STR: &str = "#####
+#...#
+#.X.#
+#...#
+#####
+
+1,1
+
+2,1
+";
+
+    #[test]
+    fn it_estimates_difficulty() {
+        let level = Level::new("easy".to_string(), EASY_LEVEL_STR.to_string()).unwrap();
+        assert_eq!(level.difficulty(), Difficulty::Easy);
+    }
+
+    #[test]
+    fn it_rejects_invalid_level() {
+        assert!(Level::new("invalid".to_string(), "#####".to_string()).is_err());
+    }
+
+    #[test]
+    fn it_marks_levels_as_solved() {
+        let level = Level::new("easy".to_string(), EASY_LEVEL_STR.to_string()).unwrap();
+        let mut library = LevelLibrary::from_level(level);
+
+        assert_eq!(library.first_unsolved(), Some(0));
+        library
+            .set_solved(0, score(3, 1), Replay::default())
+            .unwrap();
+        assert!(library.get(0).unwrap().is_solved());
+        assert_eq!(library.first_unsolved(), None);
+    }
+
+    #[test]
+    fn it_keeps_the_best_score() {
+        let level = Level::new("easy".to_string(), EASY_LEVEL_STR.to_string()).unwrap();
+        let mut library = LevelLibrary::from_level(level);
+
+        library.set_solved(0, score(5, 1), replay("ddRuu")).unwrap();
+        library.set_solved(0, score(3, 1), replay("rdD")).unwrap();
+        library.set_solved(0, score(4, 1), replay("drdD")).unwrap();
+        assert_eq!(library.get(0).unwrap().best(), Some(score(3, 1)));
+        assert_eq!(library.get(0).unwrap().best_replay(), Some(&replay("rdD")));
+    }
+
+    #[test]
+    fn it_finds_levels_by_number_or_title() {
+        let titles = ["Intro", "Level 2", "42"];
+        let find = |selector| find_level(titles.iter().copied(), selector);
+        assert_eq!(find("1"), Some(0));
+        assert_eq!(find("level 2"), Some(1));
+        // Titles which are numbers are found when the number is too large.
+        assert_eq!(find("42"), Some(2));
+        assert_eq!(find("0"), None);
+        assert_eq!(find("Outro"), None);
+    }
+
+    #[test]
+    fn it_opens_packs_of_several_levels() {
+        let path = std::env::temp_dir().join(format!("sokoban-library-{}.xsb", std::process::id()));
+        std::fs::write(
+            &path,
+            "Title: Pack\n\n#####\n#@$.#\n#####\n\nTitle: First\n\n\
+             ######\n#@$ .#\n######\n\nHint-Start: Push it twice.\n",
+        )
+        .unwrap();
+        let library = LevelLibrary::open(&path);
+        std::fs::remove_file(&path).unwrap();
+        let library = library.unwrap();
+
+        assert_eq!(library.len(), 2);
+        assert_eq!(library.find("first"), Some(0));
+        assert_eq!(library.find("2"), Some(1));
+        let second = library.get(1).unwrap();
+        assert!(second.title().starts_with("sokoban-library-"));
+        // The metadata of the level stays in its source.
+        assert!(Tutorial::new(library.get(0).unwrap()).is_empty());
+        assert!(!Tutorial::new(second).is_empty());
+    }
+
+    fn replay(lurd: &str) -> Replay {
+        lurd.parse().unwrap()
+    }
+
+    fn score(moves: u32, pushes: u32) -> Score {
+        Score {
+            moves,
+            pushes,
+            time: Duration::from_secs(1),
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! Checks of the mistakes commonly made when drawing a level, see [`Board::lint`].
+
+use std::{collections::VecDeque, fmt};
+
+use super::{Board, CellKind, Crate};
+
+/// A problem found in a level, which makes it unplayable or not shareable as is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LintIssue {
+    /// The player isn't on a floor or a target.
+    PlayerOutside,
+    /// There is a crate on a cell which can't be crossed.
+    CrateOutside((u32, u32)),
+    NoCrate,
+    /// There isn't exactly one target per crate.
+    CratesTargetsMismatch {
+        crates: usize,
+        targets: usize,
+    },
+    /// The player can reach a cell on the side of the level or next to the void, so the level
+    /// isn't closed by walls.
+    NotEnclosed((u32, u32)),
+    /// The player can't walk up to this crate.
+    UnreachableCrate((u32, u32)),
+    /// All crates are already on targets.
+    AlreadySolved,
+}
+
+impl fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use LintIssue::*;
+        match self {
+            PlayerOutside => write!(f, "The player must be on a floor or a target."),
+            CrateOutside((i, j)) => write!(f, "The crate at {},{} is out of the floor.", i, j),
+            NoCrate => write!(f, "There is no crate."),
+            CratesTargetsMismatch { crates, targets } => {
+                write!(f, "There are {} crates but {} targets.", crates, targets)
+            }
+            NotEnclosed((i, j)) => write!(f, "The level isn't closed by walls at {},{}.", i, j),
+            UnreachableCrate((i, j)) => write!(f, "The crate at {},{} can't be reached.", i, j),
+            AlreadySolved => write!(f, "All the crates are already on targets."),
+        }
+    }
+}
+
+impl Board {
+    /// Looks for problems in the starting position of the level, the most important first.
+    /// The level is fine if none is found.
+    pub fn lint(&self) -> Vec<LintIssue> {
+        let mut issues = Vec::new();
+
+        let (pi, pj) = self.original_player;
+        if !self.map.get(pi, pj).is_crossable() {
+            issues.push(LintIssue::PlayerOutside);
+        }
+        for c in self.original_crates.iter() {
+            let (i, j) = c.pos();
+            if !self.map.get(i, j).is_crossable() {
+                issues.push(LintIssue::CrateOutside((i, j)));
+            }
+        }
+
+        let crates = self.original_crates.len();
+        let targets = (0..self.height())
+            .flat_map(|j| (0..self.width()).map(move |i| (i, j)))
+            .filter(|&(i, j)| self.map.get(i, j) == CellKind::Target)
+            .count();
+        if crates == 0 {
+            issues.push(LintIssue::NoCrate);
+        } else if crates != targets {
+            issues.push(LintIssue::CratesTargetsMismatch { crates, targets });
+        }
+
+        if issues.contains(&LintIssue::PlayerOutside) {
+            return issues;
+        }
+
+        let reachable = self.reachable_cells();
+        let is_reachable = |(i, j): (u32, u32)| reachable[(j * self.width() + i) as usize];
+
+        let open_cell = (0..self.height())
+            .flat_map(|j| (0..self.width()).map(move |i| (i, j)))
+            .filter(|&pos| is_reachable(pos))
+            .find(|&(i, j)| {
+                i == 0
+                    || j == 0
+                    || i + 1 == self.width()
+                    || j + 1 == self.height()
+                    || [(i - 1, j), (i + 1, j), (i, j - 1), (i, j + 1)]
+                        .iter()
+                        .any(|&(ni, nj)| self.map.get(ni, nj) == CellKind::Void)
+            });
+        if let Some(pos) = open_cell {
+            issues.push(LintIssue::NotEnclosed(pos));
+        }
+
+        issues.extend(
+            self.original_crates
+                .iter()
+                .map(Crate::pos)
+                .filter(|&pos| self.map.get(pos.0, pos.1).is_crossable() && !is_reachable(pos))
+                .map(LintIssue::UnreachableCrate),
+        );
+
+        if crates > 0
+            && self
+                .original_crates
+                .iter()
+                .all(|c| self.map.get(c.pos().0, c.pos().1) == CellKind::Target)
+        {
+            issues.push(LintIssue::AlreadySolved);
+        }
+
+        issues
+    }
+
+    /// Cells the player can walk on from the start if there was no crate, indexed by
+    /// `j * width + i`.
+    fn reachable_cells(&self) -> Vec<bool> {
+        let width = self.width();
+        let mut reachable = vec![false; (width * self.height()) as usize];
+
+        let mut queue = VecDeque::from([self.original_player]);
+        while let Some((i, j)) = queue.pop_front() {
+            let index = (j * width + i) as usize;
+            if reachable[index] {
+                continue;
+            }
+            reachable[index] = true;
+
+            let neighbours = [
+                (i.wrapping_sub(1), j),
+                (i + 1, j),
+                (i, j.wrapping_sub(1)),
+                (i, j + 1),
+            ];
+            for (ni, nj) in neighbours {
+                if self.map.try_get(ni, nj).is_some_and(|c| c.is_crossable()) {
+                    queue.push_back((ni, nj));
+                }
+            }
+        }
+
+        reachable
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Board, CellKind, LintIssue};
+
+    const TEST_LEVEL_STR: &str = "#####
+#...#
+#.X.#
+#...#
+#####
+
+1,1
+
+2,1
+";
+
+    #[test]
+    fn it_finds_no_issue_in_a_valid_level() {
+        let board: Board = TEST_LEVEL_STR.parse().unwrap();
+        assert_eq!(board.lint(), Vec::new());
+    }
+
+    #[test]
+    fn it_finds_misplaced_items() {
+        let mut board: Board = TEST_LEVEL_STR.parse().unwrap();
+        board.set_player(0, 0);
+        board.toggle_crate(3, 3);
+        assert_eq!(
+            board.lint(),
+            vec![
+                LintIssue::PlayerOutside,
+                LintIssue::CratesTargetsMismatch {
+                    crates: 2,
+                    targets: 1
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn it_finds_holes_in_walls() {
+        let mut board: Board = TEST_LEVEL_STR.parse().unwrap();
+        board.set_cell(4, 2, CellKind::Floor);
+        assert_eq!(board.lint(), vec![LintIssue::NotEnclosed((4, 2))]);
+
+        board.set_cell(4, 2, CellKind::Wall);
+        board.set_cell(2, 1, CellKind::Wall);
+        board.set_cell(1, 2, CellKind::Wall);
+        board.toggle_crate(3, 2);
+        assert_eq!(board.lint(), vec![LintIssue::UnreachableCrate((3, 2))]);
+    }
+}
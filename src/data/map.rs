@@ -135,6 +135,15 @@ impl Map {
     pub fn get(&self, i: u32, j: u32) -> CellKind {
         self.try_get(i, j).unwrap_or(CellKind::Void)
     }
+
+    /// Changes the square at column nb. i and row nb. j, if it is in the board.
+    pub fn set(&mut self, i: u32, j: u32, kind: CellKind) {
+        if i < self.width && j < self.height {
+            self.squares
+                [usize::try_from(j * self.width + i).expect("Square id should fit in usize.")] =
+                kind;
+        }
+    }
 }
 
 impl TryFrom<&str> for Map {
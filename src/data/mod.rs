@@ -3,10 +3,27 @@
 
 use std::{error::Error, fmt, str::FromStr};
 
+mod library;
+pub(crate) use library::find_level;
+pub use library::{Difficulty, Level, LevelLibrary, LibraryError, Score};
+mod lint;
+pub use lint::LintIssue;
 mod map;
 pub use map::{CellKind, Map};
+mod move_limit;
+pub use move_limit::MoveLimit;
 mod movable;
 pub use movable::{Crate, Direction};
+mod replay;
+pub use replay::{Replay, ReplayParseError, Step};
+mod solver;
+pub use solver::{Hint, Solution, SolverError, SolverLimits};
+mod time_limit;
+pub use time_limit::TimeLimit;
+mod tutorial;
+pub use tutorial::{HintTrigger, Tutorial};
+mod xsb;
+pub use xsb::XsbParseError;
 
 /// Item maybe found on top of a cell.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -15,6 +32,7 @@ pub enum MovableItem<'a> {
     Crate(&'a Crate),
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct BoardElem<'a>(pub Option<MovableItem<'a>>, pub CellKind);
 
 /// The [`Board`] contains the [`Map`], the items ([crates](`Crate`) and the [player](`Player`)) on
@@ -26,9 +44,30 @@ pub struct Board {
     crates: Vec<Crate>,
     original_player: (u32, u32),
     original_crates: Vec<Crate>,
+    /// Number of steps done by the player since the start.
+    moves: u32,
+    /// Number of those steps which pushed a crate.
+    pushes: u32,
+    /// Steps done since the start.
+    history: Vec<Step>,
 }
 
 impl Board {
+    /// Board filled with [`CellKind::Void`], to be drawn with the editing methods (see
+    /// [`Board::set_cell`]).
+    pub fn empty(width: u32, height: u32) -> Self {
+        Board {
+            map: Map::new(width, height),
+            player: (0, 0),
+            crates: Vec::new(),
+            original_player: (0, 0),
+            original_crates: Vec::new(),
+            moves: 0,
+            pushes: 0,
+            history: Vec::new(),
+        }
+    }
+
     /*
     pub fn try_get(&self, i: u32, j: u32) -> Option<BoardElem> {
         if let Some(c) = self.map.try_get(i, j) {
@@ -45,7 +84,7 @@ impl Board {
     }
     */
 
-    pub fn get(&self, i: u32, j: u32) -> BoardElem {
+    pub fn get(&self, i: u32, j: u32) -> BoardElem<'_> {
         let c = self.map.get(i, j);
 
         if self.player == (i, j) {
@@ -64,7 +103,8 @@ impl Board {
     /// Returns:
     /// - `Some(true)` if it can move by pushing a crate,
     /// - `Some(false)` if it can move without pushing a crate,
-    /// - `None` if it can't move at all.
+    /// - `None` if it can't move at all, such as on the edge of the board where the cell in the
+    ///   way is its own.
     pub fn can_player_move(self: &Board, dir: Direction) -> Option<bool> {
         let (i, j) = dir.to_coords(self.player.0, self.player.1);
         match self.get(i, j) {
@@ -75,7 +115,7 @@ impl Board {
                     None
                 }
             }
-            BoardElem(Some(MovableItem::Player), _) => unreachable!(), // at least for now...
+            BoardElem(Some(MovableItem::Player), _) => None,
             BoardElem(None, c) => {
                 if c.is_crossable() {
                     Some(false)
@@ -118,12 +158,25 @@ impl Board {
             };
 
             self.player = (i, j);
+            self.moves += 1;
+            if c_opt.is_some() {
+                self.pushes += 1;
+            }
+            self.history.push(Step {
+                dir,
+                push: c_opt.is_some(),
+            });
             Some(c_opt)
         } else {
             None
         }
     }
 
+    /// Current position of the player.
+    pub fn player(&self) -> (u32, u32) {
+        self.player
+    }
+
     pub fn width(&self) -> u32 {
         self.map.width()
     }
@@ -136,9 +189,150 @@ impl Board {
         self.crates.iter().all(|c| c.is_placed(self))
     }
 
+    pub fn crate_count(&self) -> usize {
+        self.crates.len()
+    }
+
+    /// Number of crates currently on a target.
+    pub fn placed_crates(&self) -> usize {
+        self.crates.iter().filter(|c| c.is_placed(self)).count()
+    }
+
+    /// Number of steps done by the player since the start of the level.
+    pub fn moves(&self) -> u32 {
+        self.moves
+    }
+
+    /// Number of steps which pushed a crate since the start of the level.
+    pub fn pushes(&self) -> u32 {
+        self.pushes
+    }
+
+    /// Steps done since the start of the level, to be played again later.
+    pub fn replay(&self) -> Replay {
+        Replay::from(self.history.clone())
+    }
+
+    /// Last step done, if the player moved since the start of the level.
+    pub fn last_step(&self) -> Option<Step> {
+        self.history.last().copied()
+    }
+
+    /// Cancels the last step of the player, pulling back the crate it pushed if any.
+    ///
+    /// Returns the step which was undone, or `None` if the player didn't move since the start.
+    pub fn undo(&mut self) -> Option<Step> {
+        let step = self.history.pop()?;
+        let (i, j) = self.player;
+        let back = step.dir.opposite();
+
+        if step.push {
+            let crate_pos = step.dir.to_coords(i, j);
+            let c = self
+                .crates
+                .iter_mut()
+                .find(|c| c.pos() == crate_pos)
+                .expect(
+                    "A crate was pushed during this step, it should be in front of the player.",
+                );
+            c.do_move(back);
+            self.pushes -= 1;
+        }
+
+        self.player = back.to_coords(i, j);
+        self.moves -= 1;
+        Some(step)
+    }
+
     pub fn reset(&mut self) {
         self.player = self.original_player;
         self.crates = self.original_crates.clone();
+        self.moves = 0;
+        self.pushes = 0;
+        self.history.clear();
+    }
+
+    /// Changes the kind of a cell of the starting position, removing the crate on it if it can't
+    /// be crossed anymore.
+    ///
+    /// Like the other editing methods, it resets the board.
+    pub fn set_cell(&mut self, i: u32, j: u32, kind: CellKind) {
+        self.map.set(i, j, kind);
+        if !kind.is_crossable() {
+            self.original_crates.retain(|c| c.pos() != (i, j));
+        }
+        self.reset();
+    }
+
+    /// Moves the player of the starting position, replacing any crate there.
+    pub fn set_player(&mut self, i: u32, j: u32) {
+        self.original_player = (i, j);
+        self.original_crates.retain(|c| c.pos() != (i, j));
+        self.reset();
+    }
+
+    /// Adds a crate to the starting position, or removes it if there was already one.
+    /// Nothing is done on the player cell.
+    pub fn toggle_crate(&mut self, i: u32, j: u32) {
+        if self.original_player == (i, j) {
+            return;
+        }
+        if self.original_crates.iter().any(|c| c.pos() == (i, j)) {
+            self.original_crates.retain(|c| c.pos() != (i, j));
+        } else {
+            self.original_crates.push(Crate::new(i, j));
+        }
+        self.reset();
+    }
+
+    /// Copy of the starting position with `margin` empty cells around it, so the level can be
+    /// extended in an editor.
+    pub fn with_margin(&self, margin: u32) -> Board {
+        let mut res = Board::empty(self.width() + 2 * margin, self.height() + 2 * margin);
+        for j in 0..self.height() {
+            for i in 0..self.width() {
+                res.map.set(i + margin, j + margin, self.map.get(i, j));
+            }
+        }
+        let (pi, pj) = self.original_player;
+        res.original_player = (pi + margin, pj + margin);
+        res.original_crates = self
+            .original_crates
+            .iter()
+            .map(|c| {
+                let (i, j) = c.pos();
+                Crate::new(i + margin, j + margin)
+            })
+            .collect();
+        res.reset();
+        res
+    }
+
+    /// Lists the coordinates of the cells which look different between `self` and `previous`, so
+    /// a display can redraw only those.
+    ///
+    /// Returns `None` if both boards don't share the same [`Map`], in which case they can't be
+    /// compared cell by cell and everything should be redrawn.
+    pub fn diff(&self, previous: &Board) -> Option<Vec<(u32, u32)>> {
+        if self.map != previous.map {
+            return None;
+        }
+
+        let mut changed = Vec::new();
+
+        // Only cells holding a movable item in either board can have changed.
+        let candidates = [self.player, previous.player]
+            .into_iter()
+            .chain(self.crates.iter().map(Crate::pos))
+            .chain(previous.crates.iter().map(Crate::pos));
+
+        for (i, j) in candidates {
+            if !changed.contains(&(i, j)) && self.get(i, j) != previous.get(i, j) {
+                changed.push((i, j));
+            }
+        }
+
+        Some(changed)
     }
 }
 
@@ -150,6 +344,7 @@ pub enum LevelParseError {
     CantParseMap(<Map as FromStr>::Err),
     CantParsePlayerCoordinates(String),
     CantParseCrateCoordinates(String),
+    CantParseXsb(XsbParseError),
 }
 
 impl fmt::Display for LevelParseError {
@@ -165,6 +360,7 @@ impl fmt::Display for LevelParseError {
                 write!(f, "Can't parse player coordinates: {:?}", err)
             }
             CantParseCrateCoordinates(err) => write!(f, "Can't parse crate coordinates: {:?}", err),
+            CantParseXsb(err) => write!(f, "Can't parse XSB level: {}", err),
         }
     }
 }
@@ -174,7 +370,12 @@ impl Error for LevelParseError {}
 impl FromStr for Board {
     type Err = LevelParseError;
 
+    /// Levels in the XSB format are recognized by their player symbol (see [`Board::from_xsb`]).
     fn from_str(src: &str) -> Result<Self, Self::Err> {
+        if src.contains(['@', '+']) {
+            return Board::from_xsb(src).map_err(LevelParseError::CantParseXsb);
+        }
+
         // TODO: better format of map will only a map and reading player and crate space from
         // symbols only.
         // TODO: filter necessary?
@@ -232,6 +433,160 @@ impl FromStr for Board {
             original_crates: crates.clone(),
             crates,
             original_player: player,
+            moves: 0,
+            pushes: 0,
+            history: Vec::new(),
         })
     }
 }
+
+impl Board {
+    /// Writes the starting position in the format read by [`Board::from_str`] when there is no
+    /// player symbol: the map, then the coordinates of the player, then those of each crate.
+    pub fn to_legacy(&self) -> String {
+        let map = self.map.to_string();
+        let mut res = String::new();
+        for line in map.lines() {
+            // An empty line would end the map.
+            match line.trim_end() {
+                "" => res.push(' '),
+                line => res.push_str(line),
+            }
+            res.push('\n');
+        }
+
+        let (pi, pj) = self.original_player;
+        res.push_str(&format!("\n{},{}\n\n", pi, pj));
+        for c in self.original_crates.iter() {
+            let (i, j) = c.pos();
+            res.push_str(&format!("{},{}\n", i, j));
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Board, Direction};
+
+    const TEST_LEVEL_STR: &str = "#####
+#...#
+#.X.#
+#...#
+#####
+
+1,1
+
+2,1
+";
+
+    #[test]
+    fn it_finds_no_diff_with_itself() {
+        let board: Board = TEST_LEVEL_STR.parse().unwrap();
+        assert_eq!(board.diff(&board), Some(Vec::new()));
+    }
+
+    #[test]
+    fn it_diffs_player_and_pushed_crate() {
+        let previous: Board = TEST_LEVEL_STR.parse().unwrap();
+        let mut board = previous.clone();
+        board.do_move_player(Direction::Right);
+
+        let mut changed = board.diff(&previous).unwrap();
+        changed.sort();
+        assert_eq!(changed, vec![(1, 1), (2, 1), (3, 1)]);
+    }
+
+    #[test]
+    fn it_counts_moves_and_pushes() {
+        let mut board: Board = TEST_LEVEL_STR.parse().unwrap();
+        board.do_move_player(Direction::Down);
+        board.do_move_player(Direction::Up);
+        board.do_move_player(Direction::Right);
+        // Blocked by the wall:
+        board.do_move_player(Direction::Up);
+        assert_eq!((board.moves(), board.pushes()), (3, 1));
+        assert_eq!(board.replay().to_string(), "duR");
+
+        board.reset();
+        assert_eq!((board.moves(), board.pushes()), (0, 0));
+        assert!(board.replay().is_empty());
+    }
+
+    #[test]
+    fn it_counts_placed_crates() {
+        let mut board: Board = TEST_LEVEL_STR.parse().unwrap();
+        assert_eq!((board.placed_crates(), board.crate_count()), (0, 1));
+
+        board.toggle_crate(2, 2);
+        assert_eq!((board.placed_crates(), board.crate_count()), (1, 2));
+    }
+
+    #[test]
+    fn it_adds_a_margin() {
+        let mut board: Board = TEST_LEVEL_STR.parse().unwrap();
+        board.do_move_player(Direction::Down);
+
+        let extended = board.with_margin(2);
+        assert_eq!((extended.width(), extended.height()), (9, 9));
+        assert_eq!(extended.player(), (3, 3));
+        assert_eq!(extended.moves(), 0);
+        assert_eq!(extended.to_xsb(), board.to_xsb());
+    }
+
+    #[test]
+    fn it_keeps_the_player_on_the_edge() {
+        // The editors can put the player anywhere.
+        let mut board: Board = TEST_LEVEL_STR.parse().unwrap();
+        board.set_player(0, 0);
+        assert_eq!(board.do_move_player(Direction::Left), None);
+        assert_eq!(board.do_move_player(Direction::Up), None);
+        assert_eq!((board.player(), board.moves()), ((0, 0), 0));
+    }
+
+    #[test]
+    fn it_undoes_steps() {
+        let start: Board = TEST_LEVEL_STR.parse().unwrap();
+        let mut board = start.clone();
+        board.do_move_player(Direction::Down);
+        board.do_move_player(Direction::Up);
+        board.do_move_player(Direction::Right);
+
+        assert_eq!(board.last_step(), board.replay().steps().last().copied());
+        assert_eq!(
+            board.undo().map(|s| (s.dir, s.push)),
+            Some((Direction::Right, true))
+        );
+        assert_eq!(
+            board.undo().map(|s| (s.dir, s.push)),
+            Some((Direction::Up, false))
+        );
+        assert_eq!(
+            board.undo().map(|s| (s.dir, s.push)),
+            Some((Direction::Down, false))
+        );
+        assert_eq!(board.undo(), None);
+        assert_eq!(board.last_step(), None);
+        assert_eq!(board, start);
+    }
+
+    #[test]
+    fn it_writes_back_the_same_level() {
+        let mut board: Board = TEST_LEVEL_STR.parse().unwrap();
+        board.do_move_player(Direction::Right);
+        assert_eq!(board.to_legacy(), TEST_LEVEL_STR);
+
+        let board = Board::from_xsb("#####\n#@$.#\n#####\n").unwrap();
+        assert_eq!(
+            board.to_legacy().parse::<Board>().unwrap().to_xsb(),
+            board.to_xsb()
+        );
+    }
+
+    #[test]
+    fn it_cant_diff_different_maps() {
+        let board: Board = TEST_LEVEL_STR.parse().unwrap();
+        let other: Board = "###\n#.#\n###\n\n1,1\n\n1,1\n".parse().unwrap();
+        assert_eq!(board.diff(&other), None);
+    }
+}
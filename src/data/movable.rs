@@ -4,6 +4,11 @@ use super::{Board, BoardElem, CellKind};
 
 /// Direction a [`Movable`] can be moved.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    any(feature = "serve", feature = "remote"),
+    derive(serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
 pub enum Direction {
     Left,
     Right,
@@ -27,6 +32,17 @@ impl Direction {
 
         (res_i, res_j)
     }
+
+    pub fn opposite(self) -> Self {
+        use Direction::*;
+
+        match self {
+            Left => Right,
+            Right => Left,
+            Up => Down,
+            Down => Up,
+        }
+    }
 }
 
 /// Crate which can be pushed unless there is an *uncrossable* cell (see [`CellKind::is_crossable`]) or another crate in the way.
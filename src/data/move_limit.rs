@@ -0,0 +1,100 @@
+//! Rules of the move-limit challenge, where an attempt fails as soon as it takes more moves or
+//! pushes than the par of the level.
+
+use std::time::Duration;
+
+use super::{Board, Level, SolverLimits};
+
+/// Bounds to the search of a par for a level never solved before, short enough not to be
+/// noticed when it starts.
+const PAR_SOLVER_LIMITS: SolverLimits = SolverLimits {
+    max_states: 200_000,
+    max_time: Some(Duration::from_secs(2)),
+};
+
+/// Moves and pushes allowed to win a level.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MoveLimit {
+    pub moves: u32,
+    pub pushes: u32,
+}
+
+impl MoveLimit {
+    /// Par of the `level`: the moves and pushes of its best score if it has one, or else those
+    /// of the solution found by the solver.
+    ///
+    /// Returns `None` if the level was never solved and the solver didn't find a solution
+    /// quickly, as there is nothing to compare to.
+    pub fn par(level: &Level) -> Option<Self> {
+        if let Some(best) = level.best() {
+            return Some(MoveLimit {
+                moves: best.moves,
+                pushes: best.pushes,
+            });
+        }
+        let solution = level.board().solve(&PAR_SOLVER_LIMITS).ok()?;
+        Some(MoveLimit {
+            moves: solution.replay.len() as u32,
+            pushes: solution.replay.pushes() as u32,
+        })
+    }
+
+    /// Moves left before the `board` exceeds the limit.
+    pub fn moves_left(&self, board: &Board) -> u32 {
+        self.moves.saturating_sub(board.moves())
+    }
+
+    /// Pushes left before the `board` exceeds the limit.
+    pub fn pushes_left(&self, board: &Board) -> u32 {
+        self.pushes.saturating_sub(board.pushes())
+    }
+
+    /// If the `board` took more moves or pushes than allowed, so the attempt failed.
+    pub fn is_exceeded(&self, board: &Board) -> bool {
+        board.moves() > self.moves || board.pushes() > self.pushes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::MoveLimit;
+    use crate::data::{Direction, Level, LevelLibrary, Replay, Score};
+
+    #[test]
+    fn it_fails_once_the_par_is_exceeded() {
+        let level = Level::new(
+            "one".to_string(),
+            "#######\n#@    #\n#  $ .#\n#######\n".to_string(),
+        )
+        .unwrap();
+        let limit = MoveLimit::par(&level).unwrap();
+        assert_eq!((limit.moves, limit.pushes), (4, 2));
+
+        let mut board = level.board().clone();
+        board.do_move_player(Direction::Right);
+        board.do_move_player(Direction::Left);
+        assert_eq!(
+            (limit.moves_left(&board), limit.pushes_left(&board)),
+            (2, 2)
+        );
+        board.do_move_player(Direction::Down);
+        board.do_move_player(Direction::Right);
+        assert!(!limit.is_exceeded(&board));
+        board.do_move_player(Direction::Right);
+        assert!(limit.is_exceeded(&board));
+        assert_eq!(limit.moves_left(&board), 0);
+
+        // The best score known gives the par.
+        let mut library = LevelLibrary::from_level(level);
+        let score = Score {
+            moves: 6,
+            pushes: 2,
+            time: Duration::from_secs(5),
+        };
+        library.set_solved(0, score, Replay::default()).unwrap();
+        let limit = MoveLimit::par(library.get(0).unwrap()).unwrap();
+        assert_eq!((limit.moves, limit.pushes), (6, 2));
+    }
+}
@@ -0,0 +1,143 @@
+//! Recorded sequences of moves, written in the usual LURD notation: one letter per step
+//! (`l`eft, `u`p, `r`ight, `d`own), in upper case when the step pushes a crate.
+
+use std::{error::Error, fmt, str::FromStr};
+
+use super::Direction;
+
+/// A step of the player.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Step {
+    pub dir: Direction,
+    /// If a crate was pushed during this step.
+    pub push: bool,
+}
+
+impl Step {
+    fn to_char(self) -> char {
+        use Direction::*;
+        let c = match self.dir {
+            Left => 'l',
+            Up => 'u',
+            Right => 'r',
+            Down => 'd',
+        };
+        if self.push {
+            c.to_ascii_uppercase()
+        } else {
+            c
+        }
+    }
+}
+
+impl TryFrom<char> for Step {
+    type Error = ReplayParseError;
+
+    fn try_from(src: char) -> Result<Self, Self::Error> {
+        use Direction::*;
+        let dir = match src.to_ascii_lowercase() {
+            'l' => Left,
+            'u' => Up,
+            'r' => Right,
+            'd' => Down,
+            _ => return Err(ReplayParseError::UnknownSymbol(src)),
+        };
+        Ok(Step {
+            dir,
+            push: src.is_ascii_uppercase(),
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReplayParseError {
+    UnknownSymbol(char),
+}
+
+impl fmt::Display for ReplayParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use ReplayParseError::*;
+        match self {
+            UnknownSymbol(c) => write!(f, "Unknown move symbol `{}`.", c),
+        }
+    }
+}
+
+impl Error for ReplayParseError {}
+
+/// Moves done by the player from the start of a level.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Replay {
+    steps: Vec<Step>,
+}
+
+impl Replay {
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Number of steps pushing a crate.
+    pub fn pushes(&self) -> usize {
+        self.steps.iter().filter(|s| s.push).count()
+    }
+}
+
+impl From<Vec<Step>> for Replay {
+    fn from(steps: Vec<Step>) -> Self {
+        Replay { steps }
+    }
+}
+
+impl fmt::Display for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for step in self.steps.iter() {
+            write!(f, "{}", step.to_char())?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Replay {
+    type Err = ReplayParseError;
+
+    /// Whitespaces are ignored, so the moves can be split on several lines.
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        let steps = src
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(Step::try_from)
+            .collect::<Result<_, _>>()?;
+        Ok(Replay { steps })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Replay, ReplayParseError};
+
+    #[test]
+    fn it_parses_and_displays_lurd() {
+        let replay: Replay = "ruRR\ndL".parse().unwrap();
+        assert_eq!(replay.len(), 6);
+        assert_eq!(replay.pushes(), 3);
+        assert!(replay.steps()[2].push);
+        assert!(!replay.steps()[4].push);
+        assert_eq!(replay.to_string(), "ruRRdL");
+    }
+
+    #[test]
+    fn it_rejects_unknown_symbols() {
+        assert_eq!(
+            "rux".parse::<Replay>(),
+            Err(ReplayParseError::UnknownSymbol('x'))
+        );
+    }
+}
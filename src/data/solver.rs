@@ -0,0 +1,473 @@
+//! Automatic resolution of a level, to give hints to the player.
+//!
+//! The search is a breadth-first search on the crate pushes, so the solutions found use the
+//! least possible number of pushes (but not necessarily of moves).
+
+use std::{
+    collections::{HashSet, VecDeque},
+    error::Error,
+    fmt,
+    time::{Duration, Instant},
+};
+
+use super::{Board, CellKind, Direction, Replay, Step};
+
+/// Positions explored between two checks of [`SolverLimits::max_time`].
+const TIME_CHECK_INTERVAL: usize = 1024;
+
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Left,
+    Direction::Right,
+    Direction::Up,
+    Direction::Down,
+];
+
+/// Bounds to the search, as some levels are too hard to be solved in a reasonable time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SolverLimits {
+    /// Maximum number of positions to explore before giving up.
+    pub max_states: usize,
+    /// Longest time to search, if any.
+    pub max_time: Option<Duration>,
+}
+
+impl Default for SolverLimits {
+    fn default() -> Self {
+        SolverLimits {
+            max_states: 200_000,
+            max_time: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SolverError {
+    /// All the possibilities were tried.
+    Unsolvable { explored_states: usize },
+    /// [`SolverLimits::max_states`] were explored without finding a solution.
+    LimitReached { explored_states: usize },
+    /// No solution was found within [`SolverLimits::max_time`].
+    TimeOut { explored_states: usize },
+}
+
+impl fmt::Display for SolverError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use SolverError::*;
+        match self {
+            Unsolvable { explored_states } => write!(
+                f,
+                "The level can't be solved ({} positions explored).",
+                explored_states
+            ),
+            LimitReached { explored_states } => {
+                write!(f, "No solution found within {} positions.", explored_states)
+            }
+            TimeOut { explored_states } => write!(
+                f,
+                "No solution found in the time given ({} positions explored).",
+                explored_states
+            ),
+        }
+    }
+}
+
+impl Error for SolverError {}
+
+/// Solution found by [`Board::solve`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Solution {
+    /// Moves to do from the board position.
+    pub replay: Replay,
+    /// Number of positions explored to find it.
+    pub explored_states: usize,
+}
+
+/// Next thing to do to get closer to the solution, see [`Board::hint`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hint {
+    /// Cells the player should walk through, in order, before pushing.
+    pub path: Vec<(u32, u32)>,
+    /// Position of the crate to push.
+    pub crate_pos: (u32, u32),
+    /// Where to push it.
+    pub push: Direction,
+}
+
+/// Static part of the board, with cells as indices in a `width * height` grid.
+struct Grid {
+    width: u32,
+    height: u32,
+    crossable: Vec<bool>,
+    targets: Vec<bool>,
+    /// Cells from which a crate can still reach a target.
+    live: Vec<bool>,
+}
+
+impl Grid {
+    fn new(board: &Board) -> Self {
+        let (width, height) = (board.width(), board.height());
+        let cells = (0..height).flat_map(|j| (0..width).map(move |i| (i, j)));
+
+        let crossable: Vec<bool> = cells
+            .clone()
+            .map(|(i, j)| board.map.get(i, j).is_crossable())
+            .collect();
+        let targets: Vec<bool> = cells
+            .map(|(i, j)| board.map.get(i, j) == CellKind::Target)
+            .collect();
+
+        let mut grid = Grid {
+            width,
+            height,
+            live: vec![false; crossable.len()],
+            crossable,
+            targets,
+        };
+        grid.live = grid.live_cells();
+        grid
+    }
+
+    fn index(&self, (i, j): (u32, u32)) -> usize {
+        (j * self.width + i) as usize
+    }
+
+    fn coords(&self, index: usize) -> (u32, u32) {
+        let index = index as u32;
+        (index % self.width, index / self.width)
+    }
+
+    /// Neighbour cell in the given direction, if it is in the grid and crossable.
+    fn step(&self, index: usize, dir: Direction) -> Option<usize> {
+        let (i, j) = self.coords(index);
+        let (ni, nj) = dir.to_coords(i, j);
+        if (ni, nj) == (i, j) || ni >= self.width || nj >= self.height {
+            return None;
+        }
+        let next = self.index((ni, nj));
+        self.crossable[next].then_some(next)
+    }
+
+    /// A cell is live if a crate alone on it could be pushed to a target, which is found by
+    /// pulling crates away from the targets.
+    fn live_cells(&self) -> Vec<bool> {
+        let mut live = self.targets.clone();
+        let mut queue: VecDeque<usize> = (0..live.len()).filter(|c| live[*c]).collect();
+
+        while let Some(cell) = queue.pop_front() {
+            for dir in DIRECTIONS {
+                // The crate comes from `from`, pushed by a player standing at `player`.
+                let Some(from) = self.step(cell, dir.opposite()) else {
+                    continue;
+                };
+                if !live[from] && self.step(from, dir.opposite()).is_some() {
+                    live[from] = true;
+                    queue.push_back(from);
+                }
+            }
+        }
+
+        live
+    }
+
+    /// Cells reachable by the player from `start` without pushing crates, with the cell they
+    /// were reached from.
+    fn reachable(&self, start: usize, crates: &[usize]) -> Vec<Option<usize>> {
+        let mut from = vec![None; self.crossable.len()];
+        from[start] = Some(start);
+        let mut queue = VecDeque::from([start]);
+
+        while let Some(cell) = queue.pop_front() {
+            for dir in DIRECTIONS {
+                if let Some(next) = self.step(cell, dir) {
+                    if from[next].is_none() && !crates.contains(&next) {
+                        from[next] = Some(cell);
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+
+        from
+    }
+
+    /// Walk from `start` to `end` as found by [`Grid::reachable`].
+    fn path(&self, reachable: &[Option<usize>], start: usize, end: usize) -> Vec<usize> {
+        let mut path = vec![end];
+        let mut cell = end;
+        while cell != start {
+            cell = reachable[cell].expect("The path should only go through reachable cells.");
+            path.push(cell);
+        }
+        path.pop();
+        path.reverse();
+        path
+    }
+
+    fn direction(&self, from: usize, to: usize) -> Direction {
+        DIRECTIONS
+            .into_iter()
+            .find(|d| self.step(from, *d) == Some(to))
+            .expect("Cells of a path should be neighbours.")
+    }
+}
+
+/// Position explored by the search.
+struct Node {
+    /// Sorted positions of the crates.
+    crates: Vec<usize>,
+    player: usize,
+    /// Node this one was reached from, with the crate pushed and its direction.
+    parent: Option<(usize, usize, Direction)>,
+}
+
+impl Board {
+    /// Searches the moves to win from the current position.
+    pub fn solve(&self, limits: &SolverLimits) -> Result<Solution, SolverError> {
+        let grid = Grid::new(self);
+
+        let mut crates: Vec<usize> = self.crates.iter().map(|c| grid.index(c.pos())).collect();
+        crates.sort_unstable();
+
+        let mut nodes = vec![Node {
+            crates,
+            player: grid.index(self.player),
+            parent: None,
+        }];
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::from([0]);
+        // The clock is only read if needed, as it isn't available on every platform.
+        let deadline = limits.max_time.map(|t| Instant::now() + t);
+
+        while let Some(current) = queue.pop_front() {
+            let node = &nodes[current];
+            let reachable = grid.reachable(node.player, &node.crates);
+
+            // Identifies positions only by the area the player can reach, to avoid exploring
+            // the same pushes from different spots.
+            let area = reachable
+                .iter()
+                .position(Option::is_some)
+                .expect("The player can at least reach its own cell.");
+            if !visited.insert((node.crates.clone(), area)) {
+                continue;
+            }
+
+            if node.crates.iter().all(|c| grid.targets[*c]) {
+                return Ok(Solution {
+                    replay: self.replay_to(&grid, &nodes, current),
+                    explored_states: visited.len(),
+                });
+            }
+            if visited.len() >= limits.max_states {
+                return Err(SolverError::LimitReached {
+                    explored_states: visited.len(),
+                });
+            }
+            if (visited.len() - 1) % TIME_CHECK_INTERVAL == 0
+                && deadline.is_some_and(|d| Instant::now() >= d)
+            {
+                return Err(SolverError::TimeOut {
+                    explored_states: visited.len(),
+                });
+            }
+
+            let mut children = Vec::new();
+            for (n, crate_cell) in node.crates.iter().enumerate() {
+                for dir in DIRECTIONS {
+                    let Some(stand) = grid.step(*crate_cell, dir.opposite()) else {
+                        continue;
+                    };
+                    let Some(dest) = grid.step(*crate_cell, dir) else {
+                        continue;
+                    };
+                    if reachable[stand].is_none() || !grid.live[dest] || node.crates.contains(&dest)
+                    {
+                        continue;
+                    }
+
+                    let mut crates = node.crates.clone();
+                    crates[n] = dest;
+                    crates.sort_unstable();
+
+                    children.push(Node {
+                        crates,
+                        player: *crate_cell,
+                        parent: Some((current, *crate_cell, dir)),
+                    });
+                }
+            }
+            for child in children {
+                queue.push_back(nodes.len());
+                nodes.push(child);
+            }
+        }
+
+        Err(SolverError::Unsolvable {
+            explored_states: visited.len(),
+        })
+    }
+
+    /// First push of the solution, and how to walk to it.
+    pub fn hint(&self, limits: &SolverLimits) -> Result<Option<Hint>, SolverError> {
+        let solution = self.solve(limits)?;
+
+        let mut path = Vec::new();
+        let mut pos = self.player;
+        for step in solution.replay.steps() {
+            if step.push {
+                return Ok(Some(Hint {
+                    path,
+                    crate_pos: step.dir.to_coords(pos.0, pos.1),
+                    push: step.dir,
+                }));
+            }
+            pos = step.dir.to_coords(pos.0, pos.1);
+            path.push(pos);
+        }
+
+        // Already won.
+        Ok(None)
+    }
+
+    /// Shortest walk of the player to the cell `(i, j)` without pushing any crate, or `None` if
+    /// it can't be reached.
+    pub fn path_to(&self, i: u32, j: u32) -> Option<Vec<Direction>> {
+        if i >= self.width() || j >= self.height() {
+            return None;
+        }
+
+        let grid = Grid::new(self);
+        let crates: Vec<usize> = self.crates.iter().map(|c| grid.index(c.pos())).collect();
+        let (start, end) = (grid.index(self.player), grid.index((i, j)));
+
+        let reachable = grid.reachable(start, &crates);
+        reachable[end]?;
+
+        let mut from = start;
+        let path = grid
+            .path(&reachable, start, end)
+            .into_iter()
+            .map(|cell| {
+                let dir = grid.direction(from, cell);
+                from = cell;
+                dir
+            })
+            .collect();
+        Some(path)
+    }
+
+    /// Rebuilds all the moves leading to the node `end`.
+    fn replay_to(&self, grid: &Grid, nodes: &[Node], end: usize) -> Replay {
+        let mut pushes = Vec::new();
+        let mut current = end;
+        while let Some((parent, crate_cell, dir)) = nodes[current].parent {
+            pushes.push((parent, crate_cell, dir));
+            current = parent;
+        }
+        pushes.reverse();
+
+        let mut steps = Vec::new();
+        let mut player = grid.index(self.player);
+        for (parent, crate_cell, dir) in pushes {
+            let crates = &nodes[parent].crates;
+            let stand = grid
+                .step(crate_cell, dir.opposite())
+                .expect("The player pushed from there.");
+
+            let reachable = grid.reachable(player, crates);
+            let mut from = player;
+            for cell in grid.path(&reachable, player, stand) {
+                steps.push(Step {
+                    dir: grid.direction(from, cell),
+                    push: false,
+                });
+                from = cell;
+            }
+            steps.push(Step { dir, push: true });
+            player = crate_cell;
+        }
+
+        Replay::from(steps)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Board, SolverError, SolverLimits};
+
+    const TEST_LEVEL_STR: &str = "#######
+#.....#
+#.X...#
+#.....#
+#######
+
+1,1
+
+3,2
+";
+
+    #[test]
+    fn it_solves_a_level() {
+        let board: Board = TEST_LEVEL_STR.parse().unwrap();
+        let solution = board.solve(&SolverLimits::default()).unwrap();
+
+        let mut played = board.clone();
+        for step in solution.replay.steps() {
+            assert_eq!(
+                played.do_move_player(step.dir).map(|c| c.is_some()),
+                Some(step.push)
+            );
+        }
+        assert!(played.has_won());
+        assert_eq!(played.pushes(), 1);
+    }
+
+    #[test]
+    fn it_gives_a_hint() {
+        let board: Board = TEST_LEVEL_STR.parse().unwrap();
+        let hint = board.hint(&SolverLimits::default()).unwrap().unwrap();
+
+        assert_eq!(hint.crate_pos, (3, 2));
+        assert_eq!(hint.push, super::Direction::Left);
+        assert_eq!(hint.path.last(), Some(&(4, 2)));
+    }
+
+    #[test]
+    fn it_finds_walking_paths() {
+        use super::Direction::*;
+
+        let board: Board = TEST_LEVEL_STR.parse().unwrap();
+        assert_eq!(board.path_to(1, 1), Some(vec![]));
+        assert_eq!(board.path_to(1, 3), Some(vec![Down, Down]));
+        // Walls and crates can't be walked on.
+        assert_eq!(board.path_to(0, 0), None);
+        assert_eq!(board.path_to(3, 2), None);
+    }
+
+    #[test]
+    fn it_detects_unsolvable_levels() {
+        // The crate is stuck in a corner.
+        let board: Board = "#####\n#..X#\n#...#\n#####\n\n2,1\n\n1,1\n"
+            .parse()
+            .unwrap();
+        assert!(matches!(
+            board.solve(&SolverLimits::default()),
+            Err(SolverError::Unsolvable { .. })
+        ));
+    }
+
+    #[test]
+    fn it_stops_searching_in_time() {
+        let board: Board = TEST_LEVEL_STR.parse().unwrap();
+        let limits = SolverLimits {
+            max_time: Some(Duration::ZERO),
+            ..SolverLimits::default()
+        };
+        assert_eq!(
+            board.solve(&limits),
+            Err(SolverError::TimeOut { explored_states: 1 })
+        );
+    }
+}
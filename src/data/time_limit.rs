@@ -0,0 +1,85 @@
+//! Rules of the time-attack mode, where a level is lost if it isn't won before its par time runs
+//! out.
+
+use std::time::Duration;
+
+use super::Level;
+
+/// Par time of a level never solved before, whatever its size.
+const BASE_PAR_TIME: Duration = Duration::from_secs(30);
+/// Time added to the par of a level never solved before for each of its crates.
+const PAR_TIME_PER_CRATE: Duration = Duration::from_secs(30);
+
+/// Time given to win a level, counted down from its start.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeLimit {
+    par: Duration,
+}
+
+impl TimeLimit {
+    pub fn new(par: Duration) -> Self {
+        TimeLimit { par }
+    }
+
+    /// Par time of the `level`: half more than the time of its best score if it has one, or
+    /// else a time estimated from the number of its crates.
+    pub fn par(level: &Level) -> Self {
+        let par = match level.best() {
+            Some(best) => best.time + best.time / 2,
+            None => BASE_PAR_TIME + PAR_TIME_PER_CRATE * level.board().crate_count() as u32,
+        };
+        TimeLimit::new(par)
+    }
+
+    pub fn par_time(&self) -> Duration {
+        self.par
+    }
+
+    /// Time left once `elapsed` since the start of the level, zero once it ran out.
+    pub fn remaining(&self, elapsed: Duration) -> Duration {
+        self.par.saturating_sub(elapsed)
+    }
+
+    /// If the time ran out once `elapsed` since the start of the level, so it is lost unless
+    /// it was won before.
+    pub fn is_over(&self, elapsed: Duration) -> bool {
+        elapsed >= self.par
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::TimeLimit;
+    use crate::data::{Level, LevelLibrary, Replay, Score};
+
+    #[test]
+    fn it_counts_down_from_the_par_time() {
+        let level = Level::new(
+            "two".to_string(),
+            "######\n#@$$.#\n#  . #\n######\n".to_string(),
+        )
+        .unwrap();
+        let limit = TimeLimit::par(&level);
+        assert_eq!(limit.par_time(), Duration::from_secs(90));
+        assert_eq!(
+            limit.remaining(Duration::from_secs(60)),
+            Duration::from_secs(30)
+        );
+        assert!(!limit.is_over(Duration::from_secs(89)));
+        assert!(limit.is_over(Duration::from_secs(90)));
+        assert_eq!(limit.remaining(Duration::from_secs(100)), Duration::ZERO);
+
+        // The best time known gives the par.
+        let mut library = LevelLibrary::from_level(level);
+        let score = Score {
+            moves: 12,
+            pushes: 3,
+            time: Duration::from_secs(20),
+        };
+        library.set_solved(0, score, Replay::default()).unwrap();
+        let limit = TimeLimit::par(library.get(0).unwrap());
+        assert_eq!(limit.par_time(), Duration::from_secs(30));
+    }
+}
@@ -0,0 +1,136 @@
+//! Scripted hints of the tutorial levels, each shown the first time something happens while
+//! playing the level.
+//!
+//! They are written in the metadata lines following the level in its file, such as:
+//!
+//! ```text
+//! Hint-Start: Walk with the arrow keys.
+//! Hint-Crate: Push the crates onto the . squares.
+//! ```
+
+use super::{Board, Level};
+
+/// Start of the keys of the metadata lines giving a hint, followed by its trigger.
+const HINT_KEY_PREFIX: &str = "hint-";
+
+/// What shows a hint the first time it happens.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HintTrigger {
+    /// The level starts, with the key `Hint-Start`.
+    Start,
+    /// The player walks next to a crate, with the key `Hint-Crate`.
+    Crate,
+    /// The player pushes a crate, with the key `Hint-Push`.
+    Push,
+    /// A crate is pushed onto a target, with the key `Hint-Placed`.
+    Placed,
+}
+
+impl HintTrigger {
+    fn from_key(key: &str) -> Option<Self> {
+        use HintTrigger::*;
+        match key.to_lowercase().strip_prefix(HINT_KEY_PREFIX)? {
+            "start" => Some(Start),
+            "crate" => Some(Crate),
+            "push" => Some(Push),
+            "placed" => Some(Placed),
+            _ => None,
+        }
+    }
+
+    /// If it happened on the `board`, which had `placed` crates on targets at the start.
+    fn happened(self, board: &Board, placed: usize) -> bool {
+        use HintTrigger::*;
+        match self {
+            Start => true,
+            Crate => {
+                let (i, j) = board.player();
+                board.crates.iter().any(|c| {
+                    let (ci, cj) = c.pos();
+                    i.abs_diff(ci) + j.abs_diff(cj) == 1
+                })
+            }
+            Push => board.pushes() > 0,
+            Placed => board.placed_crates() > placed,
+        }
+    }
+}
+
+/// Hints of a level, remembering which ones were already shown during the attempt.
+#[derive(Clone, Debug, Default)]
+pub struct Tutorial {
+    /// Each hint with its trigger, and if it was shown.
+    hints: Vec<(HintTrigger, String, bool)>,
+    /// Crates on a target at the start of the level.
+    placed: usize,
+}
+
+impl Tutorial {
+    /// Reads the hints in the metadata lines of the `level`, the lines with other keys being
+    /// ignored. A level without any hint has an empty tutorial.
+    pub fn new(level: &Level) -> Self {
+        let hints = level
+            .source()
+            .lines()
+            .filter_map(|line| {
+                let (key, message) = line.split_once(':')?;
+                let trigger = HintTrigger::from_key(key.trim())?;
+                Some((trigger, message.trim().to_string(), false))
+            })
+            .filter(|(_, message, _)| !message.is_empty())
+            .collect();
+        Tutorial {
+            hints,
+            placed: level.board().placed_crates(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hints.is_empty()
+    }
+
+    /// Messages of the hints triggered for the first time by the `board`, in the order of the
+    /// file.
+    pub fn update(&mut self, board: &Board) -> Vec<&str> {
+        let placed = self.placed;
+        self.hints
+            .iter_mut()
+            .filter(|(trigger, _, shown)| !shown && trigger.happened(board, placed))
+            .map(|(_, message, shown)| {
+                *shown = true;
+                &message[..]
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Tutorial;
+    use crate::data::{Direction, Level};
+
+    #[test]
+    fn it_shows_each_hint_once() {
+        let level = Level::new(
+            "tutorial".to_string(),
+            "#######\n#@ $ .#\n#######\n\nTitle: Tutorial\nHint-Start: Walk around.\n\
+             hint-push: Well pushed!\nHint-Crate: Push it to the right.\nHint-Jump: Unknown.\n"
+                .to_string(),
+        )
+        .unwrap();
+        let mut tutorial = Tutorial::new(&level);
+        let mut board = level.board().clone();
+        assert_eq!(tutorial.update(&board), ["Walk around."]);
+        assert!(tutorial.update(&board).is_empty());
+
+        board.do_move_player(Direction::Right);
+        assert_eq!(tutorial.update(&board), ["Push it to the right."]);
+        board.do_move_player(Direction::Right);
+        assert_eq!(tutorial.update(&board), ["Well pushed!"]);
+        board.do_move_player(Direction::Right);
+        assert!(tutorial.update(&board).is_empty());
+
+        let plain = Level::new("plain".to_string(), "#####\n#@$.#\n#####\n".to_string()).unwrap();
+        assert!(Tutorial::new(&plain).is_empty());
+    }
+}
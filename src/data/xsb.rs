@@ -0,0 +1,225 @@
+//! The XSB format, used by most Sokoban games to share levels: the whole level is drawn with one
+//! character per cell, the player and the crates included.
+
+use std::{collections::VecDeque, error::Error, fmt};
+
+use super::{Board, BoardElem, CellKind, Crate, MovableItem};
+
+const XSB_WALL: char = '#';
+const XSB_FLOOR: char = ' ';
+const XSB_TARGET: char = '.';
+const XSB_PLAYER: char = '@';
+const XSB_PLAYER_ON_TARGET: char = '+';
+const XSB_CRATE: char = '$';
+const XSB_PLACED_CRATE: char = '*';
+/// Other symbols sometimes used for floors, to avoid trailing spaces.
+const XSB_FLOOR_ALIASES: [char; 2] = ['-', '_'];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum XsbParseError {
+    Empty,
+    UnknownSymbol(char),
+    NoPlayer,
+    SeveralPlayers,
+}
+
+impl fmt::Display for XsbParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use XsbParseError::*;
+        match self {
+            Empty => write!(f, "The level is empty."),
+            UnknownSymbol(c) => write!(f, "Unknown XSB symbol `{}`.", c),
+            NoPlayer => write!(f, "The level has no player."),
+            SeveralPlayers => write!(f, "The level has several players."),
+        }
+    }
+}
+
+impl Error for XsbParseError {}
+
+impl Board {
+    /// Parses a level in the XSB format, which ends at the first empty line.
+    ///
+    /// As spaces are used both outside of the level and for its floor, the floor is only
+    /// what the player can reach without crossing a wall, the rest being [`CellKind::Void`].
+    pub fn from_xsb(src: &str) -> Result<Board, XsbParseError> {
+        let lines: Vec<&str> = src
+            .lines()
+            .map(str::trim_end)
+            .take_while(|l| !l.is_empty())
+            .collect();
+
+        let height = u32::try_from(lines.len()).expect("Height should fit in u32");
+        let width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+        let width = u32::try_from(width).expect("Width should fit in u32");
+        if width == 0 || height == 0 {
+            return Err(XsbParseError::Empty);
+        }
+
+        let mut board = Board::empty(width, height);
+        let mut player = None;
+        for (j, l) in (0..).zip(lines.iter()) {
+            for (i, c) in (0..).zip(l.chars()) {
+                let kind = match c {
+                    XSB_WALL => CellKind::Wall,
+                    XSB_TARGET | XSB_PLAYER_ON_TARGET | XSB_PLACED_CRATE => CellKind::Target,
+                    XSB_FLOOR | XSB_PLAYER | XSB_CRATE => CellKind::Void,
+                    c if XSB_FLOOR_ALIASES.contains(&c) => CellKind::Void,
+                    c => return Err(XsbParseError::UnknownSymbol(c)),
+                };
+                board.map.set(i, j, kind);
+
+                if let XSB_PLAYER | XSB_PLAYER_ON_TARGET = c {
+                    if player.replace((i, j)).is_some() {
+                        return Err(XsbParseError::SeveralPlayers);
+                    }
+                }
+                if let XSB_CRATE | XSB_PLACED_CRATE = c {
+                    board.original_crates.push(Crate::new(i, j));
+                }
+            }
+        }
+        let player = player.ok_or(XsbParseError::NoPlayer)?;
+        board.original_player = player;
+
+        // Every cell which isn't a wall and which the player can reach is a floor.
+        let mut visited = vec![false; lines.len() * width as usize];
+        let mut queue = VecDeque::from([player]);
+        while let Some((i, j)) = queue.pop_front() {
+            let index = (j * width + i) as usize;
+            if visited[index] {
+                continue;
+            }
+            visited[index] = true;
+
+            if board.map.get(i, j) == CellKind::Void {
+                board.map.set(i, j, CellKind::Floor);
+            }
+            let neighbours = [
+                (i.wrapping_sub(1), j),
+                (i + 1, j),
+                (i, j.wrapping_sub(1)),
+                (i, j + 1),
+            ];
+            for (ni, nj) in neighbours {
+                if board
+                    .map
+                    .try_get(ni, nj)
+                    .is_some_and(|c| c != CellKind::Wall)
+                {
+                    queue.push_back((ni, nj));
+                }
+            }
+        }
+        // The crates can't be outside.
+        for c in board.original_crates.iter() {
+            let (i, j) = c.pos();
+            if board.map.get(i, j) == CellKind::Void {
+                board.map.set(i, j, CellKind::Floor);
+            }
+        }
+
+        board.reset();
+        Ok(board)
+    }
+
+    /// Writes the starting position in the XSB format, leaving out the empty rows and columns
+    /// around the level.
+    pub fn to_xsb(&self) -> String {
+        let mut start = self.clone();
+        start.reset();
+        start.position_to_xsb()
+    }
+
+    /// Writes the current position in the XSB format, like [`Board::to_xsb`].
+    pub fn position_to_xsb(&self) -> String {
+        let is_used = |i, j| !matches!(self.get(i, j), BoardElem(None, CellKind::Void));
+        let cols: Vec<u32> = (0..self.width())
+            .filter(|&i| (0..self.height()).any(|j| is_used(i, j)))
+            .collect();
+        let rows: Vec<u32> = (0..self.height())
+            .filter(|&j| (0..self.width()).any(|i| is_used(i, j)))
+            .collect();
+        let (Some(&first_col), Some(&last_col)) = (cols.first(), cols.last()) else {
+            return String::new();
+        };
+
+        let mut xsb = String::new();
+        for &j in rows.iter() {
+            let line: String = (first_col..=last_col)
+                .map(|i| match self.get(i, j) {
+                    BoardElem(Some(MovableItem::Player), CellKind::Target) => XSB_PLAYER_ON_TARGET,
+                    BoardElem(Some(MovableItem::Player), _) => XSB_PLAYER,
+                    BoardElem(Some(MovableItem::Crate(_)), CellKind::Target) => XSB_PLACED_CRATE,
+                    BoardElem(Some(MovableItem::Crate(_)), _) => XSB_CRATE,
+                    BoardElem(None, CellKind::Wall) => XSB_WALL,
+                    BoardElem(None, CellKind::Target) => XSB_TARGET,
+                    BoardElem(None, CellKind::Floor | CellKind::Void) => XSB_FLOOR,
+                })
+                .collect();
+            xsb.push_str(line.trim_end());
+            xsb.push('\n');
+        }
+        xsb
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::Direction, Board, BoardElem, CellKind, MovableItem, XsbParseError};
+
+    const TEST_XSB: &str = "  #####
+###   #
+#.@$  #
+### $.#
+#.##$ #
+# # . ##
+#$ *$$.#
+#   .  #
+########
+";
+
+    #[test]
+    fn it_parses_xsb() {
+        let board = Board::from_xsb(TEST_XSB).unwrap();
+        assert_eq!((board.width(), board.height()), (8, 9));
+        assert_eq!(
+            board.get(2, 2),
+            BoardElem(Some(MovableItem::Player), CellKind::Floor)
+        );
+        assert_eq!(board.get(1, 2), BoardElem(None, CellKind::Target));
+        // Outside of the walls:
+        assert_eq!(board.get(0, 0), BoardElem(None, CellKind::Void));
+        assert_eq!(board.crates.len(), 7);
+    }
+
+    #[test]
+    fn it_writes_back_the_same_xsb() {
+        let board = Board::from_xsb(TEST_XSB).unwrap();
+        assert_eq!(board.to_xsb(), TEST_XSB);
+    }
+
+    #[test]
+    fn it_writes_the_current_position() {
+        let mut board = Board::from_xsb("#####\n#@$.#\n#####\n").unwrap();
+        board.do_move_player(Direction::Right);
+        assert_eq!(board.position_to_xsb(), "#####\n# @*#\n#####\n");
+        assert_eq!(board.to_xsb(), "#####\n#@$.#\n#####\n");
+    }
+
+    #[test]
+    fn it_rejects_levels_without_one_player() {
+        assert_eq!(
+            Board::from_xsb("#####\n# $.#\n#####"),
+            Err(XsbParseError::NoPlayer)
+        );
+        assert_eq!(
+            Board::from_xsb("#####\n#@$@#\n#####"),
+            Err(XsbParseError::SeveralPlayers)
+        );
+        assert_eq!(
+            Board::from_xsb("#@x#"),
+            Err(XsbParseError::UnknownSymbol('x'))
+        );
+    }
+}
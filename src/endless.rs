@@ -0,0 +1,125 @@
+//! Endless mode: random levels played one after the other, harder as the player wins them in a
+//! row.
+
+use crate::{
+    data::{Difficulty, Level},
+    tools::{Generator, GeneratorError, GeneratorOptions},
+};
+
+/// Levels won in a row before the difficulty goes up a step.
+const LEVELS_PER_DIFFICULTY: u32 = 3;
+/// Size of the inside of the first levels, growing by one every two levels won in a row.
+const START_SIZE: u32 = 6;
+const MAX_SIZE: u32 = 10;
+/// Crates added to the hard levels at most, one every [`LEVELS_PER_DIFFICULTY`] levels.
+const MAX_EXTRA_CRATES: usize = 2;
+
+/// Run of random levels, see the [module](self).
+#[derive(Clone, Debug)]
+pub struct Endless {
+    seed: u64,
+    /// Number of levels generated so far, so each one is different.
+    generated: u64,
+    /// Levels won in a row.
+    streak: u32,
+    best_streak: u32,
+}
+
+impl Endless {
+    /// Runs with the same `seed` give the same levels, as long as they are won or lost the same.
+    pub fn new(seed: u64) -> Self {
+        Endless {
+            seed,
+            generated: 0,
+            streak: 0,
+            best_streak: 0,
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Levels won in a row since the start or the last level lost.
+    pub fn streak(&self) -> u32 {
+        self.streak
+    }
+
+    /// Longest streak of the run.
+    pub fn best_streak(&self) -> u32 {
+        self.best_streak
+    }
+
+    /// Generates the next level, as hard as the current streak.
+    pub fn next_level(&mut self) -> Result<Level, GeneratorError> {
+        let options = self.options();
+        self.generated += 1;
+        let (board, _) = Generator::new(options)?.generate()?;
+        let title = format!("Endless {}", self.generated);
+        Ok(Level::new(title, board.to_xsb()).expect("A generated level can be parsed."))
+    }
+
+    /// The last level was won, so the next one is a bit harder.
+    pub fn won(&mut self) {
+        self.streak += 1;
+        self.best_streak = self.best_streak.max(self.streak);
+    }
+
+    /// The last level was lost or skipped, so the streak starts over with easy levels.
+    pub fn lost(&mut self) {
+        self.streak = 0;
+    }
+
+    /// What the next level looks like, following the streak.
+    fn options(&self) -> GeneratorOptions {
+        let step = self.streak / LEVELS_PER_DIFFICULTY;
+        let difficulty = match step {
+            0 => Difficulty::Easy,
+            1 => Difficulty::Medium,
+            _ => Difficulty::Hard,
+        };
+        let size = (START_SIZE + self.streak / 2).min(MAX_SIZE);
+        let mut options = GeneratorOptions {
+            width: size,
+            height: size,
+            crates: None,
+            difficulty,
+            seed: self.seed.wrapping_add(self.generated),
+        };
+        let extra = (step.saturating_sub(2) as usize).min(MAX_EXTRA_CRATES);
+        options.crates = Some(options.crate_count() + extra);
+        options
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Endless;
+    use crate::data::Difficulty;
+
+    #[test]
+    fn it_gets_harder_with_the_streak() {
+        let mut endless = Endless::new(7);
+        let first = endless.next_level().unwrap();
+        assert_eq!(first.title(), "Endless 1");
+        assert_eq!(first.board().crate_count(), 2);
+
+        for _ in 0..6 {
+            endless.won();
+        }
+        let hard = endless.options();
+        assert_eq!(hard.difficulty, Difficulty::Hard);
+        assert_eq!((hard.width, hard.crate_count()), (9, 4));
+
+        endless.lost();
+        assert_eq!((endless.streak(), endless.best_streak()), (0, 6));
+        assert_eq!(endless.options().difficulty, Difficulty::Easy);
+
+        // The same seed gives the same levels.
+        let mut other = Endless::new(7);
+        assert_eq!(
+            other.next_level().unwrap().board().to_xsb(),
+            first.board().to_xsb()
+        );
+    }
+}
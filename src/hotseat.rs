@@ -0,0 +1,121 @@
+//! Hotseat mode: two players sharing the keyboard take turns moving the player of the level,
+//! and win it together.
+//!
+//! A turn ends with a push, so each player walks to the crate of their choice and pushes it
+//! once.
+
+use std::fmt;
+
+use crate::data::Board;
+
+/// Number of players taking turns.
+pub const PLAYER_COUNT: usize = 2;
+
+/// Moves and pushes done by one of the players.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PlayerCounters {
+    pub moves: u32,
+    pub pushes: u32,
+}
+
+/// Turns and counters of the players on a level, see the [module](self).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Hotseat {
+    counters: [PlayerCounters; PLAYER_COUNT],
+    /// Index of the player whose turn it is.
+    turn: usize,
+}
+
+impl Hotseat {
+    pub fn new() -> Self {
+        Hotseat::default()
+    }
+
+    /// Index of the player whose turn it is, from 0.
+    pub fn turn(&self) -> usize {
+        self.turn
+    }
+
+    /// Moves and pushes done by the player at `index`.
+    pub fn counters(&self, index: usize) -> PlayerCounters {
+        self.counters[index]
+    }
+
+    /// Counts the move done on the `board` by the player whose turn it is, passing the turn if
+    /// it pushed a crate. `last_move_result` is the one returned by [`Board::do_move_player`].
+    pub fn played(&mut self, board: &Board, last_move_result: Option<Option<(u32, u32)>>) {
+        let Some(pushed) = last_move_result else {
+            return;
+        };
+        let counters = &mut self.counters[self.turn];
+        counters.moves += 1;
+        if pushed.is_some() {
+            counters.pushes += 1;
+            // The winning push ends the game rather than the turn.
+            if !board.has_won() {
+                self.turn = (self.turn + 1) % PLAYER_COUNT;
+            }
+        }
+    }
+
+    /// The level starts over, with the first player.
+    pub fn reset(&mut self) {
+        *self = Hotseat::new();
+    }
+}
+
+impl fmt::Display for Hotseat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Player {} to play", self.turn + 1)?;
+        for (index, counters) in self.counters.iter().enumerate() {
+            write!(
+                f,
+                "{}P{}: {} moves, {} pushes",
+                if index == 0 { " (" } else { " | " },
+                index + 1,
+                counters.moves,
+                counters.pushes
+            )?;
+        }
+        write!(f, ")")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Hotseat, PlayerCounters};
+    use crate::data::{Board, Direction};
+
+    #[test]
+    fn it_passes_the_turn_after_each_push() {
+        let mut board = Board::from_xsb("#######\n#@$  .#\n#   $.#\n#######\n").unwrap();
+        let mut hotseat = Hotseat::new();
+        let mut play = |dir| {
+            let res = board.do_move_player(dir);
+            hotseat.played(&board, res);
+        };
+
+        play(Direction::Right);
+        // Bumping into a wall doesn't count.
+        play(Direction::Up);
+        play(Direction::Down);
+        play(Direction::Right);
+        play(Direction::Right);
+        play(Direction::Up);
+        assert_eq!(hotseat.turn(), 0);
+        assert_eq!(
+            hotseat.counters(1),
+            PlayerCounters {
+                moves: 3,
+                pushes: 1
+            }
+        );
+        assert_eq!(
+            hotseat.to_string(),
+            "Player 1 to play (P1: 2 moves, 1 pushes | P2: 3 moves, 1 pushes)"
+        );
+
+        hotseat.reset();
+        assert_eq!(hotseat, Hotseat::new());
+    }
+}
@@ -1,22 +1,74 @@
 #![feature(try_blocks)]
 //! Base data structures and functions to run a Sokoban-like game,
 //! see [`game`] to start it.
-use std::{error::Error, fmt, str::FromStr};
+use std::{
+    error::Error,
+    fmt, io,
+    path::{Path, PathBuf},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
 
 mod data;
-use data::{Board, LevelParseError};
+use data::Board;
+mod endless;
+pub use endless::Endless;
+mod hotseat;
+pub use hotseat::{Hotseat, PlayerCounters};
+mod speedrun;
+pub use speedrun::{Speedrun, Stopwatch};
+#[cfg(feature = "net")]
+mod net;
+pub use data::{
+    Difficulty, Hint, HintTrigger, Level, LevelLibrary, LevelParseError, LibraryError, LintIssue,
+    MoveLimit, Replay, ReplayParseError, Score, Solution, SolverError, SolverLimits, TimeLimit,
+    Tutorial, XsbParseError,
+};
+#[cfg(feature = "net")]
+pub use net::{NetError, DEFAULT_PORT};
+#[cfg(feature = "remote")]
+mod remote;
+#[cfg(feature = "remote")]
+pub use remote::{
+    open_source, parse_command, MqttSource, RemoteControlled, RemoteError, RemoteInputSource,
+    StdinSource, UdpSource, SOURCES_ENV_VAR,
+};
+#[cfg(feature = "repo")]
+mod repo;
+#[cfg(feature = "repo")]
+pub use repo::{pack_dir, PackInfo, RepoClient, RepoError, REPO_URL_ENV_VAR};
+#[cfg(feature = "serve")]
+mod serve;
+#[cfg(feature = "serve")]
+pub use serve::{serve, GameSession, DEFAULT_SERVE_PORT};
+mod tools;
+pub use tools::{
+    bench_level, check_replay, estimated_memory, parse_solutions, solve_level, validate_path,
+    validate_source, BenchComparison, BenchError, BenchOptions, BenchReport, BenchRow,
+    ConvertError, Diagnostic, FileReport, Format, Generator, GeneratorError, GeneratorOptions,
+    LevelPack, PackLevel, ReplayOutcome, ReplayReport, Severity, SolveReport,
+};
+#[cfg(feature = "thumbnail")]
+pub use tools::{write_thumbnails, ThumbnailError, ThumbnailOptions};
 mod ui;
 #[cfg(feature = "ggez")]
 pub use ui::game_ggez;
+#[cfg(feature = "thumbnail")]
+pub use ui::Thumbnail;
 #[cfg(feature = "macroquad")]
-pub use ui::game_macroquad;
-use ui::Action;
-pub use ui::{DisplayKind, Ui};
+pub use ui::{game_macroquad, game_macroquad_web, watch_replay_macroquad};
+pub use ui::{Action, AudioSink, Defeat, DisplayKind, SoundEffect, Ui, REPLAY_SPEED_ENV_VAR};
+use ui::{LostChoice, Selection, WonChoice};
 
 #[derive(Debug)]
 pub enum GameError {
     LevelParseError(LevelParseError),
+    LibraryError(LibraryError),
+    GeneratorError(GeneratorError),
+    /// The splits of a speedrun couldn't be written.
+    SplitsError(io::Error),
     UiError(Box<dyn Error>),
+    #[cfg(feature = "net")]
+    NetError(NetError),
 }
 
 impl fmt::Display for GameError {
@@ -24,7 +76,12 @@ impl fmt::Display for GameError {
         use GameError::*;
         match self {
             LevelParseError(e) => write!(f, "Error parsing level file: {}", e),
+            LibraryError(e) => write!(f, "Error with the level library: {}", e),
+            GeneratorError(e) => write!(f, "Error generating a level: {}", e),
+            SplitsError(e) => write!(f, "Error writing the splits: {}", e),
             UiError(e) => write!(f, "Error in the interface: {}", e),
+            #[cfg(feature = "net")]
+            NetError(e) => write!(f, "Error with the network: {}", e),
         }
     }
 }
@@ -37,12 +94,52 @@ impl From<LevelParseError> for GameError {
     }
 }
 
-/// Start the game by loading the level from the file content in `level_file`, and the display
-/// selection in `disp_kind`.
-pub fn game(disp_kind: DisplayKind, level: &str) -> Result<(), GameError> {
-    let ui = ui::new(disp_kind).map_err(GameError::UiError)?;
+impl From<LibraryError> for GameError {
+    fn from(src: LibraryError) -> Self {
+        GameError::LibraryError(src)
+    }
+}
+
+impl From<GeneratorError> for GameError {
+    fn from(src: GeneratorError) -> Self {
+        GameError::GeneratorError(src)
+    }
+}
+
+#[cfg(feature = "net")]
+impl From<NetError> for GameError {
+    fn from(src: NetError) -> Self {
+        GameError::NetError(src)
+    }
+}
 
-    let res = game_loop(ui.as_ref(), level);
+/// Modes the levels are played in, chosen when the game starts.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GameOptions {
+    /// Each level must be won before its [par time](`TimeLimit::par`) runs out.
+    pub time_attack: bool,
+    /// Two players take turns on each level, see [`Hotseat`]. Only the terminal interfaces have
+    /// this mode, the graphical ones ignore it.
+    pub hotseat: bool,
+    /// All the levels are played in order against the timer of a [`Speedrun`], whose splits are
+    /// written to this file.
+    pub speedrun: Option<PathBuf>,
+}
+
+/// Start the game with the levels of `library`, and the display selection in `disp_kind`.
+///
+/// The levels won during the game are marked as solved in the `library`. The levels picked for
+/// the move-limit challenge (see [`LevelLibrary::toggle_challenge`]) must be won within their
+/// [par moves and pushes](`MoveLimit::par`). The time-attack, hotseat and speedrun modes are
+/// chosen in the `options`.
+pub fn game(
+    disp_kind: DisplayKind,
+    library: &mut LevelLibrary,
+    options: &GameOptions,
+) -> Result<(), GameError> {
+    let mut ui = ui::new(disp_kind).map_err(GameError::UiError)?;
+
+    let res = game_loop(ui.as_mut(), library, None, options);
 
     // Whatever happened in the game, we close first.
     ui.cleanup().map_err(GameError::UiError)?;
@@ -50,28 +147,440 @@ pub fn game(disp_kind: DisplayKind, level: &str) -> Result<(), GameError> {
     res
 }
 
-fn game_loop(ui: &dyn Ui, level: &str) -> Result<(), GameError> {
-    let mut board = Board::from_str(level)?;
+/// Same as [`game`], starting directly with the level at `index` instead of letting the player
+/// choose. A speedrun still starts with the first level.
+pub fn game_at(
+    disp_kind: DisplayKind,
+    library: &mut LevelLibrary,
+    index: usize,
+    options: &GameOptions,
+) -> Result<(), GameError> {
+    let mut ui = ui::new(disp_kind).map_err(GameError::UiError)?;
+
+    let res = game_loop(ui.as_mut(), library, Some(index), options);
+
+    // Whatever happened in the game, we close first.
+    ui.cleanup().map_err(GameError::UiError)?;
+
+    res
+}
+
+/// Plays random levels one after the other in the [endless mode](`Endless`), from the `seed`,
+/// with the display selection in `disp_kind`, until the player quits.
+pub fn game_endless(
+    disp_kind: DisplayKind,
+    seed: u64,
+    options: &GameOptions,
+) -> Result<(), GameError> {
+    let mut ui = ui::new(disp_kind).map_err(GameError::UiError)?;
+
+    let res = endless_loop(ui.as_mut(), &mut Endless::new(seed), options);
+
+    ui.cleanup().map_err(GameError::UiError)?;
+
+    res
+}
+
+/// Shows the moves of `replay` played on the `level`, with the display selection in `disp_kind`,
+/// starting at the `speed` multiplier if given (see [`REPLAY_SPEED_ENV_VAR`]).
+pub fn watch_replay(
+    disp_kind: DisplayKind,
+    level: &Level,
+    replay: &Replay,
+    speed: Option<u32>,
+) -> Result<(), GameError> {
+    let mut ui = ui::new(disp_kind).map_err(GameError::UiError)?;
+
+    let res = ui
+        .watch_replay(level.board(), replay, speed)
+        .map_err(GameError::UiError);
+
+    ui.cleanup().map_err(GameError::UiError)?;
+
+    res
+}
+
+/// Plays the levels of `library` with the votes of the viewers in the chat of the Twitch
+/// `channel`, writing each frame to the `overlay` file if any, until the process is stopped.
+#[cfg(feature = "chat")]
+pub fn game_chat(
+    channel: &str,
+    overlay: Option<std::path::PathBuf>,
+    library: &mut LevelLibrary,
+) -> Result<(), GameError> {
+    let mut ui: Box<dyn Ui> =
+        Box::new(ui::ChatPlays::new(channel, overlay).map_err(|e| GameError::UiError(e.into()))?);
+
+    let res = game_loop(ui.as_mut(), library, None, &GameOptions::default());
+
+    ui.cleanup().map_err(GameError::UiError)?;
+
+    res
+}
+
+/// Plays the levels of `library` with the actions of the remote `sources`, shown with the
+/// display selection in `disp_kind`, until they are all closed.
+#[cfg(feature = "remote")]
+pub fn game_remote(
+    disp_kind: DisplayKind,
+    sources: Vec<Box<dyn RemoteInputSource>>,
+    library: &mut LevelLibrary,
+) -> Result<(), GameError> {
+    let display = ui::new(disp_kind).map_err(GameError::UiError)?;
+    let mut ui: Box<dyn Ui> = Box::new(RemoteControlled::new(display, sources));
+
+    let res = game_loop(ui.as_mut(), library, None, &GameOptions::default());
+
+    ui.cleanup().map_err(GameError::UiError)?;
+
+    res
+}
+
+/// Hosts a game on the `level` at `addr` for other players to [join](`join_game`), until the
+/// process is stopped.
+#[cfg(feature = "net")]
+pub fn host_game(level: &Level, addr: &str) -> Result<(), GameError> {
+    Ok(net::host(addr, level.board().clone())?)
+}
+
+/// Joins the game hosted at `addr` with the display selection in `disp_kind`, to solve its
+/// level with the other players connected.
+#[cfg(feature = "net")]
+pub fn join_game(disp_kind: DisplayKind, addr: &str) -> Result<(), GameError> {
+    let mut connection = net::Connection::connect(addr)?;
+    let mut ui = ui::new(disp_kind).map_err(GameError::UiError)?;
+
+    let res = online_loop(ui.as_mut(), &mut connection);
+
+    ui.cleanup().map_err(GameError::UiError)?;
+
+    res
+}
+
+/// Opens the level editor in the terminal on the level file at `path`, which is created when
+/// saving if it doesn't exist yet.
+#[cfg(feature = "tui")]
+pub fn edit_level(path: &std::path::Path) -> Result<(), GameError> {
+    let library = if path.exists() {
+        Some(LevelLibrary::open(path)?)
+    } else {
+        None
+    };
+    let board = library.as_ref().and_then(|l| l.get(0)).map(Level::board);
+
+    ui::edit_level(board, path).map_err(GameError::UiError)
+}
+
+fn game_loop(
+    ui: &mut dyn Ui,
+    library: &mut LevelLibrary,
+    first: Option<usize>,
+    options: &GameOptions,
+) -> Result<(), GameError> {
+    if let Some(path) = &options.speedrun {
+        return speedrun_loop(ui, library, path);
+    }
+
+    let mut next = match first {
+        Some(index) => Some(Selection::Level(index)),
+        None => ui.select_level(library).map_err(GameError::UiError)?,
+    };
+
+    let hotseat = options.hotseat.then(Hotseat::new);
+    while let Some(selection) = next {
+        let index = match selection {
+            Selection::Level(index) => index,
+            // The player comes back to the selection once the run ends.
+            Selection::Endless => {
+                let seed = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |d| d.as_nanos() as u64);
+                endless_loop(ui, &mut Endless::new(seed), options)?;
+                next = ui.select_level(library).map_err(GameError::UiError)?;
+                continue;
+            }
+        };
+        let Some(level) = library.get(index) else {
+            break;
+        };
+        let best = level.best();
+        let time_limit = options.time_attack.then(|| TimeLimit::par(level));
+        let move_limit = level
+            .is_challenge()
+            .then(|| MoveLimit::par(level))
+            .flatten();
+
+        let tutorial = Tutorial::new(level);
+
+        let board = level.board().clone();
+        let end = play_level(ui, board, tutorial, time_limit, move_limit, hotseat, None)?;
+        if hotseat.is_some() {
+            ui.show_turn(None);
+        }
+        let (score, replay) = match end {
+            LevelEnd::Won(score, replay) => (score, replay),
+            LevelEnd::Lost(defeat) => {
+                next = match ui.lost(&defeat).map_err(GameError::UiError)? {
+                    LostChoice::Retry => Some(Selection::Level(index)),
+                    LostChoice::Quit => None,
+                };
+                continue;
+            }
+            LevelEnd::Quit => break,
+        };
+        library.set_solved(index, score, replay)?;
+
+        next = match ui.won(&score, best.as_ref()).map_err(GameError::UiError)? {
+            WonChoice::Restart => Some(Selection::Level(index)),
+            WonChoice::Next if index + 1 < library.len() => Some(Selection::Level(index + 1)),
+            WonChoice::Next => ui.select_level(library).map_err(GameError::UiError)?,
+            WonChoice::Quit => None,
+        };
+    }
+
+    Ok(())
+}
+
+/// Plays the levels of the `endless` run until the player quits. A level only counts in the
+/// streak the first time it is won, and losing it or quitting it ends the streak.
+fn endless_loop(
+    ui: &mut dyn Ui,
+    endless: &mut Endless,
+    options: &GameOptions,
+) -> Result<(), GameError> {
+    let mut level = endless.next_level()?;
+    let mut counted = false;
+    loop {
+        ui.show_streak(Some(endless.streak()));
+        let time_limit = options.time_attack.then(|| TimeLimit::par(&level));
+
+        match play_level(
+            ui,
+            level.board().clone(),
+            Tutorial::default(),
+            time_limit,
+            None,
+            None,
+            None,
+        )? {
+            LevelEnd::Won(score, _) => {
+                if !counted {
+                    endless.won();
+                    counted = true;
+                }
+                match ui.won(&score, None).map_err(GameError::UiError)? {
+                    WonChoice::Restart => (),
+                    WonChoice::Next => {
+                        level = endless.next_level()?;
+                        counted = false;
+                    }
+                    WonChoice::Quit => break,
+                }
+            }
+            LevelEnd::Lost(defeat) => {
+                endless.lost();
+                if ui.lost(&defeat).map_err(GameError::UiError)? == LostChoice::Quit {
+                    break;
+                }
+            }
+            LevelEnd::Quit => break,
+        }
+    }
+    ui.show_streak(None);
+    Ok(())
+}
+
+/// Plays all the levels of `library` in order in a [`Speedrun`], then shows the time of the run.
+/// The splits are written to the file at `path` each time a level is won, so they are kept if the
+/// player quits before the end.
+fn speedrun_loop(
+    ui: &mut dyn Ui,
+    library: &mut LevelLibrary,
+    path: &Path,
+) -> Result<(), GameError> {
+    let mut run = Speedrun::new(library);
+    let (mut moves, mut pushes) = (0, 0);
+    while let Some(level) = library.get(run.current()) {
+        let index = run.current();
+        let tutorial = Tutorial::new(level);
+        let board = level.board().clone();
+        let end = play_level(ui, board, tutorial, None, None, None, Some(&mut run))?;
+        // Without limits, the level can only be won or quit.
+        let LevelEnd::Won(score, replay) = end else {
+            break;
+        };
+        run.split();
+        run.save(path).map_err(GameError::SplitsError)?;
+        moves += score.moves;
+        pushes += score.pushes;
+        library.set_solved(index, score, replay)?;
+
+        if run.is_finished() {
+            let total = Score {
+                moves,
+                pushes,
+                time: run.elapsed(),
+            };
+            ui.show_speedrun(None);
+            // The run can be tried again from the start.
+            if ui.won(&total, None).map_err(GameError::UiError)? == WonChoice::Restart {
+                run = Speedrun::new(library);
+                (moves, pushes) = (0, 0);
+            }
+        }
+    }
+    ui.show_speedrun(None);
+    Ok(())
+}
+
+/// Plays the level of the host with the other players, until the player quits.
+#[cfg(feature = "net")]
+fn online_loop(ui: &mut dyn Ui, connection: &mut net::Connection) -> Result<(), GameError> {
+    use net::{ClientMessage, NetError, ServerMessage};
+
+    let id = match connection.receive()? {
+        ServerMessage::Welcome(id) => id,
+        other => return Err(NetError::Unexpected(other.to_string()).into()),
+    };
+    let mut board = match connection.receive()? {
+        ServerMessage::Level(board) => board,
+        other => return Err(NetError::Unexpected(other.to_string()).into()),
+    };
+    let mut start = Instant::now();
+    ui.display(&board, None).map_err(GameError::UiError)?;
+
+    // If it is the turn of the player and the host didn't answer their last action yet.
+    let mut our_turn = false;
+    let mut waiting = false;
+    loop {
+        if our_turn && !waiting {
+            let message = match ui.get_action(&board).map_err(GameError::UiError)? {
+                Action::Movement(dir) => ClientMessage::Move(dir),
+                Action::ResetLevel => ClientMessage::Reset,
+                Action::Quit => return Ok(()),
+                // There is no time limit when playing with others.
+                Action::TimeOut => continue,
+            };
+            connection.send(message)?;
+            waiting = true;
+        }
+
+        match connection.receive()? {
+            ServerMessage::Turn(player) => our_turn = player == id,
+            ServerMessage::Step { player, step } => {
+                let res = board.do_move_player(step.dir);
+                ui.display(&board, res).map_err(GameError::UiError)?;
+                waiting &= player != id;
+            }
+            ServerMessage::Reset(player) => {
+                board.reset();
+                ui.display(&board, None).map_err(GameError::UiError)?;
+                waiting &= player != id;
+            }
+            ServerMessage::Won => {
+                let score = Score::new(&board, start.elapsed());
+                if ui.won(&score, None).map_err(GameError::UiError)? == WonChoice::Quit {
+                    return Ok(());
+                }
+                // The host starts the level again.
+                board.reset();
+                start = Instant::now();
+                ui.display(&board, None).map_err(GameError::UiError)?;
+            }
+            ServerMessage::Denied(_) => waiting = false,
+            ServerMessage::Joined(_) | ServerMessage::Left(_) => (),
+            other @ (ServerMessage::Welcome(_) | ServerMessage::Level(_)) => {
+                return Err(NetError::Unexpected(other.to_string()).into())
+            }
+        }
+    }
+}
+
+/// How a level played with [`play_level`] ended.
+enum LevelEnd {
+    /// With the score and the moves which achieved it.
+    Won(Score, Replay),
+    /// A limit was exceeded first.
+    Lost(Defeat),
+    Quit,
+}
+
+/// Plays the level until it is won, until the `time_limit` runs out or the `move_limit` is
+/// exceeded if any, or until the player quits. The hints of the `tutorial` are shown as they
+/// are triggered. In the `hotseat` mode, the turns of the players are counted from the start of
+/// the level. The timer of the `speedrun` if any runs from the start of the level, until it is
+/// split by the caller once the level is won.
+fn play_level(
+    ui: &mut dyn Ui,
+    mut board: Board,
+    mut tutorial: Tutorial,
+    time_limit: Option<TimeLimit>,
+    move_limit: Option<MoveLimit>,
+    mut hotseat: Option<Hotseat>,
+    mut speedrun: Option<&mut Speedrun>,
+) -> Result<LevelEnd, GameError> {
+    let start = Instant::now();
+    if let Some(run) = &mut speedrun {
+        run.start();
+    }
+    // The interface starts counting after the game, so it never runs out first.
+    ui.start_countdown(time_limit);
+    ui.start_challenge(move_limit);
     loop {
         let res: Result<(), Box<dyn Error>> = try {
+            if let Some(hotseat) = &hotseat {
+                ui.show_turn(Some(hotseat));
+            }
+            if let Some(run) = &speedrun {
+                ui.show_speedrun(Some(run.stopwatch()));
+            }
             ui.display(&board, None)?;
+            for message in tutorial.update(&board) {
+                ui.show_message(message)?;
+            }
             loop {
-                match ui.get_action(&board)? {
+                let action = ui.get_action(&board)?;
+                // The moves done after the time ran out don't count.
+                if let Some(limit) = time_limit.filter(|l| l.is_over(start.elapsed())) {
+                    return Ok(LevelEnd::Lost(Defeat::TimeOut(limit)));
+                }
+                match action {
                     Action::Movement(dir) => {
                         let res = board.do_move_player(dir);
 
+                        if let Some(hotseat) = &mut hotseat {
+                            hotseat.played(&board, res);
+                            ui.show_turn(Some(hotseat));
+                        }
+                        if let Some(run) = &speedrun {
+                            ui.show_speedrun(Some(run.stopwatch()));
+                        }
                         ui.display(&board, res)?;
+                        for message in tutorial.update(&board) {
+                            ui.show_message(message)?;
+                        }
 
+                        // Even the winning move must be within the limit.
+                        if let Some(limit) = move_limit.filter(|l| l.is_exceeded(&board)) {
+                            return Ok(LevelEnd::Lost(Defeat::OutOfMoves(limit)));
+                        }
                         // Si on a déplacé une caisse.
                         if let Some(Some(_)) = res {
                             if board.has_won() {
-                                ui.won()?;
-                                return Ok(());
+                                let score = Score::new(&board, start.elapsed());
+                                return Ok(LevelEnd::Won(score, board.replay()));
                             }
                         }
                     }
-                    Action::ResetLevel => board.reset(),
-                    Action::Quit => return Ok(()),
+                    Action::ResetLevel => {
+                        board.reset();
+                        if let Some(hotseat) = &mut hotseat {
+                            hotseat.reset();
+                        }
+                    }
+                    Action::Quit => return Ok(LevelEnd::Quit),
+                    // Only noticed once the time is over for the game too.
+                    Action::TimeOut => (),
                 }
             }
         };
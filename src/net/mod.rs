@@ -0,0 +1,77 @@
+//! Cooperative play over the network: a [host](`host`) keeps the authoritative [`Board`] of a
+//! level, and the players [connected](`Connection`) to it take turns to move its player.
+//!
+//! A player keeps the turn while walking and gives it to the next one once they pushed a
+//! crate, so each push is decided by one of them. Every step accepted by the host is sent to
+//! all the players, who play it on their own copy of the board.
+//!
+//! The messages are lines of text over TCP, see [`ClientMessage`] and [`ServerMessage`].
+
+use std::{
+    error::Error,
+    fmt,
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpStream, ToSocketAddrs},
+};
+
+#[cfg(doc)]
+use crate::data::Board;
+
+mod protocol;
+pub use protocol::{ClientMessage, ProtocolError, ServerMessage};
+mod server;
+pub use server::host;
+
+/// Port used when none is given.
+pub const DEFAULT_PORT: u16 = 7878;
+
+#[derive(Debug)]
+pub enum NetError {
+    IO(io::Error),
+    Protocol(ProtocolError),
+    /// The other side closed the connection.
+    Closed,
+    /// A message came when another one was expected.
+    Unexpected(String),
+}
+
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use NetError::*;
+        match self {
+            IO(e) => write!(f, "Network error : {}", e),
+            Protocol(e) => write!(f, "Invalid message : {}", e),
+            Closed => write!(f, "The connection was closed."),
+            Unexpected(m) => write!(f, "Unexpected message `{}`.", m),
+        }
+    }
+}
+
+impl Error for NetError {}
+
+/// Connection of a player to the host.
+pub struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Connection {
+    pub fn connect(addr: impl ToSocketAddrs) -> Result<Self, NetError> {
+        let writer = TcpStream::connect(addr).map_err(NetError::IO)?;
+        let reader = BufReader::new(writer.try_clone().map_err(NetError::IO)?);
+        Ok(Connection { reader, writer })
+    }
+
+    pub fn send(&mut self, message: ClientMessage) -> Result<(), NetError> {
+        writeln!(self.writer, "{}", message).map_err(NetError::IO)
+    }
+
+    /// Waits for the next message of the host.
+    pub fn receive(&mut self) -> Result<ServerMessage, NetError> {
+        let mut line = String::new();
+        match self.reader.read_line(&mut line).map_err(NetError::IO)? {
+            0 => Err(NetError::Closed),
+            _ => line.parse().map_err(NetError::Protocol),
+        }
+    }
+}
@@ -0,0 +1,255 @@
+//! Messages exchanged between the host and the players, one per line of text.
+//!
+//! A message starts with its name in upper case, followed by its arguments separated by
+//! spaces. The directions and the steps are written as in the LURD notation of the
+//! [replays](`Replay`), and the levels as XSB on a single line, the rows being separated by
+//! `|` and the floor written `-`.
+
+use std::{error::Error, fmt, str::FromStr};
+
+use crate::data::{Board, Direction, Replay, ReplayParseError, Step, XsbParseError};
+
+const ROW_SEPARATOR: char = '|';
+const FLOOR: char = '-';
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProtocolError {
+    UnknownMessage(String),
+    MissingArgument(&'static str),
+    InvalidArgument(String),
+    Replay(ReplayParseError),
+    Level(XsbParseError),
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use ProtocolError::*;
+        match self {
+            UnknownMessage(m) => write!(f, "Unknown message `{}`.", m),
+            MissingArgument(m) => write!(f, "Missing argument to `{}`.", m),
+            InvalidArgument(a) => write!(f, "Invalid argument `{}`.", a),
+            Replay(e) => write!(f, "Invalid steps : {}", e),
+            Level(e) => write!(f, "Invalid level : {}", e),
+        }
+    }
+}
+
+impl Error for ProtocolError {}
+
+/// Messages sent by a player to the host.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClientMessage {
+    /// Moves the player, if it is their turn.
+    Move(Direction),
+    /// Puts the level back at its start, if it is their turn.
+    Reset,
+}
+
+/// Messages sent by the host to the players.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ServerMessage {
+    /// Id given to the player who just connected.
+    Welcome(u32),
+    /// Level played, with the steps already done on it.
+    Level(Board),
+    Joined(u32),
+    Left(u32),
+    /// The player with this id can move, until they push a crate.
+    Turn(u32),
+    /// A player did a step, which everyone plays on their board.
+    Step {
+        player: u32,
+        step: Step,
+    },
+    /// A player put the level back at its start.
+    Reset(u32),
+    /// The level is won, and starts again.
+    Won,
+    /// The last message of the player was refused, for this reason.
+    Denied(String),
+}
+
+fn step_to_string(step: Step) -> String {
+    Replay::from(vec![step]).to_string()
+}
+
+fn parse_step(src: &str) -> Result<Step, ProtocolError> {
+    let replay: Replay = src.parse().map_err(ProtocolError::Replay)?;
+    match replay.steps() {
+        [step] => Ok(*step),
+        _ => Err(ProtocolError::InvalidArgument(src.to_string())),
+    }
+}
+
+fn parse_id(src: Option<&str>, message: &'static str) -> Result<u32, ProtocolError> {
+    let src = src.ok_or(ProtocolError::MissingArgument(message))?;
+    src.parse()
+        .map_err(|_| ProtocolError::InvalidArgument(src.to_string()))
+}
+
+impl fmt::Display for ClientMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use ClientMessage::*;
+        match self {
+            Move(dir) => write!(
+                f,
+                "MOVE {}",
+                step_to_string(Step {
+                    dir: *dir,
+                    push: false
+                })
+            ),
+            Reset => write!(f, "RESET"),
+        }
+    }
+}
+
+impl FromStr for ClientMessage {
+    type Err = ProtocolError;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        let mut args = src.split_whitespace();
+        match args.next() {
+            Some("MOVE") => {
+                let step = args.next().ok_or(ProtocolError::MissingArgument("MOVE"))?;
+                Ok(ClientMessage::Move(parse_step(step)?.dir))
+            }
+            Some("RESET") => Ok(ClientMessage::Reset),
+            _ => Err(ProtocolError::UnknownMessage(src.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for ServerMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use ServerMessage::*;
+        match self {
+            Welcome(id) => write!(f, "WELCOME {}", id),
+            Level(board) => {
+                let rows: Vec<String> = board
+                    .to_xsb()
+                    .lines()
+                    .map(|l| l.replace(' ', &FLOOR.to_string()))
+                    .collect();
+                write!(f, "LEVEL {}", rows.join(&ROW_SEPARATOR.to_string()))?;
+                if board.moves() > 0 {
+                    write!(f, " {}", board.replay())?;
+                }
+                Ok(())
+            }
+            Joined(id) => write!(f, "JOINED {}", id),
+            Left(id) => write!(f, "LEFT {}", id),
+            Turn(id) => write!(f, "TURN {}", id),
+            Step { player, step } => write!(f, "STEP {} {}", player, step_to_string(*step)),
+            Reset(id) => write!(f, "RESET {}", id),
+            Won => write!(f, "WON"),
+            Denied(reason) => write!(f, "DENIED {}", reason),
+        }
+    }
+}
+
+impl FromStr for ServerMessage {
+    type Err = ProtocolError;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        let (name, rest) = src
+            .trim_end()
+            .split_once(' ')
+            .unwrap_or((src.trim_end(), ""));
+        let mut args = rest.split_whitespace();
+        match name {
+            "WELCOME" => Ok(ServerMessage::Welcome(parse_id(args.next(), "WELCOME")?)),
+            "LEVEL" => {
+                let rows = args.next().ok_or(ProtocolError::MissingArgument("LEVEL"))?;
+                let mut board = Board::from_xsb(&rows.replace(ROW_SEPARATOR, "\n"))
+                    .map_err(ProtocolError::Level)?;
+                let history: Replay = args
+                    .next()
+                    .unwrap_or_default()
+                    .parse()
+                    .map_err(ProtocolError::Replay)?;
+                for step in history.steps() {
+                    board
+                        .do_move_player(step.dir)
+                        .ok_or_else(|| ProtocolError::InvalidArgument(history.to_string()))?;
+                }
+                Ok(ServerMessage::Level(board))
+            }
+            "JOINED" => Ok(ServerMessage::Joined(parse_id(args.next(), "JOINED")?)),
+            "LEFT" => Ok(ServerMessage::Left(parse_id(args.next(), "LEFT")?)),
+            "TURN" => Ok(ServerMessage::Turn(parse_id(args.next(), "TURN")?)),
+            "STEP" => {
+                let player = parse_id(args.next(), "STEP")?;
+                let step = args.next().ok_or(ProtocolError::MissingArgument("STEP"))?;
+                Ok(ServerMessage::Step {
+                    player,
+                    step: parse_step(step)?,
+                })
+            }
+            "RESET" => Ok(ServerMessage::Reset(parse_id(args.next(), "RESET")?)),
+            "WON" => Ok(ServerMessage::Won),
+            "DENIED" => Ok(ServerMessage::Denied(rest.to_string())),
+            _ => Err(ProtocolError::UnknownMessage(src.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Board, ClientMessage, Direction, ProtocolError, ServerMessage, Step};
+
+    const TEST_XSB: &str = "#####
+#@$.#
+#   #
+#####
+";
+
+    #[test]
+    fn it_round_trips_the_client_messages() {
+        for message in [ClientMessage::Move(Direction::Up), ClientMessage::Reset] {
+            assert_eq!(message.to_string().parse(), Ok(message));
+        }
+        assert_eq!(ClientMessage::Move(Direction::Left).to_string(), "MOVE l");
+        assert_eq!(
+            "JUMP".parse::<ClientMessage>(),
+            Err(ProtocolError::UnknownMessage("JUMP".to_string()))
+        );
+    }
+
+    #[test]
+    fn it_sends_the_level_with_its_history() {
+        let mut board = Board::from_xsb(TEST_XSB).unwrap();
+        board.do_move_player(Direction::Down);
+        board.do_move_player(Direction::Right);
+
+        let message = ServerMessage::Level(board.clone());
+        assert_eq!(message.to_string(), "LEVEL #####|#@$.#|#---#|##### dr");
+        assert_eq!(message.to_string().parse(), Ok(message));
+    }
+
+    #[test]
+    fn it_parses_the_server_messages() {
+        assert_eq!(
+            "STEP 2 R".parse(),
+            Ok(ServerMessage::Step {
+                player: 2,
+                step: Step {
+                    dir: Direction::Right,
+                    push: true
+                }
+            })
+        );
+        assert_eq!(
+            "DENIED Not your turn.".parse(),
+            Ok(ServerMessage::Denied("Not your turn.".to_string()))
+        );
+        assert_eq!(
+            "TURN".parse::<ServerMessage>(),
+            Err(ProtocolError::MissingArgument("TURN"))
+        );
+        assert_eq!(
+            "STEP 1 rr".parse::<ServerMessage>(),
+            Err(ProtocolError::InvalidArgument("rr".to_string()))
+        );
+    }
+}
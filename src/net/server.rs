@@ -0,0 +1,205 @@
+//! Host of a cooperative game, keeping the board and the turns of the players.
+//!
+//! Each connection is read by its own thread, which forwards the messages to the main one
+//! where the board is changed, so the messages are handled one at a time in the order they
+//! came. Writing to a player never blocks that thread for longer than [`WRITE_TIMEOUT`]: the
+//! players who can't be written to are dropped.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs},
+    sync::mpsc::{self, Sender},
+    thread,
+    time::Duration,
+};
+
+use super::{ClientMessage, NetError, ProtocolError, ServerMessage};
+use crate::data::Board;
+
+/// Time after which a player who doesn't read their messages is dropped.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+enum Event {
+    Connected(TcpStream),
+    Message(u32, Result<ClientMessage, ProtocolError>),
+    Disconnected(u32),
+}
+
+struct Player {
+    id: u32,
+    stream: TcpStream,
+}
+
+struct Host {
+    board: Board,
+    /// Players in the order of their turns.
+    players: Vec<Player>,
+    /// Index in `players` of the one who can move.
+    turn: usize,
+    next_id: u32,
+}
+
+/// Hosts a game on the `board` at `addr`, until the process is stopped. What happens is
+/// printed on the standard output.
+pub fn host(addr: impl ToSocketAddrs, board: Board) -> Result<(), NetError> {
+    let listener = TcpListener::bind(addr).map_err(NetError::IO)?;
+    if let Ok(addr) = listener.local_addr() {
+        println!("Hosting on {}", addr);
+    }
+
+    let (sender, events) = mpsc::channel();
+    let connections = sender.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if connections.send(Event::Connected(stream)).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut host = Host {
+        board,
+        players: Vec::new(),
+        turn: 0,
+        next_id: 1,
+    };
+    // The sender kept here means the channel is never closed.
+    for event in events {
+        match event {
+            Event::Connected(stream) => host.connect(stream, &sender),
+            Event::Message(id, Ok(message)) => host.handle(id, message),
+            Event::Message(id, Err(err)) => host.send(id, &ServerMessage::Denied(err.to_string())),
+            Event::Disconnected(id) => host.disconnect(id),
+        }
+    }
+    Ok(())
+}
+
+/// Forwards the messages read on the `stream` of the player `id`, until it is closed.
+fn read_messages(id: u32, stream: TcpStream, events: Sender<Event>) {
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if events.send(Event::Message(id, line.parse())).is_err() {
+            return;
+        }
+    }
+    let _ = events.send(Event::Disconnected(id));
+}
+
+impl Host {
+    fn current(&self) -> Option<u32> {
+        self.players.get(self.turn).map(|p| p.id)
+    }
+
+    /// Sends a message to a player, who is dropped if it fails.
+    fn send(&mut self, id: u32, message: &ServerMessage) {
+        let failed = self
+            .players
+            .iter_mut()
+            .find(|p| p.id == id)
+            .is_some_and(|p| writeln!(p.stream, "{}", message).is_err());
+        if failed {
+            self.disconnect(id);
+        }
+    }
+
+    /// Sends a message to all the players, dropping the ones it fails for.
+    fn broadcast(&mut self, message: &ServerMessage) {
+        let failed: Vec<u32> = self
+            .players
+            .iter_mut()
+            .filter_map(|p| writeln!(p.stream, "{}", message).is_err().then_some(p.id))
+            .collect();
+        for id in failed {
+            self.disconnect(id);
+        }
+    }
+
+    fn connect(&mut self, stream: TcpStream, events: &Sender<Event>) {
+        if stream.set_write_timeout(Some(WRITE_TIMEOUT)).is_err() {
+            return;
+        }
+        let Ok(reader) = stream.try_clone() else {
+            return;
+        };
+        let id = self.next_id;
+        self.next_id += 1;
+        let events = events.clone();
+        thread::spawn(move || read_messages(id, reader, events));
+
+        println!("Player {} joined", id);
+        self.broadcast(&ServerMessage::Joined(id));
+        self.players.push(Player { id, stream });
+        self.send(id, &ServerMessage::Welcome(id));
+        self.send(id, &ServerMessage::Level(self.board.clone()));
+        if let Some(current) = self.current() {
+            self.send(id, &ServerMessage::Turn(current));
+        }
+    }
+
+    fn disconnect(&mut self, id: u32) {
+        let Some(index) = self.players.iter().position(|p| p.id == id) else {
+            return;
+        };
+        let had_turn = index == self.turn;
+        // Stops their reading thread, if they were dropped.
+        let _ = self.players.remove(index).stream.shutdown(Shutdown::Both);
+        println!("Player {} left", id);
+        self.broadcast(&ServerMessage::Left(id));
+
+        // The turn stays with the same player, or goes to the next one if they left.
+        if index < self.turn {
+            self.turn -= 1;
+        }
+        if self.turn >= self.players.len() {
+            self.turn = 0;
+        }
+        if had_turn {
+            self.give_turn();
+        }
+    }
+
+    fn give_turn(&mut self) {
+        if let Some(current) = self.current() {
+            self.broadcast(&ServerMessage::Turn(current));
+        }
+    }
+
+    fn next_turn(&mut self) {
+        self.turn = (self.turn + 1) % self.players.len();
+        self.give_turn();
+    }
+
+    fn handle(&mut self, id: u32, message: ClientMessage) {
+        if self.current() != Some(id) {
+            self.send(id, &ServerMessage::Denied("Not your turn.".to_string()));
+            return;
+        }
+
+        match message {
+            ClientMessage::Move(dir) => match self.board.do_move_player(dir) {
+                None => self.send(id, &ServerMessage::Denied("Can't move there.".to_string())),
+                Some(pushed) => {
+                    let step = self.board.last_step().expect("The player just moved.");
+                    self.broadcast(&ServerMessage::Step { player: id, step });
+
+                    if self.board.has_won() {
+                        println!("Level won in {} moves", self.board.moves());
+                        self.broadcast(&ServerMessage::Won);
+                        self.board.reset();
+                    }
+                    // The turn already passed if the player was dropped meanwhile.
+                    if pushed.is_some() && self.current() == Some(id) {
+                        self.next_turn();
+                    }
+                }
+            },
+            ClientMessage::Reset => {
+                self.board.reset();
+                self.broadcast(&ServerMessage::Reset(id));
+            }
+        }
+    }
+}
@@ -0,0 +1,261 @@
+//! Actions coming from outside of the interfaces, to play with other controls than theirs:
+//! hardware buttons, home automation, programs, ...
+//!
+//! Each [source](`RemoteInputSource`) is read in its own thread, and their actions are played
+//! in the order they come by a [`RemoteControlled`] interface, which leaves the display to
+//! another one.
+//!
+//! The sources receive commands as text, either a JSON object such as
+//! `{"action": "move", "dir": "left"}`, `{"action": "reset"}` or `{"action": "quit"}`, or the
+//! words of the command-line interface: `left` (`l`), `right` (`r`), `up` (`u`), `down` (`d`),
+//! `reset` (`re`) and `quit` (`qu`).
+
+use std::{
+    env,
+    error::Error,
+    fmt, io,
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use serde::Deserialize;
+
+use crate::{
+    data::{Board, Direction, MoveLimit, Replay, Score, TimeLimit},
+    ui::{self, Action, Defeat, DisplayKind, LostChoice, Ui, WonChoice},
+    Hotseat, Stopwatch,
+};
+
+mod mqtt;
+pub use mqtt::MqttSource;
+mod stdin;
+pub use stdin::StdinSource;
+mod udp;
+pub use udp::UdpSource;
+
+/// Sources read by the interface created with [`Ui::initialize`], separated by commas, see
+/// [`open_source`].
+pub const SOURCES_ENV_VAR: &str = "SOKOBAN_REMOTE_INPUTS";
+
+#[derive(Debug)]
+pub enum RemoteError {
+    IO(io::Error),
+    /// The text received isn't a command, the source can still be read.
+    InvalidCommand(String),
+    UnknownSource(String),
+    Mqtt(String),
+}
+
+impl fmt::Display for RemoteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use RemoteError::*;
+        match self {
+            IO(e) => write!(f, "IO error : {}", e),
+            InvalidCommand(c) => write!(f, "Invalid command `{}`.", c),
+            UnknownSource(s) => write!(
+                f,
+                "Unknown input source `{}`, expected `stdin`, `udp:<address>` or `mqtt:<host>[:<port>]/<topic>`.",
+                s
+            ),
+            Mqtt(e) => write!(f, "MQTT error : {}", e),
+        }
+    }
+}
+
+impl Error for RemoteError {}
+
+/// Source of actions outside of the interfaces, read in its own thread.
+pub trait RemoteInputSource: Send {
+    /// Describes the source in the messages, such as `udp 0.0.0.0:7880`.
+    fn name(&self) -> String;
+
+    /// Waits for the next action, or returns `None` once the source is closed.
+    ///
+    /// After a [`RemoteError::InvalidCommand`], the source is read again, while the other
+    /// errors close it.
+    fn next_action(&mut self) -> Result<Option<Action>, RemoteError>;
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+enum Command {
+    Move { dir: Direction },
+    Reset,
+    Quit,
+}
+
+/// Reads a command, see the [module](self) for their format.
+pub fn parse_command(text: &str) -> Result<Action, RemoteError> {
+    let text = text.trim();
+    if text.starts_with('{') {
+        let command = serde_json::from_str(text)
+            .map_err(|_| RemoteError::InvalidCommand(text.to_string()))?;
+        return Ok(match command {
+            Command::Move { dir } => Action::Movement(dir),
+            Command::Reset => Action::ResetLevel,
+            Command::Quit => Action::Quit,
+        });
+    }
+
+    Ok(match &text.to_lowercase()[..] {
+        "l" | "left" => Action::Movement(Direction::Left),
+        "r" | "right" => Action::Movement(Direction::Right),
+        "u" | "up" => Action::Movement(Direction::Up),
+        "d" | "down" => Action::Movement(Direction::Down),
+        "re" | "reset" => Action::ResetLevel,
+        "qu" | "quit" => Action::Quit,
+        _ => return Err(RemoteError::InvalidCommand(text.to_string())),
+    })
+}
+
+/// Opens the source described by `spec`: `stdin` for the lines of the standard input,
+/// `udp:<address>` for the datagrams received at the address, or
+/// `mqtt:<host>[:<port>]/<topic>` for the messages published on a topic of a MQTT broker.
+pub fn open_source(spec: &str) -> Result<Box<dyn RemoteInputSource>, RemoteError> {
+    Ok(match spec.split_once(':') {
+        _ if spec == "stdin" => Box::new(StdinSource),
+        Some(("udp", addr)) => Box::new(UdpSource::bind(addr)?),
+        Some(("mqtt", broker)) => Box::new(MqttSource::connect(broker)?),
+        _ => return Err(RemoteError::UnknownSource(spec.to_string())),
+    })
+}
+
+/// Interface playing the actions of remote sources, and showing the game with another
+/// interface.
+///
+/// The levels are played in order, and the next one starts as soon as one is won. Once all
+/// the sources are closed, the game is quit.
+pub struct RemoteControlled {
+    ui: Box<dyn Ui>,
+    actions: Receiver<Action>,
+}
+
+impl RemoteControlled {
+    pub fn new(ui: Box<dyn Ui>, sources: Vec<Box<dyn RemoteInputSource>>) -> Self {
+        let (sender, actions) = mpsc::channel();
+        for mut source in sources {
+            let sender = sender.clone();
+            thread::spawn(move || loop {
+                match source.next_action() {
+                    Ok(Some(action)) => {
+                        if sender.send(action).is_err() {
+                            break;
+                        }
+                    }
+                    Ok(None) => break,
+                    Err(err @ RemoteError::InvalidCommand(_)) => {
+                        eprintln!("{}: {}", source.name(), err)
+                    }
+                    Err(err) => {
+                        eprintln!("{} closed: {}", source.name(), err);
+                        break;
+                    }
+                }
+            });
+        }
+        RemoteControlled { ui, actions }
+    }
+}
+
+impl Ui for RemoteControlled {
+    /// Reads the sources of [`SOURCES_ENV_VAR`], and shows the game with the command-line
+    /// interface.
+    fn initialize() -> Result<Self, Box<dyn Error>> {
+        let sources = env::var(SOURCES_ENV_VAR)
+            .unwrap_or_default()
+            .split(',')
+            .filter(|s| !s.trim().is_empty())
+            .map(|s| open_source(s.trim()))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(RemoteControlled::new(ui::new(DisplayKind::CLI)?, sources))
+    }
+
+    fn cleanup(self: Box<Self>) -> Result<(), Box<dyn Error>> {
+        self.ui.cleanup()
+    }
+
+    fn get_action(&mut self, _board: &Board) -> Result<Action, Box<dyn Error>> {
+        Ok(self.actions.recv().unwrap_or(Action::Quit))
+    }
+
+    fn display(
+        &mut self,
+        board: &Board,
+        last_move_result: Option<Option<(u32, u32)>>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.ui.display(board, last_move_result)
+    }
+
+    fn watch_replay(
+        &mut self,
+        board: &Board,
+        replay: &Replay,
+        speed: Option<u32>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.ui.watch_replay(board, replay, speed)
+    }
+
+    fn won(&mut self, _score: &Score, _best: Option<&Score>) -> Result<WonChoice, Box<dyn Error>> {
+        Ok(WonChoice::Next)
+    }
+
+    /// The display shows the time left, though it runs out only at the next remote action.
+    fn start_countdown(&mut self, limit: Option<TimeLimit>) {
+        self.ui.start_countdown(limit)
+    }
+
+    fn start_challenge(&mut self, limit: Option<MoveLimit>) {
+        self.ui.start_challenge(limit)
+    }
+
+    fn show_streak(&mut self, streak: Option<u32>) {
+        self.ui.show_streak(streak)
+    }
+
+    fn show_turn(&mut self, hotseat: Option<&Hotseat>) {
+        self.ui.show_turn(hotseat)
+    }
+
+    fn show_speedrun(&mut self, stopwatch: Option<Stopwatch>) {
+        self.ui.show_speedrun(stopwatch)
+    }
+
+    fn show_message(&mut self, message: &str) -> Result<(), Box<dyn Error>> {
+        self.ui.show_message(message)
+    }
+
+    fn lost(&mut self, _defeat: &Defeat) -> Result<LostChoice, Box<dyn Error>> {
+        Ok(LostChoice::Retry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_command, Action, Direction};
+
+    #[test]
+    fn it_parses_json_commands() {
+        assert_eq!(
+            parse_command(r#"{"action": "move", "dir": "left"}"#).ok(),
+            Some(Action::Movement(Direction::Left))
+        );
+        assert_eq!(
+            parse_command(r#" {"action": "reset"}"#).ok(),
+            Some(Action::ResetLevel)
+        );
+        assert!(parse_command(r#"{"action": "move", "dir": "back"}"#).is_err());
+    }
+
+    #[test]
+    fn it_parses_words() {
+        assert_eq!(
+            parse_command("Up\n").ok(),
+            Some(Action::Movement(Direction::Up))
+        );
+        assert_eq!(parse_command("qu").ok(), Some(Action::Quit));
+        assert_eq!(
+            parse_command("jump").map_err(|e| e.to_string()).err(),
+            Some("Invalid command `jump`.".to_string())
+        );
+    }
+}
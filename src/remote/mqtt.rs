@@ -0,0 +1,238 @@
+//! Commands published on a topic of a MQTT broker, as home automation systems do.
+//!
+//! Only what is needed to subscribe to a topic of MQTT 3.1.1 is implemented: the messages are
+//! received at most once, without authentication nor encryption.
+
+use std::{
+    io::{self, Read, Write},
+    net::TcpStream,
+    process, thread,
+    time::Duration,
+};
+
+use super::{parse_command, Action, RemoteError, RemoteInputSource};
+
+const DEFAULT_PORT: u16 = 1883;
+/// The broker closes the connection if nothing is sent for this long.
+const KEEP_ALIVE: Duration = Duration::from_secs(60);
+
+const CONNECT: u8 = 1;
+const CONNACK: u8 = 2;
+const PUBLISH: u8 = 3;
+const SUBSCRIBE: u8 = 8;
+const SUBACK: u8 = 9;
+const PINGREQ: u8 = 12;
+/// Return code of a refused subscription.
+const SUBSCRIPTION_FAILURE: u8 = 0x80;
+
+pub struct MqttSource {
+    broker: String,
+    topic: String,
+    stream: TcpStream,
+}
+
+impl MqttSource {
+    /// Subscribes to a topic of a broker, described as `<host>[:<port>]/<topic>`.
+    pub fn connect(spec: &str) -> Result<Self, RemoteError> {
+        let (broker, topic) =
+            parse_spec(spec).ok_or_else(|| RemoteError::UnknownSource(format!("mqtt:{}", spec)))?;
+        let mut stream = TcpStream::connect(&broker).map_err(RemoteError::IO)?;
+
+        let client_id = format!("sokoban-{}", process::id());
+        stream
+            .write_all(&connect_packet(&client_id))
+            .map_err(RemoteError::IO)?;
+        match read_packet(&mut stream).map_err(RemoteError::IO)? {
+            (kind, body) if kind >> 4 == CONNACK && body.get(1) == Some(&0) => (),
+            (kind, body) if kind >> 4 == CONNACK => {
+                return Err(RemoteError::Mqtt(format!(
+                    "connection refused with code {}",
+                    body.get(1).copied().unwrap_or_default()
+                )))
+            }
+            _ => return Err(RemoteError::Mqtt("connection not acknowledged".to_string())),
+        }
+        stream
+            .write_all(&subscribe_packet(&topic))
+            .map_err(RemoteError::IO)?;
+
+        // Pings the broker in the background, so it keeps the connection while no command
+        // comes.
+        let mut pinger = stream.try_clone().map_err(RemoteError::IO)?;
+        thread::spawn(move || loop {
+            thread::sleep(KEEP_ALIVE / 2);
+            if pinger.write_all(&packet(PINGREQ << 4, &[])).is_err() {
+                break;
+            }
+        });
+
+        Ok(MqttSource {
+            broker,
+            topic,
+            stream,
+        })
+    }
+}
+
+impl RemoteInputSource for MqttSource {
+    fn name(&self) -> String {
+        format!("mqtt {}/{}", self.broker, self.topic)
+    }
+
+    fn next_action(&mut self) -> Result<Option<Action>, RemoteError> {
+        loop {
+            let (kind, body) = match read_packet(&mut self.stream) {
+                Ok(packet) => packet,
+                Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+                Err(err) => return Err(RemoteError::IO(err)),
+            };
+            match kind >> 4 {
+                PUBLISH => {
+                    let Some((_, payload)) = parse_publish(kind, &body) else {
+                        return Err(RemoteError::Mqtt("invalid message".to_string()));
+                    };
+                    return parse_command(&String::from_utf8_lossy(payload)).map(Some);
+                }
+                SUBACK if body.get(2) == Some(&SUBSCRIPTION_FAILURE) => {
+                    return Err(RemoteError::Mqtt(format!(
+                        "subscription to `{}` refused",
+                        self.topic
+                    )))
+                }
+                // Acknowledgments and answers to the pings.
+                _ => (),
+            }
+        }
+    }
+}
+
+/// Splits `<host>[:<port>]/<topic>` into the address of the broker and the topic.
+fn parse_spec(spec: &str) -> Option<(String, String)> {
+    let (host, topic) = spec.split_once('/')?;
+    if host.is_empty() || topic.is_empty() {
+        return None;
+    }
+    let broker = if host.contains(':') {
+        host.to_string()
+    } else {
+        format!("{}:{}", host, DEFAULT_PORT)
+    };
+    Some((broker, topic.to_string()))
+}
+
+/// Packet made of its first byte, giving its kind and flags, and its `body`.
+fn packet(kind: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = vec![kind];
+    // The length is written 7 bits at a time, the highest bit telling if more follow.
+    let mut len = body.len();
+    loop {
+        let byte = (len % 128) as u8;
+        len /= 128;
+        if len == 0 {
+            packet.push(byte);
+            break;
+        }
+        packet.push(byte | 0x80);
+    }
+    packet.extend_from_slice(body);
+    packet
+}
+
+fn push_string(body: &mut Vec<u8>, string: &str) {
+    body.extend_from_slice(&(string.len() as u16).to_be_bytes());
+    body.extend_from_slice(string.as_bytes());
+}
+
+fn connect_packet(client_id: &str) -> Vec<u8> {
+    let mut body = Vec::new();
+    push_string(&mut body, "MQTT");
+    // Version 3.1.1, and a clean session.
+    body.extend_from_slice(&[4, 0x02]);
+    body.extend_from_slice(&(KEEP_ALIVE.as_secs() as u16).to_be_bytes());
+    push_string(&mut body, client_id);
+    packet(CONNECT << 4, &body)
+}
+
+fn subscribe_packet(topic: &str) -> Vec<u8> {
+    // Id of the packet, only one being sent.
+    let mut body = vec![0, 1];
+    push_string(&mut body, topic);
+    // Messages delivered at most once.
+    body.push(0);
+    packet((SUBSCRIBE << 4) | 0x02, &body)
+}
+
+/// Reads the next packet, returning its first byte and its body.
+fn read_packet(stream: &mut impl Read) -> Result<(u8, Vec<u8>), io::Error> {
+    let mut byte = [0];
+    stream.read_exact(&mut byte)?;
+    let kind = byte[0];
+
+    let mut len = 0;
+    for shift in (0..28).step_by(7) {
+        stream.read_exact(&mut byte)?;
+        len |= usize::from(byte[0] & 0x7f) << shift;
+        if byte[0] & 0x80 == 0 {
+            break;
+        }
+    }
+
+    let mut body = vec![0; len];
+    stream.read_exact(&mut body)?;
+    Ok((kind, body))
+}
+
+/// Topic and payload of a `PUBLISH` packet.
+fn parse_publish(kind: u8, body: &[u8]) -> Option<(&str, &[u8])> {
+    let topic_len = usize::from(u16::from_be_bytes([*body.first()?, *body.get(1)?]));
+    let topic = std::str::from_utf8(body.get(2..2 + topic_len)?).ok()?;
+    // The messages delivered more than at most once have an id.
+    let qos = (kind >> 1) & 0x03;
+    let payload_start = 2 + topic_len + if qos > 0 { 2 } else { 0 };
+    Some((topic, body.get(payload_start..)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{connect_packet, packet, parse_publish, parse_spec, read_packet, subscribe_packet};
+
+    #[test]
+    fn it_reads_the_broker_and_topic() {
+        assert_eq!(
+            parse_spec("localhost/home/sokoban"),
+            Some(("localhost:1883".to_string(), "home/sokoban".to_string()))
+        );
+        assert_eq!(
+            parse_spec("10.0.0.2:1884/buttons"),
+            Some(("10.0.0.2:1884".to_string(), "buttons".to_string()))
+        );
+        assert_eq!(parse_spec("localhost"), None);
+        assert_eq!(parse_spec("localhost/"), None);
+    }
+
+    #[test]
+    fn it_writes_packets() {
+        assert_eq!(
+            connect_packet("id"),
+            [0x10, 14, 0, 4, b'M', b'Q', b'T', b'T', 4, 0x02, 0, 60, 0, 2, b'i', b'd']
+        );
+        assert_eq!(
+            subscribe_packet("a/b"),
+            [0x82, 8, 0, 1, 0, 3, b'a', b'/', b'b', 0]
+        );
+        // The length takes two bytes from 128.
+        assert_eq!(packet(0x30, &[0; 200])[..3], [0x30, 0xc8, 0x01]);
+    }
+
+    #[test]
+    fn it_reads_messages() {
+        let message = packet(0x30, &[0, 1, b't', b'l', b'e', b'f', b't']);
+        let (kind, body) = read_packet(&mut &message[..]).unwrap();
+        assert_eq!(parse_publish(kind, &body), Some(("t", &b"left"[..])));
+
+        // With an id, when delivered at least once.
+        let message = packet(0x32, &[0, 1, b't', 0, 7, b'u', b'p']);
+        let (kind, body) = read_packet(&mut &message[..]).unwrap();
+        assert_eq!(parse_publish(kind, &body), Some(("t", &b"up"[..])));
+    }
+}
@@ -0,0 +1,25 @@
+//! Commands read on the lines of the standard input, for instance written by another program.
+
+use std::io;
+
+use super::{parse_command, Action, RemoteError, RemoteInputSource};
+
+pub struct StdinSource;
+
+impl RemoteInputSource for StdinSource {
+    fn name(&self) -> String {
+        "stdin".to_string()
+    }
+
+    fn next_action(&mut self) -> Result<Option<Action>, RemoteError> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            match io::stdin().read_line(&mut line).map_err(RemoteError::IO)? {
+                0 => return Ok(None),
+                _ if line.trim().is_empty() => continue,
+                _ => return parse_command(&line).map(Some),
+            }
+        }
+    }
+}
@@ -0,0 +1,38 @@
+//! Commands received as UDP datagrams, one per datagram, convenient for small devices.
+
+use std::net::UdpSocket;
+
+use super::{parse_command, Action, RemoteError, RemoteInputSource};
+
+/// Longest command read, the rest of a datagram is lost.
+const MAX_DATAGRAM_LEN: usize = 512;
+
+pub struct UdpSource {
+    socket: UdpSocket,
+}
+
+impl UdpSource {
+    pub fn bind(addr: &str) -> Result<Self, RemoteError> {
+        Ok(UdpSource {
+            socket: UdpSocket::bind(addr).map_err(RemoteError::IO)?,
+        })
+    }
+}
+
+impl RemoteInputSource for UdpSource {
+    fn name(&self) -> String {
+        match self.socket.local_addr() {
+            Ok(addr) => format!("udp {}", addr),
+            Err(_) => "udp".to_string(),
+        }
+    }
+
+    fn next_action(&mut self) -> Result<Option<Action>, RemoteError> {
+        let mut buffer = [0; MAX_DATAGRAM_LEN];
+        let (len, _) = self
+            .socket
+            .recv_from(&mut buffer)
+            .map_err(RemoteError::IO)?;
+        parse_command(&String::from_utf8_lossy(&buffer[..len])).map(Some)
+    }
+}
@@ -0,0 +1,295 @@
+//! Client of a repository of community level packs, to list, search, download and rate them.
+//!
+//! The repository is a REST API in JSON, whose address is given by [`REPO_URL_ENV_VAR`]:
+//!
+//! - `GET /packs` lists the packs, and `GET /packs?search=<words>` only the matching ones, as
+//!   an array of [`PackInfo`],
+//! - `GET /packs/<id>/levels` gives the levels of a pack, as an array of objects with a
+//!   `title` and the level in `xsb`,
+//! - `POST /packs/<id>/rating` with `{"rating": 4}` rates a pack from 1 to 5, and answers with
+//!   its updated [`PackInfo`].
+//!
+//! The packs are installed as directories of level files, which can be opened as a
+//! [`LevelLibrary`].
+
+use std::{
+    env,
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    data::{Level, LevelLibrary, LevelParseError, LibraryError},
+    tools::file_name,
+};
+
+/// Address of the level repository, such as `https://example.org/api`.
+pub const REPO_URL_ENV_VAR: &str = "SOKOBAN_REPO_URL";
+/// Directory where the packs are installed, `./packs` by default.
+pub const PACKS_DIR_ENV_VAR: &str = "SOKOBAN_PACKS_DIR";
+const DEFAULT_PACKS_DIR: &str = "packs";
+const TIMEOUT: Duration = Duration::from_secs(10);
+pub const MAX_RATING: u8 = 5;
+
+#[derive(Debug)]
+pub enum RepoError {
+    /// The request failed, or the repository answered with an error.
+    Http(String),
+    InvalidResponse(String),
+    InvalidLevel(String, LevelParseError),
+    InvalidRating(u8),
+    /// Pack ids only have ASCII letters, digits, `-` and `_`.
+    InvalidId(String),
+    IO(PathBuf, io::Error),
+    Library(LibraryError),
+}
+
+impl fmt::Display for RepoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use RepoError::*;
+        match self {
+            Http(e) => write!(f, "Request to the level repository failed: {}", e),
+            InvalidResponse(e) => write!(f, "Invalid answer of the level repository: {}", e),
+            InvalidLevel(title, e) => write!(f, "Invalid level `{}`: {}", title, e),
+            InvalidRating(r) => write!(f, "Invalid rating {}, it must be 1 to {}.", r, MAX_RATING),
+            InvalidId(id) => write!(f, "Invalid pack id `{}`.", id),
+            IO(path, e) => write!(f, "Could not write `{}`: {}", path.display(), e),
+            Library(e) => e.fmt(f),
+        }
+    }
+}
+
+impl Error for RepoError {}
+
+impl From<ureq::Error> for RepoError {
+    fn from(src: ureq::Error) -> Self {
+        RepoError::Http(src.to_string())
+    }
+}
+
+/// Description of a pack of the repository.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct PackInfo {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub author: String,
+    pub level_count: usize,
+    /// Average rating, from 1 to [`MAX_RATING`], if it was rated.
+    #[serde(default)]
+    pub rating: Option<f32>,
+    #[serde(default)]
+    pub votes: u32,
+}
+
+impl fmt::Display for PackInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if !self.author.is_empty() {
+            write!(f, " by {}", self.author)?;
+        }
+        write!(f, " ({} levels, ", self.level_count)?;
+        match self.rating {
+            Some(rating) => write!(f, "rated {:.1}/{} by {})", rating, MAX_RATING, self.votes),
+            None => write!(f, "not rated yet)"),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PackLevel {
+    title: String,
+    xsb: String,
+}
+
+/// Connection to a level repository.
+pub struct RepoClient {
+    url: String,
+    agent: ureq::Agent,
+}
+
+impl RepoClient {
+    pub fn new(url: &str) -> Self {
+        RepoClient {
+            url: url.trim_end_matches('/').to_string(),
+            agent: ureq::AgentBuilder::new().timeout(TIMEOUT).build(),
+        }
+    }
+
+    /// Client of the repository given by [`REPO_URL_ENV_VAR`], if any.
+    pub fn from_env() -> Option<Self> {
+        env::var(REPO_URL_ENV_VAR)
+            .ok()
+            .filter(|url| !url.is_empty())
+            .map(|url| RepoClient::new(&url))
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn list(&self) -> Result<Vec<PackInfo>, RepoError> {
+        let response = self.agent.get(&format!("{}/packs", self.url)).call()?;
+        parse_json(response)
+    }
+
+    /// Packs matching the `query`, or all of them if it is empty.
+    pub fn search(&self, query: &str) -> Result<Vec<PackInfo>, RepoError> {
+        if query.trim().is_empty() {
+            return self.list();
+        }
+        let response = self
+            .agent
+            .get(&format!("{}/packs", self.url))
+            .query("search", query.trim())
+            .call()?;
+        parse_json(response)
+    }
+
+    /// Levels of the pack `id`, in order.
+    pub fn download(&self, id: &str) -> Result<Vec<Level>, RepoError> {
+        let response = self
+            .agent
+            .get(&format!("{}/packs/{}/levels", self.url, check_id(id)?))
+            .call()?;
+        let levels: Vec<PackLevel> = parse_json(response)?;
+        levels
+            .into_iter()
+            .map(|l| {
+                Level::new(l.title.clone(), l.xsb).map_err(|e| RepoError::InvalidLevel(l.title, e))
+            })
+            .collect()
+    }
+
+    /// Gives the `rating` to the pack `id`, and returns its updated description.
+    pub fn rate(&self, id: &str, rating: u8) -> Result<PackInfo, RepoError> {
+        if !(1..=MAX_RATING).contains(&rating) {
+            return Err(RepoError::InvalidRating(rating));
+        }
+        let response = self
+            .agent
+            .post(&format!("{}/packs/{}/rating", self.url, check_id(id)?))
+            .send_json(json!({ "rating": rating }))?;
+        parse_json(response)
+    }
+
+    /// Downloads the pack `id` in [its directory](`pack_dir`), which is opened as a library.
+    /// The levels already solved in it stay solved.
+    pub fn install(&self, id: &str) -> Result<LevelLibrary, RepoError> {
+        let levels = self.download(id)?;
+        write_pack(&pack_dir(id), &levels)
+    }
+}
+
+/// Checks that the pack `id` can be put in a URL and a file name as is.
+fn check_id(id: &str) -> Result<&str, RepoError> {
+    if !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        Ok(id)
+    } else {
+        Err(RepoError::InvalidId(id.to_string()))
+    }
+}
+
+/// Writes the `levels` of a pack in `dir`, replacing the levels of a previous version of the
+/// pack but keeping the ones solved, and opens it as a library.
+fn write_pack(dir: &Path, levels: &[Level]) -> Result<LevelLibrary, RepoError> {
+    let io_error = |path: &Path| {
+        let path = path.to_path_buf();
+        move |e| RepoError::IO(path, e)
+    };
+    fs::create_dir_all(dir).map_err(io_error(dir))?;
+
+    for entry in fs::read_dir(dir).map_err(io_error(dir))? {
+        let path = entry.map_err(io_error(dir))?.path();
+        if path.extension().is_some_and(|e| e == "xsb") {
+            fs::remove_file(&path).map_err(io_error(&path))?;
+        }
+    }
+
+    for (index, level) in levels.iter().enumerate() {
+        // The number keeps the levels in the order of the pack.
+        let path = dir.join(format!("{:03}-{}.xsb", index + 1, file_name(level.title())));
+        fs::write(&path, level.source()).map_err(io_error(&path))?;
+    }
+
+    LevelLibrary::open(dir).map_err(RepoError::Library)
+}
+
+fn parse_json<T: serde::de::DeserializeOwned>(response: ureq::Response) -> Result<T, RepoError> {
+    response
+        .into_json()
+        .map_err(|e| RepoError::InvalidResponse(e.to_string()))
+}
+
+/// Directory where the pack `id` is installed, inside the one given by [`PACKS_DIR_ENV_VAR`].
+pub fn pack_dir(id: &str) -> PathBuf {
+    env::var_os(PACKS_DIR_ENV_VAR)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Path::new(DEFAULT_PACKS_DIR).to_path_buf())
+        .join(file_name(id))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{check_id, write_pack, PackInfo};
+    use crate::data::Level;
+
+    #[test]
+    fn it_reads_pack_descriptions() {
+        let packs: Vec<PackInfo> = serde_json::from_str(
+            r#"[
+                {"id": "micro", "name": "Microban", "author": "David Skinner",
+                 "level_count": 155, "rating": 4.52, "votes": 12},
+                {"id": "new", "name": "New pack", "level_count": 3}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            packs[0].to_string(),
+            "Microban by David Skinner (155 levels, rated 4.5/5 by 12)"
+        );
+        assert_eq!(packs[1].to_string(), "New pack (3 levels, not rated yet)");
+    }
+
+    #[test]
+    fn it_checks_pack_ids() {
+        assert!(check_id("micro_ban-2").is_ok());
+        assert!(check_id("").is_err());
+        assert!(check_id("../levels").is_err());
+        assert!(check_id("a b?c").is_err());
+    }
+
+    #[test]
+    fn it_installs_packs_in_order() {
+        let dir = std::env::temp_dir().join(format!("sokoban-pack-{}", std::process::id()));
+        let level = |title: &str| {
+            Level::new(title.to_string(), "#####\n#@$.#\n#####\n".to_string()).unwrap()
+        };
+
+        write_pack(&dir, &[level("First"), level("Old")]).unwrap();
+        fs::write(dir.join(".solved"), "First\n").unwrap();
+        // A new version of the pack replaces the old levels.
+        let library = write_pack(&dir, &[level("First"), level("Second")]).unwrap();
+
+        let mut files: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        files.sort();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(files, vec![".solved", "001-First.xsb", "002-Second.xsb"]);
+        let titles: Vec<_> = library.levels().iter().map(|l| l.title()).collect();
+        assert_eq!(titles, vec!["001-First", "002-Second"]);
+    }
+}
@@ -0,0 +1,298 @@
+//! Headless server of [game sessions](`GameSession`), for web frontends or bots to play
+//! through the network.
+//!
+//! Each connection gets its own session, driven with requests in JSON, one per line over
+//! TCP or one per text message over WebSocket. A request is an object with a `cmd` field, and
+//! the other fields depend on the command:
+//!
+//! - `{"cmd": "levels"}` lists the levels of the server,
+//! - `{"cmd": "load", "level": 2}` or `{"cmd": "load", "xsb": "..."}` starts a level, the
+//!   levels in XSB having at most 100 rows and columns,
+//! - `{"cmd": "move", "dir": "left"}` moves the player, `dir` being `left`, `right`, `up` or
+//!   `down`,
+//! - `{"cmd": "undo"}` and `{"cmd": "reset"}` cancel the last step or all of them,
+//! - `{"cmd": "state"}` only describes the board.
+//!
+//! Each request is answered with an object whose `ok` field tells if it succeeded. If it
+//! didn't, `error` tells why, otherwise most answers carry the `state` of the board.
+
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    sync::Arc,
+    thread,
+};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tungstenite::Message;
+
+use crate::data::{Board, Direction, LevelLibrary};
+
+/// Port used when none is given.
+pub const DEFAULT_SERVE_PORT: u16 = 7879;
+/// Width and height of the levels a client can load at most.
+const MAX_LEVEL_SIZE: usize = 100;
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "cmd", rename_all = "lowercase")]
+enum Request {
+    Levels,
+    Load {
+        level: Option<usize>,
+        xsb: Option<String>,
+    },
+    Move {
+        dir: Direction,
+    },
+    Undo,
+    Reset,
+    State,
+}
+
+/// Level played by a client of the server, starting with the first level of the library.
+pub struct GameSession {
+    library: Arc<LevelLibrary>,
+    title: String,
+    board: Board,
+}
+
+impl GameSession {
+    pub fn new(library: Arc<LevelLibrary>) -> Self {
+        let level = library
+            .get(0)
+            .expect("A library always contains at least one level.");
+        GameSession {
+            title: level.title().to_string(),
+            board: level.board().clone(),
+            library,
+        }
+    }
+
+    /// Answers a request in JSON, see the [module](self) for the available ones.
+    pub fn handle_json(&mut self, request: &str) -> String {
+        let response = serde_json::from_str(request)
+            .map_err(|e| format!("Invalid request : {}", e))
+            .and_then(|request| self.handle(request));
+        match response {
+            Ok(mut response) => {
+                response["ok"] = Value::Bool(true);
+                response
+            }
+            Err(error) => json!({ "ok": false, "error": error }),
+        }
+        .to_string()
+    }
+
+    fn handle(&mut self, request: Request) -> Result<Value, String> {
+        match request {
+            Request::Levels => {
+                let levels: Vec<Value> = self
+                    .library
+                    .levels()
+                    .iter()
+                    .enumerate()
+                    .map(|(index, level)| {
+                        json!({
+                            "index": index,
+                            "title": level.title(),
+                            "difficulty": level.difficulty().to_string(),
+                        })
+                    })
+                    .collect();
+                return Ok(json!({ "levels": levels }));
+            }
+            Request::Load {
+                level: Some(index),
+                xsb: None,
+            } => {
+                let level = self
+                    .library
+                    .get(index)
+                    .ok_or_else(|| format!("No level {}.", index))?;
+                self.title = level.title().to_string();
+                self.board = level.board().clone();
+            }
+            Request::Load {
+                level: None,
+                xsb: Some(xsb),
+            } => {
+                // The board is allocated before being checked.
+                if xsb.lines().count() > MAX_LEVEL_SIZE
+                    || xsb.lines().any(|l| l.chars().count() > MAX_LEVEL_SIZE)
+                {
+                    return Err(format!("The level is larger than {0}x{0}.", MAX_LEVEL_SIZE));
+                }
+                self.board = Board::from_xsb(&xsb).map_err(|e| e.to_string())?;
+                self.title = "Custom level".to_string();
+            }
+            Request::Load { .. } => return Err("Give either a `level` or a `xsb`.".to_string()),
+            Request::Move { dir } => {
+                let res = self.board.do_move_player(dir);
+                return Ok(json!({
+                    "moved": res.is_some(),
+                    "pushed": matches!(res, Some(Some(_))),
+                    "state": self.state(),
+                }));
+            }
+            Request::Undo => {
+                let undone = self.board.undo().is_some();
+                return Ok(json!({ "undone": undone, "state": self.state() }));
+            }
+            Request::Reset => self.board.reset(),
+            Request::State => (),
+        }
+        Ok(json!({ "state": self.state() }))
+    }
+
+    fn state(&self) -> Value {
+        let xsb = self.board.position_to_xsb();
+        let rows: Vec<&str> = xsb.lines().collect();
+        json!({
+            "title": self.title,
+            "rows": rows,
+            "moves": self.board.moves(),
+            "pushes": self.board.pushes(),
+            "crates": self.board.crate_count(),
+            "placed": self.board.placed_crates(),
+            "won": self.board.has_won(),
+            "replay": self.board.replay().to_string(),
+        })
+    }
+}
+
+/// Serves sessions on the levels of the `library` on all interfaces at `port`, until the
+/// process is stopped.
+pub fn serve(library: LevelLibrary, port: u16) -> Result<(), io::Error> {
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    println!("Serving on {}", listener.local_addr()?);
+
+    let library = Arc::new(library);
+    for stream in listener.incoming().flatten() {
+        let library = library.clone();
+        thread::spawn(move || {
+            let peer = stream
+                .peer_addr()
+                .map(|a| a.to_string())
+                .unwrap_or_default();
+            println!("{} connected", peer);
+            if let Err(err) = handle_connection(stream, GameSession::new(library)) {
+                println!("{} failed : {}", peer, err);
+            }
+            println!("{} disconnected", peer);
+        });
+    }
+    Ok(())
+}
+
+/// Answers the requests of a client until they leave, over WebSocket if the connection starts
+/// as an HTTP request or else over lines of TCP.
+fn handle_connection(mut stream: TcpStream, mut session: GameSession) -> Result<(), io::Error> {
+    let mut start = [0; 4];
+    let read = stream.peek(&mut start)?;
+
+    if &start[..read] == b"GET " {
+        let mut socket =
+            tungstenite::accept(stream).map_err(|e| io::Error::other(e.to_string()))?;
+        loop {
+            match socket.read() {
+                Ok(Message::Text(request)) => socket
+                    .send(Message::Text(session.handle_json(&request)))
+                    .map_err(|e| io::Error::other(e.to_string()))?,
+                Ok(Message::Close(_)) | Err(tungstenite::Error::ConnectionClosed) => break,
+                Ok(_) => (),
+                Err(err) => return Err(io::Error::other(err.to_string())),
+            }
+        }
+    } else {
+        let reader = BufReader::new(stream.try_clone()?);
+        for request in reader.lines() {
+            let request = request?;
+            if !request.trim().is_empty() {
+                writeln!(stream, "{}", session.handle_json(&request))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::Value;
+
+    use super::GameSession;
+    use crate::data::{Level, LevelLibrary};
+
+    fn session() -> GameSession {
+        let level = Level::new("Test".to_string(), "#####\n#@$.#\n#####\n".to_string()).unwrap();
+        GameSession::new(Arc::new(LevelLibrary::from_level(level)))
+    }
+
+    fn request(session: &mut GameSession, request: &str) -> Value {
+        serde_json::from_str(&session.handle_json(request)).unwrap()
+    }
+
+    #[test]
+    fn it_moves_and_undoes() {
+        let mut session = session();
+        let res = request(&mut session, r#"{"cmd": "move", "dir": "right"}"#);
+        assert_eq!(res["ok"], true);
+        assert_eq!(res["pushed"], true);
+        assert_eq!(res["state"]["won"], true);
+        assert_eq!(res["state"]["rows"][1], "# @*#");
+
+        let res = request(&mut session, r#"{"cmd": "undo"}"#);
+        assert_eq!(res["undone"], true);
+        assert_eq!(res["state"]["moves"], 0);
+        assert_eq!(res["state"]["rows"][1], "#@$.#");
+    }
+
+    #[test]
+    fn it_loads_levels() {
+        let mut session = session();
+        let res = request(&mut session, r#"{"cmd": "levels"}"#);
+        assert_eq!(res["levels"][0]["title"], "Test");
+
+        let res = request(
+            &mut session,
+            "{\"cmd\": \"load\", \"xsb\": \"####\\n#@.#\\n####\"}",
+        );
+        assert_eq!(res["state"]["crates"], 0);
+        let res = request(&mut session, r#"{"cmd": "load", "level": 3}"#);
+        assert_eq!(res["ok"], false);
+        assert_eq!(res["error"], "No level 3.");
+    }
+
+    #[test]
+    fn it_stays_in_custom_levels() {
+        let mut session = session();
+        // The player is on the edge of the board, which isn't closed by walls.
+        let res = request(&mut session, r#"{"cmd": "load", "xsb": "@$."}"#);
+        assert_eq!(res["ok"], true);
+        let res = request(&mut session, r#"{"cmd": "move", "dir": "left"}"#);
+        assert_eq!(res["moved"], false);
+        let res = request(&mut session, r#"{"cmd": "move", "dir": "up"}"#);
+        assert_eq!(res["moved"], false);
+
+        let xsb = format!("#@$.{}", "#".repeat(100));
+        let res = request(
+            &mut session,
+            &format!(r#"{{"cmd": "load", "xsb": "{}"}}"#, xsb),
+        );
+        assert_eq!(res["ok"], false);
+        assert_eq!(res["error"], "The level is larger than 100x100.");
+    }
+
+    #[test]
+    fn it_rejects_invalid_requests() {
+        let mut session = session();
+        let res = request(&mut session, r#"{"cmd": "jump"}"#);
+        assert_eq!(res["ok"], false);
+        assert!(res["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("Invalid request"));
+    }
+}
@@ -0,0 +1,248 @@
+//! Speedrun mode: the levels of a pack are played in order against a single timer, split at
+//! each level won, and the times are exported for [LiveSplit](https://livesplit.org).
+//!
+//! The timer only runs while a level is played: it is paused on the menus and between the
+//! levels.
+
+use std::{
+    fs, io,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use crate::data::LevelLibrary;
+
+/// Name of the game in the exported splits.
+const GAME_NAME: &str = "Sokoban";
+/// Category of the runs in the exported splits, as all the levels must be won.
+const CATEGORY_NAME: &str = "All levels";
+
+/// Timer which can be paused, counting from zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stopwatch {
+    /// Time counted before the last start.
+    counted: Duration,
+    /// When the stopwatch was last started, if it is running.
+    running_since: Option<Instant>,
+}
+
+impl Stopwatch {
+    /// Starts counting again, if it was paused.
+    pub fn start(&mut self) {
+        self.running_since.get_or_insert_with(Instant::now);
+    }
+
+    /// Stops counting until the next [start](Self::start).
+    pub fn pause(&mut self) {
+        if let Some(since) = self.running_since.take() {
+            self.counted += since.elapsed();
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running_since.is_some()
+    }
+
+    /// Time counted while it was running.
+    pub fn elapsed(&self) -> Duration {
+        self.counted + self.running_since.map_or(Duration::ZERO, |s| s.elapsed())
+    }
+}
+
+/// Run through the levels of a pack, see the [module](self).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Speedrun {
+    /// Titles of the levels, in the order they must be won.
+    titles: Vec<String>,
+    /// Time of the timer when each of the levels won so far was won.
+    splits: Vec<Duration>,
+    stopwatch: Stopwatch,
+}
+
+impl Speedrun {
+    /// Run through all the levels of the `library`, with the timer paused until the first one
+    /// starts.
+    pub fn new(library: &LevelLibrary) -> Self {
+        Speedrun {
+            titles: library
+                .levels()
+                .iter()
+                .map(|l| l.title().to_string())
+                .collect(),
+            splits: Vec::new(),
+            stopwatch: Stopwatch::default(),
+        }
+    }
+
+    pub fn stopwatch(&self) -> Stopwatch {
+        self.stopwatch
+    }
+
+    /// The level being played starts or resumes.
+    pub fn start(&mut self) {
+        if !self.is_finished() {
+            self.stopwatch.start();
+        }
+    }
+
+    /// The level being played is interrupted, by a menu for instance.
+    pub fn pause(&mut self) {
+        self.stopwatch.pause();
+    }
+
+    /// Total time of the run so far.
+    pub fn elapsed(&self) -> Duration {
+        self.stopwatch.elapsed()
+    }
+
+    /// Index of the level to win next, or the number of levels once the run is finished.
+    pub fn current(&self) -> usize {
+        self.splits.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.titles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.titles.is_empty()
+    }
+
+    /// If all the levels were won.
+    pub fn is_finished(&self) -> bool {
+        self.current() >= self.len()
+    }
+
+    /// The current level was won: pauses the timer and records its split, returning the time
+    /// spent on the level.
+    pub fn split(&mut self) -> Duration {
+        self.pause();
+        let time = self.elapsed();
+        let segment = time - self.splits.last().copied().unwrap_or_default();
+        if !self.is_finished() {
+            self.splits.push(time);
+        }
+        segment
+    }
+
+    /// Time of the timer when each of the levels won so far was won, with their titles.
+    pub fn splits(&self) -> impl Iterator<Item = (&str, Duration)> {
+        self.titles
+            .iter()
+            .map(|t| &t[..])
+            .zip(self.splits.iter().copied())
+    }
+
+    /// Splits of the run in the format of LiveSplit (`.lss`), the levels not won yet having no
+    /// time.
+    pub fn to_livesplit(&self) -> String {
+        let mut lss = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        lss.push_str("<Run version=\"1.7.0\">\n  <GameIcon />\n");
+        lss.push_str(&format!("  <GameName>{}</GameName>\n", GAME_NAME));
+        lss.push_str(&format!(
+            "  <CategoryName>{}</CategoryName>\n",
+            CATEGORY_NAME
+        ));
+        lss.push_str("  <Offset>00:00:00</Offset>\n  <AttemptCount>1</AttemptCount>\n");
+        lss.push_str("  <AttemptHistory />\n  <Segments>\n");
+        let mut previous = Duration::ZERO;
+        for (index, title) in self.titles.iter().enumerate() {
+            lss.push_str(&format!(
+                "    <Segment>\n      <Name>{}</Name>\n      <Icon />\n",
+                xml_escape(title)
+            ));
+            match self.splits.get(index) {
+                Some(&time) => {
+                    lss.push_str(&format!(
+                        "      <SplitTimes>\n        <SplitTime name=\"Personal Best\">\n          <RealTime>{}</RealTime>\n        </SplitTime>\n      </SplitTimes>\n",
+                        livesplit_time(time)
+                    ));
+                    lss.push_str(&format!(
+                        "      <BestSegmentTime>\n        <RealTime>{}</RealTime>\n      </BestSegmentTime>\n",
+                        livesplit_time(time - previous)
+                    ));
+                    previous = time;
+                }
+                None => lss.push_str(
+                    "      <SplitTimes>\n        <SplitTime name=\"Personal Best\" />\n      </SplitTimes>\n      <BestSegmentTime />\n",
+                ),
+            }
+            lss.push_str("      <SegmentHistory />\n    </Segment>\n");
+        }
+        lss.push_str("  </Segments>\n  <AutoSplitterSettings />\n</Run>\n");
+        lss
+    }
+
+    /// Writes the [splits](Self::to_livesplit) to the file at `path`.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_livesplit())
+    }
+}
+
+/// Formats a duration as LiveSplit does, `hh:mm:ss.fffffff`.
+fn livesplit_time(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!(
+        "{:02}:{:02}:{:02}.{:07}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        duration.subsec_nanos() / 100
+    )
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{livesplit_time, Speedrun, Stopwatch};
+    use crate::data::{Level, LevelLibrary};
+
+    #[test]
+    fn it_only_counts_while_running() {
+        let mut stopwatch = Stopwatch::default();
+        assert_eq!(stopwatch.elapsed(), Duration::ZERO);
+        stopwatch.start();
+        assert!(stopwatch.is_running());
+        stopwatch.pause();
+        let paused = stopwatch.elapsed();
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(stopwatch.elapsed(), paused);
+    }
+
+    #[test]
+    fn it_exports_the_splits_for_livesplit() {
+        assert_eq!(
+            livesplit_time(Duration::from_millis(3_723_456)),
+            "01:02:03.4560000"
+        );
+
+        let level = Level::new("A & B".to_string(), "#####\n#@$.#\n#####\n".to_string());
+        let mut run = Speedrun::new(&LevelLibrary::from_level(level.unwrap()));
+        // The levels not won yet have no time.
+        let lss = run.to_livesplit();
+        assert!(lss.contains("<Name>A &amp; B</Name>"));
+        assert!(lss.contains(r#"<SplitTime name="Personal Best" />"#));
+        assert!(!lss.contains("<RealTime>"));
+
+        run.start();
+        let segment = run.split();
+        assert!(run.is_finished());
+        assert!(!run.stopwatch().is_running());
+        assert_eq!(run.splits().collect::<Vec<_>>(), vec![("A & B", segment)]);
+        // Nothing is counted once the run is finished.
+        run.start();
+        assert!(!run.stopwatch().is_running());
+
+        let lss = run.to_livesplit();
+        assert_eq!(lss.matches("<RealTime>").count(), 2);
+        assert!(!lss.contains(r#"<SplitTime name="Personal Best" />"#));
+    }
+}
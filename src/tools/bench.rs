@@ -0,0 +1,490 @@
+//! Runs of the [solver](`Board::solve`) on many levels, to see how changes to it affect the
+//! levels it solves and how fast. The reports are written in CSV or JSON, and a CSV one can be
+//! read back to compare it with a later run.
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt,
+    time::{Duration, Instant},
+};
+
+use super::{json_string, PackLevel};
+#[cfg(doc)]
+use crate::data::Board;
+use crate::data::{SolverError, SolverLimits};
+
+const CSV_HEADER: &str = "pack,level,crates,status,moves,pushes,explored_states,time_ms,memory_kb";
+/// Rough size of a position kept by the solver, in its queue and among the visited ones,
+/// without its crates.
+const STATE_BYTES: usize = 96;
+/// Bytes added to a position by each crate.
+const CRATE_BYTES: usize = 24;
+
+/// Memory the solver roughly needs to explore `states` positions of a level with `crates`.
+pub fn estimated_memory(crates: usize, states: usize) -> usize {
+    states * (STATE_BYTES + crates * CRATE_BYTES)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum BenchError {
+    /// A line of a CSV report can't be read.
+    InvalidCsv { line: usize, reason: String },
+}
+
+impl fmt::Display for BenchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BenchError::InvalidCsv { line, reason } => {
+                write!(f, "Invalid report at line {}: {}", line, reason)
+            }
+        }
+    }
+}
+
+impl Error for BenchError {}
+
+/// Budgets of the solver for each level.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BenchOptions {
+    pub limits: SolverLimits,
+    /// Bytes the solver may use, as [estimated](estimated_memory) from the positions explored.
+    pub max_memory: Option<usize>,
+}
+
+impl BenchOptions {
+    /// Limits of the solver for a level with `crates`, the memory budget lowering the number of
+    /// positions explored.
+    fn limits(&self, crates: usize) -> SolverLimits {
+        let mut limits = self.limits;
+        if let Some(bytes) = self.max_memory {
+            let states = bytes / estimated_memory(crates, 1);
+            limits.max_states = limits.max_states.min(states.max(1));
+        }
+        limits
+    }
+}
+
+/// Result of the solver on a level.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BenchRow {
+    /// File or directory the level comes from.
+    pub pack: String,
+    pub level: String,
+    pub crates: usize,
+    /// `solved`, `unsolvable`, `limit` or `timeout`.
+    pub status: String,
+    /// Length of the solution, if found.
+    pub moves: Option<usize>,
+    pub pushes: Option<usize>,
+    pub explored_states: usize,
+    pub time: Duration,
+    /// [Estimated](estimated_memory) bytes used.
+    pub memory: usize,
+}
+
+impl BenchRow {
+    pub fn is_solved(&self) -> bool {
+        self.status == "solved"
+    }
+}
+
+/// Solves the `level` of the `pack` within the budgets of the `options`.
+pub fn bench_level(pack: &str, level: &PackLevel, options: &BenchOptions) -> BenchRow {
+    let crates = level.board.crate_count();
+    let start = Instant::now();
+    let result = level.board.solve(&options.limits(crates));
+    let time = start.elapsed();
+
+    let (status, explored_states, solution) = match result {
+        Ok(solution) => ("solved", solution.explored_states, Some(solution.replay)),
+        Err(SolverError::Unsolvable { explored_states }) => ("unsolvable", explored_states, None),
+        Err(SolverError::LimitReached { explored_states }) => ("limit", explored_states, None),
+        Err(SolverError::TimeOut { explored_states }) => ("timeout", explored_states, None),
+    };
+    BenchRow {
+        pack: pack.to_string(),
+        level: level.title.clone(),
+        crates,
+        status: status.to_string(),
+        moves: solution.as_ref().map(|s| s.len()),
+        pushes: solution.as_ref().map(|s| s.pushes()),
+        explored_states,
+        time,
+        memory: estimated_memory(crates, explored_states),
+    }
+}
+
+/// Results of a run of the solver.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BenchReport {
+    pub rows: Vec<BenchRow>,
+}
+
+impl BenchReport {
+    pub fn solved(&self) -> usize {
+        self.rows.iter().filter(|r| r.is_solved()).count()
+    }
+
+    pub fn total_time(&self) -> Duration {
+        self.rows.iter().map(|r| r.time).sum()
+    }
+
+    /// The report with a header line and a line per level, the times in milliseconds and the
+    /// memory in kilobytes.
+    pub fn to_csv(&self) -> String {
+        let mut csv = format!("{}\n", CSV_HEADER);
+        for row in self.rows.iter() {
+            let optional = |n: Option<usize>| n.map_or(String::new(), |n| n.to_string());
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{},{:.3},{}\n",
+                csv_field(&row.pack),
+                csv_field(&row.level),
+                row.crates,
+                row.status,
+                optional(row.moves),
+                optional(row.pushes),
+                row.explored_states,
+                row.time.as_secs_f64() * 1000.,
+                row.memory / 1024
+            ));
+        }
+        csv
+    }
+
+    /// Reads a report written by [`BenchReport::to_csv`].
+    pub fn from_csv(src: &str) -> Result<Self, BenchError> {
+        let mut lines = src
+            .lines()
+            .enumerate()
+            .filter(|(_, l)| !l.trim().is_empty());
+        if lines.next().map(|(_, l)| l.trim()) != Some(CSV_HEADER) {
+            return Err(BenchError::InvalidCsv {
+                line: 1,
+                reason: "missing header".to_string(),
+            });
+        }
+
+        let rows = lines
+            .map(|(index, line)| {
+                let invalid = |reason: &str| BenchError::InvalidCsv {
+                    line: index + 1,
+                    reason: reason.to_string(),
+                };
+                let fields = split_csv_line(line).ok_or_else(|| invalid("unclosed quote"))?;
+                let [pack, level, crates, status, moves, pushes, states, time, memory] =
+                    <[String; 9]>::try_from(fields).map_err(|_| invalid("expected 9 fields"))?;
+
+                let number = |n: &str| n.parse().map_err(|_| invalid("invalid number"));
+                let optional = |n: &str| (!n.is_empty()).then(|| number(n)).transpose();
+                let time_ms = time
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|t| t.is_finite() && *t >= 0.)
+                    .ok_or_else(|| invalid("invalid time"))?;
+                Ok(BenchRow {
+                    pack,
+                    level,
+                    crates: number(&crates)?,
+                    status,
+                    moves: optional(&moves)?,
+                    pushes: optional(&pushes)?,
+                    explored_states: number(&states)?,
+                    time: Duration::from_micros((time_ms * 1000.).round() as u64),
+                    memory: number(&memory)? * 1024,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(BenchReport { rows })
+    }
+
+    /// The report as a JSON object, with the `limits` of the solver and a `levels` list of
+    /// objects with the same fields as the [CSV](BenchReport::to_csv) ones.
+    pub fn to_json(&self, options: &BenchOptions) -> String {
+        let optional = |n: Option<usize>| n.map_or("null".to_string(), |n| n.to_string());
+        let levels: Vec<String> = self
+            .rows
+            .iter()
+            .map(|row| {
+                format!(
+                    "    {{\"pack\": {}, \"level\": {}, \"crates\": {}, \"status\": \"{}\", \"moves\": {}, \"pushes\": {}, \"explored_states\": {}, \"time_ms\": {:.3}, \"memory_kb\": {}}}",
+                    json_string(&row.pack),
+                    json_string(&row.level),
+                    row.crates,
+                    row.status,
+                    optional(row.moves),
+                    optional(row.pushes),
+                    row.explored_states,
+                    row.time.as_secs_f64() * 1000.,
+                    row.memory / 1024
+                )
+            })
+            .collect();
+        format!(
+            "{{\n  \"limits\": {{\"max_states\": {}, \"max_time_ms\": {}, \"max_memory_kb\": {}}},\n  \"solved\": {},\n  \"levels\": [\n{}\n  ]\n}}\n",
+            options.limits.max_states,
+            optional(options.limits.max_time.map(|t| t.as_millis() as usize)),
+            optional(options.max_memory.map(|m| m / 1024)),
+            self.solved(),
+            levels.join(",\n")
+        )
+    }
+
+    /// Differences with an earlier run on the same levels.
+    pub fn compare(&self, baseline: &BenchReport) -> BenchComparison {
+        let before: HashMap<_, _> = baseline
+            .rows
+            .iter()
+            .map(|r| ((&r.pack, &r.level), r))
+            .collect();
+
+        let mut comparison = BenchComparison::default();
+        for row in self.rows.iter() {
+            let Some(old) = before.get(&(&row.pack, &row.level)) else {
+                continue;
+            };
+            comparison.levels += 1;
+            let name = format!("{}: {}", row.pack, row.level);
+            match (old.is_solved(), row.is_solved()) {
+                (true, true) => {
+                    comparison.time.0 += old.time;
+                    comparison.time.1 += row.time;
+                    comparison.states.0 += old.explored_states;
+                    comparison.states.1 += row.explored_states;
+                }
+                (false, true) => comparison.newly_solved.push(name),
+                (true, false) => comparison.no_longer_solved.push(name),
+                (false, false) => (),
+            }
+        }
+        comparison
+    }
+}
+
+impl fmt::Display for BenchReport {
+    /// A table of the results, followed by their totals.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .rows
+            .iter()
+            .map(|r| r.level.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max(5);
+        writeln!(
+            f,
+            "{:<width$}  {:<10}  {:>7}  {:>7}  {:>10}  {:>10}  {:>11}",
+            "Level", "Status", "Moves", "Pushes", "Positions", "Time (ms)", "Memory (kB)"
+        )?;
+        for row in self.rows.iter() {
+            let optional = |n: Option<usize>| n.map_or("-".to_string(), |n| n.to_string());
+            writeln!(
+                f,
+                "{:<width$}  {:<10}  {:>7}  {:>7}  {:>10}  {:>10.1}  {:>11}",
+                row.level,
+                row.status,
+                optional(row.moves),
+                optional(row.pushes),
+                row.explored_states,
+                row.time.as_secs_f64() * 1000.,
+                row.memory / 1024
+            )?;
+        }
+        write!(
+            f,
+            "{}/{} levels solved in {:.2}s, {} positions explored",
+            self.solved(),
+            self.rows.len(),
+            self.total_time().as_secs_f32(),
+            self.rows.iter().map(|r| r.explored_states).sum::<usize>()
+        )
+    }
+}
+
+/// How a run of the solver differs from an earlier one, see [`BenchReport::compare`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BenchComparison {
+    /// Levels found in both runs.
+    pub levels: usize,
+    pub newly_solved: Vec<String>,
+    pub no_longer_solved: Vec<String>,
+    /// Time spent before and after on the levels solved by both runs.
+    pub time: (Duration, Duration),
+    /// Positions explored before and after on the levels solved by both runs.
+    pub states: (usize, usize),
+}
+
+impl BenchComparison {
+    /// If some levels aren't solved anymore.
+    pub fn is_regression(&self) -> bool {
+        !self.no_longer_solved.is_empty()
+    }
+}
+
+impl fmt::Display for BenchComparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let change = |before: f64, after: f64| {
+            if before > 0. {
+                format!(" ({:+.1}%)", (after - before) / before * 100.)
+            } else {
+                String::new()
+            }
+        };
+        let (time_before, time_after) = (self.time.0.as_secs_f64(), self.time.1.as_secs_f64());
+        writeln!(f, "Compared with the baseline on {} levels:", self.levels)?;
+        writeln!(
+            f,
+            "  time on the levels solved by both: {:.2}s -> {:.2}s{}",
+            time_before,
+            time_after,
+            change(time_before, time_after)
+        )?;
+        write!(
+            f,
+            "  positions explored on them: {} -> {}{}",
+            self.states.0,
+            self.states.1,
+            change(self.states.0 as f64, self.states.1 as f64)
+        )?;
+        for level in self.newly_solved.iter() {
+            write!(f, "\n  newly solved: {}", level)?;
+        }
+        for level in self.no_longer_solved.iter() {
+            write!(f, "\n  no longer solved: {}", level)?;
+        }
+        Ok(())
+    }
+}
+
+/// `text` quoted if it holds a comma, a quote or a line break.
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Fields of a CSV line, or `None` if a quote isn't closed.
+fn split_csv_line(line: &str) -> Option<Vec<String>> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.next_if_eq(&'"').is_some() => field.push('"'),
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    if quoted {
+        return None;
+    }
+    fields.push(field);
+    Some(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{bench_level, split_csv_line, BenchError, BenchOptions, BenchReport};
+    use crate::{
+        data::{Board, SolverLimits},
+        tools::PackLevel,
+    };
+
+    fn level(title: &str, xsb: &str) -> PackLevel {
+        PackLevel {
+            title: title.to_string(),
+            ..PackLevel::new(Board::from_xsb(xsb).unwrap())
+        }
+    }
+
+    fn run(options: &BenchOptions) -> BenchReport {
+        let levels = [
+            level("Easy, short", "#######\n#@ $ .#\n#######\n"),
+            level("Two", "#######\n#.$@$.#\n#######\n"),
+        ];
+        BenchReport {
+            rows: levels
+                .iter()
+                .map(|l| bench_level("pack.xsb", l, options))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn it_runs_the_solver_within_budgets() {
+        let options = BenchOptions::default();
+        let report = run(&options);
+        assert_eq!(report.solved(), 2);
+        assert_eq!(report.rows[0].moves, Some(3));
+        assert_eq!(report.rows[0].pushes, Some(2));
+
+        let options = BenchOptions {
+            limits: SolverLimits {
+                max_states: 1,
+                ..SolverLimits::default()
+            },
+            max_memory: None,
+        };
+        let report = run(&options);
+        assert_eq!(report.solved(), 0);
+        assert_eq!(report.rows[0].status, "limit");
+        assert_eq!(report.rows[0].moves, None);
+
+        // Less than a position per level.
+        let options = BenchOptions {
+            max_memory: Some(1),
+            ..BenchOptions::default()
+        };
+        assert_eq!(run(&options).rows[1].status, "limit");
+    }
+
+    #[test]
+    fn it_reads_back_csv_reports() {
+        let mut report = run(&BenchOptions::default());
+        for row in report.rows.iter_mut() {
+            row.time = Duration::from_micros(1500);
+            row.memory = 2048;
+        }
+        let csv = report.to_csv();
+        assert!(csv.contains("pack.xsb,\"Easy, short\",1,solved,3,2,"));
+        assert_eq!(BenchReport::from_csv(&csv).unwrap(), report);
+        assert_eq!(
+            BenchReport::from_csv("level,status\n"),
+            Err(BenchError::InvalidCsv {
+                line: 1,
+                reason: "missing header".to_string()
+            })
+        );
+        assert_eq!(
+            split_csv_line("a,\"b \"\"c\"\", d\",").unwrap(),
+            vec!["a", "b \"c\", d", ""]
+        );
+    }
+
+    #[test]
+    fn it_compares_runs() {
+        let baseline = run(&BenchOptions::default());
+        let mut current = baseline.clone();
+        current.rows[0].status = "limit".to_string();
+        current.rows[1].explored_states *= 2;
+
+        let comparison = current.compare(&baseline);
+        assert_eq!(comparison.levels, 2);
+        assert!(comparison.is_regression());
+        assert_eq!(comparison.no_longer_solved, vec!["pack.xsb: Easy, short"]);
+        assert_eq!(
+            comparison.states,
+            (
+                baseline.rows[1].explored_states,
+                current.rows[1].explored_states
+            )
+        );
+        assert!(!baseline.compare(&current).is_regression());
+    }
+}
@@ -0,0 +1,129 @@
+//! Packs in JSON, as an object with the metadata of the pack and its `levels`:
+//!
+//! ```json
+//! {
+//!   "title": "Tiny",
+//!   "author": "Someone",
+//!   "levels": [{ "title": "First", "xsb": "#####\n#@$.#\n#####\n" }]
+//! }
+//! ```
+//!
+//! A list of levels alone, as given by the level repositories, is read too.
+
+use serde::{Deserialize, Serialize};
+
+use super::{ConvertError, LevelPack, PackLevel};
+use crate::data::{Board, LevelParseError};
+
+#[derive(Serialize, Deserialize)]
+struct JsonPack {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    title: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    author: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    description: String,
+    levels: Vec<JsonLevel>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonLevel {
+    #[serde(default)]
+    title: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    author: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    comment: String,
+    xsb: String,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonFile {
+    Pack(JsonPack),
+    Levels(Vec<JsonLevel>),
+}
+
+pub(super) fn parse(src: &str) -> Result<LevelPack, ConvertError> {
+    let pack = match serde_json::from_str(src) {
+        Ok(JsonFile::Pack(pack)) => pack,
+        Ok(JsonFile::Levels(levels)) => JsonPack {
+            title: String::new(),
+            author: String::new(),
+            description: String::new(),
+            levels,
+        },
+        Err(e) => return Err(ConvertError::InvalidJson(e.to_string())),
+    };
+
+    let levels = pack
+        .levels
+        .into_iter()
+        .enumerate()
+        .map(|(index, level)| {
+            let board = Board::from_xsb(&level.xsb).map_err(|e| {
+                ConvertError::InvalidLevel(
+                    if level.title.is_empty() {
+                        (index + 1).to_string()
+                    } else {
+                        level.title.clone()
+                    },
+                    LevelParseError::CantParseXsb(e),
+                )
+            })?;
+            Ok(PackLevel {
+                title: level.title,
+                author: level.author,
+                comment: level.comment,
+                ..PackLevel::new(board)
+            })
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(LevelPack {
+        title: pack.title,
+        author: pack.author,
+        description: pack.description,
+        levels,
+    })
+}
+
+pub(super) fn write(pack: &LevelPack) -> String {
+    let pack = JsonPack {
+        title: pack.title.clone(),
+        author: pack.author.clone(),
+        description: pack.description.clone(),
+        levels: pack
+            .levels
+            .iter()
+            .map(|level| JsonLevel {
+                title: level.title.clone(),
+                author: level.author.clone(),
+                comment: level.comment.clone(),
+                xsb: level.board.to_xsb(),
+            })
+            .collect(),
+    };
+    let mut json = serde_json::to_string_pretty(&pack).expect("Packs should be serializable");
+    json.push('\n');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{parse, write};
+
+    #[test]
+    fn it_reads_packs_and_lists_of_levels() {
+        let level = json!({"title": "First", "author": "Someone", "xsb": "#####\n#@$.#\n#####\n"});
+        let pack = parse(&json!({"title": "Tiny", "levels": [level]}).to_string()).unwrap();
+        assert_eq!(pack.title, "Tiny");
+        assert_eq!(pack.levels[0].author, "Someone");
+        assert_eq!(parse(&write(&pack)).unwrap(), pack);
+
+        let pack = parse(&json!([level]).to_string()).unwrap();
+        assert_eq!(pack.levels[0].title, "First");
+    }
+}
@@ -0,0 +1,380 @@
+//! Conversion of levels and packs between the formats of the Sokoban games:
+//!
+//! - the legacy format of this game, with only the map drawn and the coordinates of the player
+//!   and the crates listed below it, which holds a single level without metadata,
+//! - XSB, one character per cell, with the packs written as consecutive levels followed by their
+//!   `Title:`, `Author:` and `Comment:`,
+//! - RLE-XSB, the same with each level on one line, the repeated symbols counted and the rows
+//!   separated by `|`,
+//! - SLC, the XML of the level collections,
+//! - JSON, an object with the metadata of the pack and its `levels`, each with its `title` and
+//!   its map in `xsb` (with the `json` feature).
+
+use std::{
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use super::file_name;
+use crate::data::{find_level, Board, LevelParseError, Replay};
+
+#[cfg(feature = "json")]
+mod json;
+mod slc;
+mod xsb;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Legacy,
+    Xsb,
+    RleXsb,
+    Slc,
+    #[cfg(feature = "json")]
+    Json,
+}
+
+impl Format {
+    /// Format usually given by the extension of `path`, `txt` being the legacy one.
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        extension.parse().ok()
+    }
+
+    /// Recognizes the format of a level or a pack.
+    pub fn detect(src: &str) -> Self {
+        let src = src.trim_start();
+        if src.starts_with('<') {
+            return Format::Slc;
+        }
+        #[cfg(feature = "json")]
+        if src.starts_with(['{', '[']) {
+            return Format::Json;
+        }
+        if src.lines().any(|l| xsb::is_rle_row(l.trim_end())) {
+            Format::RleXsb
+        } else if src.contains(['@', '+']) {
+            Format::Xsb
+        } else {
+            Format::Legacy
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Legacy => "txt",
+            Format::Xsb => "xsb",
+            Format::RleXsb => "rle",
+            Format::Slc => "slc",
+            #[cfg(feature = "json")]
+            Format::Json => "json",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = ConvertError;
+
+    /// Reads the name of a format, which is also its extension.
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        Ok(match &src.to_lowercase()[..] {
+            "legacy" | "txt" => Format::Legacy,
+            "xsb" => Format::Xsb,
+            "rle" => Format::RleXsb,
+            "slc" => Format::Slc,
+            #[cfg(feature = "json")]
+            "json" => Format::Json,
+            _ => return Err(ConvertError::UnknownFormat(src.to_string())),
+        })
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Format::Legacy => "legacy",
+            Format::Xsb => "XSB",
+            Format::RleXsb => "RLE-XSB",
+            Format::Slc => "SLC",
+            #[cfg(feature = "json")]
+            Format::Json => "JSON",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug)]
+pub enum ConvertError {
+    IO(PathBuf, io::Error),
+    UnknownFormat(String),
+    /// A level of the pack, given by its title or its number, can't be read.
+    InvalidLevel(String, LevelParseError),
+    InvalidXml(String),
+    #[cfg(feature = "json")]
+    InvalidJson(String),
+    NoLevel,
+    /// The pack has several levels, and the format holds only one.
+    SeveralLevels(Format),
+}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use ConvertError::*;
+        match self {
+            IO(path, e) => write!(f, "Could not access `{}`: {}", path.display(), e),
+            UnknownFormat(format) => write!(
+                f,
+                "Unknown format `{}`, expected legacy, xsb, rle, slc{}.",
+                format,
+                if cfg!(feature = "json") {
+                    " or json"
+                } else {
+                    ""
+                }
+            ),
+            InvalidLevel(level, e) => write!(f, "Invalid level `{}`: {}", level, e),
+            InvalidXml(e) => write!(f, "Invalid SLC file: {}", e),
+            #[cfg(feature = "json")]
+            InvalidJson(e) => write!(f, "Invalid JSON file: {}", e),
+            NoLevel => write!(f, "No level could be found."),
+            SeveralLevels(format) => write!(
+                f,
+                "The {} format holds only one level, write the pack to a directory.",
+                format
+            ),
+        }
+    }
+}
+
+impl Error for ConvertError {}
+
+/// Level with the metadata kept by the formats, the empty ones being unknown.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PackLevel {
+    pub title: String,
+    pub author: String,
+    pub comment: String,
+    /// Other metadata lines of the XSB formats with their key as written, such as the hints of
+    /// a [tutorial](`crate::Tutorial`).
+    pub metadata: Vec<(String, String)>,
+    pub board: Board,
+}
+
+impl PackLevel {
+    pub fn new(board: Board) -> Self {
+        PackLevel {
+            title: String::new(),
+            author: String::new(),
+            comment: String::new(),
+            metadata: Vec::new(),
+            board,
+        }
+    }
+}
+
+/// Levels with the metadata of the pack, the empty ones being unknown.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LevelPack {
+    pub title: String,
+    pub author: String,
+    pub description: String,
+    pub levels: Vec<PackLevel>,
+}
+
+impl LevelPack {
+    /// Reads a level or a pack, in the given `format` or else the [detected](`Format::detect`)
+    /// one.
+    pub fn parse(src: &str, format: Option<Format>) -> Result<Self, ConvertError> {
+        let pack = match format.unwrap_or_else(|| Format::detect(src)) {
+            Format::Legacy => {
+                let board = src
+                    .parse()
+                    .map_err(|e| ConvertError::InvalidLevel("1".to_string(), e))?;
+                LevelPack {
+                    levels: vec![PackLevel::new(board)],
+                    ..LevelPack::default()
+                }
+            }
+            Format::Xsb | Format::RleXsb => xsb::parse(src)?,
+            Format::Slc => slc::parse(src)?,
+            #[cfg(feature = "json")]
+            Format::Json => json::parse(src)?,
+        };
+        if pack.levels.is_empty() {
+            return Err(ConvertError::NoLevel);
+        }
+        Ok(pack)
+    }
+
+    /// Index of the level given by its number, from 1, or by its title.
+    pub fn find(&self, selector: &str) -> Option<usize> {
+        find_level(self.levels.iter().map(|l| &l.title[..]), selector)
+    }
+
+    /// Writes the pack in the `format`.
+    pub fn write(&self, format: Format) -> Result<String, ConvertError> {
+        Ok(match format {
+            Format::Legacy => match &self.levels[..] {
+                [level] => level.board.to_legacy(),
+                [] => return Err(ConvertError::NoLevel),
+                _ => return Err(ConvertError::SeveralLevels(format)),
+            },
+            Format::Xsb => xsb::write(self, false),
+            Format::RleXsb => xsb::write(self, true),
+            Format::Slc => slc::write(self),
+            #[cfg(feature = "json")]
+            Format::Json => json::write(self),
+        })
+    }
+
+    /// Reads the level or the pack in the file at `path`, or all the levels of the directory,
+    /// which is then the title of the pack. In a directory, the files which are replays, such as
+    /// solutions, are skipped.
+    ///
+    /// The levels without title are named after their file, numbered if it holds several.
+    pub fn open(path: &Path, format: Option<Format>) -> Result<Self, ConvertError> {
+        let io_err = |e| ConvertError::IO(path.to_path_buf(), e);
+        if !path.is_dir() {
+            let src = fs::read_to_string(path).map_err(io_err)?;
+            let mut pack = LevelPack::parse(&src, format).map_err(|e| in_file(e, path))?;
+            name_levels(&mut pack.levels, path);
+            return Ok(pack);
+        }
+
+        let mut paths = fs::read_dir(path)
+            .map_err(io_err)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(io_err)?;
+        paths.sort();
+
+        let mut pack = LevelPack {
+            title: title_from_path(path),
+            ..LevelPack::default()
+        };
+        for path in paths {
+            let hidden = path
+                .file_name()
+                .is_some_and(|n| n.to_string_lossy().starts_with('.'));
+            if hidden || !path.is_file() {
+                continue;
+            }
+            let src = fs::read_to_string(&path).map_err(|e| ConvertError::IO(path.clone(), e))?;
+            if src.trim().parse::<Replay>().is_ok() {
+                continue;
+            }
+            let mut levels = LevelPack::parse(&src, format)
+                .map_err(|e| in_file(e, &path))?
+                .levels;
+            name_levels(&mut levels, &path);
+            pack.levels.append(&mut levels);
+        }
+        if pack.levels.is_empty() {
+            return Err(ConvertError::NoLevel);
+        }
+        Ok(pack)
+    }
+
+    /// Writes each level in its own file of the directory at `path`, numbered to keep their
+    /// order, and returns the paths of the files.
+    pub fn write_dir(&self, path: &Path, format: Format) -> Result<Vec<PathBuf>, ConvertError> {
+        fs::create_dir_all(path).map_err(|e| ConvertError::IO(path.to_path_buf(), e))?;
+
+        let mut paths = Vec::with_capacity(self.levels.len());
+        for (index, level) in self.levels.iter().enumerate() {
+            let level_path = path.join(format!(
+                "{:03}-{}.{}",
+                index + 1,
+                file_name(&level.title),
+                format.extension()
+            ));
+            // The metadata of the pack would stop the levels from being played alone.
+            let pack = LevelPack {
+                levels: vec![level.clone()],
+                ..LevelPack::default()
+            };
+            fs::write(&level_path, pack.write(format)?)
+                .map_err(|e| ConvertError::IO(level_path.clone(), e))?;
+            paths.push(level_path);
+        }
+        Ok(paths)
+    }
+}
+
+/// Index of the line where each level of `src` starts, if it is written in the `format` with
+/// one character per cell, so the cells of its levels can be found in the file.
+pub(super) fn level_lines(src: &str, format: Format) -> Option<Vec<usize>> {
+    match format {
+        Format::Legacy => Some(vec![0]),
+        Format::Xsb => Some(xsb::level_lines(src)),
+        _ => None,
+    }
+}
+
+/// Tells in which file is the invalid level of `err`.
+fn in_file(err: ConvertError, path: &Path) -> ConvertError {
+    match err {
+        ConvertError::InvalidLevel(level, e) => {
+            ConvertError::InvalidLevel(format!("{}` of `{}", level, path.display()), e)
+        }
+        err => err,
+    }
+}
+
+/// Gives the levels without title the name of their file.
+fn name_levels(levels: &mut [PackLevel], path: &Path) {
+    let several = levels.len() > 1;
+    for (index, level) in levels.iter_mut().enumerate() {
+        if level.title.is_empty() {
+            level.title = if several {
+                format!("{} {}", title_from_path(path), index + 1)
+            } else {
+                title_from_path(path)
+            };
+        }
+    }
+}
+
+fn title_from_path(path: &Path) -> String {
+    path.file_stem()
+        .map_or_else(|| path.to_string_lossy(), |s| s.to_string_lossy())
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConvertError, Format, LevelPack};
+
+    const LEGACY_LEVEL: &str = "#####\n#...#\n#.X.#\n#...#\n#####\n\n1,1\n\n2,1\n";
+
+    #[test]
+    fn it_detects_formats() {
+        assert_eq!(Format::detect(LEGACY_LEVEL), Format::Legacy);
+        assert_eq!(Format::detect("#####\n#@$.#\n#####\n"), Format::Xsb);
+        assert_eq!(Format::detect("5#|#@$.#|5#"), Format::RleXsb);
+        assert_eq!(Format::detect("\n<?xml version=\"1.0\"?>"), Format::Slc);
+    }
+
+    #[test]
+    fn it_converts_between_formats() {
+        let pack = LevelPack::parse(LEGACY_LEVEL, None).unwrap();
+        let xsb = pack.write(Format::Xsb).unwrap();
+        assert_eq!(xsb, "#####\n#@$ #\n# . #\n#   #\n#####\n");
+
+        for format in [Format::Legacy, Format::RleXsb, Format::Slc] {
+            let converted = LevelPack::parse(&pack.write(format).unwrap(), Some(format)).unwrap();
+            assert_eq!(converted.write(Format::Xsb).unwrap(), xsb, "{}", format);
+        }
+    }
+
+    #[test]
+    fn it_keeps_packs_out_of_the_legacy_format() {
+        let mut pack = LevelPack::parse(LEGACY_LEVEL, None).unwrap();
+        pack.levels.push(pack.levels[0].clone());
+        assert!(matches!(
+            pack.write(Format::Legacy),
+            Err(ConvertError::SeveralLevels(Format::Legacy))
+        ));
+    }
+}
@@ -0,0 +1,309 @@
+//! Packs in the SLC format, the XML of the level collections:
+//!
+//! ```xml
+//! <SokobanLevels>
+//!   <Title>Tiny</Title>
+//!   <Description>Two levels to start with.</Description>
+//!   <LevelCollection Copyright="Someone">
+//!     <Level Id="First" Width="5" Height="3" Copyright="Someone">
+//!       <L>#####</L>
+//!       <L>#@$.#</L>
+//!       <L>#####</L>
+//!     </Level>
+//!   </LevelCollection>
+//! </SokobanLevels>
+//! ```
+//!
+//! Only what is needed to read those tags is implemented, without validating the XML.
+
+use super::{ConvertError, LevelPack, PackLevel};
+use crate::data::{Board, LevelParseError};
+
+/// Part of an XML document.
+#[derive(Debug, PartialEq, Eq)]
+enum Node {
+    /// Opening tag, with its name and attributes.
+    Start(String, Vec<(String, String)>),
+    End(String),
+    Text(String),
+}
+
+/// Splits the XML `src` into tags and texts, leaving out the declarations and comments.
+fn parse_nodes(src: &str) -> Result<Vec<Node>, ConvertError> {
+    let unclosed = || ConvertError::InvalidXml("unclosed tag".to_string());
+
+    let mut nodes = Vec::new();
+    let mut rest = src;
+    while let Some(start) = rest.find('<') {
+        if start > 0 {
+            nodes.push(Node::Text(unescape(&rest[..start])));
+        }
+        rest = &rest[start..];
+
+        // Declarations, comments and doctypes.
+        for (open, close) in [("<?", "?>"), ("<!--", "-->"), ("<!", ">")] {
+            if rest.starts_with(open) {
+                let end = rest.find(close).ok_or_else(unclosed)?;
+                rest = &rest[end + close.len()..];
+            }
+        }
+        if !rest.starts_with('<') || rest.starts_with("<!") || rest.starts_with("<?") {
+            continue;
+        }
+
+        let end = rest.find('>').ok_or_else(unclosed)?;
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+
+        if let Some(name) = tag.strip_prefix('/') {
+            nodes.push(Node::End(name.trim().to_string()));
+            continue;
+        }
+        let (tag, self_closing) = match tag.strip_suffix('/') {
+            Some(tag) => (tag, true),
+            None => (tag, false),
+        };
+        let (name, attributes) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+        nodes.push(Node::Start(name.to_string(), parse_attributes(attributes)?));
+        if self_closing {
+            nodes.push(Node::End(name.to_string()));
+        }
+    }
+    if !rest.trim().is_empty() {
+        nodes.push(Node::Text(unescape(rest)));
+    }
+    Ok(nodes)
+}
+
+/// Reads `name="value"` attributes, quoted with `"` or `'`.
+fn parse_attributes(src: &str) -> Result<Vec<(String, String)>, ConvertError> {
+    let mut attributes = Vec::new();
+    let mut rest = src.trim_start();
+    while !rest.is_empty() {
+        let invalid = || ConvertError::InvalidXml(format!("invalid attributes `{}`", src.trim()));
+
+        let (name, value) = rest.split_once('=').ok_or_else(invalid)?;
+        let value = value.trim_start();
+        let quote = value
+            .chars()
+            .next()
+            .filter(|q| *q == '"' || *q == '\'')
+            .ok_or_else(invalid)?;
+        let end = value[1..].find(quote).ok_or_else(invalid)? + 1;
+        attributes.push((name.trim().to_string(), unescape(&value[1..end])));
+        rest = value[end + 1..].trim_start();
+    }
+    Ok(attributes)
+}
+
+fn unescape(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        res.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';') else { break };
+        let entity = &rest[1..end];
+        let c = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|n| u32::from_str_radix(n, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse))
+                .and_then(Result::ok)
+                .and_then(char::from_u32),
+        };
+        match c {
+            Some(c) => {
+                res.push(c);
+                rest = &rest[end + 1..];
+            }
+            // Left as is.
+            None => {
+                res.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    res.push_str(rest);
+    res
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn attribute<'a>(attributes: &'a [(String, String)], name: &str) -> &'a str {
+    attributes
+        .iter()
+        .find(|(n, _)| n == name)
+        .map_or("", |(_, v)| &v[..])
+}
+
+pub(super) fn parse(src: &str) -> Result<LevelPack, ConvertError> {
+    let mut pack = LevelPack::default();
+    // Tags opened around the current node.
+    let mut path: Vec<String> = Vec::new();
+    // Rows of the level being read, with its title and author.
+    let mut level: Option<(Vec<String>, String, String)> = None;
+
+    for node in parse_nodes(src)? {
+        match node {
+            Node::Start(name, attributes) => {
+                match &name[..] {
+                    "LevelCollection" => {
+                        pack.author = attribute(&attributes, "Copyright").to_string()
+                    }
+                    "Level" => {
+                        level = Some((
+                            Vec::new(),
+                            attribute(&attributes, "Id").to_string(),
+                            attribute(&attributes, "Copyright").to_string(),
+                        ))
+                    }
+                    _ => (),
+                }
+                path.push(name);
+            }
+            Node::End(name) => {
+                if path.pop().as_ref() != Some(&name) {
+                    return Err(ConvertError::InvalidXml(format!(
+                        "unexpected closing tag `{}`",
+                        name
+                    )));
+                }
+                if name != "Level" {
+                    continue;
+                }
+                let Some((rows, title, author)) = level.take() else {
+                    continue;
+                };
+                let number = pack.levels.len() + 1;
+                let board = Board::from_xsb(&rows.join("\n")).map_err(|e| {
+                    ConvertError::InvalidLevel(
+                        if title.is_empty() {
+                            number.to_string()
+                        } else {
+                            title.clone()
+                        },
+                        LevelParseError::CantParseXsb(e),
+                    )
+                })?;
+                pack.levels.push(PackLevel {
+                    title,
+                    author,
+                    ..PackLevel::new(board)
+                });
+            }
+            Node::Text(text) => match (path.last().map(|n| &n[..]), &mut level) {
+                (Some("L"), Some((rows, _, _))) => rows.push(text),
+                (Some("Title"), None) => pack.title = text.trim().to_string(),
+                (Some("Description"), None) => pack.description = text.trim().to_string(),
+                _ => (),
+            },
+        }
+    }
+
+    Ok(pack)
+}
+
+pub(super) fn write(pack: &LevelPack) -> String {
+    let mut res = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<SokobanLevels>\n");
+    res.push_str(&format!("  <Title>{}</Title>\n", escape(&pack.title)));
+    res.push_str(&format!(
+        "  <Description>{}</Description>\n",
+        escape(&pack.description)
+    ));
+    res.push_str(&format!(
+        "  <LevelCollection Copyright=\"{}\">\n",
+        escape(&pack.author)
+    ));
+
+    for level in pack.levels.iter() {
+        let board = &level.board;
+        let xsb = board.to_xsb();
+        let width = xsb.lines().map(|l| l.chars().count()).max().unwrap_or(0);
+        res.push_str(&format!(
+            "    <Level Id=\"{}\" Width=\"{}\" Height=\"{}\"",
+            escape(&level.title),
+            width,
+            xsb.lines().count()
+        ));
+        if !level.author.is_empty() {
+            res.push_str(&format!(" Copyright=\"{}\"", escape(&level.author)));
+        }
+        res.push_str(">\n");
+        for row in xsb.lines() {
+            res.push_str(&format!("      <L>{}</L>\n", escape(row)));
+        }
+        res.push_str("    </Level>\n");
+    }
+
+    res.push_str("  </LevelCollection>\n</SokobanLevels>\n");
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, parse_nodes, unescape, write, Node};
+
+    #[test]
+    fn it_splits_xml() {
+        assert_eq!(
+            parse_nodes("<?xml version=\"1.0\"?><!-- c --><a b='1 &amp; 2'>x<c/></a>").unwrap(),
+            vec![
+                Node::Start(
+                    "a".to_string(),
+                    vec![("b".to_string(), "1 & 2".to_string())]
+                ),
+                Node::Text("x".to_string()),
+                Node::Start("c".to_string(), vec![]),
+                Node::End("c".to_string()),
+                Node::End("a".to_string()),
+            ]
+        );
+        assert_eq!(unescape("&lt;&#65;&#x42;&unknown;"), "<AB&unknown;");
+    }
+
+    #[test]
+    fn it_reads_level_collections() {
+        let pack = parse(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<SokobanLevels>
+  <Title>Tiny &amp; easy</Title>
+  <Description>
+    Two levels.
+  </Description>
+  <LevelCollection Copyright="Someone">
+    <Level Id="First" Width="5" Height="3">
+      <L>#####</L>
+      <L>#@$.#</L>
+      <L>#####</L>
+    </Level>
+    <Level Id="Second" Width="7" Height="3" Copyright="Someone else">
+      <L>#######</L>
+      <L>#@ $ .#</L>
+      <L>#######</L>
+    </Level>
+  </LevelCollection>
+</SokobanLevels>"#,
+        )
+        .unwrap();
+        assert_eq!(pack.title, "Tiny & easy");
+        assert_eq!(pack.description, "Two levels.");
+        assert_eq!(pack.author, "Someone");
+        assert_eq!(pack.levels.len(), 2);
+        assert_eq!(pack.levels[1].title, "Second");
+        assert_eq!(pack.levels[1].author, "Someone else");
+        assert_eq!(pack.levels[1].board.to_xsb(), "#######\n#@ $ .#\n#######\n");
+
+        assert_eq!(parse(&write(&pack)).unwrap(), pack);
+    }
+}
@@ -0,0 +1,269 @@
+//! Packs in the XSB format, or in RLE-XSB where each level is written on one line.
+
+use super::{ConvertError, LevelPack, PackLevel};
+use crate::data::{Board, LevelParseError, XsbParseError};
+
+/// Symbols of the levels, the floor being also written `-` or `_`.
+const XSB_SYMBOLS: &str = "#@+$*. -_";
+const RLE_FLOOR: char = '-';
+const RLE_ROW_SEPARATOR: char = '|';
+/// Ends the comments written on several lines, after a `Comment:` line.
+const COMMENT_END: &str = "comment-end:";
+
+/// If `line` is a row of a level, or several rows in RLE-XSB.
+fn is_row(line: &str) -> bool {
+    line.contains('#')
+        && line
+            .chars()
+            .all(|c| XSB_SYMBOLS.contains(c) || c.is_ascii_digit() || c == RLE_ROW_SEPARATOR)
+}
+
+/// If `line` is a metadata line, such as `Title: Level 1`.
+fn is_metadata(line: &str) -> bool {
+    line.split_once(':').is_some_and(|(key, _)| is_key(key))
+}
+
+/// If `line` is a row of a level with counted symbols, or several rows.
+pub(super) fn is_rle_row(line: &str) -> bool {
+    is_row(line) && line.contains(|c: char| c.is_ascii_digit() || c == RLE_ROW_SEPARATOR)
+}
+
+/// Rows of `line` with the counted symbols repeated.
+fn expand_rle(line: &str) -> String {
+    let mut rows = String::new();
+    let mut count = 0;
+    for c in line.chars() {
+        match c.to_digit(10) {
+            Some(digit) => count = count * 10 + digit as usize,
+            None if c == RLE_ROW_SEPARATOR => {
+                rows.push('\n');
+                count = 0;
+            }
+            None => {
+                rows.extend(std::iter::repeat_n(c, count.max(1)));
+                count = 0;
+            }
+        }
+    }
+    rows
+}
+
+/// Rows of `xsb` on one line, the repeated symbols being counted.
+fn to_rle(xsb: &str) -> String {
+    let rows: Vec<String> = xsb
+        .lines()
+        .map(|row| {
+            let mut rle = String::new();
+            let mut chars = row
+                .chars()
+                .map(|c| if c == ' ' { RLE_FLOOR } else { c })
+                .peekable();
+            while let Some(c) = chars.next() {
+                let mut count = 1;
+                while chars.next_if_eq(&c).is_some() {
+                    count += 1;
+                }
+                if count > 1 {
+                    rle.push_str(&count.to_string());
+                }
+                rle.push(c);
+            }
+            rle
+        })
+        .collect();
+    rows.join(&RLE_ROW_SEPARATOR.to_string())
+}
+
+/// If `key` can be the key of a metadata line, such as `Title` or `Author`.
+fn is_key(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_alphabetic() || c == '-' || c == '_')
+}
+
+/// Reads the levels, each of them followed by its metadata lines. The metadata lines before the
+/// first level are those of the pack, and a line of text just before a level is its title
+/// unless it has a `Title:`.
+pub(super) fn parse(src: &str) -> Result<LevelPack, ConvertError> {
+    let mut pack = LevelPack::default();
+    let mut heading: Option<String> = None;
+
+    let mut lines = src.lines().map(str::trim_end).peekable();
+    while let Some(line) = lines.next() {
+        if is_row(line) {
+            let mut rows = expand_rle(line);
+            while let Some(row) = lines.next_if(|l| is_row(l)) {
+                rows.push('\n');
+                rows.push_str(&expand_rle(row));
+            }
+
+            let number = pack.levels.len() + 1;
+            let invalid = |e| {
+                ConvertError::InvalidLevel(
+                    heading.clone().unwrap_or_else(|| number.to_string()),
+                    LevelParseError::CantParseXsb(e),
+                )
+            };
+            // A row with an unknown symbol would else be read as the title of the next level.
+            if let Some(row) = lines
+                .peek()
+                .filter(|l| l.contains('#') && !l.starts_with(';') && !is_metadata(l))
+            {
+                if let Some(c) = row.chars().find(|&c| {
+                    !(XSB_SYMBOLS.contains(c) || c.is_ascii_digit() || c == RLE_ROW_SEPARATOR)
+                }) {
+                    return Err(invalid(XsbParseError::UnknownSymbol(c)));
+                }
+            }
+            let board = Board::from_xsb(&rows).map_err(invalid)?;
+            let mut level = PackLevel::new(board);
+            level.title = heading.take().unwrap_or_default();
+            pack.levels.push(level);
+            continue;
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once(':').filter(|(k, _)| is_key(k)) else {
+            heading = Some(line.trim_start_matches(';').trim().to_string());
+            continue;
+        };
+        let name = key.trim();
+        let key = key.to_lowercase();
+        let mut value = value.trim().to_string();
+        if key == "comment" && value.is_empty() {
+            value = lines
+                .by_ref()
+                .take_while(|l| l.trim().to_lowercase() != COMMENT_END)
+                .collect::<Vec<_>>()
+                .join("\n");
+        }
+
+        match (&key[..], pack.levels.last_mut()) {
+            ("title", Some(level)) => level.title = value,
+            ("author", Some(level)) => level.author = value,
+            ("comment", Some(level)) => level.comment = value,
+            ("title", None) => pack.title = value,
+            ("author", None) => pack.author = value,
+            ("comment" | "description", None) => pack.description = value,
+            (_, Some(level)) => level.metadata.push((name.to_string(), value)),
+            _ => (),
+        }
+    }
+
+    Ok(pack)
+}
+
+/// Index of the first line of each level of `src`.
+pub(super) fn level_lines(src: &str) -> Vec<usize> {
+    let mut previous_row = false;
+    src.lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let row = is_row(line.trim_end());
+            let start = row && !previous_row;
+            previous_row = row;
+            start.then_some(index)
+        })
+        .collect()
+}
+
+/// Writes the metadata of the pack, then each level followed by its metadata after an empty
+/// line, so a file with a single level can still be played.
+pub(super) fn write(pack: &LevelPack, rle: bool) -> String {
+    let mut res = metadata(&pack.title, &pack.author, &pack.description);
+    if !res.is_empty() {
+        res.push('\n');
+    }
+
+    let levels: Vec<String> = pack
+        .levels
+        .iter()
+        .map(|level| {
+            let xsb = level.board.to_xsb();
+            let mut res = if rle {
+                format!("{}\n", to_rle(&xsb))
+            } else {
+                xsb
+            };
+            let mut metadata = metadata(&level.title, &level.author, &level.comment);
+            for (key, value) in level.metadata.iter() {
+                metadata.push_str(&format!("{}: {}\n", key, value));
+            }
+            if !metadata.is_empty() {
+                res.push('\n');
+                res.push_str(&metadata);
+            }
+            res
+        })
+        .collect();
+    res.push_str(&levels.join("\n"));
+    res
+}
+
+fn metadata(title: &str, author: &str, comment: &str) -> String {
+    let mut res = String::new();
+    if !title.is_empty() {
+        res.push_str(&format!("Title: {}\n", title));
+    }
+    if !author.is_empty() {
+        res.push_str(&format!("Author: {}\n", author));
+    }
+    if comment.contains('\n') {
+        res.push_str(&format!("Comment:\n{}\nComment-End:\n", comment));
+    } else if !comment.is_empty() {
+        res.push_str(&format!("Comment: {}\n", comment));
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{expand_rle, parse, to_rle, write};
+
+    const PACK: &str = "Title: Tiny
+Author: Someone
+Comment:
+Two levels
+to start with.
+Comment-End:
+
+; First
+#####
+#@$.#
+#####
+
+#######
+#@ $ .#
+#######
+Title: Second
+Author: Someone else
+";
+
+    #[test]
+    fn it_reads_packs() {
+        let pack = parse(PACK).unwrap();
+        assert_eq!((&pack.title[..], &pack.author[..]), ("Tiny", "Someone"));
+        assert_eq!(pack.description, "Two levels\nto start with.");
+        assert_eq!(pack.levels.len(), 2);
+        assert_eq!(pack.levels[0].title, "First");
+        assert_eq!(pack.levels[0].author, "");
+        assert_eq!(pack.levels[1].title, "Second");
+        assert_eq!(pack.levels[1].author, "Someone else");
+        assert_eq!(pack.levels[1].board.to_xsb(), "#######\n#@ $ .#\n#######\n");
+
+        assert_eq!(parse(&write(&pack, false)).unwrap(), pack);
+        assert_eq!(parse(&write(&pack, true)).unwrap(), pack);
+    }
+
+    #[test]
+    fn it_counts_repeated_symbols() {
+        let xsb = "  #####\n###   #\n#.@$  #\n#######\n";
+        let rle = to_rle(xsb);
+        assert_eq!(rle, "2-5#|3#3-#|#.@$2-#|7#");
+        assert_eq!(expand_rle(&rle), xsb.replace(' ', "-").trim_end());
+        assert_eq!(expand_rle("12#"), "############");
+    }
+}
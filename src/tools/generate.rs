@@ -0,0 +1,386 @@
+//! Random levels, built backwards from their solved position: the player pulls the crates away
+//! from the targets, so the levels can always be solved. The [solver](`Board::solve`) then gives
+//! their shortest solution, which tells how hard they are.
+
+use std::{collections::VecDeque, error::Error, fmt};
+
+use super::{LevelPack, PackLevel};
+use crate::data::{Board, Difficulty, Replay, Solution, SolverLimits};
+
+/// Levels tried before giving up, as the search for a hard one may fail.
+const MAX_ATTEMPTS: usize = 200;
+/// Times the player pulls each crate away from the targets.
+const PULLS_PER_CRATE: usize = 6;
+/// Cells a crate is pulled at most at once.
+const MAX_PULL_LENGTH: usize = 4;
+/// Positions the solver explores before dropping a level, the others being generated faster.
+const MAX_SOLVER_STATES: usize = 20_000;
+/// Part of the inside of the level which is floor.
+const FLOOR_RATIO: f32 = 0.6;
+const OFFSETS: [(isize, isize); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GeneratorError {
+    /// The level is too small to hold the crates.
+    TooSmall,
+    /// None of the levels tried was hard enough, or they were too hard for the solver.
+    NotFound { attempts: usize },
+}
+
+impl fmt::Display for GeneratorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use GeneratorError::*;
+        match self {
+            TooSmall => write!(f, "The level is too small for so many crates."),
+            NotFound { attempts } => write!(
+                f,
+                "No level of this difficulty was found in {} attempts.",
+                attempts
+            ),
+        }
+    }
+}
+
+impl Error for GeneratorError {}
+
+/// What the generated levels look like.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GeneratorOptions {
+    /// Size of the inside of the levels, without the walls around.
+    pub width: u32,
+    pub height: u32,
+    /// Number of crates, by default 2, 3 or 4 following the `difficulty`.
+    pub crates: Option<usize>,
+    /// The harder, the more pushes the solutions need for each crate.
+    pub difficulty: Difficulty,
+    /// Levels generated with the same seed and options are the same.
+    pub seed: u64,
+}
+
+impl Default for GeneratorOptions {
+    fn default() -> Self {
+        GeneratorOptions {
+            width: 8,
+            height: 8,
+            crates: None,
+            difficulty: Difficulty::Medium,
+            seed: 0,
+        }
+    }
+}
+
+impl GeneratorOptions {
+    pub fn crate_count(&self) -> usize {
+        self.crates.unwrap_or(match self.difficulty {
+            Difficulty::Easy => 2,
+            Difficulty::Medium => 3,
+            Difficulty::Hard => 4,
+        })
+    }
+
+    /// Least number of pushes of the solutions.
+    fn min_pushes(&self) -> usize {
+        let per_crate = match self.difficulty {
+            Difficulty::Easy => 1,
+            Difficulty::Medium => 2,
+            Difficulty::Hard => 3,
+        };
+        per_crate * self.crate_count()
+    }
+
+    /// Number of floor cells of the levels.
+    fn floor_cells(&self) -> usize {
+        ((self.width * self.height) as f32 * FLOOR_RATIO) as usize
+    }
+}
+
+/// Pseudo-random numbers of the SplitMix64 generator, good enough for levels.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Number from 0 to `n` excluded.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+/// Generates levels one after the other, see the [module](self).
+pub struct Generator {
+    options: GeneratorOptions,
+    rng: Rng,
+    limits: SolverLimits,
+}
+
+impl Generator {
+    pub fn new(options: GeneratorOptions) -> Result<Self, GeneratorError> {
+        // Room for the crates, their targets and the player.
+        if options.width < 3
+            || options.height < 3
+            || options.crate_count() == 0
+            || options.floor_cells() < options.crate_count() * 3 + 1
+        {
+            return Err(GeneratorError::TooSmall);
+        }
+        Ok(Generator {
+            options,
+            rng: Rng(options.seed),
+            limits: SolverLimits {
+                max_states: MAX_SOLVER_STATES,
+                max_time: None,
+            },
+        })
+    }
+
+    /// Next level, with its shortest solution in pushes.
+    pub fn generate(&mut self) -> Result<(Board, Solution), GeneratorError> {
+        for _ in 0..MAX_ATTEMPTS {
+            let board = self.candidate();
+            match board.solve(&self.limits) {
+                Ok(solution) if solution.replay.pushes() >= self.options.min_pushes() => {
+                    return Ok((board, solution))
+                }
+                _ => (),
+            }
+        }
+        Err(GeneratorError::NotFound {
+            attempts: MAX_ATTEMPTS,
+        })
+    }
+
+    /// Pack of the `count` next levels, titled after the seed, with their solutions.
+    pub fn pack(&mut self, count: usize) -> Result<(LevelPack, Vec<Replay>), GeneratorError> {
+        let mut pack = LevelPack {
+            title: format!("Generated levels (seed {})", self.options.seed),
+            ..LevelPack::default()
+        };
+        let mut solutions = Vec::with_capacity(count);
+        for number in 1..=count {
+            let (board, solution) = self.generate()?;
+            pack.levels.push(PackLevel {
+                title: format!("Level {}", number),
+                comment: format!(
+                    "Solved in {} moves and {} pushes.",
+                    solution.replay.len(),
+                    solution.replay.pushes()
+                ),
+                ..PackLevel::new(board)
+            });
+            solutions.push(solution.replay);
+        }
+        Ok((pack, solutions))
+    }
+
+    /// Level which can be solved, but maybe too easily.
+    fn candidate(&mut self) -> Board {
+        let (width, height) = (self.options.width as usize, self.options.height as usize);
+        // With the walls around.
+        let (w, h) = (width + 2, height + 2);
+        let index = |i: usize, j: usize| j * w + i;
+
+        // The floor is dug by a random walk, so all of it can be reached.
+        let mut floor = vec![false; w * h];
+        let (mut i, mut j) = (1 + self.rng.below(width), 1 + self.rng.below(height));
+        let mut floor_cells = Vec::new();
+        while floor_cells.len() < self.options.floor_cells() {
+            if !floor[index(i, j)] {
+                floor[index(i, j)] = true;
+                floor_cells.push(index(i, j));
+            }
+            let (di, dj) = OFFSETS[self.rng.below(OFFSETS.len())];
+            let (ni, nj) = (i.wrapping_add_signed(di), j.wrapping_add_signed(dj));
+            if (1..=width).contains(&ni) && (1..=height).contains(&nj) {
+                (i, j) = (ni, nj);
+            }
+        }
+
+        // Shuffles the floor to pick the targets and the player.
+        for k in (1..floor_cells.len()).rev() {
+            floor_cells.swap(k, self.rng.below(k + 1));
+        }
+        let crate_count = self.options.crate_count();
+        let targets = &floor_cells[..crate_count];
+        let mut crates = targets.to_vec();
+        let mut player = floor_cells[crate_count];
+
+        // Going back from the solved position: the player walks to a crate and pulls it in a
+        // straight line.
+        for _ in 0..PULLS_PER_CRATE * crate_count {
+            let c = self.rng.below(crate_count);
+            let (di, dj) = OFFSETS[self.rng.below(OFFSETS.len())];
+            let offset = dj * w as isize + di;
+            let free = |cell: usize, crates: &[usize]| floor[cell] && !crates.contains(&cell);
+
+            let start = crates[c].wrapping_add_signed(offset);
+            if !free(start, &crates) || !reachable(&floor, &crates, w, player, start) {
+                continue;
+            }
+            player = start;
+            for _ in 0..1 + self.rng.below(MAX_PULL_LENGTH) {
+                let next = player.wrapping_add_signed(offset);
+                if !free(next, &crates) {
+                    break;
+                }
+                crates[c] = player;
+                player = next;
+            }
+        }
+
+        // Only the walls around the floor are drawn.
+        let near_floor = |i: usize, j: usize| {
+            (j.saturating_sub(1)..=(j + 1).min(h - 1))
+                .any(|nj| (i.saturating_sub(1)..=(i + 1).min(w - 1)).any(|ni| floor[index(ni, nj)]))
+        };
+        let mut xsb = String::new();
+        for j in 0..h {
+            let row: String = (0..w)
+                .map(|i| {
+                    let cell = index(i, j);
+                    match (targets.contains(&cell), crates.contains(&cell)) {
+                        (true, _) if cell == player => '+',
+                        _ if cell == player => '@',
+                        (true, true) => '*',
+                        (false, true) => '$',
+                        (true, false) => '.',
+                        _ if !floor[cell] && near_floor(i, j) => '#',
+                        _ => ' ',
+                    }
+                })
+                .collect();
+            // The rows away from the floor would end the level.
+            if !row.trim_end().is_empty() {
+                xsb.push_str(&row);
+                xsb.push('\n');
+            }
+        }
+        Board::from_xsb(&xsb).expect("Generated levels should be valid")
+    }
+}
+
+/// If the player can walk from `from` to `to`, in a grid `width` cells wide.
+fn reachable(floor: &[bool], crates: &[usize], width: usize, from: usize, to: usize) -> bool {
+    let mut visited = vec![false; floor.len()];
+    let mut queue = VecDeque::from([from]);
+    while let Some(cell) = queue.pop_front() {
+        if cell == to {
+            return true;
+        }
+        if visited[cell] {
+            continue;
+        }
+        visited[cell] = true;
+        for (di, dj) in OFFSETS {
+            let next = cell.wrapping_add_signed(dj * width as isize + di);
+            if floor[next] && !crates.contains(&next) {
+                queue.push_back(next);
+            }
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Generator, GeneratorError, GeneratorOptions};
+    use crate::{
+        data::{Difficulty, LevelLibrary, SolverLimits},
+        tools::{solve_level, validate_source, Format},
+    };
+
+    #[test]
+    fn it_generates_solvable_levels() {
+        let options = GeneratorOptions {
+            difficulty: Difficulty::Easy,
+            seed: 42,
+            ..GeneratorOptions::default()
+        };
+        let mut generator = Generator::new(options).unwrap();
+        for _ in 0..3 {
+            let (board, solution) = generator.generate().unwrap();
+            assert_eq!(board.crate_count(), 2);
+            assert!(solution.replay.pushes() >= 2);
+
+            let mut board = board.clone();
+            for step in solution.replay.steps() {
+                board.do_move_player(step.dir);
+            }
+            assert!(board.has_won());
+        }
+    }
+
+    #[test]
+    fn it_repeats_levels_of_the_same_seed() {
+        let generate = |seed| {
+            let options = GeneratorOptions {
+                seed,
+                ..GeneratorOptions::default()
+            };
+            Generator::new(options)
+                .unwrap()
+                .generate()
+                .unwrap()
+                .0
+                .to_xsb()
+        };
+        assert_eq!(generate(7), generate(7));
+        assert_ne!(generate(7), generate(8));
+    }
+
+    #[test]
+    fn it_writes_packs_the_other_commands_read() {
+        let options = GeneratorOptions {
+            difficulty: Difficulty::Easy,
+            seed: 3,
+            ..GeneratorOptions::default()
+        };
+        let (pack, solutions) = Generator::new(options).unwrap().pack(3).unwrap();
+        assert_eq!(solutions.len(), 3);
+
+        let dir = std::env::temp_dir().join(format!("sokoban-gen-{}", std::process::id()));
+        let formats = [
+            Format::Xsb,
+            Format::RleXsb,
+            Format::Slc,
+            #[cfg(feature = "json")]
+            Format::Json,
+        ];
+        for format in formats {
+            let src = pack.write(format).unwrap();
+            assert_eq!(validate_source(&src), vec![], "{}", format);
+
+            let path = dir.join(format!("pack.{}", format.extension()));
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(&path, src).unwrap();
+            let library = LevelLibrary::open(&path);
+            std::fs::remove_dir_all(&dir).unwrap();
+            let library = library.unwrap();
+
+            assert_eq!(library.len(), 3, "{}", format);
+            assert_eq!(library.find("Level 2"), Some(1));
+            for level in library.levels() {
+                assert!(solve_level(level, &SolverLimits::default()).is_solved());
+            }
+        }
+    }
+
+    #[test]
+    fn it_rejects_too_small_levels() {
+        let options = GeneratorOptions {
+            width: 3,
+            height: 3,
+            crates: Some(4),
+            ..GeneratorOptions::default()
+        };
+        assert!(matches!(
+            Generator::new(options),
+            Err(GeneratorError::TooSmall)
+        ));
+    }
+}
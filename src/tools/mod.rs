@@ -0,0 +1,79 @@
+//! Command-line tools working on the levels without playing them, for scripts and other
+//! programs.
+
+mod bench;
+pub use bench::{
+    bench_level, estimated_memory, BenchComparison, BenchError, BenchOptions, BenchReport, BenchRow,
+};
+mod convert;
+pub use convert::{ConvertError, Format, LevelPack, PackLevel};
+mod generate;
+pub use generate::{Generator, GeneratorError, GeneratorOptions};
+mod replay;
+pub use replay::{check_replay, parse_solutions, ReplayOutcome, ReplayReport};
+mod solve;
+pub use solve::{solve_level, SolveReport};
+#[cfg(feature = "thumbnail")]
+mod thumbnails;
+#[cfg(feature = "thumbnail")]
+pub use thumbnails::{write_thumbnails, ThumbnailError, ThumbnailOptions};
+mod validate;
+pub use validate::{validate_path, validate_source, Diagnostic, FileReport, Severity};
+
+/// `name` with only the characters safe in a file name, the others being replaced by `_`.
+pub(crate) fn file_name(name: &str) -> String {
+    let name: String = name
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || " -_".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.is_empty() {
+        "_".to_string()
+    } else {
+        name
+    }
+}
+
+/// `text` as a JSON string, with its quotes.
+fn json_string(text: &str) -> String {
+    let mut json = String::from('"');
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{file_name, json_string};
+
+    #[test]
+    fn it_escapes_json_strings() {
+        assert_eq!(json_string("Level 1"), "\"Level 1\"");
+        assert_eq!(
+            json_string("a \"b\"\\\n\t"),
+            "\"a \\\"b\\\"\\\\\\n\\u0009\""
+        );
+    }
+
+    #[test]
+    fn it_makes_safe_file_names() {
+        assert_eq!(file_name("Level 1 - Easy"), "Level 1 - Easy");
+        assert_eq!(file_name("../../etc/passwd"), "______etc_passwd");
+        assert_eq!(file_name("a:b*c?"), "a_b_c_");
+        assert_eq!(file_name("  "), "_");
+    }
+}
@@ -0,0 +1,151 @@
+//! Checks of replays and solutions, played on their level without showing them.
+
+use std::fmt;
+
+use crate::data::{Board, Replay, ReplayParseError};
+
+/// How a replay ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplayOutcome {
+    /// The level is solved, with the steps after it left over.
+    Solved { left_over: usize },
+    /// All the steps were played without solving the level.
+    Unsolved { placed: usize, crates: usize },
+    /// The step at this index, from 0, walks into a wall or pushes a blocked crate.
+    InvalidStep(usize),
+}
+
+/// Result of a replay played on its level.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReplayReport {
+    pub title: String,
+    /// Steps played until the level was solved or the replay stopped.
+    pub moves: u32,
+    pub pushes: u32,
+    pub outcome: ReplayOutcome,
+}
+
+impl ReplayReport {
+    pub fn is_solved(&self) -> bool {
+        matches!(self.outcome, ReplayOutcome::Solved { .. })
+    }
+}
+
+/// Plays the `replay` on the `board`, until it is solved or a step can't be done.
+pub fn check_replay(title: &str, board: &Board, replay: &Replay) -> ReplayReport {
+    let mut board = board.clone();
+    let mut outcome = None;
+    for (index, step) in replay.steps().iter().enumerate() {
+        if board.do_move_player(step.dir).is_none() {
+            outcome = Some(ReplayOutcome::InvalidStep(index));
+            break;
+        }
+        if board.has_won() {
+            outcome = Some(ReplayOutcome::Solved {
+                left_over: replay.len() - index - 1,
+            });
+            break;
+        }
+    }
+    ReplayReport {
+        title: title.to_string(),
+        moves: board.moves(),
+        pushes: board.pushes(),
+        outcome: outcome.unwrap_or(ReplayOutcome::Unsolved {
+            placed: board.placed_crates(),
+            crates: board.crate_count(),
+        }),
+    }
+}
+
+impl fmt::Display for ReplayReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.outcome {
+            ReplayOutcome::Solved { left_over: 0 } => write!(
+                f,
+                "{}: solved in {} moves, {} pushes",
+                self.title, self.moves, self.pushes
+            ),
+            ReplayOutcome::Solved { left_over } => write!(
+                f,
+                "{}: solved in {} moves, {} pushes ({} steps left over)",
+                self.title, self.moves, self.pushes, left_over
+            ),
+            ReplayOutcome::Unsolved { placed, crates } => write!(
+                f,
+                "{}: not solved, {}/{} crates placed after {} moves, {} pushes",
+                self.title, placed, crates, self.moves, self.pushes
+            ),
+            ReplayOutcome::InvalidStep(index) => write!(
+                f,
+                "{}: step {} can't be played, after {} moves, {} pushes",
+                self.title,
+                index + 1,
+                self.moves,
+                self.pushes
+            ),
+        }
+    }
+}
+
+/// Reads a replay, or the solutions of a pack written as `title<Tab>moves` lines, which
+/// are given with the title of their level.
+pub fn parse_solutions(src: &str) -> Result<Vec<(Option<String>, Replay)>, ReplayParseError> {
+    if !src.contains('\t') {
+        return Ok(vec![(None, src.parse()?)]);
+    }
+    src.lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|line| match line.rsplit_once('\t') {
+            Some((title, moves)) => Ok((Some(title.trim().to_string()), moves.parse()?)),
+            None => Ok((None, line.parse()?)),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_replay, parse_solutions, ReplayOutcome};
+    use crate::data::{Board, Replay};
+
+    const LEVEL: &str = "#######\n#@ $ .#\n#######\n";
+
+    #[test]
+    fn it_checks_replays() {
+        let board = Board::from_xsb(LEVEL).unwrap();
+        let check = |moves: &str| check_replay("Test", &board, &moves.parse::<Replay>().unwrap());
+
+        let report = check("rRRl");
+        assert_eq!(report.outcome, ReplayOutcome::Solved { left_over: 1 });
+        assert_eq!((report.moves, report.pushes), (3, 2));
+        assert_eq!(
+            report.to_string(),
+            "Test: solved in 3 moves, 2 pushes (1 steps left over)"
+        );
+
+        let report = check("rR");
+        assert_eq!(
+            report.outcome,
+            ReplayOutcome::Unsolved {
+                placed: 0,
+                crates: 1
+            }
+        );
+        assert!(!report.is_solved());
+
+        assert_eq!(check("rRu").outcome, ReplayOutcome::InvalidStep(2));
+    }
+
+    #[test]
+    fn it_reads_solutions_of_packs() {
+        let solutions = parse_solutions("rRR\n").unwrap();
+        assert_eq!(solutions.len(), 1);
+        assert_eq!(solutions[0].0, None);
+
+        let solutions = parse_solutions("Level 1\trRR\nLevel 2\tuL\n\n").unwrap();
+        assert_eq!(solutions.len(), 2);
+        assert_eq!(solutions[1].0.as_deref(), Some("Level 2"));
+        assert_eq!(solutions[1].1.to_string(), "uL");
+        assert!(parse_solutions("Level 1\trxR").is_err());
+    }
+}
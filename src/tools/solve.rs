@@ -0,0 +1,128 @@
+//! Solutions of levels found by the [solver](`Board::solve`), with the statistics of the
+//! search.
+
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+use super::json_string;
+#[cfg(doc)]
+use crate::data::Board;
+use crate::data::{Level, Solution, SolverError, SolverLimits};
+
+/// Result of the search of a solution to a level.
+#[derive(Clone, Debug)]
+pub struct SolveReport {
+    pub title: String,
+    pub result: Result<Solution, SolverError>,
+    /// How long the search took.
+    pub time: Duration,
+}
+
+/// Searches a solution to the `level` within the `limits`.
+pub fn solve_level(level: &Level, limits: &SolverLimits) -> SolveReport {
+    let start = Instant::now();
+    let result = level.board().solve(limits);
+    SolveReport {
+        title: level.title().to_string(),
+        result,
+        time: start.elapsed(),
+    }
+}
+
+impl SolveReport {
+    pub fn is_solved(&self) -> bool {
+        self.result.is_ok()
+    }
+
+    /// The report as a JSON object on a single line. If the level is solved, it has the
+    /// `solution` in the LURD notation with its number of `moves` and `pushes`, otherwise the
+    /// `error` is `unsolvable`, `limit` or `timeout`.
+    pub fn to_json(&self) -> String {
+        let time_ms = self.time.as_millis();
+        match &self.result {
+            Ok(solution) => format!(
+                "{{\"title\": {}, \"solved\": true, \"solution\": \"{}\", \"moves\": {}, \"pushes\": {}, \"explored_states\": {}, \"time_ms\": {}}}",
+                json_string(&self.title),
+                solution.replay,
+                solution.replay.len(),
+                solution.replay.pushes(),
+                solution.explored_states,
+                time_ms
+            ),
+            Err(err) => {
+                let (error, explored_states) = match err {
+                    SolverError::Unsolvable { explored_states } => ("unsolvable", explored_states),
+                    SolverError::LimitReached { explored_states } => ("limit", explored_states),
+                    SolverError::TimeOut { explored_states } => ("timeout", explored_states),
+                };
+                format!(
+                    "{{\"title\": {}, \"solved\": false, \"error\": \"{}\", \"explored_states\": {}, \"time_ms\": {}}}",
+                    json_string(&self.title),
+                    error,
+                    explored_states,
+                    time_ms
+                )
+            }
+        }
+    }
+}
+
+impl fmt::Display for SolveReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.result {
+            Ok(solution) => write!(
+                f,
+                "{}: solved in {} moves, {} pushes ({} positions explored in {:.2}s)\n{}",
+                self.title,
+                solution.replay.len(),
+                solution.replay.pushes(),
+                solution.explored_states,
+                self.time.as_secs_f32(),
+                solution.replay
+            ),
+            Err(err) => write!(f, "{}: {}", self.title, err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{solve_level, SolveReport, SolverError};
+    use crate::data::{Level, SolverLimits};
+
+    #[test]
+    fn it_reports_solutions() {
+        let level = Level::new("Test".to_string(), "#####\n#@$.#\n#####\n".to_string()).unwrap();
+        let mut report = solve_level(&level, &SolverLimits::default());
+        report.time = Duration::from_millis(12);
+
+        assert_eq!(
+            report.to_string(),
+            "Test: solved in 1 moves, 1 pushes (2 positions explored in 0.01s)\nR"
+        );
+        assert_eq!(
+            report.to_json(),
+            "{\"title\": \"Test\", \"solved\": true, \"solution\": \"R\", \"moves\": 1, \"pushes\": 1, \"explored_states\": 2, \"time_ms\": 12}"
+        );
+    }
+
+    #[test]
+    fn it_reports_failures() {
+        let report = SolveReport {
+            title: "Hard".to_string(),
+            result: Err(SolverError::LimitReached {
+                explored_states: 10,
+            }),
+            time: Duration::from_secs(2),
+        };
+        assert!(!report.is_solved());
+        assert_eq!(
+            report.to_json(),
+            "{\"title\": \"Hard\", \"solved\": false, \"error\": \"limit\", \"explored_states\": 10, \"time_ms\": 2000}"
+        );
+    }
+}
@@ -0,0 +1,105 @@
+//! Pictures of the levels in PNG files, for the web pages and the lists of packs.
+
+use std::{
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::{file_name, LevelPack};
+use crate::ui::Thumbnail;
+
+#[derive(Debug)]
+pub enum ThumbnailError {
+    IO(PathBuf, io::Error),
+    Image(PathBuf, image::ImageError),
+    /// The pack has several levels, and the output is a single picture.
+    SeveralLevels,
+}
+
+impl fmt::Display for ThumbnailError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use ThumbnailError::*;
+        match self {
+            IO(path, e) => write!(f, "Could not access `{}`: {}", path.display(), e),
+            Image(path, e) => write!(f, "Could not write `{}`: {}", path.display(), e),
+            SeveralLevels => write!(
+                f,
+                "The pack has several levels, write them to a directory or in a sheet."
+            ),
+        }
+    }
+}
+
+impl Error for ThumbnailError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ThumbnailOptions {
+    /// Size of the cells, in pixels.
+    pub cell_size: u32,
+    /// Number of columns of the sheet holding all the levels, if they are drawn in a single
+    /// picture.
+    pub sheet_columns: Option<usize>,
+    /// Pixels between the levels of the sheet.
+    pub padding: u32,
+}
+
+impl Default for ThumbnailOptions {
+    fn default() -> Self {
+        ThumbnailOptions {
+            cell_size: 8,
+            sheet_columns: None,
+            padding: 8,
+        }
+    }
+}
+
+/// Draws the levels of the `pack` in the PNG file at `path`, or in the directory at `path` with
+/// a file per level, numbered to keep their order. Returns the paths of the files.
+pub fn write_thumbnails(
+    pack: &LevelPack,
+    path: &Path,
+    dir: bool,
+    options: &ThumbnailOptions,
+) -> Result<Vec<PathBuf>, ThumbnailError> {
+    let thumbnails: Vec<Thumbnail> = pack
+        .levels
+        .iter()
+        .map(|l| Thumbnail::render(&l.board, options.cell_size))
+        .collect();
+
+    let single = match (options.sheet_columns, &thumbnails[..]) {
+        (Some(columns), _) => Some(Thumbnail::sheet(&thumbnails, columns, options.padding)),
+        (None, [thumbnail]) if !dir => Some(thumbnail.clone()),
+        (None, _) if !dir => return Err(ThumbnailError::SeveralLevels),
+        (None, _) => None,
+    };
+    if let Some(thumbnail) = single {
+        let path = if dir {
+            fs::create_dir_all(path).map_err(|e| ThumbnailError::IO(path.to_path_buf(), e))?;
+            let title = if pack.title.is_empty() {
+                "levels"
+            } else {
+                &pack.title
+            };
+            path.join(format!("{}.png", file_name(title)))
+        } else {
+            path.to_path_buf()
+        };
+        thumbnail
+            .save_png(&path)
+            .map_err(|e| ThumbnailError::Image(path.clone(), e))?;
+        return Ok(vec![path]);
+    }
+
+    fs::create_dir_all(path).map_err(|e| ThumbnailError::IO(path.to_path_buf(), e))?;
+    let mut paths = Vec::with_capacity(thumbnails.len());
+    for (index, (level, thumbnail)) in pack.levels.iter().zip(thumbnails.iter()).enumerate() {
+        let level_path = path.join(format!("{:03}-{}.png", index + 1, file_name(&level.title)));
+        thumbnail
+            .save_png(&level_path)
+            .map_err(|e| ThumbnailError::Image(level_path.clone(), e))?;
+        paths.push(level_path);
+    }
+    Ok(paths)
+}
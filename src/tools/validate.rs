@@ -0,0 +1,227 @@
+//! Checks of level files before sharing them, reporting where the problems are like a
+//! compiler does, for instance in the continuous integration of a level pack.
+
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::{convert::level_lines, ConvertError, Format, LevelPack};
+use crate::data::{LevelParseError, LintIssue, Replay, XsbParseError};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    /// The level can still be played.
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Problem found in a level file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Line and column in the file, starting at 1, if the problem is at a precise place.
+    pub position: Option<(usize, usize)>,
+    pub message: String,
+}
+
+/// Problems found in a file.
+#[derive(Debug)]
+pub struct FileReport {
+    pub path: PathBuf,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl FileReport {
+    pub fn count(&self, severity: Severity) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == severity)
+            .count()
+    }
+}
+
+impl fmt::Display for FileReport {
+    /// One line per problem, as `<path>:<line>:<column>: <severity>: <message>`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for diagnostic in self.diagnostics.iter() {
+            write!(f, "{}", self.path.display())?;
+            if let Some((line, column)) = diagnostic.position {
+                write!(f, ":{}:{}", line, column)?;
+            }
+            writeln!(f, ": {}: {}", diagnostic.severity, diagnostic.message)?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks the level or pack file at `path`, or all the level files of the directory. In a
+/// directory, the files which are replays, such as solutions, are skipped.
+pub fn validate_path(path: &Path) -> Result<Vec<FileReport>, io::Error> {
+    if !path.is_dir() {
+        let source = fs::read_to_string(path)?;
+        return Ok(vec![FileReport {
+            path: path.to_path_buf(),
+            diagnostics: validate_source(&source),
+        }]);
+    }
+
+    let mut paths = fs::read_dir(path)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    paths.sort();
+
+    let mut reports = Vec::new();
+    for path in paths {
+        let hidden = path
+            .file_name()
+            .is_some_and(|n| n.to_string_lossy().starts_with('.'));
+        if hidden || !path.is_file() {
+            continue;
+        }
+        let source = fs::read_to_string(&path)?;
+        if source.trim().parse::<Replay>().is_ok() {
+            continue;
+        }
+        reports.push(FileReport {
+            diagnostics: validate_source(&source),
+            path,
+        });
+    }
+    Ok(reports)
+}
+
+/// Parses and lints each level of the level or pack in `source`, in any format.
+pub fn validate_source(source: &str) -> Vec<Diagnostic> {
+    let format = Format::detect(source);
+    let pack = match LevelPack::parse(source, Some(format)) {
+        Ok(pack) => pack,
+        Err(err) => {
+            let symbol = match err {
+                ConvertError::InvalidLevel(_, LevelParseError::CantParseMap((_, c)))
+                | ConvertError::InvalidLevel(
+                    _,
+                    LevelParseError::CantParseXsb(XsbParseError::UnknownSymbol(c)),
+                ) => Some(c),
+                _ => None,
+            };
+            return vec![Diagnostic {
+                severity: Severity::Error,
+                position: symbol.and_then(|c| find_char(source, c)),
+                message: err.to_string(),
+            }];
+        }
+    };
+
+    let lines = level_lines(source, format);
+    let several = pack.levels.len() > 1;
+    let mut diagnostics = Vec::new();
+    for (index, level) in pack.levels.iter().enumerate() {
+        // The rows of each board are consecutive lines of the file.
+        let first_line = lines.as_ref().and_then(|l| l.get(index).copied());
+        let name = match &level.title[..] {
+            "" => (index + 1).to_string(),
+            title => title.to_string(),
+        };
+        diagnostics.extend(level.board.lint().iter().map(|issue| {
+            use LintIssue::*;
+            let (severity, cell) = match *issue {
+                NotEnclosed(cell) => (Severity::Warning, Some(cell)),
+                AlreadySolved => (Severity::Warning, None),
+                CrateOutside(cell) | UnreachableCrate(cell) => (Severity::Error, Some(cell)),
+                PlayerOutside => (Severity::Error, Some(level.board.player())),
+                NoCrate | CratesTargetsMismatch { .. } => (Severity::Error, None),
+            };
+            Diagnostic {
+                severity,
+                position: first_line
+                    .zip(cell)
+                    .map(|(line, (i, j))| (line + j as usize + 1, i as usize + 1)),
+                message: if several {
+                    format!("Level `{}`: {}", name, issue)
+                } else {
+                    issue.to_string()
+                },
+            }
+        }));
+    }
+    diagnostics
+}
+
+/// Line and column of the first `c` in the rows of the levels of `source`.
+fn find_char(source: &str, c: char) -> Option<(usize, usize)> {
+    source
+        .lines()
+        .enumerate()
+        .filter(|(_, text)| text.contains('#'))
+        .find_map(|(line, text)| {
+            text.chars()
+                .position(|x| x == c)
+                .map(|column| (line + 1, column + 1))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{validate_source, Diagnostic, Severity};
+
+    #[test]
+    fn it_accepts_valid_levels() {
+        assert_eq!(validate_source("#####\n#@$.#\n#####\n"), vec![]);
+    }
+
+    #[test]
+    fn it_locates_unknown_symbols() {
+        let diagnostics = validate_source("#####\n#@$.#\n##?##\n");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(diagnostics[0].position, Some((3, 3)));
+    }
+
+    #[test]
+    fn it_locates_lint_issues() {
+        // The player can walk out of the level on the right.
+        let diagnostics = validate_source("#####\n#@$. \n#####\n");
+        assert_eq!(
+            diagnostics,
+            vec![Diagnostic {
+                severity: Severity::Warning,
+                position: Some((2, 5)),
+                message: "The level isn't closed by walls at 4,1.".to_string(),
+            }]
+        );
+
+        let diagnostics = validate_source("######\n#@$ $#\n#.   #\n######\n");
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(diagnostics[0].position, None);
+    }
+
+    #[test]
+    fn it_checks_each_level_of_packs() {
+        let pack = "Title: Pack\n\n#####\n#@$.#\n#####\nTitle: First\n\n\
+                    #####\n#@$.#\n##?##\n";
+        let diagnostics = validate_source(pack);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].position, Some((10, 3)));
+
+        let pack = "#####\n#@$.#\n#####\nTitle: First\n\n#####\n#@$. \n#####\nTitle: Open\n";
+        let diagnostics = validate_source(pack);
+        assert_eq!(
+            diagnostics,
+            vec![Diagnostic {
+                severity: Severity::Warning,
+                position: Some((7, 5)),
+                message: "Level `Open`: The level isn't closed by walls at 4,1.".to_string(),
+            }]
+        );
+    }
+}
@@ -0,0 +1,81 @@
+//! Animation curves shared by the graphical interfaces.
+
+/// How an animation progresses over its duration.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Easing {
+    /// Constant speed.
+    Linear,
+    /// Fast at first, slowing down at the end.
+    EaseOut,
+    /// Overshoots the end and bounces back a few times.
+    Bounce,
+}
+
+impl Easing {
+    pub const ALL: [Easing; 3] = [Easing::Linear, Easing::EaseOut, Easing::Bounce];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Easing::Linear => "linear",
+            Easing::EaseOut => "ease-out",
+            Easing::Bounce => "bounce",
+        }
+    }
+
+    /// Easing following this one in [`Easing::ALL`], to cycle through them.
+    pub fn next(self) -> Self {
+        let index = Easing::ALL.iter().position(|e| *e == self).unwrap_or(0);
+        Easing::ALL[(index + 1) % Easing::ALL.len()]
+    }
+
+    /// Progress of the animation from `0.` to `1.`, given the fraction `t` of its duration
+    /// which elapsed (clamped between `0.` and `1.`).
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0., 1.);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseOut => 1. - (1. - t).powi(3),
+            Easing::Bounce => bounce_out(t),
+        }
+    }
+}
+
+/// Classic "bounce out" curve: the object falls to the end and bounces three times, each
+/// bounce smaller than the previous.
+fn bounce_out(t: f32) -> f32 {
+    const N: f32 = 7.5625;
+    const D: f32 = 2.75;
+
+    if t < 1. / D {
+        N * t * t
+    } else if t < 2. / D {
+        let t = t - 1.5 / D;
+        N * t * t + 0.75
+    } else if t < 2.5 / D {
+        let t = t - 2.25 / D;
+        N * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / D;
+        N * t * t + 0.984375
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Easing;
+
+    #[test]
+    fn it_starts_and_ends_every_easing() {
+        for easing in Easing::ALL {
+            assert!(easing.apply(0.).abs() < 1e-5, "{}", easing.name());
+            assert!((easing.apply(1.) - 1.).abs() < 1e-5, "{}", easing.name());
+            assert!((easing.apply(2.) - 1.).abs() < 1e-5, "{}", easing.name());
+        }
+    }
+
+    #[test]
+    fn it_eases_out_faster_than_linear() {
+        assert!(Easing::EaseOut.apply(0.5) > Easing::Linear.apply(0.5));
+        assert_eq!(Easing::Bounce.next().next(), Easing::EaseOut);
+    }
+}
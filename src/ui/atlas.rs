@@ -0,0 +1,192 @@
+//! Manifest of a tile atlas: a single image holding all the tiles of a theme, and the named
+//! regions where each tile is.
+//!
+//! The manifest is a text file with one entry per line, empty lines and lines starting with
+//! `#` being ignored:
+//!
+//! ```text
+//! image /atlas.png
+//! wall 0 0 34 34
+//! ```
+//!
+//! The `image` entry gives the path of the atlas, and the other ones give the name of a tile
+//! followed by the position and the size of its region in pixels.
+
+use std::{collections::HashMap, error::Error, fmt, str::FromStr};
+
+const IMAGE_ENTRY: &str = "image";
+const COMMENT_PREFIX: char = '#';
+
+/// Tiles every atlas must define.
+pub const TILE_NAMES: [&str; 9] = [
+    "floor",
+    "wall",
+    "target",
+    "crate",
+    "placed_crate",
+    "player_up",
+    "player_down",
+    "player_left",
+    "player_right",
+];
+
+/// Position and size of a tile in the atlas, in pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Region {
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AtlasParseError {
+    MissingImage,
+    /// The line (starting from 1) isn't an image or a region entry.
+    InvalidLine(usize),
+    /// The region is defined several times.
+    DuplicateRegion(String),
+    /// One of the [`TILE_NAMES`] isn't defined.
+    MissingRegion(&'static str),
+}
+
+impl fmt::Display for AtlasParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use AtlasParseError::*;
+        match self {
+            MissingImage => write!(f, "The atlas manifest doesn't give the image path."),
+            InvalidLine(line) => write!(f, "Invalid atlas manifest entry at line {}.", line),
+            DuplicateRegion(name) => write!(f, "The tile `{}` is defined several times.", name),
+            MissingRegion(name) => write!(f, "The tile `{}` is missing from the atlas.", name),
+        }
+    }
+}
+
+impl Error for AtlasParseError {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AtlasManifest {
+    /// Path of the atlas image, relative to the resources.
+    pub image: String,
+    regions: HashMap<String, Region>,
+}
+
+impl AtlasManifest {
+    /// Region of the tile `name`, always present for the [`TILE_NAMES`].
+    pub fn region(&self, name: &str) -> Option<Region> {
+        self.regions.get(name).copied()
+    }
+}
+
+impl FromStr for AtlasManifest {
+    type Err = AtlasParseError;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        let mut image = None;
+        let mut regions = HashMap::new();
+
+        for (index, line) in src.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(COMMENT_PREFIX) {
+                continue;
+            }
+            let invalid = AtlasParseError::InvalidLine(index + 1);
+
+            let mut words = line.split_whitespace();
+            let name = words.next().ok_or_else(|| invalid.clone())?;
+            let values: Vec<&str> = words.collect();
+
+            if name == IMAGE_ENTRY {
+                match values[..] {
+                    [path] => image = Some(path.to_string()),
+                    _ => return Err(invalid),
+                }
+                continue;
+            }
+
+            let values: Vec<u32> = values
+                .iter()
+                .map(|v| v.parse())
+                .collect::<Result<_, _>>()
+                .map_err(|_| invalid.clone())?;
+            let region = match values[..] {
+                [x, y, w, h] if w > 0 && h > 0 => Region { x, y, w, h },
+                _ => return Err(invalid),
+            };
+            if regions.insert(name.to_string(), region).is_some() {
+                return Err(AtlasParseError::DuplicateRegion(name.to_string()));
+            }
+        }
+
+        if let Some(missing) = TILE_NAMES.iter().find(|n| !regions.contains_key(**n)) {
+            return Err(AtlasParseError::MissingRegion(missing));
+        }
+        Ok(AtlasManifest {
+            image: image.ok_or(AtlasParseError::MissingImage)?,
+            regions,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AtlasManifest, AtlasParseError, Region};
+
+    const TEST_MANIFEST: &str = "# Test atlas
+image /atlas.png
+
+floor 0 0 1 1
+wall 1 0 1 1
+target 2 0 1 1
+crate 3 0 1 1
+placed_crate 4 0 1 1
+player_up 0 1 1 1
+player_down 1 1 1 1
+player_left 2 1 1 1
+player_right 3 1 2 3
+";
+
+    #[test]
+    fn it_parses_a_manifest() {
+        let manifest: AtlasManifest = TEST_MANIFEST.parse().unwrap();
+        assert_eq!(manifest.image, "/atlas.png");
+        assert_eq!(
+            manifest.region("player_right"),
+            Some(Region {
+                x: 3,
+                y: 1,
+                w: 2,
+                h: 3
+            })
+        );
+        assert_eq!(manifest.region("unknown"), None);
+    }
+
+    #[test]
+    fn it_rejects_invalid_manifests() {
+        assert_eq!(
+            TEST_MANIFEST
+                .replace("wall 1 0 1 1", "wall 1 0 1")
+                .parse::<AtlasManifest>(),
+            Err(AtlasParseError::InvalidLine(5))
+        );
+        assert_eq!(
+            TEST_MANIFEST
+                .replace("target", "wall")
+                .parse::<AtlasManifest>(),
+            Err(AtlasParseError::DuplicateRegion("wall".to_string()))
+        );
+        assert_eq!(
+            TEST_MANIFEST
+                .replace("crate 3", "box 3")
+                .parse::<AtlasManifest>(),
+            Err(AtlasParseError::MissingRegion("crate"))
+        );
+        assert_eq!(
+            TEST_MANIFEST
+                .replace("image /atlas.png", "")
+                .parse::<AtlasManifest>(),
+            Err(AtlasParseError::MissingImage)
+        );
+    }
+}
@@ -0,0 +1,77 @@
+//! Sounds played in reaction to the game, for the interfaces able to play them.
+
+use super::{Board, BoardElem, CellKind};
+
+/// Sound effect for something which happened in the game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SoundEffect {
+    /// The player walked.
+    Step,
+    /// The player pushed a crate.
+    Push,
+    /// The player bumped into a wall or a crate which can't move.
+    Blocked,
+    /// A crate was pushed on a target.
+    CrateOnTarget,
+    /// The level was won.
+    Won,
+}
+
+impl SoundEffect {
+    /// Sound of the last move, based on its result (see [`Board::do_move_player`]).
+    pub fn from_move(board: &Board, last_move_result: Option<Option<(u32, u32)>>) -> Self {
+        match last_move_result {
+            None => SoundEffect::Blocked,
+            Some(None) => SoundEffect::Step,
+            Some(Some((i, j))) => match board.get(i, j) {
+                BoardElem(_, CellKind::Target) => SoundEffect::CrateOnTarget,
+                _ => SoundEffect::Push,
+            },
+        }
+    }
+}
+
+/// Something which can play the sounds of the game.
+pub trait AudioSink {
+    fn play_effect(&mut self, effect: SoundEffect);
+
+    /// Starts the background music, which loops until the end of the game.
+    fn play_music(&mut self);
+
+    fn music_volume(&self) -> f32;
+
+    /// Changes the volume of the music, clamped between `0.` (muted) and `1.`.
+    fn set_music_volume(&mut self, volume: f32);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Board, SoundEffect};
+    use crate::data::Direction;
+
+    const TEST_LEVEL_STR: &str = "#####
+#...#
+#...#
+#.X.#
+#####
+
+1,1
+
+2,2
+";
+
+    #[test]
+    fn it_chooses_the_sound_of_a_move() {
+        let mut board: Board = TEST_LEVEL_STR.parse().unwrap();
+
+        let res = board.do_move_player(Direction::Up);
+        assert_eq!(SoundEffect::from_move(&board, res), SoundEffect::Blocked);
+        let res = board.do_move_player(Direction::Right);
+        assert_eq!(SoundEffect::from_move(&board, res), SoundEffect::Step);
+        let res = board.do_move_player(Direction::Down);
+        assert_eq!(
+            SoundEffect::from_move(&board, res),
+            SoundEffect::CrateOnTarget
+        );
+    }
+}
@@ -0,0 +1,282 @@
+//! # Chat plays interface.
+//!
+//! The viewers of a stream play together by sending commands in the chat of a channel, read
+//! anonymously over IRC, by default on Twitch. After the first vote, they have a few seconds
+//! to vote for a command, and the most voted one is played, see [`Votes`].
+//!
+//! The display is meant to be captured by the streaming software: each frame is plain text
+//! printed on a cleared terminal, and it can also be written to a file for a text source of
+//! the stream overlay.
+use std::{
+    env,
+    error::Error,
+    fmt, fs,
+    io::{self, BufRead, BufReader, Write},
+    net::TcpStream,
+    path::PathBuf,
+    process,
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use super::{
+    chat_votes::{ChatCommand, ChatMessage, Votes},
+    cli::board_to_text,
+    format_duration, Action, Board, Defeat, LostChoice, Score, Ui, WonChoice,
+};
+
+/// Channel to read the votes from, when the interface is created with [`Ui::initialize`].
+pub const CHANNEL_ENV_VAR: &str = "SOKOBAN_CHAT_CHANNEL";
+/// Address of the IRC server, to play with the chat of another server than Twitch.
+pub const SERVER_ENV_VAR: &str = "SOKOBAN_CHAT_SERVER";
+/// File where each frame is written, when the interface is created with [`Ui::initialize`].
+pub const OVERLAY_ENV_VAR: &str = "SOKOBAN_CHAT_OVERLAY";
+const TWITCH_IRC_SERVER: &str = "irc.chat.twitch.tv:6667";
+
+/// How long the viewers can vote after the first vote of a move.
+const VOTE_WINDOW: Duration = Duration::from_secs(4);
+/// How long the victory or the defeat is shown before the next level starts, or the same one
+/// again.
+const WON_PAUSE: Duration = Duration::from_secs(5);
+
+/// Clears the terminal and puts the cursor back at the top left.
+const CLEAR: &str = "\x1b[2J\x1b[H";
+
+#[derive(Debug)]
+pub enum ChatError {
+    IO(io::Error),
+    NoChannel,
+    /// The connection to the chat was closed.
+    Disconnected,
+}
+
+impl fmt::Display for ChatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use ChatError::*;
+        match self {
+            IO(e) => write!(f, "IO error with the chat : {}", e),
+            NoChannel => write!(f, "No channel given, set {}.", CHANNEL_ENV_VAR),
+            Disconnected => write!(f, "The connection to the chat was closed."),
+        }
+    }
+}
+
+impl Error for ChatError {}
+
+/// Interface played by the viewers of a stream through its chat.
+pub struct ChatPlays {
+    channel: String,
+    messages: Receiver<ChatMessage>,
+    overlay: Option<PathBuf>,
+    votes: Votes,
+    /// When the current votes close, once someone voted.
+    deadline: Option<Instant>,
+    /// Last command played, with its number of votes.
+    last: Option<(ChatCommand, usize)>,
+    /// Board and stats of the last frame, drawn again when the votes change.
+    board: String,
+    /// Last message of the level, shown under the votes.
+    tip: Option<String>,
+}
+
+impl ChatPlays {
+    /// Joins the chat of `channel`, writing each frame to the `overlay` file if any.
+    pub fn new(channel: &str, overlay: Option<PathBuf>) -> Result<Self, ChatError> {
+        let channel = channel.trim_start_matches('#').to_lowercase();
+        let server = env::var(SERVER_ENV_VAR).unwrap_or_else(|_| TWITCH_IRC_SERVER.to_string());
+
+        let mut stream = TcpStream::connect(&server).map_err(ChatError::IO)?;
+        // The `justinfan` nicknames can read the chats of Twitch without an account.
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.subsec_nanos());
+        let nick = format!("justinfan{}", (seed ^ process::id()) % 100_000);
+        write!(stream, "NICK {}\r\nJOIN #{}\r\n", nick, channel).map_err(ChatError::IO)?;
+
+        let (sender, messages) = mpsc::channel();
+        thread::spawn(move || read_chat(stream, sender));
+
+        Ok(ChatPlays {
+            channel,
+            messages,
+            overlay,
+            votes: Votes::default(),
+            deadline: None,
+            last: None,
+            board: String::new(),
+            tip: None,
+        })
+    }
+
+    /// Shows the frame, made of the last board and `status`, on the terminal and in the
+    /// overlay file.
+    fn show(&self, status: &str) -> Result<(), ChatError> {
+        let frame = format!("{}{}", self.board, status);
+        print!("{}{}", CLEAR, frame);
+        io::stdout().flush().map_err(ChatError::IO)?;
+        if let Some(overlay) = &self.overlay {
+            fs::write(overlay, frame).map_err(ChatError::IO)?;
+        }
+        Ok(())
+    }
+
+    /// Tells how to vote, the current votes and the last command played.
+    fn status(&self) -> String {
+        let mut status =
+            "Vote in the chat: up, down, left, right (u, d, l, r) or reset.\n".to_string();
+        match self.deadline {
+            Some(deadline) => {
+                let tally: Vec<String> = self
+                    .votes
+                    .tally()
+                    .iter()
+                    .map(|(command, count)| format!("{} {}", command.name(), count))
+                    .collect();
+                let left = deadline.saturating_duration_since(Instant::now());
+                status.push_str(&format!(
+                    "Votes ({}s left): {}\n",
+                    left.as_secs_f32().ceil(),
+                    tally.join(" | ")
+                ));
+            }
+            None => status.push_str("Waiting for votes...\n"),
+        }
+        if let Some((command, count)) = self.last {
+            status.push_str(&format!(
+                "Last move: {} ({} vote{})\n",
+                command.name(),
+                count,
+                if count > 1 { "s" } else { "" }
+            ));
+        }
+        if let Some(tip) = &self.tip {
+            status.push_str(&format!("Tip: {}\n", tip));
+        }
+        status
+    }
+}
+
+/// Forwards the messages of the chat read on the `stream`, until it is closed.
+fn read_chat(stream: TcpStream, messages: Sender<ChatMessage>) {
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        // The server closes the connections which don't answer its pings.
+        if let Some(server) = line.strip_prefix("PING ") {
+            if write!(writer, "PONG {}\r\n", server.trim_end()).is_err() {
+                break;
+            }
+        } else if let Some(message) = ChatMessage::from_irc(&line) {
+            if messages.send(message).is_err() {
+                break;
+            }
+        }
+    }
+}
+
+impl Ui for ChatPlays {
+    fn initialize() -> Result<Self, Box<dyn Error>> {
+        let channel = env::var(CHANNEL_ENV_VAR).map_err(|_| Box::new(ChatError::NoChannel))?;
+        let overlay = env::var_os(OVERLAY_ENV_VAR).map(PathBuf::from);
+        Ok(ChatPlays::new(&channel, overlay)?)
+    }
+
+    fn get_action(&mut self, _board: &Board) -> Result<Action, Box<dyn Error>> {
+        loop {
+            let message = match self.deadline {
+                None => Some(
+                    self.messages
+                        .recv()
+                        .map_err(|_| Box::new(ChatError::Disconnected))?,
+                ),
+                Some(deadline) => match self
+                    .messages
+                    .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                {
+                    Ok(message) => Some(message),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => {
+                        return Err(Box::new(ChatError::Disconnected))
+                    }
+                },
+            };
+
+            match message {
+                Some(message) => {
+                    if let Some(command) = ChatCommand::parse(&message.text) {
+                        self.votes.vote(&message.user, command);
+                        self.deadline
+                            .get_or_insert_with(|| Instant::now() + VOTE_WINDOW);
+                        self.show(&self.status())?;
+                    }
+                }
+                // The votes are closed.
+                None => {
+                    let winner = self.votes.winner();
+                    self.votes.clear();
+                    self.deadline = None;
+                    if let Some((command, count)) = winner {
+                        self.last = Some((command, count));
+                        return Ok(command.into());
+                    }
+                }
+            }
+        }
+    }
+
+    fn display(
+        &mut self,
+        board: &Board,
+        _last_move_result: Option<Option<(u32, u32)>>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.board = format!(
+            "Sokoban played by the chat of #{}\n\n{}\nMoves: {}  Pushes: {}  Crates: {}/{}\n\n",
+            self.channel,
+            board_to_text(board),
+            board.moves(),
+            board.pushes(),
+            board.placed_crates(),
+            board.crate_count()
+        );
+        self.show(&self.status())?;
+        Ok(())
+    }
+
+    fn won(&mut self, score: &Score, _best: Option<&Score>) -> Result<WonChoice, Box<dyn Error>> {
+        self.last = None;
+        self.tip = None;
+        self.show(&format!(
+            "The chat won in {} moves, {} pushes and {}!\nNext level in {} seconds...\n",
+            score.moves,
+            score.pushes,
+            format_duration(score.time),
+            WON_PAUSE.as_secs()
+        ))?;
+        thread::sleep(WON_PAUSE);
+        Ok(WonChoice::Next)
+    }
+
+    fn show_message(&mut self, message: &str) -> Result<(), Box<dyn Error>> {
+        self.tip = Some(message.to_string());
+        self.show(&self.status())?;
+        Ok(())
+    }
+
+    fn lost(&mut self, defeat: &Defeat) -> Result<LostChoice, Box<dyn Error>> {
+        self.last = None;
+        self.tip = None;
+        self.show(&format!(
+            "{} {}\nTrying again in {} seconds...\n",
+            defeat.title(),
+            defeat,
+            WON_PAUSE.as_secs()
+        ))?;
+        thread::sleep(WON_PAUSE);
+        Ok(LostChoice::Retry)
+    }
+}
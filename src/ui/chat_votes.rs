@@ -0,0 +1,185 @@
+//! Commands sent in the chat for the [chat plays interface](`super::chat_plays`), and the
+//! votes deciding which one is played.
+
+use std::cmp::Reverse;
+
+use super::{Action, Direction};
+
+/// Command a viewer can send, with or without a leading `!`, in any case.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChatCommand {
+    Move(Direction),
+    Reset,
+}
+
+impl ChatCommand {
+    /// Command at the start of a chat message, if any.
+    pub fn parse(text: &str) -> Option<Self> {
+        let word = text.split_whitespace().next()?.trim_start_matches('!');
+        Some(match &word.to_lowercase()[..] {
+            "l" | "left" => ChatCommand::Move(Direction::Left),
+            "r" | "right" => ChatCommand::Move(Direction::Right),
+            "u" | "up" => ChatCommand::Move(Direction::Up),
+            "d" | "down" => ChatCommand::Move(Direction::Down),
+            "reset" => ChatCommand::Reset,
+            _ => return None,
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ChatCommand::Move(Direction::Left) => "left",
+            ChatCommand::Move(Direction::Right) => "right",
+            ChatCommand::Move(Direction::Up) => "up",
+            ChatCommand::Move(Direction::Down) => "down",
+            ChatCommand::Reset => "reset",
+        }
+    }
+}
+
+impl From<ChatCommand> for Action {
+    fn from(src: ChatCommand) -> Self {
+        match src {
+            ChatCommand::Move(dir) => Action::Movement(dir),
+            ChatCommand::Reset => Action::ResetLevel,
+        }
+    }
+}
+
+/// Message read in the chat.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChatMessage {
+    pub user: String,
+    pub text: String,
+}
+
+impl ChatMessage {
+    /// Reads a `PRIVMSG` line of IRC, such as `:nick!user@host PRIVMSG #channel :text`, the
+    /// tags sent by some servers before it being ignored.
+    pub fn from_irc(line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        let line = match line.strip_prefix('@') {
+            Some(tagged) => tagged.split_once(' ')?.1,
+            None => line,
+        };
+        let (prefix, rest) = line.strip_prefix(':')?.split_once(' ')?;
+        let (_, text) = rest.strip_prefix("PRIVMSG ")?.split_once(" :")?;
+        let user = prefix.split('!').next()?;
+        Some(ChatMessage {
+            user: user.to_string(),
+            text: text.to_string(),
+        })
+    }
+}
+
+/// Votes of a window, each viewer counting once with their last command.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Votes {
+    /// Viewers and their vote, in the order they first voted.
+    votes: Vec<(String, ChatCommand)>,
+}
+
+impl Votes {
+    pub fn vote(&mut self, user: &str, command: ChatCommand) {
+        match self.votes.iter_mut().find(|(u, _)| u == user) {
+            Some(vote) => vote.1 = command,
+            None => self.votes.push((user.to_string(), command)),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.votes.clear();
+    }
+
+    /// Number of votes for each command, the most voted first. Between commands with as many
+    /// votes, the first one voted comes first.
+    pub fn tally(&self) -> Vec<(ChatCommand, usize)> {
+        let mut tally: Vec<(ChatCommand, usize)> = Vec::new();
+        for (_, command) in self.votes.iter() {
+            match tally.iter_mut().find(|(c, _)| c == command) {
+                Some(count) => count.1 += 1,
+                None => tally.push((*command, 1)),
+            }
+        }
+        // The sort is stable, so the order of the first votes is kept.
+        tally.sort_by_key(|(_, count)| Reverse(*count));
+        tally
+    }
+
+    /// The most voted command, with its number of votes.
+    pub fn winner(&self) -> Option<(ChatCommand, usize)> {
+        self.tally().first().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChatCommand, ChatMessage, Direction, Votes};
+
+    #[test]
+    fn it_parses_commands() {
+        assert_eq!(
+            ChatCommand::parse("!UP please"),
+            Some(ChatCommand::Move(Direction::Up))
+        );
+        assert_eq!(
+            ChatCommand::parse("l"),
+            Some(ChatCommand::Move(Direction::Left))
+        );
+        assert_eq!(ChatCommand::parse("reset"), Some(ChatCommand::Reset));
+        assert_eq!(ChatCommand::parse("hello up"), None);
+        assert_eq!(ChatCommand::parse(""), None);
+    }
+
+    #[test]
+    fn it_reads_irc_messages() {
+        assert_eq!(
+            ChatMessage::from_irc(":alice!alice@alice.tmi.twitch.tv PRIVMSG #sokoban :up up\r"),
+            Some(ChatMessage {
+                user: "alice".to_string(),
+                text: "up up".to_string()
+            })
+        );
+        assert_eq!(
+            ChatMessage::from_irc("@badges=;color= :bob!bob@host PRIVMSG #sokoban :left")
+                .map(|m| m.user),
+            Some("bob".to_string())
+        );
+        assert_eq!(ChatMessage::from_irc("PING :tmi.twitch.tv"), None);
+        assert_eq!(
+            ChatMessage::from_irc(":tmi.twitch.tv 001 justinfan :Welcome"),
+            None
+        );
+    }
+
+    #[test]
+    fn it_counts_the_last_vote_of_each_viewer() {
+        let mut votes = Votes::default();
+        votes.vote("alice", ChatCommand::Move(Direction::Up));
+        votes.vote("bob", ChatCommand::Move(Direction::Left));
+        votes.vote("carol", ChatCommand::Move(Direction::Left));
+        // Alice changes her mind.
+        votes.vote("alice", ChatCommand::Reset);
+        assert_eq!(
+            votes.tally(),
+            vec![
+                (ChatCommand::Move(Direction::Left), 2),
+                (ChatCommand::Reset, 1)
+            ]
+        );
+
+        votes.vote("bob", ChatCommand::Reset);
+        assert_eq!(votes.winner(), Some((ChatCommand::Reset, 2)));
+
+        votes.clear();
+        assert_eq!(votes.winner(), None);
+
+        // Ties go to the first command voted.
+        votes.vote("bob", ChatCommand::Move(Direction::Down));
+        votes.vote("alice", ChatCommand::Move(Direction::Up));
+        assert_eq!(
+            votes.winner(),
+            Some((ChatCommand::Move(Direction::Down), 1))
+        );
+    }
+}
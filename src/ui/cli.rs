@@ -6,9 +6,14 @@ use std::{
     error::Error,
     fmt,
     io::{self, Write},
+    time::Instant,
 };
 
-use super::{terminal::*, Action, Board, BoardElem, CellKind, Direction, MovableItem, Ui};
+use super::{
+    format_duration, format_duration_millis, terminal::*, Action, Board, BoardElem, CellKind,
+    Defeat, Direction, Hotseat, LostChoice, MovableItem, MoveLimit, Score, Stopwatch, TimeLimit,
+    Ui, WonChoice,
+};
 
 #[derive(Debug)]
 pub enum CliError {
@@ -26,18 +31,59 @@ impl fmt::Display for CliError {
 
 impl Error for CliError {}
 
+/// Board drawn with the terminal symbols, one line per row.
+pub(super) fn board_to_text(board: &Board) -> String {
+    let mut text = String::new();
+    for j in 0..board.height() {
+        for i in 0..board.width() {
+            use CellKind::*;
+            use MovableItem::*;
+
+            text.push(match board.get(i, j) {
+                BoardElem(_, Void) => SYMBOL_VOID,
+                BoardElem(_, Wall) => SYMBOL_WALL,
+                BoardElem(None, Floor) => SYMBOL_FLOOR,
+                BoardElem(None, Target) => SYMBOL_TARGET,
+                BoardElem(Some(Player), Floor) => SYMBOL_PLAYER,
+                BoardElem(Some(Crate(_)), Floor) => SYMBOL_CRATE,
+                BoardElem(Some(Player), Target) => SYMBOL_PLAYER_ON_TARGET,
+                BoardElem(Some(Crate(_)), Target) => SYMBOL_PLACED_CRATE,
+            });
+        }
+        text.push('\n');
+    }
+    text
+}
+
 /// Base command-line interface.
 /// The whole scene is reprinted each step and the input isn't real-time.
-pub struct Cli;
+pub struct Cli {
+    /// Time limit of the level and when it started, to print the time left.
+    countdown: Option<(TimeLimit, Instant)>,
+    /// Moves and pushes allowed to win the level, to print how many are left.
+    challenge: Option<MoveLimit>,
+    /// Levels won in a row, in the endless mode.
+    streak: Option<u32>,
+    /// Turns and counters of the players, in the hotseat mode.
+    hotseat: Option<Hotseat>,
+    /// Timer of the run, in the speedrun mode.
+    speedrun: Option<Stopwatch>,
+}
 
 impl Ui for Cli {
     fn initialize() -> Result<Self, Box<dyn Error>> {
         println!("Welcome in my Sokoban.\nPush the crates around until all of them are placed on a target.\nEach turn, you must enter a command followed by 'enter': left (l), right (r), up (u), down (d), reset (re) or quit (qu).\n\nSymbols:\n- {} : floor\n- {} : wall\n- {} : target\n- {} : player\n- {} : player on a target (nothing particular, just to know there's a terget under)\n- {} : crate\n- {} : crate placed on a target (in the end, all crate should look like that).\n", SYMBOL_FLOOR, SYMBOL_WALL, SYMBOL_TARGET, SYMBOL_PLAYER, SYMBOL_PLAYER_ON_TARGET, SYMBOL_CRATE, SYMBOL_PLACED_CRATE);
 
-        Ok(Cli)
+        Ok(Cli {
+            countdown: None,
+            challenge: None,
+            streak: None,
+            hotseat: None,
+            speedrun: None,
+        })
     }
 
-    fn get_action(&self, _board: &Board) -> Result<Action, Box<dyn Error>> {
+    fn get_action(&mut self, _board: &Board) -> Result<Action, Box<dyn Error>> {
         let action = loop {
             print!("> ");
 
@@ -69,43 +115,140 @@ impl Ui for Cli {
     }
 
     fn display(
-        &self,
+        &mut self,
         board: &Board,
         _last_move_result: Option<Option<(u32, u32)>>,
     ) -> Result<(), Box<dyn Error>> {
-        let width = board.width();
-        let height = board.height();
-        for j in 0..height {
-            for i in 0..width {
-                use CellKind::*;
-                use MovableItem::*;
-
-                print!(
-                    "{}",
-                    match board.get(i, j) {
-                        BoardElem(_, Void) => SYMBOL_VOID,
-                        BoardElem(_, Wall) => SYMBOL_WALL,
-                        BoardElem(None, Floor) => SYMBOL_FLOOR,
-                        BoardElem(None, Target) => SYMBOL_TARGET,
-                        BoardElem(Some(Player), Floor) => SYMBOL_PLAYER,
-                        BoardElem(Some(Crate(_)), Floor) => SYMBOL_CRATE,
-                        BoardElem(Some(Player), Target) => SYMBOL_PLAYER_ON_TARGET,
-                        BoardElem(Some(Crate(_)), Target) => SYMBOL_PLACED_CRATE,
-                    }
-                )
-            }
-            println!();
+        print!("{}", board_to_text(board));
+        if let Some(streak) = self.streak {
+            println!("Streak: {}", streak);
+        }
+        if let Some(hotseat) = self.hotseat {
+            println!("{}", hotseat);
+        }
+        if let Some(stopwatch) = self.speedrun {
+            println!("Run: {}", format_duration_millis(stopwatch.elapsed()));
+        }
+        if let Some((limit, start)) = self.countdown {
+            println!(
+                "Time left: {}",
+                format_duration(limit.remaining(start.elapsed()))
+            );
+        }
+        if let Some(limit) = self.challenge {
+            println!(
+                "Moves left: {}, pushes left: {}",
+                limit.moves_left(board),
+                limit.pushes_left(board)
+            );
         }
 
         Ok(())
     }
 
-    fn won(&self) -> Result<(), Box<dyn Error>> {
+    fn won(&mut self, score: &Score, best: Option<&Score>) -> Result<WonChoice, Box<dyn Error>> {
         println!("+----------+");
         println!("| You won! |");
         println!("+----------+");
+        println!(
+            "{} moves, {} pushes in {}.",
+            score.moves,
+            score.pushes,
+            format_duration(score.time)
+        );
+        match best {
+            Some(best) if score.is_better_than(best) => println!("New personal best!"),
+            Some(best) => println!(
+                "Personal best: {} moves, {} pushes in {}.",
+                best.moves,
+                best.pushes,
+                format_duration(best.time)
+            ),
+            None => (),
+        }
+        println!("\nWhat now? restart (re), next (n) or quit (qu).");
+
+        let choice = loop {
+            print!("> ");
+            io::stdout()
+                .flush()
+                .map_err(|e| Box::new(CliError::IO(e)))?;
+
+            let mut buffer = String::new();
+            match io::stdin()
+                .read_line(&mut buffer)
+                .map_err(|e| Box::new(CliError::IO(e)))?
+            {
+                0 => break WonChoice::Quit,
+                _ => match &buffer.trim().to_lowercase()[..] {
+                    "re" | "restart" => break WonChoice::Restart,
+                    "n" | "next" => break WonChoice::Next,
+                    "qu" | "quit" => break WonChoice::Quit,
+                    _ => println!("Unknown command `{}`, please try again:", buffer.trim()),
+                },
+            };
+        };
+
+        Ok(choice)
+    }
+
+    fn start_countdown(&mut self, limit: Option<TimeLimit>) {
+        self.countdown = limit.map(|l| (l, Instant::now()));
+    }
+
+    fn start_challenge(&mut self, limit: Option<MoveLimit>) {
+        self.challenge = limit;
+    }
+
+    fn show_streak(&mut self, streak: Option<u32>) {
+        self.streak = streak;
+    }
+
+    fn show_turn(&mut self, hotseat: Option<&Hotseat>) {
+        self.hotseat = hotseat.copied();
+    }
+
+    fn show_speedrun(&mut self, stopwatch: Option<Stopwatch>) {
+        self.speedrun = stopwatch;
+    }
+
+    fn show_message(&mut self, message: &str) -> Result<(), Box<dyn Error>> {
+        println!("Tip: {}", message);
         Ok(())
     }
+
+    fn lost(&mut self, defeat: &Defeat) -> Result<LostChoice, Box<dyn Error>> {
+        self.countdown = None;
+        self.challenge = None;
+        let border = "-".repeat(defeat.title().len() + 2);
+        println!("+{}+", border);
+        println!("| {} |", defeat.title());
+        println!("+{}+", border);
+        println!("{}", defeat);
+        println!("\nWhat now? retry (re) or quit (qu).");
+
+        let choice = loop {
+            print!("> ");
+            io::stdout()
+                .flush()
+                .map_err(|e| Box::new(CliError::IO(e)))?;
+
+            let mut buffer = String::new();
+            match io::stdin()
+                .read_line(&mut buffer)
+                .map_err(|e| Box::new(CliError::IO(e)))?
+            {
+                0 => break LostChoice::Quit,
+                _ => match &buffer.trim().to_lowercase()[..] {
+                    "re" | "retry" => break LostChoice::Retry,
+                    "qu" | "quit" => break LostChoice::Quit,
+                    _ => println!("Unknown command `{}`, please try again:", buffer.trim()),
+                },
+            };
+        };
+
+        Ok(choice)
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,225 @@
+//! Level editor of the graphical interfaces ([ggez](`super::ggez`) and
+//! [macroquad](`super::macroquad`)): the cells are painted with the mouse, and the crates and
+//! the player can be dragged around.
+
+use super::{Board, BoardElem, CellKind, MovableItem};
+
+/// What is painted on the cells clicked on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Brush {
+    Wall,
+    Floor,
+    Target,
+    Void,
+    Crate,
+    Player,
+}
+
+/// Brushes in the order of their shortcut, from `1`.
+pub const BRUSHES: [Brush; 6] = [
+    Brush::Wall,
+    Brush::Floor,
+    Brush::Target,
+    Brush::Void,
+    Brush::Crate,
+    Brush::Player,
+];
+
+impl Brush {
+    pub fn name(self) -> &'static str {
+        match self {
+            Brush::Wall => "Wall",
+            Brush::Floor => "Floor",
+            Brush::Target => "Target",
+            Brush::Void => "Void",
+            Brush::Crate => "Crate",
+            Brush::Player => "Player",
+        }
+    }
+}
+
+/// What the mouse does while its button is held.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Stroke {
+    /// Painting with the brush each cell the mouse goes over.
+    Painting,
+    /// Emptying each cell the mouse goes over.
+    Erasing,
+    /// Moving the player or the crate which was on the cell.
+    Dragging { from: (u32, u32), player: bool },
+}
+
+pub struct Editor {
+    pub brush: Brush,
+    stroke: Option<Stroke>,
+    /// Last cell painted, so a crate isn't toggled again while the mouse stays on it.
+    last_cell: Option<(u32, u32)>,
+    /// If the level changed since it was last saved.
+    pub unsaved: bool,
+}
+
+impl Editor {
+    pub fn new() -> Self {
+        Editor {
+            brush: Brush::Wall,
+            stroke: None,
+            last_cell: None,
+            unsaved: false,
+        }
+    }
+
+    /// The mouse button is pressed on `cell`: the item on it is picked up, or else the cell is
+    /// painted.
+    ///
+    /// Like the other mouse events, returns if the `board` changed.
+    pub fn press(&mut self, board: &mut Board, cell: (u32, u32)) -> bool {
+        match board.get(cell.0, cell.1) {
+            BoardElem(Some(item), _) if self.brush != Brush::Crate => {
+                let player = matches!(item, MovableItem::Player);
+                self.stroke = Some(Stroke::Dragging { from: cell, player });
+                false
+            }
+            _ => {
+                self.stroke = Some(Stroke::Painting);
+                self.last_cell = None;
+                self.paint(board, cell, self.brush)
+            }
+        }
+    }
+
+    /// The button to erase (usually the right one) is pressed on `cell`: it is emptied, as the
+    /// cells the mouse goes over until it is released.
+    pub fn press_erase(&mut self, board: &mut Board, cell: (u32, u32)) -> bool {
+        self.stroke = Some(Stroke::Erasing);
+        self.last_cell = None;
+        self.paint(board, cell, Brush::Void)
+    }
+
+    /// The mouse went over `cell`, with its button held or not.
+    pub fn hover(&mut self, board: &mut Board, cell: (u32, u32)) -> bool {
+        match self.stroke {
+            Some(Stroke::Painting) => self.paint(board, cell, self.brush),
+            Some(Stroke::Erasing) => self.paint(board, cell, Brush::Void),
+            _ => false,
+        }
+    }
+
+    /// The mouse button is released over `cell`, or out of the board: the item dragged is
+    /// dropped if the cell is free.
+    pub fn release(&mut self, board: &mut Board, cell: Option<(u32, u32)>) -> bool {
+        let stroke = self.stroke.take();
+        let (Some(Stroke::Dragging { from, player }), Some((i, j))) = (stroke, cell) else {
+            return false;
+        };
+        if (i, j) == from || board.get(i, j).0.is_some() {
+            return false;
+        }
+
+        if player {
+            board.set_player(i, j);
+        } else {
+            board.toggle_crate(from.0, from.1);
+            board.toggle_crate(i, j);
+        }
+        self.unsaved = true;
+        true
+    }
+
+    /// Cell of the item being dragged, and if it is the player.
+    pub fn dragged(&self) -> Option<((u32, u32), bool)> {
+        match self.stroke {
+            Some(Stroke::Dragging { from, player }) => Some((from, player)),
+            _ => None,
+        }
+    }
+
+    fn paint(&mut self, board: &mut Board, (i, j): (u32, u32), brush: Brush) -> bool {
+        if self.last_cell.replace((i, j)) == Some((i, j)) {
+            return false;
+        }
+
+        let BoardElem(item, kind) = board.get(i, j);
+        let set_kind = |board: &mut Board, new_kind| {
+            if kind == new_kind {
+                false
+            } else {
+                board.set_cell(i, j, new_kind);
+                true
+            }
+        };
+        let changed = match brush {
+            Brush::Wall => set_kind(board, CellKind::Wall),
+            Brush::Floor => set_kind(board, CellKind::Floor),
+            Brush::Target => set_kind(board, CellKind::Target),
+            Brush::Void => set_kind(board, CellKind::Void),
+            Brush::Crate if matches!(item, Some(MovableItem::Player)) => false,
+            Brush::Crate => {
+                board.toggle_crate(i, j);
+                true
+            }
+            Brush::Player if matches!(item, Some(MovableItem::Player)) => false,
+            Brush::Player => {
+                board.set_player(i, j);
+                true
+            }
+        };
+        self.unsaved |= changed;
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Board, Editor, BRUSHES};
+
+    const TEST_XSB: &str = "#####
+#@$.#
+#   #
+#####
+";
+
+    #[test]
+    fn it_paints_each_cell_once() {
+        let mut board = Board::from_xsb(TEST_XSB).unwrap();
+        let mut editor = Editor::new();
+        editor.brush = BRUSHES[4];
+        assert_eq!(editor.brush.name(), "Crate");
+
+        assert!(editor.press(&mut board, (1, 2)));
+        assert!(!editor.hover(&mut board, (1, 2)));
+        assert!(editor.hover(&mut board, (2, 2)));
+        assert!(!editor.release(&mut board, Some((2, 2))));
+        assert_eq!(board.crate_count(), 3);
+        assert!(editor.unsaved);
+    }
+
+    #[test]
+    fn it_drags_items_to_free_cells() {
+        let mut board = Board::from_xsb(TEST_XSB).unwrap();
+        let mut editor = Editor::new();
+
+        assert!(!editor.press(&mut board, (2, 1)));
+        assert_eq!(editor.dragged(), Some(((2, 1), false)));
+        assert!(!editor.hover(&mut board, (1, 1)));
+        assert!(!editor.release(&mut board, Some((1, 1))));
+        assert_eq!(board.to_xsb(), TEST_XSB);
+
+        editor.press(&mut board, (1, 1));
+        assert!(editor.release(&mut board, Some((3, 2))));
+        assert_eq!(board.to_xsb(), "#####\n# $.#\n#  @#\n#####\n");
+        assert_eq!(editor.dragged(), None);
+    }
+
+    #[test]
+    fn it_erases_cells_and_their_crates() {
+        let mut board = Board::from_xsb(TEST_XSB).unwrap();
+        let mut editor = Editor::new();
+
+        assert!(editor.press_erase(&mut board, (2, 1)));
+        assert!(editor.hover(&mut board, (2, 2)));
+        assert!(!editor.release(&mut board, Some((2, 2))));
+        assert_eq!(board.crate_count(), 0);
+        assert_eq!(editor.brush, BRUSHES[0]);
+        assert!(!editor.hover(&mut board, (3, 2)));
+    }
+}
@@ -2,22 +2,95 @@
 //!
 //! This version provides it's own event loop.
 
-use std::{env, path::PathBuf, str::FromStr, time::Instant};
+use std::{
+    collections::VecDeque,
+    env,
+    io::{Read, Write},
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
 use ggez::{
-    conf::{Conf, WindowMode},
-    event::{self, Button, GamepadId},
+    audio::{self, SoundSource},
+    conf::{Conf, FullscreenType, WindowMode, WindowSetup},
+    event::{self, Axis, Button, GamepadId, MouseButton},
     glam::Vec2,
-    graphics::{self, Color, DrawMode, DrawParam, Drawable, Rect, Text, TextAlign, TextLayout},
+    graphics::{
+        self, Color, DrawMode, DrawParam, ImageEncodingFormat, ImageFormat, InstanceArray, Rect,
+        Text, TextAlign, TextLayout,
+    },
     input::keyboard::{KeyCode, KeyInput},
     Context, ContextBuilder, GameError, GameResult,
 };
 
-use super::{Board, BoardElem, CellKind, Direction, MovableItem};
+use crate::{data::Tutorial, Endless, GameOptions, Speedrun};
+
+use super::{
+    animation::Easing,
+    atlas::AtlasManifest,
+    audio::{AudioSink, SoundEffect},
+    editor::{Editor, BRUSHES},
+    format_duration, format_duration_millis, lint_summary, Board, BoardElem, CellKind, Defeat,
+    Direction, LevelLibrary, MovableItem, MoveLimit, Score, TimeLimit,
+};
 
 const ANIMATION_DURATION_MILIS: u64 = 200;
+/// Most direction presses kept while a move is being animated.
+const MAX_BUFFERED_MOVES: usize = 3;
+/// Scale of the images when the camera follows the player.
+const FOLLOW_SCALE: f32 = 2.;
+/// Below this scale, the board is too small to be readable when fitted in the window, so the
+/// camera follows the player instead.
+const MIN_FIT_SCALE: f32 = 1.;
+/// How fast the camera catches up with the player, in fraction of the distance per second.
+const CAMERA_FOLLOW_SPEED: f32 = 8.;
+/// How long the burst of particles lasts when a crate is pushed on a target.
+const BURST_DURATION: Duration = Duration::from_millis(400);
+const BURST_PARTICLES: u32 = 12;
+/// Distance reached by the particles, relative to the size of a cell.
+const BURST_RADIUS: f32 = 0.9;
+/// Distance from the center under which the analog stick is ignored, between `0.` and `1.`.
+const STICK_DEAD_ZONE: f32 = 0.5;
+/// Environment variable containing how long a direction must be held with the analog stick
+/// before the player moves again, in milliseconds.
+const STICK_REPEAT_ENV_VAR: &str = "SOKOBAN_STICK_REPEAT_MS";
+const DEFAULT_STICK_REPEAT_DELAY: Duration = Duration::from_millis(300);
+/// Once repeating, the player moves again as soon as the last move is drawn.
+const STICK_REPEAT_INTERVAL: Duration = Duration::from_millis(ANIMATION_DURATION_MILIS);
+/// Height of the lines of the HUD in the top-left corner.
+const HUD_TEXT_SIZE: f32 = 15.;
+/// Number of lines of the HUD: title, counters, crates placed, time and FPS.
+const HUD_LINES: usize = 5;
+/// Height of the lines of the menus.
+const MENU_TEXT_SIZE: f32 = 24.;
+/// Most entries shown at once in a menu, the list scrolling around the selected one.
+const MENU_VISIBLE_ENTRIES: usize = 10;
+/// Volume of the music when starting, between `0.` and `1.`.
+const DEFAULT_MUSIC_VOLUME: f32 = 0.5;
+/// Change of the music volume for each key press.
+const VOLUME_STEP: f32 = 0.1;
+/// How long a notice (like the new volume) is shown under the HUD.
+const NOTICE_DURATION: Duration = Duration::from_secs(1);
+const GAME_NAME: &str = "Sokoban";
+/// Icon of the window, in the resources.
+const ICON_PATH: &str = "/icon.png";
+/// Shader of the CRT filter, in the resources.
+const CRT_SHADER_PATH: &str = "/crt_shader.wgsl";
+/// Environment variable containing the visual settings to enable, separated by commas.
+const VISUALS_ENV_VAR: &str = "SOKOBAN_VISUALS";
+const VISUALS_CRT: &str = "crt";
+const VISUALS_SMOOTH: &str = "smooth";
+/// Directory of the screenshots, in the user data directory.
+const SCREENSHOTS_DIR: &str = "/screenshots";
+/// Empty cells added around the edited level, so it can be extended.
+const EDITOR_MARGIN: u32 = 2;
+/// Where the levels made with the editor are saved, in the user data directory.
+const EDITED_LEVELS_DIR: &str = "/levels";
+/// Environment variable containing the name of the animation easing to start with.
+const EASING_ENV_VAR: &str = "SOKOBAN_EASING";
 
-pub fn game_ggez(level: &str) -> GameResult {
+/// Plays the levels of `library` in the modes of `options`.
+pub fn game_ggez(library: LevelLibrary, options: GameOptions) -> GameResult {
     let resource_dir = if let Ok(manifest_dir) = env::var("CARGO_MANIFEST_DIR") {
         println!("{manifest_dir}");
         let mut path = PathBuf::from(manifest_dir);
@@ -27,9 +100,10 @@ pub fn game_ggez(level: &str) -> GameResult {
         PathBuf::from("/images")
     };
 
-    let (ctx, event_loop) = ContextBuilder::new("Sokoban", "GuyDuNigo")
+    let (ctx, event_loop) = ContextBuilder::new(GAME_NAME, "GuyDuNigo")
         .default_conf(Conf::default())
         .resources_dir_name(resource_dir)
+        .window_setup(WindowSetup::default().title(GAME_NAME).icon(ICON_PATH))
         .window_mode(WindowMode {
             resizable: true,
             ..Default::default()
@@ -37,34 +111,416 @@ pub fn game_ggez(level: &str) -> GameResult {
         .build()
         .expect("Couldn't initialize context.");
 
-    let state = State::new(&ctx, level)?;
+    let state = State::new(&ctx, library, &options)?;
 
     event::run(ctx, event_loop, state);
 }
 
-struct Images {
-    caisse: graphics::Image,
-    caisse_ok: graphics::Image,
-    mario_bas: graphics::Image,
-    mario_droite: graphics::Image,
-    mario_gauche: graphics::Image,
-    mario_haut: graphics::Image,
-    mur: graphics::Image,
-    objectif: graphics::Image,
+/// Manifest of the tile atlas, in the resources.
+const ATLAS_MANIFEST_PATH: &str = "/atlas.txt";
+
+/// Part of the atlas to draw for a tile.
+#[derive(Clone, Copy, Debug)]
+struct Tile {
+    /// Source rectangle, relative to the size of the atlas
+    src: Rect,
+    /// Scale for the tile to be the size of a cell
+    fit: Vec2,
+}
+
+/// Tiles of the game, all cut from the same atlas image.
+struct Tiles {
+    atlas: graphics::Image,
+    /// Size of a cell, the one of the wall tile
+    dimensions: Rect,
+    floor: Tile,
+    wall: Tile,
+    target: Tile,
+    crate_: Tile,
+    placed_crate: Tile,
+    player_up: Tile,
+    player_down: Tile,
+    player_left: Tile,
+    player_right: Tile,
+}
+
+impl Tiles {
+    /// Loads the atlas described by the manifest at [`ATLAS_MANIFEST_PATH`].
+    fn new(ctx: &Context) -> GameResult<Self> {
+        let mut manifest = String::new();
+        ctx.fs
+            .open(ATLAS_MANIFEST_PATH)?
+            .read_to_string(&mut manifest)?;
+        let manifest: AtlasManifest = manifest
+            .parse()
+            .map_err(|e| GameError::ResourceLoadError(format!("Couldn't load atlas : {e}")))?;
+
+        let atlas = graphics::Image::from_path(ctx, &manifest.image)?;
+        let (atlas_w, atlas_h) = (atlas.width() as f32, atlas.height() as f32);
+        let wall = manifest
+            .region("wall")
+            .expect("The atlas should have a wall");
+        let dimensions = Rect::new(0., 0., wall.w as f32, wall.h as f32);
+
+        let tile = |name| {
+            let region = manifest
+                .region(name)
+                .expect("The atlas should have all the tiles");
+            let (x, y, w, h) = (
+                region.x as f32,
+                region.y as f32,
+                region.w as f32,
+                region.h as f32,
+            );
+            Tile {
+                src: Rect::new(x / atlas_w, y / atlas_h, w / atlas_w, h / atlas_h),
+                fit: Vec2::new(dimensions.w / w, dimensions.h / h),
+            }
+        };
+
+        Ok(Tiles {
+            floor: tile("floor"),
+            wall: tile("wall"),
+            target: tile("target"),
+            crate_: tile("crate"),
+            placed_crate: tile("placed_crate"),
+            player_up: tile("player_up"),
+            player_down: tile("player_down"),
+            player_left: tile("player_left"),
+            player_right: tile("player_right"),
+            atlas,
+            dimensions,
+        })
+    }
+}
+
+/// Cells which don't change during a game (floors, walls and targets), batched so they are
+/// drawn in a single call instead of one per cell.
+struct StaticLayer {
+    batch: InstanceArray,
+}
+
+impl StaticLayer {
+    /// Places the cells of the `board` with a scale of `1.`.
+    fn new(ctx: &Context, board: &Board, tiles: &Tiles) -> Self {
+        let mut batch = InstanceArray::new(ctx, tiles.atlas.clone());
+
+        for j in 0..board.height() {
+            for i in 0..board.width() {
+                let params = DrawParam::default().dest(Vec2::new(
+                    i as f32 * tiles.dimensions.w,
+                    j as f32 * tiles.dimensions.h,
+                ));
+                let mut push = |tile: Tile| batch.push(params.src(tile.src).scale(tile.fit));
+
+                match board.get(i, j) {
+                    BoardElem(_, CellKind::Void) => (),
+                    BoardElem(_, CellKind::Wall) => push(tiles.wall),
+                    BoardElem(_, CellKind::Floor) => push(tiles.floor),
+                    BoardElem(_, CellKind::Target) => {
+                        push(tiles.floor);
+                        push(tiles.target);
+                    }
+                }
+            }
+        }
+
+        StaticLayer { batch }
+    }
+
+    /// Draws the cells with the `params` of the whole board.
+    fn draw(&self, canvas: &mut graphics::Canvas, params: DrawParam) {
+        canvas.draw(&self.batch, params);
+    }
+}
+
+/// How the game is rendered, whatever the tiles.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct VisualSettings {
+    /// If the whole frame goes through the CRT filter (scanlines and vignette)
+    crt_filter: bool,
+    /// If the tiles are scaled with linear filtering, instead of keeping sharp pixels
+    smooth: bool,
+}
+
+impl VisualSettings {
+    /// Settings listed in [`VISUALS_ENV_VAR`], all disabled by default.
+    fn from_env() -> Self {
+        let options = env::var(VISUALS_ENV_VAR).unwrap_or_default();
+        let enabled = |name| options.split(',').any(|o| o.trim() == name);
+        VisualSettings {
+            crt_filter: enabled(VISUALS_CRT),
+            smooth: enabled(VISUALS_SMOOTH),
+        }
+    }
+
+    fn sampler(&self) -> graphics::Sampler {
+        if self.smooth {
+            graphics::Sampler::linear_clamp()
+        } else {
+            // Best for pixel art as it doesn't make things blurry.
+            graphics::Sampler::nearest_clamp()
+        }
+    }
+}
+
+/// Plays the sounds with the ggez audio API.
+///
+/// As ggez needs its context to play sounds, they are only requested through [`AudioSink`] and
+/// actually played on next [`GgezAudio::update`].
+struct GgezAudio {
+    step: audio::Source,
+    push: audio::Source,
+    blocked: audio::Source,
+    crate_on_target: audio::Source,
+    won: audio::Source,
+    music: audio::Source,
+    /// Effects to play on next update.
+    pending: Vec<SoundEffect>,
+    /// If the music should be playing.
+    music_requested: bool,
+}
+
+impl GgezAudio {
+    fn new(ctx: &Context) -> GameResult<Self> {
+        let mut music = audio::Source::new(ctx, "/sounds/music.wav")?;
+        music.set_repeat(true);
+        music.set_volume(DEFAULT_MUSIC_VOLUME);
+
+        Ok(GgezAudio {
+            step: audio::Source::new(ctx, "/sounds/step.wav")?,
+            push: audio::Source::new(ctx, "/sounds/push.wav")?,
+            blocked: audio::Source::new(ctx, "/sounds/blocked.wav")?,
+            crate_on_target: audio::Source::new(ctx, "/sounds/target.wav")?,
+            won: audio::Source::new(ctx, "/sounds/won.wav")?,
+            music,
+            pending: Vec::new(),
+            music_requested: false,
+        })
+    }
+
+    /// Plays the requested sounds.
+    fn update(&mut self, ctx: &Context) -> GameResult {
+        for effect in self.pending.drain(..) {
+            let source = match effect {
+                SoundEffect::Step => &mut self.step,
+                SoundEffect::Push => &mut self.push,
+                SoundEffect::Blocked => &mut self.blocked,
+                SoundEffect::CrateOnTarget => &mut self.crate_on_target,
+                SoundEffect::Won => &mut self.won,
+            };
+            // Detached so quick steps can overlap.
+            source.play_detached(ctx)?;
+        }
+
+        if self.music_requested && !self.music.playing() {
+            self.music.play(ctx)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl AudioSink for GgezAudio {
+    fn play_effect(&mut self, effect: SoundEffect) {
+        self.pending.push(effect);
+    }
+
+    fn play_music(&mut self) {
+        self.music_requested = true;
+    }
+
+    fn music_volume(&self) -> f32 {
+        self.music.volume()
+    }
+
+    fn set_music_volume(&mut self, volume: f32) {
+        self.music.set_volume(volume.clamp(0., 1.));
+    }
+}
+
+/// Entries of the pause menu.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PauseEntry {
+    Resume,
+    Restart,
+    LevelSelect,
+    Edit,
+    Quit,
+}
+
+const PAUSE_ENTRIES: [PauseEntry; 5] = [
+    PauseEntry::Resume,
+    PauseEntry::Restart,
+    PauseEntry::LevelSelect,
+    PauseEntry::Edit,
+    PauseEntry::Quit,
+];
+
+impl PauseEntry {
+    fn label(self) -> &'static str {
+        match self {
+            PauseEntry::Resume => "Resume",
+            PauseEntry::Restart => "Restart",
+            PauseEntry::LevelSelect => "Level select",
+            PauseEntry::Edit => "Edit level",
+            PauseEntry::Quit => "Quit",
+        }
+    }
+}
+
+/// Entries of the menu shown once a level is won.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum WonEntry {
+    Next,
+    Replay,
+    Quit,
+}
+
+const WON_ENTRIES: [WonEntry; 3] = [WonEntry::Next, WonEntry::Replay, WonEntry::Quit];
+
+impl WonEntry {
+    fn label(self) -> &'static str {
+        match self {
+            WonEntry::Next => "Next level",
+            WonEntry::Replay => "Replay",
+            WonEntry::Quit => "Quit",
+        }
+    }
+}
+
+/// Entries of the menu shown once the time ran out, in the time-attack mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum LostEntry {
+    Retry,
+    LevelSelect,
+    Quit,
+}
+
+const LOST_ENTRIES: [LostEntry; 3] = [LostEntry::Retry, LostEntry::LevelSelect, LostEntry::Quit];
+
+impl LostEntry {
+    fn label(self) -> &'static str {
+        match self {
+            LostEntry::Retry => "Retry",
+            LostEntry::LevelSelect => "Level select",
+            LostEntry::Quit => "Quit",
+        }
+    }
+}
+
+/// Menu shown over the board, with the index of the selected entry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Menu {
+    Pause(usize),
+    /// The level is won.
+    Won(usize),
+    /// A limit was exceeded before the level was won.
+    Lost(usize),
+    /// List of the levels of the library.
+    LevelSelect(usize),
+}
+
+/// Navigation in a menu, from the keyboard or a gamepad.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum MenuInput {
+    Previous,
+    Next,
+    Confirm,
+    Back,
+    /// Switches the selected entry on or off, such as the move-limit challenge of a level.
+    Toggle,
+}
+
+/// How the board is placed in the window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CameraMode {
+    /// The whole board is scaled to fit in the window.
+    Fit,
+    /// The board is drawn big enough to be readable, and the camera follows the player.
+    Follow,
 }
 
 struct State {
+    /// Levels which can be played
+    library: LevelLibrary,
+    /// Index in the library of the current level
+    level_index: usize,
+    /// Title of the level
+    title: String,
     /// Game state
     board: Board,
+    /// Menu opened over the game, which is paused meanwhile
+    menu: Option<Menu>,
+    /// When the menu was opened (to stop the clock)
+    menu_opened_instant: Instant,
+    /// When the level was started (for the elapsed time)
+    start_instant: Instant,
+    /// How long it took to win the level, once it is won
+    won_time: Option<Duration>,
+    /// Score of the won level and the previous best score, once it is saved in the library
+    won_score: Option<(Score, Option<Score>)>,
+    /// If each level must be won before its par time runs out
+    time_attack: bool,
+    /// Time given to win the level, in the time-attack mode
+    time_limit: Option<TimeLimit>,
+    /// Moves and pushes allowed to win the level, if it is played in the move-limit challenge
+    move_limit: Option<MoveLimit>,
+    /// Run of random levels, in the endless mode
+    endless: Option<Endless>,
+    /// Run through the levels and where its splits are written, in the speedrun mode
+    speedrun: Option<(Speedrun, PathBuf)>,
+    /// Hints of the level being played, if it is part of a tutorial
+    tutorial: Tutorial,
+    /// Last hints triggered, shown at the bottom of the window
+    tip: Option<String>,
     /// Loaded images
-    images: Images,
+    tiles: Tiles,
+    /// Cells which don't move, built again only when the level changes
+    static_layer: StaticLayer,
     /// Direction indicating where the caracting is facing
     direction: Direction,
+    /// Direction of the last move (for animation), opposite to `direction` when undoing
+    move_direction: Direction,
     /// When the player moved last (for animation)
     last_move_instant: Instant,
     /// New position of the moved crated if any (for animation)
     moved_crate: Option<(u32, u32)>,
-    shader: graphics::Shader,
+    /// Cells where a crate was pushed on a target, and when their burst of particles starts
+    bursts: Vec<((u32, u32), Instant)>,
+    visuals: VisualSettings,
+    crt_shader: graphics::Shader,
+    /// Frame drawn before going through the CRT filter
+    scene: graphics::ScreenImage,
+    audio: GgezAudio,
+    /// Message about the last setting changed, and when (to show it for a while)
+    notice: Option<(String, Instant)>,
+    /// Curve of the movement animation
+    easing: Easing,
+    /// Last title given to the window
+    window_title: String,
+    /// Steps waiting for the current animation to end: the directions pressed meanwhile, or
+    /// the steps left to walk to the cell the player clicked on
+    queued_moves: VecDeque<Direction>,
+    /// If the queued steps lead to a clicked cell, so any other input stops the walk
+    walking_to_click: bool,
+    camera_mode: CameraMode,
+    /// If the window is in borderless fullscreen
+    fullscreen: bool,
+    /// Position of the top-left corner of the window on the scaled board, in `Follow` mode
+    camera: Vec2,
+    /// Position of the left analog stick, `y` going up
+    stick: Vec2,
+    /// Direction held with the stick, if out of the dead zone
+    stick_direction: Option<Direction>,
+    /// When the held direction is repeated
+    stick_repeat_instant: Instant,
+    /// Delay before the held direction is repeated the first time
+    stick_repeat_delay: Duration,
+    /// Level editor, while the level is edited instead of played
+    editor: Option<Editor>,
+    /// Last position of the mouse in the window, to draw the dragged item
+    mouse_pos: Vec2,
 }
 
 struct ScaleInfos {
@@ -77,55 +533,418 @@ struct ScaleInfos {
     win_h: f32,
 }
 
+impl ScaleInfos {
+    /// Scale of the images so the whole board fits in the window.
+    fn scale(&self) -> f32 {
+        f32::min(self.scale_w, self.scale_h)
+    }
+
+    /// Coordinates of the board cell under the window position `(x, y)`, if any, with the
+    /// board drawn from `origin` with `scale`.
+    fn cell_at(
+        &self,
+        board: &Board,
+        (scale, origin): (f32, Vec2),
+        x: f32,
+        y: f32,
+    ) -> Option<(u32, u32)> {
+        let (i, j) = (
+            ((x - origin.x) / (self.dimensions.w * scale)).floor(),
+            ((y - origin.y) / (self.dimensions.h * scale)).floor(),
+        );
+        if i < 0. || j < 0. || i >= board.width() as f32 || j >= board.height() as f32 {
+            return None;
+        }
+        Some((i as u32, j as u32))
+    }
+}
+
 impl State {
-    fn new(ctx: &Context, level: &str) -> GameResult<Self> {
-        let state = State {
-            board: Board::from_str(level)
-                .map_err(|e| GameError::CustomError(format!("Couldn't parse level : {e}")))?,
-            images: Images {
-                caisse: graphics::Image::from_path(ctx, "/caisse.jpg")?,
-                caisse_ok: graphics::Image::from_path(ctx, "/caisse_ok.jpg")?,
-                mario_bas: graphics::Image::from_path(ctx, "/mario_bas.gif")?,
-                mario_droite: graphics::Image::from_path(ctx, "/mario_droite.gif")?,
-                mario_gauche: graphics::Image::from_path(ctx, "/mario_gauche.gif")?,
-                mario_haut: graphics::Image::from_path(ctx, "/mario_haut.gif")?,
-                mur: graphics::Image::from_path(ctx, "/mur.jpg")?,
-                objectif: graphics::Image::from_path(ctx, "/objectif.png")?,
-            },
+    fn new(ctx: &Context, library: LevelLibrary, options: &GameOptions) -> GameResult<Self> {
+        let speedrun = options
+            .speedrun
+            .clone()
+            .map(|path| (Speedrun::new(&library), path));
+        // A speedrun starts with the first level.
+        let level_index = match speedrun {
+            Some(_) => 0,
+            None => library.first_unsolved().unwrap_or(0),
+        };
+        let level = library
+            .get(level_index)
+            .expect("A library always contains at least one level.");
+        let board = level.board().clone();
+        let tiles = Tiles::new(ctx)?;
+
+        let mut state = State {
+            static_layer: StaticLayer::new(ctx, &board, &tiles),
+            title: level.title().to_string(),
+            library,
+            level_index,
+            board,
+            menu: None,
+            menu_opened_instant: Instant::now(),
+            start_instant: Instant::now(),
+            won_time: None,
+            won_score: None,
+            time_attack: options.time_attack,
+            time_limit: None,
+            move_limit: None,
+            endless: None,
+            speedrun,
+            tutorial: Tutorial::default(),
+            tip: None,
+            tiles,
             direction: Direction::Down,
+            move_direction: Direction::Down,
             last_move_instant: Instant::now(),
             moved_crate: None,
-            shader: graphics::ShaderBuilder::new()
-                .fragment_path("/rand_noise_shader.wgsl")
+            bursts: Vec::new(),
+            visuals: VisualSettings::from_env(),
+            crt_shader: graphics::ShaderBuilder::new()
+                .fragment_path(CRT_SHADER_PATH)
                 .build(&ctx.gfx)?,
+            scene: graphics::ScreenImage::new(ctx, None, 1., 1., 1),
+            audio: GgezAudio::new(ctx)?,
+            notice: None,
+            easing: env::var(EASING_ENV_VAR)
+                .ok()
+                .and_then(|name| Easing::ALL.into_iter().find(|e| e.name() == name))
+                .unwrap_or(Easing::Linear),
+            window_title: GAME_NAME.to_string(),
+            queued_moves: VecDeque::new(),
+            walking_to_click: false,
+            camera_mode: CameraMode::Fit,
+            fullscreen: false,
+            camera: Vec2::ZERO,
+            stick: Vec2::ZERO,
+            stick_direction: None,
+            stick_repeat_instant: Instant::now(),
+            stick_repeat_delay: env::var(STICK_REPEAT_ENV_VAR)
+                .ok()
+                .and_then(|ms| ms.parse().ok())
+                .map_or(DEFAULT_STICK_REPEAT_DELAY, Duration::from_millis),
+            editor: None,
+            mouse_pos: Vec2::ZERO,
         };
+        state.audio.play_music();
+        state.load_level(ctx, level_index);
 
         Ok(state)
     }
 
+    /// Starts the level at `index` in the library.
+    fn load_level(&mut self, ctx: &Context, index: usize) {
+        let Some(level) = self.library.get(index) else {
+            return;
+        };
+        self.level_index = index;
+        self.endless = None;
+        // A speedrun is only against its timer.
+        let limits = self.speedrun.is_none();
+        self.time_limit = (limits && self.time_attack).then(|| TimeLimit::par(level));
+        self.move_limit = (limits && level.is_challenge())
+            .then(|| MoveLimit::par(level))
+            .flatten();
+        self.tutorial = Tutorial::new(level);
+        self.title = level.title().to_string();
+        self.board = level.board().clone();
+        self.board_changed(ctx);
+        self.update_tutorial();
+    }
+
+    /// Starts a run of random levels, see [`Endless`].
+    fn start_endless(&mut self, ctx: &Context) {
+        // The clock gives a different run each time.
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+        self.next_endless_level(ctx, Endless::new(seed));
+    }
+
+    /// Starts the next level of the `endless` run, or ends it if no level could be generated.
+    fn next_endless_level(&mut self, ctx: &Context, mut endless: Endless) {
+        match endless.next_level() {
+            Ok(level) => {
+                self.time_limit = self.time_attack.then(|| TimeLimit::par(&level));
+                self.move_limit = None;
+                self.tutorial = Tutorial::default();
+                self.title = level.title().to_string();
+                self.board = level.board().clone();
+                self.board_changed(ctx);
+                self.endless = Some(endless);
+            }
+            Err(err) => self.notify(format!("Couldn't generate a level : {err}")),
+        }
+    }
+
+    /// Starts the new `board` over, choosing how to show it.
+    fn board_changed(&mut self, ctx: &Context) {
+        self.static_layer = StaticLayer::new(ctx, &self.board, &self.tiles);
+        self.tip = None;
+        self.reset();
+
+        // Big levels would be unreadable if fitted in the window.
+        let camera_mode = if self.get_screen_scale(ctx, None).scale() < MIN_FIT_SCALE {
+            CameraMode::Follow
+        } else {
+            CameraMode::Fit
+        };
+        if camera_mode != self.camera_mode {
+            self.toggle_camera_mode(ctx);
+        }
+    }
+
     fn reset(&mut self) {
+        self.queued_moves.clear();
+        self.walking_to_click = false;
+        self.bursts.clear();
         self.board.reset();
+        self.start_instant = Instant::now();
+        self.won_time = None;
+        self.won_score = None;
         self.direction = Direction::Down;
+        self.move_direction = Direction::Down;
         self.last_move_instant = Instant::now();
     }
 
+    /// Time spent on the level, until it was won.
+    fn elapsed(&self) -> Duration {
+        self.won_time
+            .unwrap_or_else(|| self.start_instant.elapsed())
+    }
+
+    /// Time limit of the level being played, if any. Editing isn't timed.
+    fn countdown(&self) -> Option<TimeLimit> {
+        self.time_limit.filter(|_| self.editor.is_none())
+    }
+
+    /// Moves and pushes allowed in the level being played, if any. Editing isn't limited.
+    fn challenge(&self) -> Option<MoveLimit> {
+        self.move_limit.filter(|_| self.editor.is_none())
+    }
+
+    /// If the level being played is the one the speedrun is on, so its timer runs while it is
+    /// played.
+    fn on_speedrun_level(&self) -> bool {
+        self.speedrun.as_ref().is_some_and(|(run, _)| {
+            run.current() == self.level_index && self.endless.is_none() && self.editor.is_none()
+        })
+    }
+
+    /// Splits the speedrun if it was on the level just won, and writes its splits.
+    fn split_speedrun(&mut self) {
+        if !self.on_speedrun_level() {
+            return;
+        }
+        let Some((run, path)) = &mut self.speedrun else {
+            return;
+        };
+        run.split();
+        if let Err(err) = run.save(path) {
+            self.notify(format!("Couldn't write the splits : {err}"));
+        }
+    }
+
+    /// Why the level was lost, if a limit was exceeded before it was won.
+    fn defeat(&self) -> Option<Defeat> {
+        if let Some(limit) = self.challenge().filter(|l| l.is_exceeded(&self.board)) {
+            return Some(Defeat::OutOfMoves(limit));
+        }
+        if self.board.has_won() {
+            return None;
+        }
+        self.countdown()
+            .filter(|limit| limit.is_over(self.elapsed()))
+            .map(Defeat::TimeOut)
+    }
+
+    /// Moves the player, or waits for the current move to be drawn first.
+    fn queue_move(&mut self, dir: Direction) {
+        let animating =
+            self.last_move_instant.elapsed() < Duration::from_millis(ANIMATION_DURATION_MILIS);
+        if animating || !self.queued_moves.is_empty() {
+            if self.queued_moves.len() < MAX_BUFFERED_MOVES {
+                self.queued_moves.push_back(dir);
+            }
+        } else {
+            self.do_move_player(dir);
+        }
+    }
+
+    /// Stops walking to the clicked cell, if it was.
+    fn stop_walk(&mut self) {
+        if self.walking_to_click {
+            self.queued_moves.clear();
+            self.walking_to_click = false;
+        }
+    }
+
     fn do_move_player(&mut self, dir: Direction) {
-        if let Some(moved) = self.board.do_move_player(dir) {
+        let res = self.board.do_move_player(dir);
+        if let Some(moved) = res {
             self.last_move_instant = Instant::now();
             self.moved_crate = moved;
         }
         self.direction = dir;
+        self.move_direction = dir;
+        if self.board.has_won() && self.won_time.is_none() {
+            self.won_time = Some(self.start_instant.elapsed());
+            self.split_speedrun();
+        }
+
+        let effect = SoundEffect::from_move(&self.board, res);
+        if let (SoundEffect::CrateOnTarget, Some(Some(pos))) = (effect, res) {
+            // Once the crate arrived.
+            let start = Instant::now() + Duration::from_millis(ANIMATION_DURATION_MILIS);
+            self.bursts.push((pos, start));
+        }
+        self.audio.play_effect(effect);
+        if self.editor.is_none() {
+            self.update_tutorial();
+        }
+    }
+
+    /// Shows the hints of the tutorial triggered by the board for the first time, until the
+    /// next ones.
+    fn update_tutorial(&mut self) {
+        let messages = self.tutorial.update(&self.board);
+        if !messages.is_empty() {
+            self.tip = Some(messages.join(" "));
+        }
+    }
+
+    /// Draws a flash and particles spreading from the cells where a crate was just placed.
+    fn draw_bursts(&self, canvas: &mut graphics::Canvas, cell: Vec2, origin: Vec2) {
+        let now = Instant::now();
+        for &((i, j), start) in self.bursts.iter().filter(|(_, start)| *start <= now) {
+            let progress = (now - start).as_secs_f32() / BURST_DURATION.as_secs_f32();
+            if progress >= 1. {
+                continue;
+            }
+            let fading = 1. - progress;
+            let corner = origin + Vec2::new(i as f32, j as f32) * cell;
+
+            canvas.draw(
+                &graphics::Quad,
+                DrawParam::default()
+                    .dest(corner)
+                    .scale(cell)
+                    .color(Color::new(1., 1., 1., 0.6 * fading))
+                    .z(15),
+            );
+
+            let center = corner + cell / 2.;
+            let size = cell * 0.15 * fading;
+            for k in 0..BURST_PARTICLES {
+                let angle = k as f32 * std::f32::consts::TAU / BURST_PARTICLES as f32;
+                let pos = center + Vec2::from_angle(angle) * cell * BURST_RADIUS * progress;
+                canvas.draw(
+                    &graphics::Quad,
+                    DrawParam::default()
+                        .dest(pos - size / 2.)
+                        .scale(size)
+                        .color(Color::new(1., 0.85, 0.2, fading))
+                        .z(15),
+                );
+            }
+        }
+    }
+
+    /// Cancels the last move, animating it backward.
+    fn undo(&mut self) {
+        self.queued_moves.clear();
+        self.walking_to_click = false;
+        if let Some(step) = self.board.undo() {
+            // The clock goes on if the winning move is cancelled.
+            self.won_time = None;
+            let (i, j) = self.board.player();
+            self.last_move_instant = Instant::now();
+            self.moved_crate = step.push.then(|| step.dir.to_coords(i, j));
+            // Still facing the same way, but walking backward.
+            self.direction = step.dir;
+            self.move_direction = step.dir.opposite();
+            self.audio.play_effect(if step.push {
+                SoundEffect::Push
+            } else {
+                SoundEffect::Step
+            });
+        }
+    }
+
+    fn change_music_volume(&mut self, change: f32) {
+        let volume = self.audio.music_volume() + change;
+        self.audio.set_music_volume(volume);
+        self.notify(format!(
+            "Music : {}%",
+            (self.audio.music_volume() * 100.).round()
+        ));
+    }
+
+    fn toggle_crt_filter(&mut self) {
+        self.visuals.crt_filter = !self.visuals.crt_filter;
+        let state = if self.visuals.crt_filter { "on" } else { "off" };
+        self.notify(format!("CRT filter : {state}"));
+    }
+
+    fn toggle_smooth(&mut self) {
+        self.visuals.smooth = !self.visuals.smooth;
+        let state = if self.visuals.smooth {
+            "smooth"
+        } else {
+            "sharp"
+        };
+        self.notify(format!("Pixels : {state}"));
+    }
+
+    fn next_easing(&mut self) {
+        self.easing = self.easing.next();
+        self.notify(format!("Animation : {}", self.easing.name()));
+    }
+
+    /// Saves the last drawn frame in a PNG file named after the current time, in the
+    /// [`SCREENSHOTS_DIR`] of the user data directory.
+    fn take_screenshot(&mut self, ctx: &Context) -> GameResult {
+        let frame = ctx.gfx.frame();
+        let mut pixels = frame.to_pixels(ctx)?;
+        // The window surface is often in BGRA, which can't be encoded.
+        if matches!(
+            frame.format(),
+            ImageFormat::Bgra8Unorm | ImageFormat::Bgra8UnormSrgb
+        ) {
+            pixels.chunks_exact_mut(4).for_each(|p| p.swap(0, 2));
+        }
+        let image = graphics::Image::from_pixels(
+            ctx,
+            &pixels,
+            ImageFormat::Rgba8UnormSrgb,
+            frame.width(),
+            frame.height(),
+        );
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let path = format!("{SCREENSHOTS_DIR}/sokoban-{timestamp}.png");
+        ctx.fs.create_dir(SCREENSHOTS_DIR)?;
+        image.encode(ctx, ImageEncodingFormat::Png, &path)?;
+
+        let full_path = ctx.fs.user_data_dir().join(path.trim_start_matches('/'));
+        self.notify(format!("Screenshot saved to {}", full_path.display()));
+        Ok(())
+    }
+
+    /// Shows the `message` under the HUD for a while.
+    fn notify(&mut self, message: String) {
+        self.notice = Some((message, Instant::now()));
     }
 
     /// Calculates scale based on new window size.
     ///
     /// `win_resize` can contain the new size of the window, otherwise we get it from ctx.
-    fn get_screen_scale(&self, ctx: &mut Context, win_resize: Option<(f32, f32)>) -> ScaleInfos {
-        let dimensions = self
-            .images
-            .mur
-            .dimensions(ctx)
-            .expect("Can't get dimensions of wall picture !");
+    fn get_screen_scale(&self, ctx: &Context, win_resize: Option<(f32, f32)>) -> ScaleInfos {
+        let dimensions = self.tiles.dimensions;
 
         let (board_w, board_h) = (self.board.width() as f32, self.board.height() as f32);
         let (win_w, win_h) = win_resize.unwrap_or_else(|| ctx.gfx.size());
@@ -144,172 +963,754 @@ impl State {
     }
 }
 
-impl ggez::event::EventHandler<GameError> for State {
-    fn update(&mut self, _ctx: &mut Context) -> GameResult {
+impl State {
+    fn open_menu(&mut self, menu: Menu) {
+        if self.menu.is_none() {
+            self.menu_opened_instant = Instant::now();
+        }
+        self.queued_moves.clear();
+        self.walking_to_click = false;
+        self.menu = Some(menu);
+    }
+
+    /// Goes back to the game, the time spent in the menu not being counted.
+    fn close_menu(&mut self) {
+        if self.menu.take().is_some() {
+            self.start_instant += self.menu_opened_instant.elapsed();
+        }
+    }
+
+    /// Saves the score of the won level in the library, or counts it in the endless streak, and
+    /// shows what to do next.
+    fn level_won(&mut self) -> GameResult {
+        let score = Score::new(&self.board, self.elapsed());
+        if let Some(endless) = &mut self.endless {
+            endless.won();
+            self.won_score = Some((score, None));
+            self.audio.play_effect(SoundEffect::Won);
+            self.open_menu(Menu::Won(0));
+            return Ok(());
+        }
+        let best = self.library.get(self.level_index).and_then(|l| l.best());
+        self.library
+            .set_solved(self.level_index, score, self.board.replay())
+            .map_err(|e| GameError::CustomError(format!("Couldn't save the score : {e}")))?;
+
+        self.won_score = Some((score, best));
+        self.audio.play_effect(SoundEffect::Won);
+        self.open_menu(Menu::Won(0));
         Ok(())
     }
 
-    fn draw(&mut self, ctx: &mut Context) -> GameResult {
-        let scale_infos = self.get_screen_scale(ctx, None);
+    fn menu_input(&mut self, ctx: &mut Context, input: MenuInput) {
+        let Some(menu) = self.menu else {
+            return;
+        };
+        let (selected, len) = match menu {
+            Menu::Pause(selected) => (selected, PAUSE_ENTRIES.len()),
+            Menu::Won(selected) => (selected, WON_ENTRIES.len()),
+            Menu::Lost(selected) => (selected, LOST_ENTRIES.len()),
+            // The endless mode is after the levels.
+            Menu::LevelSelect(selected) => (selected, self.library.len() + 1),
+        };
+        let moved = match input {
+            MenuInput::Previous => (selected + len - 1) % len,
+            MenuInput::Next => (selected + 1) % len,
+            MenuInput::Confirm | MenuInput::Back | MenuInput::Toggle => selected,
+        };
+
+        match (menu, input) {
+            (Menu::Pause(_), MenuInput::Previous | MenuInput::Next) => {
+                self.menu = Some(Menu::Pause(moved))
+            }
+            (Menu::Won(_), MenuInput::Previous | MenuInput::Next) => {
+                self.menu = Some(Menu::Won(moved))
+            }
+            (Menu::Lost(_), MenuInput::Previous | MenuInput::Next) => {
+                self.menu = Some(Menu::Lost(moved))
+            }
+            (Menu::LevelSelect(_), MenuInput::Previous | MenuInput::Next) => {
+                self.menu = Some(Menu::LevelSelect(moved))
+            }
+            (Menu::Pause(_), MenuInput::Back) => self.close_menu(),
+            (Menu::Pause(_), MenuInput::Confirm) => match PAUSE_ENTRIES[selected] {
+                PauseEntry::Resume => self.close_menu(),
+                PauseEntry::Restart => {
+                    self.close_menu();
+                    self.reset();
+                }
+                PauseEntry::LevelSelect => {
+                    self.menu = Some(Menu::LevelSelect(self.level_index));
+                }
+                PauseEntry::Edit => self.open_editor(ctx),
+                PauseEntry::Quit => ctx.request_quit(),
+            },
+            // A choice must be made once the level is won.
+            (Menu::Won(_), MenuInput::Back) => (),
+            (Menu::Won(_), MenuInput::Confirm) => match WON_ENTRIES[selected] {
+                WonEntry::Next if self.endless.is_some() => {
+                    self.close_menu();
+                    if let Some(endless) = self.endless.take() {
+                        self.next_endless_level(ctx, endless);
+                    }
+                }
+                WonEntry::Next if self.level_index + 1 < self.library.len() => {
+                    self.close_menu();
+                    self.load_level(ctx, self.level_index + 1);
+                }
+                // Some levels may have been skipped before the last one.
+                WonEntry::Next => {
+                    let next = self.library.first_unsolved().unwrap_or(0);
+                    self.menu = Some(Menu::LevelSelect(next));
+                }
+                WonEntry::Replay => {
+                    self.close_menu();
+                    self.reset();
+                }
+                WonEntry::Quit => ctx.request_quit(),
+            },
+            (Menu::Lost(_), MenuInput::Back) => (),
+            (Menu::Lost(_), MenuInput::Confirm) => match LOST_ENTRIES[selected] {
+                LostEntry::Retry => {
+                    // Trying again ends the streak.
+                    if let Some(endless) = &mut self.endless {
+                        endless.lost();
+                    }
+                    self.close_menu();
+                    self.reset();
+                }
+                LostEntry::LevelSelect => {
+                    self.menu = Some(Menu::LevelSelect(self.level_index));
+                }
+                LostEntry::Quit => ctx.request_quit(),
+            },
+            (Menu::LevelSelect(_), MenuInput::Back) if self.defeat().is_some() => {
+                let entry = LOST_ENTRIES
+                    .iter()
+                    .position(|e| *e == LostEntry::LevelSelect)
+                    .unwrap_or(0);
+                self.menu = Some(Menu::Lost(entry));
+            }
+            (Menu::LevelSelect(_), MenuInput::Back) if self.board.has_won() => {
+                self.menu = Some(Menu::Won(0));
+            }
+            (Menu::LevelSelect(_), MenuInput::Toggle) => self.library.toggle_challenge(selected),
+            (_, MenuInput::Toggle) => (),
+            (Menu::LevelSelect(_), MenuInput::Back) => {
+                let entry = PAUSE_ENTRIES
+                    .iter()
+                    .position(|e| *e == PauseEntry::LevelSelect)
+                    .unwrap_or(0);
+                self.menu = Some(Menu::Pause(entry));
+            }
+            (Menu::LevelSelect(_), MenuInput::Confirm) if selected == self.library.len() => {
+                self.close_menu();
+                self.start_endless(ctx);
+            }
+            (Menu::LevelSelect(_), MenuInput::Confirm) => {
+                self.close_menu();
+                self.load_level(ctx, selected);
+            }
+        }
+    }
 
-        let mut canvas = graphics::Canvas::from_frame(ctx, Color::BLACK);
+    /// Header lines and entries of the opened menu, with the index of the selected entry.
+    fn menu_entries(&self) -> Option<(Vec<String>, Vec<String>, usize)> {
+        match self.menu? {
+            Menu::Pause(selected) => Some((
+                vec!["Pause".to_string()],
+                PAUSE_ENTRIES
+                    .iter()
+                    .map(|e| e.label().to_string())
+                    .collect(),
+                selected,
+            )),
+            Menu::Won(selected) => {
+                let mut header = vec![format!("{} won!", self.title)];
+                if let Some((score, best)) = self.won_score {
+                    header.push(format!(
+                        "Moves : {}  Pushes : {}  Time : {}",
+                        score.moves,
+                        score.pushes,
+                        format_duration(score.time)
+                    ));
+                    header.push(match (best, &self.endless) {
+                        (_, Some(endless)) => format!("Streak : {}", endless.streak()),
+                        (Some(best), _) if score.is_better_than(&best) => {
+                            "New best score!".to_string()
+                        }
+                        (Some(best), _) => format!(
+                            "Best : {} moves, {} pushes in {}",
+                            best.moves,
+                            best.pushes,
+                            format_duration(best.time)
+                        ),
+                        (None, _) => "First time solved!".to_string(),
+                    });
+                }
+                if let Some((run, _)) = self
+                    .speedrun
+                    .as_ref()
+                    .filter(|(r, _)| r.is_finished() && self.level_index + 1 == r.len())
+                {
+                    header.push(format!(
+                        "Run finished in {}",
+                        format_duration_millis(run.elapsed())
+                    ));
+                }
+                Some((
+                    header,
+                    WON_ENTRIES.iter().map(|e| e.label().to_string()).collect(),
+                    selected,
+                ))
+            }
+            Menu::Lost(selected) => {
+                let header = match self.defeat() {
+                    Some(defeat) => vec![defeat.title().to_string(), defeat.to_string()],
+                    None => Vec::new(),
+                };
+                Some((
+                    header,
+                    LOST_ENTRIES.iter().map(|e| e.label().to_string()).collect(),
+                    selected,
+                ))
+            }
+            Menu::LevelSelect(selected) => Some((
+                vec!["Levels".to_string(), "M : move-limit challenge".to_string()],
+                self.library
+                    .levels()
+                    .iter()
+                    .map(|l| {
+                        let solved = if l.is_solved() { " (solved)" } else { "" };
+                        let challenge = if l.is_challenge() { " (par)" } else { "" };
+                        format!("{}{}{}", l.title(), solved, challenge)
+                    })
+                    .chain(["Endless mode".to_string()])
+                    .collect(),
+                selected,
+            )),
+        }
+    }
 
-        let rect = graphics::Mesh::new_rectangle(
+    /// Draws the opened menu over the board, if any.
+    fn draw_menu(&self, ctx: &Context, canvas: &mut graphics::Canvas) -> GameResult {
+        let Some((header, entries, selected)) = self.menu_entries() else {
+            return Ok(());
+        };
+        let (win_w, win_h) = ctx.gfx.size();
+
+        let background = graphics::Mesh::new_rectangle(
             ctx,
             DrawMode::fill(),
-            scale_infos.dimensions,
-            Color::WHITE,
+            Rect::new(0., 0., win_w, win_h),
+            Color::from_rgba(0, 0, 0, 180),
         )?;
+        canvas.draw(&background, DrawParam::default().z(20));
+
+        // Only the entries around the selected one if there are too many.
+        let first = selected
+            .saturating_sub(MENU_VISIBLE_ENTRIES / 2)
+            .min(entries.len().saturating_sub(MENU_VISIBLE_ENTRIES));
+        let shown = entries.len().min(MENU_VISIBLE_ENTRIES);
+        let top = (win_h - (header.len() + 1 + shown) as f32 * MENU_TEXT_SIZE) / 2.;
+
+        let mut lines: Vec<(String, Color)> =
+            header.into_iter().map(|l| (l, Color::WHITE)).collect();
+        lines.push((String::new(), Color::WHITE));
+        lines.extend(
+            entries
+                .into_iter()
+                .enumerate()
+                .skip(first)
+                .take(shown)
+                .map(|(i, entry)| {
+                    if i == selected {
+                        (format!("> {} <", entry), Color::YELLOW)
+                    } else {
+                        (entry, Color::WHITE)
+                    }
+                }),
+        );
+
+        for (row, (text, color)) in (0..).zip(lines) {
+            let mut text = Text::new(text);
+            text.set_scale(MENU_TEXT_SIZE).set_layout(TextLayout {
+                h_align: TextAlign::Middle,
+                v_align: TextAlign::Begin,
+            });
+            canvas.draw(
+                &text,
+                DrawParam::default()
+                    .dest(Vec2::new(win_w / 2., top + row as f32 * MENU_TEXT_SIZE))
+                    .color(color)
+                    .z(30),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Edits the starting position of the level, with some room around it to extend it.
+    fn open_editor(&mut self, ctx: &Context) {
+        self.close_menu();
+        self.board = self.board.with_margin(EDITOR_MARGIN);
+        self.board_changed(ctx);
+        self.editor = Some(Editor::new());
+    }
+
+    /// Goes back to playing the edited level, without the empty rows and columns left around
+    /// it.
+    fn close_editor(&mut self, ctx: &Context) {
+        self.editor = None;
+        if let Ok(board) = Board::from_xsb(&self.board.to_xsb()) {
+            self.board = board;
+        }
+        self.board_changed(ctx);
+    }
+
+    fn editor_key(&mut self, ctx: &Context, keycode: KeyCode) {
+        let Some(editor) = &mut self.editor else {
+            return;
+        };
+        let brush = match keycode {
+            KeyCode::Key1 | KeyCode::Numpad1 => Some(0),
+            KeyCode::Key2 | KeyCode::Numpad2 => Some(1),
+            KeyCode::Key3 | KeyCode::Numpad3 => Some(2),
+            KeyCode::Key4 | KeyCode::Numpad4 => Some(3),
+            KeyCode::Key5 | KeyCode::Numpad5 => Some(4),
+            KeyCode::Key6 | KeyCode::Numpad6 => Some(5),
+            _ => None,
+        };
+
+        if let Some(brush) = brush {
+            editor.brush = BRUSHES[brush];
+            return;
+        }
+
+        match keycode {
+            KeyCode::Escape if editor.unsaved => {
+                editor.unsaved = false;
+                self.notify("Unsaved changes : press Escape again to leave.".to_string());
+            }
+            KeyCode::Escape => self.close_editor(ctx),
+            KeyCode::S => {
+                // A failed save isn't worth stopping the game.
+                if let Err(err) = self.save_edited_level(ctx) {
+                    self.notify(format!("Couldn't save the level : {err}"));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Writes the edited level in an XSB file named after its title, in the
+    /// [`EDITED_LEVELS_DIR`] of the user data directory.
+    fn save_edited_level(&mut self, ctx: &Context) -> GameResult {
+        let name: String = self
+            .title
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '_' })
+            .collect();
+        let path = format!("{EDITED_LEVELS_DIR}/{name}.xsb");
+        ctx.fs.create_dir(EDITED_LEVELS_DIR)?;
+        ctx.fs
+            .create(&path)?
+            .write_all(self.board.to_xsb().as_bytes())?;
+
+        if let Some(editor) = &mut self.editor {
+            editor.unsaved = false;
+        }
+        let full_path = ctx.fs.user_data_dir().join(path.trim_start_matches('/'));
+        let issues = self.board.lint().len();
+        self.notify(if issues == 0 {
+            format!("Level saved to {}", full_path.display())
+        } else {
+            format!(
+                "Level saved to {}, with {} issue(s) left",
+                full_path.display(),
+                issues
+            )
+        });
+        Ok(())
+    }
+
+    /// Moves the player or the menu selection in the direction held with the stick.
+    fn stick_action(&mut self, ctx: &mut Context, dir: Direction) {
+        if self.menu.is_some() {
+            match dir {
+                Direction::Up => self.menu_input(ctx, MenuInput::Previous),
+                Direction::Down => self.menu_input(ctx, MenuInput::Next),
+                Direction::Left | Direction::Right => (),
+            }
+        } else if self.editor.is_none() && !self.board.has_won() {
+            self.stop_walk();
+            self.queue_move(dir);
+        }
+    }
+
+    fn toggle_fullscreen(&mut self, ctx: &mut Context) -> GameResult {
+        self.fullscreen = !self.fullscreen;
+        ctx.gfx.set_fullscreen(if self.fullscreen {
+            FullscreenType::Desktop
+        } else {
+            FullscreenType::Windowed
+        })
+    }
+
+    fn toggle_camera_mode(&mut self, ctx: &Context) {
+        self.camera_mode = match self.camera_mode {
+            CameraMode::Fit => CameraMode::Follow,
+            CameraMode::Follow => CameraMode::Fit,
+        };
+        // No need to travel from the last position.
+        self.camera = self.camera_target(ctx);
+    }
+
+    /// Where the camera should be to have the player in the middle of the window, without
+    /// showing too much outside of the board.
+    fn camera_target(&self, ctx: &Context) -> Vec2 {
+        let scale_infos = self.get_screen_scale(ctx, None);
+        let cell = Vec2::new(scale_infos.dimensions.w, scale_infos.dimensions.h) * FOLLOW_SCALE;
+        let board = Vec2::new(scale_infos.tot_w, scale_infos.tot_h) * FOLLOW_SCALE;
+        let window = Vec2::new(scale_infos.win_w, scale_infos.win_h);
+
+        let (i, j) = self.board.player();
+        let player_center = Vec2::new(i as f32 + 0.5, j as f32 + 0.5) * cell;
+        let target = player_center - window / 2.;
+
+        // Centered on the axis where the board is smaller than the window, clamped to the
+        // sides otherwise.
+        let clamp_axis = |target: f32, board: f32, window: f32| {
+            if board <= window {
+                (board - window) / 2.
+            } else {
+                target.clamp(0., board - window)
+            }
+        };
+        Vec2::new(
+            clamp_axis(target.x, board.x, window.x),
+            clamp_axis(target.y, board.y, window.y),
+        )
+    }
+
+    /// Scale and window position of the top-left corner of the board.
+    fn view(&self, scale_infos: &ScaleInfos) -> (f32, Vec2) {
+        match self.camera_mode {
+            CameraMode::Fit => {
+                // Centered if the window doesn't have the shape of the board, like in fullscreen.
+                let scale = scale_infos.scale();
+                let origin = Vec2::new(
+                    scale_infos.win_w - scale_infos.tot_w * scale,
+                    scale_infos.win_h - scale_infos.tot_h * scale,
+                ) / 2.;
+                (scale, origin)
+            }
+            CameraMode::Follow => (FOLLOW_SCALE, -self.camera),
+        }
+    }
+}
+
+impl ggez::event::EventHandler<GameError> for State {
+    fn update(&mut self, ctx: &mut Context) -> GameResult {
+        // The queued steps are done once the last one is drawn.
+        if self.last_move_instant.elapsed() >= Duration::from_millis(ANIMATION_DURATION_MILIS) {
+            if let Some(dir) = self.queued_moves.pop_front() {
+                self.do_move_player(dir);
+            }
+        }
+
+        let now = Instant::now();
+        self.bursts
+            .retain(|(_, start)| *start > now || now - *start < BURST_DURATION);
+
+        if let Some(dir) = self.stick_direction {
+            if Instant::now() >= self.stick_repeat_instant {
+                self.stick_repeat_instant = Instant::now() + STICK_REPEAT_INTERVAL;
+                self.stick_action(ctx, dir);
+            }
+        }
+
+        // The timer of the speedrun only runs while its level is played, not in the menus.
+        let running = self.on_speedrun_level() && self.menu.is_none() && !self.board.has_won();
+        if let Some((run, _)) = &mut self.speedrun {
+            if running {
+                run.start();
+            } else {
+                run.pause();
+            }
+        }
+
+        // Placing all the crates while editing doesn't count.
+        if self.editor.is_none()
+            && self.board.has_won()
+            && self.won_score.is_none()
+            && self.defeat().is_none()
+        {
+            self.level_won()?;
+        }
+        if self.menu.is_none() && self.defeat().is_some() {
+            self.open_menu(Menu::Lost(0));
+        }
+
+        if self.camera_mode == CameraMode::Follow {
+            let target = self.camera_target(ctx);
+            let catch_up = (ctx.time.delta().as_secs_f32() * CAMERA_FOLLOW_SPEED).min(1.);
+            self.camera += (target - self.camera) * catch_up;
+        }
+
+        // Updated only when it changes, not to bother the window manager at each frame.
+        let window_title = if self.editor.is_some() {
+            format!("{} — {} (editing)", GAME_NAME, self.title)
+        } else {
+            format!(
+                "{} — {} ({} moves)",
+                GAME_NAME,
+                self.title,
+                self.board.moves()
+            )
+        };
+        if window_title != self.window_title {
+            ctx.gfx.set_window_title(&window_title);
+            self.window_title = window_title;
+        }
+
+        self.audio.update(ctx)
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        let scale_infos = self.get_screen_scale(ctx, None);
+
+        // Drawn aside first to go through the filter.
+        let mut canvas = if self.visuals.crt_filter {
+            graphics::Canvas::from_screen_image(ctx, &mut self.scene, Color::BLACK)
+        } else {
+            graphics::Canvas::from_frame(ctx, Color::BLACK)
+        };
 
-        let scale = f32::min(scale_infos.scale_w, scale_infos.scale_h);
+        let (scale, origin) = self.view(&scale_infos);
         let scale_vec = Vec2::new(scale, scale);
 
+        canvas.set_sampler(self.visuals.sampler());
+        self.static_layer.draw(
+            &mut canvas,
+            DrawParam::default().dest(origin).scale(scale_vec),
+        );
+
         let (mario, offset) = {
             let millis_since_last_move = Instant::now()
                 .duration_since(self.last_move_instant)
                 .as_millis() as f32;
             let ratio_move = 1.
-                - f32::min(
-                    1.,
-                    millis_since_last_move / (ANIMATION_DURATION_MILIS as f32),
-                );
+                - self
+                    .easing
+                    .apply(millis_since_last_move / (ANIMATION_DURATION_MILIS as f32));
 
-            match self.direction {
-                Direction::Up => (&self.images.mario_haut, Vec2::new(0., -ratio_move)),
-                Direction::Down => (&self.images.mario_bas, Vec2::new(0., ratio_move)),
-                Direction::Left => (&self.images.mario_gauche, Vec2::new(-ratio_move, 0.)),
-                Direction::Right => (&self.images.mario_droite, Vec2::new(ratio_move, 0.)),
-            }
+            let mario = match self.direction {
+                Direction::Up => self.tiles.player_up,
+                Direction::Down => self.tiles.player_down,
+                Direction::Left => self.tiles.player_left,
+                Direction::Right => self.tiles.player_right,
+            };
+            let offset = match self.move_direction {
+                Direction::Up => Vec2::new(0., -ratio_move),
+                Direction::Down => Vec2::new(0., ratio_move),
+                Direction::Left => Vec2::new(-ratio_move, 0.),
+                Direction::Right => Vec2::new(ratio_move, 0.),
+            };
+            (mario, offset)
         };
 
+        // Only the movable items are drawn one by one.
         for j in 0..self.board.height() {
-            if j % 2 == 0 {
-                canvas.set_shader(&self.shader);
-            } else {
-                canvas.set_default_shader();
-            }
             for i in 0..self.board.width() {
-                use CellKind::*;
-
-                if i % 2 == 0 {
-                    // Best for pixel art as it doesn't make things blurry.
-                    canvas.set_sampler(graphics::Sampler::nearest_clamp());
-                }
+                let BoardElem(Some(movable), under) = self.board.get(i, j) else {
+                    continue;
+                };
 
                 let (x, y) = (
-                    i as f32 * scale_infos.dimensions.w * scale,
-                    j as f32 * scale_infos.dimensions.h * scale,
+                    origin.x + i as f32 * scale_infos.dimensions.w * scale,
+                    origin.y + j as f32 * scale_infos.dimensions.h * scale,
                 );
-                let params = DrawParam::default().dest(Vec2::new(x, y)).scale(scale_vec);
-
-                match self.board.get(i, j) {
-                    BoardElem(_, Void) => (),
-                    BoardElem(_, Wall) => canvas.draw(&self.images.mur, params),
-                    BoardElem(None, Floor) => canvas.draw(&rect, params),
-                    BoardElem(None, Target) => {
-                        canvas.draw(&rect, params);
-                        canvas.draw(&self.images.objectif, params);
-                    }
-                    BoardElem(Some(movable), under) => {
-                        match under {
-                            Floor => canvas.draw(&rect, params),
-                            Target => {
-                                canvas.draw(&rect, params);
-                                canvas.draw(&self.images.objectif, params);
-                            }
-                            Void | Wall => {
-                                unreachable!("Mario can neither go on a wall or on the void.")
-                            }
-                        }
-
-                        let image = match movable {
-                            MovableItem::Player => mario,
-                            MovableItem::Crate(_) if under == Target => &self.images.caisse_ok,
-                            MovableItem::Crate(_) => &self.images.caisse,
-                        };
-
-                        let offset = match movable {
-                            MovableItem::Player => offset,
-                            MovableItem::Crate(_) => self
-                                .moved_crate
-                                .filter(|(a, b)| (*a, *b) == (i, j))
-                                .map_or_else(|| Vec2::new(0., 0.), |_| offset),
-                        };
-
-                        canvas.draw(image, params.z(10).offset(offset));
+                let mut params = DrawParam::default().dest(Vec2::new(x, y)).z(10);
+                // The dragged item follows the mouse.
+                if let Some(((a, b), _)) = self.editor.as_ref().and_then(Editor::dragged) {
+                    if (a, b) == (i, j) {
+                        let cell = Vec2::new(scale_infos.dimensions.w, scale_infos.dimensions.h);
+                        params = params.dest(self.mouse_pos - cell * scale / 2.).z(12);
                     }
                 }
 
-                if i % 2 == 0 {
-                    canvas.set_default_sampler();
-                }
+                let tile = match movable {
+                    MovableItem::Player => mario,
+                    MovableItem::Crate(_) if under == CellKind::Target => self.tiles.placed_crate,
+                    MovableItem::Crate(_) => self.tiles.crate_,
+                };
+
+                let offset = match movable {
+                    MovableItem::Player => offset,
+                    MovableItem::Crate(_) => self
+                        .moved_crate
+                        .filter(|(a, b)| (*a, *b) == (i, j))
+                        .map_or_else(|| Vec2::new(0., 0.), |_| offset),
+                };
+
+                canvas.draw(
+                    &self.tiles.atlas,
+                    params
+                        .src(tile.src)
+                        .scale(scale_vec * tile.fit)
+                        .offset(offset),
+                );
             }
         }
-        canvas.set_default_shader();
+        canvas.set_default_sampler();
 
-        canvas.draw(
-            Text::new(format!("fps : {}", ctx.time.fps() as i32)).set_scale(15.),
-            DrawParam::default().dest(Vec2::ZERO),
+        self.draw_bursts(
+            &mut canvas,
+            Vec2::new(scale_infos.dimensions.w, scale_infos.dimensions.h) * scale,
+            origin,
         );
 
-        if self.board.has_won() {
-            let mut won_msg = Text::new("You won!\n(Press Escape key to quit...)");
-            won_msg.set_scale(21.);
-            won_msg.set_layout(TextLayout {
-                h_align: TextAlign::Middle,
-                v_align: TextAlign::Begin,
-            });
-
-            let dest = Vec2::from(ctx.gfx.size()) / 2.;
-
-            let params = DrawParam::default().dest(dest).z(10);
-
-            {
-                let dimensions = won_msg
-                    .dimensions(ctx)
-                    .expect("Text should have dimensions !");
-
-                let margin = dimensions.h * 0.1;
-                let rect = Rect::new(
-                    -(dimensions.w + margin) / 2.,
-                    -(dimensions.h + margin) / 2.,
-                    dimensions.w + margin,
-                    dimensions.h + margin,
-                );
-                let won_box = graphics::Mesh::new_rectangle(
-                    ctx,
-                    DrawMode::fill(),
-                    rect,
-                    Color::from_rgba(150, 150, 0, 200),
-                )?;
-                canvas.draw(&won_box, params);
-            }
+        let elapsed = self.elapsed();
+        let time = match self.countdown() {
+            Some(limit) => format!("Time left : {}", format_duration(limit.remaining(elapsed))),
+            None => format!("Time : {}", format_duration(elapsed)),
+        };
+        let time = match &self.speedrun {
+            Some((run, _)) => format!(
+                "{time}  Run : {} ({}/{})",
+                format_duration_millis(run.elapsed()),
+                run.current(),
+                run.len()
+            ),
+            None => time,
+        };
+        let hud: [String; HUD_LINES] = if let Some(editor) = &self.editor {
+            [
+                format!("Editor : {}", self.title),
+                format!("Brush : {}", editor.brush.name()),
+                "1-6: brush, right click: erase, S: save, Esc: back".to_string(),
+                lint_summary(&self.board),
+                format!("fps : {}", ctx.time.fps() as i32),
+            ]
+        } else {
+            // The counters are shown against the par in the move-limit challenge.
+            let counters = match self.challenge() {
+                Some(limit) => format!(
+                    "Moves : {}/{}  Pushes : {}/{}",
+                    self.board.moves(),
+                    limit.moves,
+                    self.board.pushes(),
+                    limit.pushes
+                ),
+                None => format!(
+                    "Moves : {}  Pushes : {}",
+                    self.board.moves(),
+                    self.board.pushes()
+                ),
+            };
+            let title = match &self.endless {
+                Some(endless) => format!("{}  Streak : {}", self.title, endless.streak()),
+                None => self.title.clone(),
+            };
+            [
+                title,
+                counters,
+                format!(
+                    "Crates : {}/{}",
+                    self.board.placed_crates(),
+                    self.board.crate_count()
+                ),
+                time,
+                format!("fps : {}", ctx.time.fps() as i32),
+            ]
+        };
+        for (line, text) in (0..).zip(hud) {
+            canvas.draw(
+                Text::new(text).set_scale(HUD_TEXT_SIZE),
+                DrawParam::default().dest(Vec2::new(0., line as f32 * HUD_TEXT_SIZE)),
+            );
+        }
 
+        if let Some((notice, _)) = self
+            .notice
+            .as_ref()
+            .filter(|(_, i)| i.elapsed() < NOTICE_DURATION)
+        {
             canvas.draw(
-                &won_msg,
-                params.offset(Vec2::new(0., 0.5)).color(Color::BLACK),
+                Text::new(notice.as_str()).set_scale(HUD_TEXT_SIZE),
+                DrawParam::default().dest(Vec2::new(0., HUD_LINES as f32 * HUD_TEXT_SIZE)),
             );
         }
+        if let Some(tip) = self.tip.as_deref().filter(|_| self.editor.is_none()) {
+            let (_, win_h) = ctx.gfx.size();
+            canvas.draw(
+                Text::new(tip).set_scale(HUD_TEXT_SIZE),
+                DrawParam::default().dest(Vec2::new(0., win_h - HUD_TEXT_SIZE)),
+            );
+        }
+
+        self.draw_menu(ctx, &mut canvas)?;
 
         canvas.finish(ctx)?;
 
+        if self.visuals.crt_filter {
+            let scene = self.scene.image(ctx);
+            let mut canvas = graphics::Canvas::from_frame(ctx, Color::BLACK);
+            canvas.set_shader(&self.crt_shader);
+            canvas.draw(&scene, DrawParam::default());
+            canvas.finish(ctx)?;
+        }
+
         Ok(())
     }
 
     fn key_down_event(&mut self, ctx: &mut Context, input: KeyInput, _repeat: bool) -> GameResult {
+        // Any key stops the walk to a clicked cell.
+        self.stop_walk();
+
+        if input.keycode == Some(KeyCode::F12) {
+            // A failed screenshot isn't worth stopping the game.
+            if let Err(err) = self.take_screenshot(ctx) {
+                self.notify(format!("Couldn't save the screenshot : {err}"));
+            }
+            return Ok(());
+        }
+
         if let Some(keycode) = input.keycode {
-            if self.board.has_won() {
-                if keycode == KeyCode::Escape {
-                    ctx.request_quit();
-                }
-            } else {
+            if self.editor.is_some() {
+                self.editor_key(ctx, keycode);
+            } else if self.menu.is_some() {
+                let input = match keycode {
+                    KeyCode::Up => MenuInput::Previous,
+                    KeyCode::Down => MenuInput::Next,
+                    KeyCode::Return | KeyCode::NumpadEnter | KeyCode::Space => MenuInput::Confirm,
+                    KeyCode::Escape | KeyCode::Back => MenuInput::Back,
+                    KeyCode::M => MenuInput::Toggle,
+                    _ => return Ok(()),
+                };
+                self.menu_input(ctx, input);
+            } else if !self.board.has_won() {
                 match keycode {
-                    KeyCode::Escape | KeyCode::Q => ctx.request_quit(),
+                    KeyCode::Escape => self.open_menu(Menu::Pause(0)),
+                    KeyCode::Q => ctx.request_quit(),
                     KeyCode::R => self.reset(),
-                    KeyCode::Left => self.do_move_player(Direction::Left),
-                    KeyCode::Right => self.do_move_player(Direction::Right),
-                    KeyCode::Up => self.do_move_player(Direction::Up),
-                    KeyCode::Down => self.do_move_player(Direction::Down),
+                    KeyCode::C => self.toggle_camera_mode(ctx),
+                    KeyCode::F => self.toggle_fullscreen(ctx)?,
+                    KeyCode::E => self.next_easing(),
+                    KeyCode::V => self.toggle_crt_filter(),
+                    KeyCode::N => self.toggle_smooth(),
+                    KeyCode::U | KeyCode::Back => self.undo(),
+                    KeyCode::Equals | KeyCode::Plus | KeyCode::NumpadAdd => {
+                        self.change_music_volume(VOLUME_STEP)
+                    }
+                    KeyCode::Minus | KeyCode::NumpadSubtract => {
+                        self.change_music_volume(-VOLUME_STEP)
+                    }
+                    KeyCode::Left => self.queue_move(Direction::Left),
+                    KeyCode::Right => self.queue_move(Direction::Right),
+                    KeyCode::Up => self.queue_move(Direction::Up),
+                    KeyCode::Down => self.queue_move(Direction::Down),
                     _ => (),
                 }
             }
@@ -317,36 +1718,171 @@ impl ggez::event::EventHandler<GameError> for State {
         Ok(())
     }
 
+    fn mouse_button_down_event(
+        &mut self,
+        ctx: &mut Context,
+        button: MouseButton,
+        x: f32,
+        y: f32,
+    ) -> GameResult {
+        // The right button only erases in the editor.
+        let erase = match button {
+            MouseButton::Left => false,
+            MouseButton::Right if self.editor.is_some() => true,
+            _ => return Ok(()),
+        };
+        if self.menu.is_some() {
+            return Ok(());
+        }
+
+        let scale_infos = self.get_screen_scale(ctx, None);
+        let cell = scale_infos.cell_at(&self.board, self.view(&scale_infos), x, y);
+        if let Some(editor) = &mut self.editor {
+            let changed = cell.is_some_and(|cell| {
+                if erase {
+                    editor.press_erase(&mut self.board, cell)
+                } else {
+                    editor.press(&mut self.board, cell)
+                }
+            });
+            if changed {
+                self.static_layer = StaticLayer::new(ctx, &self.board, &self.tiles);
+            }
+        } else if self.board.has_won() {
+            return Ok(());
+        } else if let Some(path) = cell.and_then(|(i, j)| self.board.path_to(i, j)) {
+            self.queued_moves = path.into();
+            self.walking_to_click = true;
+        }
+        Ok(())
+    }
+
+    fn mouse_button_up_event(
+        &mut self,
+        ctx: &mut Context,
+        button: MouseButton,
+        x: f32,
+        y: f32,
+    ) -> GameResult {
+        if button != MouseButton::Left && button != MouseButton::Right {
+            return Ok(());
+        }
+
+        let scale_infos = self.get_screen_scale(ctx, None);
+        let cell = scale_infos.cell_at(&self.board, self.view(&scale_infos), x, y);
+        if let Some(editor) = &mut self.editor {
+            if editor.release(&mut self.board, cell) {
+                self.static_layer = StaticLayer::new(ctx, &self.board, &self.tiles);
+            }
+        }
+        Ok(())
+    }
+
+    fn mouse_motion_event(
+        &mut self,
+        ctx: &mut Context,
+        x: f32,
+        y: f32,
+        _dx: f32,
+        _dy: f32,
+    ) -> GameResult {
+        self.mouse_pos = Vec2::new(x, y);
+
+        let scale_infos = self.get_screen_scale(ctx, None);
+        let cell = scale_infos.cell_at(&self.board, self.view(&scale_infos), x, y);
+        if let (Some(editor), Some(cell)) = (&mut self.editor, cell) {
+            if editor.hover(&mut self.board, cell) {
+                self.static_layer = StaticLayer::new(ctx, &self.board, &self.tiles);
+            }
+        }
+        Ok(())
+    }
+
     fn gamepad_button_down_event(
         &mut self,
         ctx: &mut Context,
         btn: Button,
         _id: GamepadId,
     ) -> GameResult {
-        if self.board.has_won() {
-            if btn == Button::Start {
-                ctx.request_quit();
-            }
-        } else {
+        self.stop_walk();
+
+        if self.menu.is_some() {
+            let input = match btn {
+                Button::DPadUp => MenuInput::Previous,
+                Button::DPadDown => MenuInput::Next,
+                Button::South => MenuInput::Confirm,
+                Button::East | Button::Start => MenuInput::Back,
+                Button::West => MenuInput::Toggle,
+                _ => return Ok(()),
+            };
+            self.menu_input(ctx, input);
+        } else if self.editor.is_none() && !self.board.has_won() {
             match btn {
-                Button::Start => ctx.request_quit(),
+                Button::Start => self.open_menu(Menu::Pause(0)),
                 Button::West => self.reset(),
-                Button::DPadLeft => self.do_move_player(Direction::Left),
-                Button::DPadRight => self.do_move_player(Direction::Right),
-                Button::DPadUp => self.do_move_player(Direction::Up),
-                Button::DPadDown => self.do_move_player(Direction::Down),
+                Button::East => self.undo(),
+                Button::DPadLeft => self.queue_move(Direction::Left),
+                Button::DPadRight => self.queue_move(Direction::Right),
+                Button::DPadUp => self.queue_move(Direction::Up),
+                Button::DPadDown => self.queue_move(Direction::Down),
                 _ => (),
             }
         }
         Ok(())
     }
 
+    fn gamepad_axis_event(
+        &mut self,
+        ctx: &mut Context,
+        axis: Axis,
+        value: f32,
+        _id: GamepadId,
+    ) -> GameResult {
+        match axis {
+            Axis::LeftStickX => self.stick.x = value,
+            Axis::LeftStickY => self.stick.y = value,
+            _ => return Ok(()),
+        }
+
+        // Only the main direction of the stick counts.
+        let direction = if self.stick.abs().max_element() < STICK_DEAD_ZONE {
+            None
+        } else if self.stick.x.abs() > self.stick.y.abs() {
+            Some(if self.stick.x > 0. {
+                Direction::Right
+            } else {
+                Direction::Left
+            })
+        } else {
+            Some(if self.stick.y > 0. {
+                Direction::Up
+            } else {
+                Direction::Down
+            })
+        };
+
+        if direction != self.stick_direction {
+            self.stick_direction = direction;
+            if let Some(dir) = direction {
+                self.stick_repeat_instant = Instant::now() + self.stick_repeat_delay;
+                self.stick_action(ctx, dir);
+            }
+        }
+        Ok(())
+    }
+
     fn resize_event(&mut self, ctx: &mut Context, win_w: f32, win_h: f32) -> GameResult {
+        // The window can have any shape when following the player, and can't be resized in
+        // fullscreen.
+        if self.camera_mode == CameraMode::Follow || self.fullscreen {
+            return Ok(());
+        }
+
         let scale_infos = self.get_screen_scale(ctx, Some((win_w, win_h)));
 
         // To avoid unstable resize, we accept a small difference between w and h scales.
         if (scale_infos.scale_w * 10.).floor() != (scale_infos.scale_h * 10.).floor() {
-            let scale = f32::min(scale_infos.scale_w, scale_infos.scale_h);
+            let scale = scale_infos.scale();
             let (new_width, new_height) = (scale_infos.tot_w * scale, scale_infos.tot_h * scale);
 
             if (new_width, new_height) != (scale_infos.win_w, scale_infos.win_h) {
@@ -1,40 +1,260 @@
 //! Macroquad engine version
 //!
 //! This version provides it's own event loop.
+//!
+//! It can also be built for `wasm32-unknown-unknown` to be played in a browser, see
+//! [`game_macroquad_web`].
 
-use std::{error::Error, str::FromStr, time::Instant};
+use std::{collections::VecDeque, env, error::Error, path::PathBuf, time::Duration};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{io::Cursor, sync::Arc};
 
-use macroquad::{prelude::*, Window};
+use macroquad::{
+    hash,
+    prelude::*,
+    ui::{root_ui, widgets},
+    Window,
+};
 
-use super::{Board, BoardElem, CellKind, Direction, MovableItem};
+use super::{
+    format_duration, format_duration_millis, lint_summary,
+    macroquad_editor::{EditorAction, LevelEditor},
+    macroquad_replay::{ReplayPlayer, Transport},
+    macroquad_select::{LevelSelect, SelectChoice},
+    AudioSink, Board, BoardElem, CellKind, Defeat, Direction, LevelLibrary, MovableItem, MoveLimit,
+    Replay, Score, SoundEffect, TimeLimit,
+};
+use crate::{
+    data::{Level, Tutorial},
+    Endless, GameOptions, Speedrun,
+};
 
 const ANIMATION_DURATION_MILIS: u64 = 200;
+/// Distance a touch must travel to be a swipe rather than a tap, in pixels.
+const SWIPE_MIN_DISTANCE: f32 = 30.;
+const DEFAULT_MUSIC_VOLUME: f32 = 0.5;
+const HUD_TEXT_SIZE: f32 = 21.;
+const MENU_TEXT_SIZE: f32 = 30.;
+const MENU_WIDTH: f32 = 260.;
+const PANEL_SIZE: Vec2 = vec2(320., 400.);
+/// Themes which can be chosen in the settings panel.
+const THEMES: [Theme; 3] = [
+    Theme {
+        name: "Black",
+        background: BLACK,
+        smooth: false,
+    },
+    Theme {
+        name: "Night",
+        background: Color::new(0.08, 0.09, 0.19, 1.),
+        smooth: false,
+    },
+    Theme {
+        name: "Slate",
+        background: Color::new(0.25, 0.27, 0.3, 1.),
+        smooth: true,
+    },
+];
+/// Duration of the bump of the player into a wall or a stuck crate, in seconds.
+const BUMP_DURATION: f64 = 0.15;
+/// How far the player goes toward what it bumps into, as a fraction of a cell.
+const BUMP_DISTANCE: f32 = 0.15;
+/// Environment variable to set to `1` to start in fullscreen.
+const FULLSCREEN_ENV_VAR: &str = "SOKOBAN_FULLSCREEN";
+/// Environment variable containing the delay before a held arrow key repeats its move, and
+/// the interval between the repeated moves, in milliseconds: `<delay>,<interval>`.
+const KEY_REPEAT_ENV_VAR: &str = "SOKOBAN_KEY_REPEAT_MS";
+const DEFAULT_KEY_REPEAT_DELAY_MILIS: u64 = 300;
+const DEFAULT_KEY_REPEAT_INTERVAL_MILIS: u64 = ANIMATION_DURATION_MILIS;
+/// Arrow keys and the direction they move the player to.
+const ARROW_KEYS: [(KeyCode, Direction); 4] = [
+    (KeyCode::Left, Direction::Left),
+    (KeyCode::Right, Direction::Right),
+    (KeyCode::Up, Direction::Up),
+    (KeyCode::Down, Direction::Down),
+];
+/// Level played by the web version, fetched relative to the page.
+const WEB_LEVEL_PATH: &str = "map.txt";
+/// Empty cells added around a level when editing it, so it can be extended.
+const EDITOR_MARGIN: u32 = 2;
+
+fn window_conf() -> Conf {
+    Conf {
+        window_title: "Sokoban".to_owned(),
+        fullscreen: start_fullscreen(),
+        ..Default::default()
+    }
+}
+
+/// Delay and interval of the held keys, in seconds, as set in [`KEY_REPEAT_ENV_VAR`].
+fn key_repeat() -> (f64, f64) {
+    let millis = env::var(KEY_REPEAT_ENV_VAR)
+        .ok()
+        .and_then(|v| {
+            let (delay, interval) = v.split_once(',')?;
+            Some((delay.trim().parse().ok()?, interval.trim().parse().ok()?))
+        })
+        .unwrap_or((
+            DEFAULT_KEY_REPEAT_DELAY_MILIS,
+            DEFAULT_KEY_REPEAT_INTERVAL_MILIS,
+        ));
+    (millis.0 as f64 / 1000., millis.1 as f64 / 1000.)
+}
+
+fn start_fullscreen() -> bool {
+    env::var(FULLSCREEN_ENV_VAR).is_ok_and(|v| v.trim() == "1")
+}
 
+/// Plays the levels of `library` in the modes of `options`.
 // Normally through a macro for main.
-pub fn game_macroquad(level: &str) {
+pub fn game_macroquad(library: LevelLibrary, options: GameOptions) {
+    Window::from_config(window_conf(), game_macroquad_async(library, options, None));
+}
+
+/// Shows the steps of `replay` played on the first level of the `library`, with buttons to
+/// pause, step and change the speed.
+pub fn watch_replay_macroquad(library: LevelLibrary, replay: Replay) {
     Window::from_config(
-        Conf {
-            window_title: "Sokoban".to_owned(),
-            ..Default::default()
-        },
-        game_macroquad_async(level.to_string()),
+        window_conf(),
+        game_macroquad_async(library, GameOptions::default(), Some(replay)),
     );
 }
 
-async fn game_macroquad_async(level: String) {
-    let mut state = State::new(level).await.unwrap();
+/// Entry point of the web version, as the browser gives access neither to the arguments nor
+/// to the files.
+///
+/// The level ([`WEB_LEVEL_PATH`]) and the `images` directory are fetched relative to the page
+/// loading the game, see `web/index.html`.
+pub fn game_macroquad_web() {
+    Window::from_config(window_conf(), async {
+        let level = load_string(WEB_LEVEL_PATH)
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|source| Level::new("map".to_string(), source).map_err(|e| e.to_string()));
+        match level {
+            Ok(level) => {
+                game_macroquad_async(
+                    LevelLibrary::from_level(level),
+                    GameOptions::default(),
+                    None,
+                )
+                .await
+            }
+            Err(err) => error!("Couldn't load the level `{}` : {}", WEB_LEVEL_PATH, err),
+        }
+    });
+}
 
-    state.draw().unwrap();
+/// Runs the game, or only shows the `replay` if any.
+async fn game_macroquad_async(library: LevelLibrary, options: GameOptions, replay: Option<Replay>) {
+    let mut state = State::new(library, &options).await.unwrap();
+    state.audio.play_music();
+    // The touches are handled on their own, a swipe mustn't be a click too.
+    simulate_mouse_with_touch(false);
+
+    // The level is chosen before playing.
+    let mut level_select = LevelSelect::new(&state.library, state.level_index);
+    let mut selecting = replay.is_none();
+    if let Some(replay) = replay {
+        state.load_level(0);
+        state.replay = Some(ReplayPlayer::new(replay));
+    }
     loop {
-        state.resize_window_if_needed();
-        if state.manage_input_and_should_quit() {
-            break;
+        if selecting {
+            match level_select.update() {
+                Some(SelectChoice::Play(index)) => {
+                    state.load_level(index);
+                    selecting = false;
+                }
+                Some(SelectChoice::ToggleChallenge(index)) => state.library.toggle_challenge(index),
+                Some(SelectChoice::Endless) => {
+                    // The clock gives a different run each time.
+                    state.start_endless((macroquad::miniquad::date::now() * 1000.) as u64);
+                    selecting = state.endless.is_none();
+                }
+                Some(SelectChoice::Quit) => break,
+                None => (),
+            }
+            state.update_speedrun(selecting);
+        } else {
+            state.resize_window_if_needed();
+            match state.manage_input() {
+                Some(Leave::LevelSelect) => {
+                    level_select.select(state.level_index);
+                    selecting = true;
+                }
+                Some(Leave::Quit) => break,
+                None => (),
+            }
+            state.update_speedrun(selecting);
+        }
+
+        if selecting {
+            level_select.draw(&state.library);
+        } else {
+            state.draw().unwrap();
+            state.settings_panel();
         }
-        state.draw().unwrap();
         next_frame().await
     }
 }
 
+/// Entries of the pause menu.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PauseEntry {
+    Resume,
+    Restart,
+    LevelSelect,
+    Edit,
+    Quit,
+}
+
+const PAUSE_ENTRIES: [PauseEntry; 5] = [
+    PauseEntry::Resume,
+    PauseEntry::Restart,
+    PauseEntry::LevelSelect,
+    PauseEntry::Edit,
+    PauseEntry::Quit,
+];
+
+impl PauseEntry {
+    fn label(self) -> &'static str {
+        match self {
+            PauseEntry::Resume => "Resume",
+            PauseEntry::Restart => "Restart",
+            PauseEntry::LevelSelect => "Level select",
+            PauseEntry::Edit => "Edit level",
+            PauseEntry::Quit => "Quit",
+        }
+    }
+}
+
+/// Where the pause menu entry at `index` is drawn, in a window of size `win`.
+fn pause_entry_area(index: usize, win: Vec2) -> Rect {
+    let height = MENU_TEXT_SIZE * 1.6;
+    let top = (win.y - height * PAUSE_ENTRIES.len() as f32) / 2.;
+    Rect::new(
+        (win.x - MENU_WIDTH) / 2.,
+        top + index as f32 * height,
+        MENU_WIDTH,
+        height,
+    )
+}
+
+/// Where to go when leaving the level being played.
+enum Leave {
+    LevelSelect,
+    Quit,
+}
+
+/// Look of the game around and over the images.
+struct Theme {
+    name: &'static str,
+    background: Color,
+    /// If the board is smoothed once scaled to the window, instead of keeping crisp pixels
+    smooth: bool,
+}
+
 struct Images {
     caisse: Texture2D,
     caisse_ok: Texture2D,
@@ -46,20 +266,324 @@ struct Images {
     objectif: Texture2D,
 }
 
+impl Images {
+    async fn load() -> Result<Self, macroquad::Error> {
+        let images = Images {
+            caisse: load_texture("images/caisse.jpg").await?,
+            caisse_ok: load_texture("images/caisse_ok.jpg").await?,
+            mario_bas: load_texture("images/mario_bas.gif").await?,
+            mario_droite: load_texture("images/mario_droite.gif").await?,
+            mario_gauche: load_texture("images/mario_gauche.gif").await?,
+            mario_haut: load_texture("images/mario_haut.gif").await?,
+            mur: load_texture("images/mur.jpg").await?,
+            objectif: load_texture("images/objectif.png").await?,
+        };
+        // The images are drawn at their size on the board target, which alone is scaled.
+        for texture in [
+            &images.caisse,
+            &images.caisse_ok,
+            &images.mario_bas,
+            &images.mario_droite,
+            &images.mario_gauche,
+            &images.mario_haut,
+            &images.mur,
+            &images.objectif,
+        ] {
+            texture.set_filter(FilterMode::Nearest);
+        }
+        Ok(images)
+    }
+
+    /// Sprite of the player facing `direction`.
+    fn player(&self, direction: Direction) -> &Texture2D {
+        match direction {
+            Direction::Up => &self.mario_haut,
+            Direction::Down => &self.mario_bas,
+            Direction::Left => &self.mario_gauche,
+            Direction::Right => &self.mario_droite,
+        }
+    }
+}
+
+/// Sounds of the game, played with rodio as the `audio` feature of macroquad can't be enabled
+/// (see `Cargo.toml`). The game is silent without an audio device, and on the web.
+struct MacroquadAudio {
+    #[cfg(not(target_arch = "wasm32"))]
+    output: Option<RodioOutput>,
+    music_volume: f32,
+    muted: bool,
+}
+
+impl MacroquadAudio {
+    async fn new() -> Self {
+        MacroquadAudio {
+            #[cfg(not(target_arch = "wasm32"))]
+            output: RodioOutput::new()
+                .await
+                .inspect_err(|err| warn!("The game will be silent: {}", err))
+                .ok(),
+            music_volume: DEFAULT_MUSIC_VOLUME,
+            muted: false,
+        }
+    }
+
+    /// Silences all the sounds, or brings them back.
+    fn toggle_mute(&mut self) {
+        self.muted = !self.muted;
+        self.set_music_volume(self.music_volume);
+    }
+}
+
+impl AudioSink for MacroquadAudio {
+    fn play_effect(&mut self, effect: SoundEffect) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(output) = self.output.as_ref().filter(|_| !self.muted) {
+            output.play_effect(effect);
+        }
+    }
+
+    fn play_music(&mut self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(output) = &self.output {
+            output.music.play();
+        }
+    }
+
+    fn music_volume(&self) -> f32 {
+        self.music_volume
+    }
+
+    fn set_music_volume(&mut self, volume: f32) {
+        self.music_volume = volume.clamp(0., 1.);
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(output) = &self.output {
+            output
+                .music
+                .set_volume(if self.muted { 0. } else { self.music_volume });
+        }
+    }
+}
+
+/// Audio device with the sounds of the game loaded.
+#[cfg(not(target_arch = "wasm32"))]
+struct RodioOutput {
+    /// The sounds stop when it is dropped.
+    _stream: rodio::OutputStream,
+    handle: rodio::OutputStreamHandle,
+    /// Paused until it is requested.
+    music: rodio::Sink,
+    step: Arc<[u8]>,
+    push: Arc<[u8]>,
+    blocked: Arc<[u8]>,
+    crate_on_target: Arc<[u8]>,
+    won: Arc<[u8]>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl RodioOutput {
+    async fn new() -> Result<Self, Box<dyn Error>> {
+        use rodio::Source;
+
+        let (stream, handle) = rodio::OutputStream::try_default()?;
+        let music = rodio::Sink::try_new(&handle)?;
+        music.pause();
+        music.set_volume(DEFAULT_MUSIC_VOLUME);
+        let track = Cursor::new(load_wav("music").await?);
+        music.append(rodio::Decoder::new(track)?.repeat_infinite());
+
+        Ok(RodioOutput {
+            _stream: stream,
+            handle,
+            music,
+            step: load_wav("step").await?,
+            push: load_wav("push").await?,
+            blocked: load_wav("blocked").await?,
+            crate_on_target: load_wav("target").await?,
+            won: load_wav("won").await?,
+        })
+    }
+
+    fn play_effect(&self, effect: SoundEffect) {
+        use rodio::Source;
+
+        let sound = match effect {
+            SoundEffect::Step => &self.step,
+            SoundEffect::Push => &self.push,
+            SoundEffect::Blocked => &self.blocked,
+            SoundEffect::CrateOnTarget => &self.crate_on_target,
+            SoundEffect::Won => &self.won,
+        };
+        // Played on their own, so quick steps can overlap. A sound which can't be played is
+        // skipped.
+        if let Ok(decoder) = rodio::Decoder::new(Cursor::new(sound.clone())) {
+            let _ = self.handle.play_raw(decoder.convert_samples());
+        }
+    }
+}
+
+/// Content of the sound file `name`.
+#[cfg(not(target_arch = "wasm32"))]
+async fn load_wav(name: &str) -> Result<Arc<[u8]>, macroquad::Error> {
+    Ok(load_file(&format!("images/sounds/{}.wav", name))
+        .await?
+        .into())
+}
+
 struct State {
+    /// Levels which can be played
+    library: LevelLibrary,
+    /// Index in the library of the current level
+    level_index: usize,
     /// Game state
     board: Board,
+    /// When the level was started, in seconds since the start of the game
+    start_time: f64,
+    /// How long it took to win the level, once it is won
+    won_time: Option<Duration>,
+    /// If each level must be won before its par time runs out
+    time_attack: bool,
+    /// Time given to win the level, in the time-attack mode
+    time_limit: Option<TimeLimit>,
+    /// Moves and pushes allowed to win the level, if it is played in the move-limit challenge
+    move_limit: Option<MoveLimit>,
+    /// Run of random levels and the one being played, in the endless mode
+    endless: Option<(Endless, Level)>,
+    /// Run through the levels and where its splits are written, in the speedrun mode
+    ///
+    /// It is never enabled in the browser, where its timer wouldn't work.
+    speedrun: Option<(Speedrun, PathBuf)>,
+    /// Hints of the level being played, if it is part of a tutorial
+    tutorial: Tutorial,
+    /// Last hints triggered, shown under the board
+    tip: Option<String>,
+    /// Entry selected in the pause menu and when it was opened (to stop the clock), while
+    /// the game is paused
+    paused: Option<(usize, f64)>,
     /// Loaded images
     images: Images,
+    /// Where the board is drawn before being scaled to the window, the size of the board in
+    /// pixels of the images
+    board_target: RenderTarget,
     /// Direction indicating where the caracting is facing
     direction: Direction,
-    /// When the player moved last (for animation)
-    last_move_instant: Instant,
+    /// Direction of the last move (for animation), opposite to `direction` when undoing
+    move_direction: Direction,
+    /// When the player moved last, in seconds since the start (for animation)
+    ///
+    /// `std::time::Instant` isn't available in the browser.
+    last_move_time: f64,
     /// New position of the moved crated if any (for animation)
     moved_crate: Option<(u32, u32)>,
+    /// When the player last bumped into something while trying to move, in seconds since the
+    /// start (for animation)
+    bump_time: f64,
+    /// Touch being followed, and where it started
+    touch_start: Option<(u64, Vec2)>,
+    /// If the window is in fullscreen, so it isn't resized to the board
+    fullscreen: bool,
+    /// Arrow key held, and when its move is repeated next
+    held_key: Option<(KeyCode, f64)>,
+    /// Delay before a held key repeats its move, and interval between the repeated moves
+    key_repeat: (f64, f64),
+    /// If the settings and debug panel is shown
+    panel_open: bool,
+    /// Speed of the animations, `1.` being the normal one
+    animation_speed: f32,
+    /// Index of the theme in [`THEMES`]
+    theme: usize,
+    /// If the board is smoothed when scaled, the setting of the theme unless changed since
+    smooth: bool,
+    /// Steps left to walk to the cell tapped on, done once the previous one is drawn
+    queued_moves: VecDeque<Direction>,
+    /// Replay being watched instead of playing, if any
+    replay: Option<ReplayPlayer>,
+    /// Level editor, while the level is edited instead of played
+    editor: Option<LevelEditor>,
+    /// Board being edited and its editor, while the level is playtested
+    playtest: Option<(Board, LevelEditor)>,
+    audio: MacroquadAudio,
     // shader: Material,
 }
 
+/// Draws a box in the middle of the window with the `title` and the `hint` under it.
+fn draw_banner(title: &str, hint: &str, background: Color, scale_infos: &ScaleInfos) {
+    let title_measure = measure_text(title, None, 21, 1.);
+    let hint_measure = measure_text(hint, None, 21, 1.);
+    let w = f32::max(title_measure.width, hint_measure.width);
+    let h = title_measure.height + hint_measure.height;
+
+    let margin = h * 0.2;
+
+    draw_rectangle(
+        (scale_infos.win_w - w) / 2. - margin * 2.,
+        (scale_infos.win_h - h) / 2. - margin * 4.,
+        w + margin * 4.,
+        h + margin * 8.,
+        background,
+    );
+
+    draw_text(
+        title,
+        (scale_infos.win_w - title_measure.width) / 2.,
+        scale_infos.win_h / 2. - margin - title_measure.height + title_measure.offset_y,
+        21.,
+        BLACK,
+    );
+    draw_text(
+        hint,
+        (scale_infos.win_w - hint_measure.width) / 2.,
+        scale_infos.win_h / 2. + margin + hint_measure.offset_y,
+        21.,
+        BLACK,
+    );
+}
+
+/// Draws the `tip` in a band at the bottom of the window.
+fn draw_tip(tip: &str, scale_infos: &ScaleInfos) {
+    let measure = measure_text(tip, None, HUD_TEXT_SIZE as u16, 1.);
+    let h = measure.height * 2.;
+    draw_rectangle(
+        0.,
+        scale_infos.win_h - h,
+        scale_infos.win_w,
+        h,
+        Color::new(0., 0., 0., 0.7),
+    );
+    draw_text(
+        tip,
+        (scale_infos.win_w - measure.width) / 2.,
+        scale_infos.win_h - (h + measure.height) / 2. + measure.offset_y,
+        HUD_TEXT_SIZE,
+        WHITE,
+    );
+}
+
+/// Copy of the starting position of `board` without the empty rows and columns around it, if
+/// it is a valid level.
+fn cropped(board: &Board) -> Board {
+    Board::from_xsb(&board.to_xsb()).unwrap_or_else(|_| board.clone())
+}
+
+/// Render target fitting the `board` drawn with the `images` at their size, smoothed once
+/// scaled if `smooth`.
+fn board_target(board: &Board, images: &Images, smooth: bool) -> RenderTarget {
+    let target = render_target(
+        board.width() * images.mur.width() as u32,
+        board.height() * images.mur.height() as u32,
+    );
+    set_smooth(&target, smooth);
+    target
+}
+
+fn set_smooth(target: &RenderTarget, smooth: bool) {
+    target.texture.set_filter(if smooth {
+        FilterMode::Linear
+    } else {
+        // Keeps the pixel art crisp.
+        FilterMode::Nearest
+    });
+}
+
 struct ScaleInfos {
     img_w: f32,
     img_h: f32,
@@ -71,23 +595,89 @@ struct ScaleInfos {
     win_h: f32,
 }
 
+impl ScaleInfos {
+    /// Scale of the images so the whole board fits in the window.
+    fn scale(&self) -> f32 {
+        f32::min(self.scale_w, self.scale_h)
+    }
+
+    /// Position of the top-left corner of the board, centered in the window (which matters
+    /// in fullscreen, as the window doesn't fit the board).
+    fn origin(&self) -> Vec2 {
+        (vec2(self.win_w, self.win_h) - vec2(self.tot_w, self.tot_h) * self.scale()) / 2.
+    }
+
+    /// Coordinates of the board cell under the window position `pos`, if any.
+    fn cell_at(&self, board: &Board, pos: Vec2) -> Option<(u32, u32)> {
+        let scale = self.scale();
+        let pos = pos - self.origin();
+        let (i, j) = (
+            (pos.x / (self.img_w * scale)).floor(),
+            (pos.y / (self.img_h * scale)).floor(),
+        );
+        if i < 0. || j < 0. || i >= board.width() as f32 || j >= board.height() as f32 {
+            return None;
+        }
+        Some((i as u32, j as u32))
+    }
+}
+
 impl State {
-    async fn new(level: String) -> Result<Self, Box<dyn Error>> {
-        let state = State {
-            board: Board::from_str(&level[..])?,
-            images: Images {
-                caisse: load_texture("images/caisse.jpg").await?,
-                caisse_ok: load_texture("images/caisse_ok.jpg").await?,
-                mario_bas: load_texture("images/mario_bas.gif").await?,
-                mario_droite: load_texture("images/mario_droite.gif").await?,
-                mario_gauche: load_texture("images/mario_gauche.gif").await?,
-                mario_haut: load_texture("images/mario_haut.gif").await?,
-                mur: load_texture("images/mur.jpg").await?,
-                objectif: load_texture("images/objectif.png").await?,
-            },
+    async fn new(library: LevelLibrary, options: &GameOptions) -> Result<Self, Box<dyn Error>> {
+        let speedrun = options
+            .speedrun
+            .clone()
+            .map(|path| (Speedrun::new(&library), path));
+        // A speedrun starts with the first level, and is only against its timer.
+        let (level_index, limits) = match speedrun {
+            Some(_) => (0, false),
+            None => (library.first_unsolved().unwrap_or(0), true),
+        };
+        let level = library
+            .get(level_index)
+            .expect("A library always contains at least one level.");
+        let board = level.board().clone();
+        let time_limit = (limits && options.time_attack).then(|| TimeLimit::par(level));
+        let move_limit = (limits && level.is_challenge())
+            .then(|| MoveLimit::par(level))
+            .flatten();
+        let tutorial = Tutorial::new(level);
+        let images = Images::load().await?;
+        let smooth = THEMES[0].smooth;
+        let mut state = State {
+            library,
+            level_index,
+            board_target: board_target(&board, &images, smooth),
+            board,
+            start_time: get_time(),
+            won_time: None,
+            time_attack: options.time_attack,
+            time_limit,
+            move_limit,
+            endless: None,
+            speedrun,
+            tutorial,
+            tip: None,
+            paused: None,
+            images,
             direction: Direction::Down,
-            last_move_instant: Instant::now(),
+            move_direction: Direction::Down,
+            last_move_time: get_time(),
             moved_crate: None,
+            bump_time: f64::NEG_INFINITY,
+            touch_start: None,
+            fullscreen: start_fullscreen(),
+            held_key: None,
+            key_repeat: key_repeat(),
+            panel_open: false,
+            animation_speed: 1.,
+            theme: 0,
+            smooth,
+            queued_moves: VecDeque::new(),
+            replay: None,
+            editor: None,
+            playtest: None,
+            audio: MacroquadAudio::new().await,
             // shader: load_material(
             //     ShaderSource::Glsl {
             //         fragment: MY_FRAGMENT_SHADER,
@@ -96,22 +686,275 @@ impl State {
             //     Default::default(),
             // )?,
         };
+        state.update_tutorial();
 
         Ok(state)
     }
 
+    fn load_level(&mut self, index: usize) {
+        let Some(level) = self.library.get(index) else {
+            return;
+        };
+        self.level_index = index;
+        self.endless = None;
+        // A speedrun is only against its timer.
+        let limits = self.speedrun.is_none();
+        self.time_limit = (limits && self.time_attack).then(|| TimeLimit::par(level));
+        self.move_limit = (limits && level.is_challenge())
+            .then(|| MoveLimit::par(level))
+            .flatten();
+        self.tutorial = Tutorial::new(level);
+        self.set_board(level.board().clone());
+        self.update_tutorial();
+    }
+
+    /// Starts a run of random levels from the `seed`, see [`Endless`].
+    fn start_endless(&mut self, seed: u64) {
+        self.next_endless_level(Endless::new(seed));
+    }
+
+    /// Plays the next level of the `endless` run, or ends it if no level could be generated.
+    fn next_endless_level(&mut self, mut endless: Endless) {
+        match endless.next_level() {
+            Ok(level) => {
+                self.time_limit = self.time_attack.then(|| TimeLimit::par(&level));
+                self.move_limit = None;
+                self.tutorial = Tutorial::default();
+                self.set_board(level.board().clone());
+                self.endless = Some((endless, level));
+            }
+            Err(err) => {
+                error!("Couldn't generate a level : {}", err);
+                self.endless = None;
+            }
+        }
+    }
+
+    /// Title of the level being played.
+    fn title(&self) -> &str {
+        match &self.endless {
+            Some((_, level)) => level.title(),
+            None => self.library.get(self.level_index).map_or("", |l| l.title()),
+        }
+    }
+
+    /// Plays from the start of `board`, which may not have the size of the previous one.
+    fn set_board(&mut self, board: Board) {
+        self.board = board;
+        self.tip = None;
+        self.board_target = board_target(&self.board, &self.images, self.smooth);
+        self.reset();
+    }
+
     fn reset(&mut self) {
         self.board.reset();
+        self.start_time = get_time();
+        self.won_time = None;
+        self.queued_moves.clear();
         self.direction = Direction::Down;
-        self.last_move_instant = Instant::now();
+        self.move_direction = Direction::Down;
+        self.last_move_time = get_time();
     }
 
-    fn do_move_player(&mut self, dir: Direction) {
-        if let Some(moved) = self.board.do_move_player(dir) {
-            self.last_move_instant = Instant::now();
+    /// Returns if the player moved.
+    fn do_move_player(&mut self, dir: Direction) -> bool {
+        let res = self.board.do_move_player(dir);
+        if let Some(moved) = res {
+            self.last_move_time = get_time();
             self.moved_crate = moved;
+        } else {
+            self.bump_time = get_time();
         }
         self.direction = dir;
+        self.move_direction = dir;
+
+        self.audio
+            .play_effect(SoundEffect::from_move(&self.board, res));
+        // A winning move exceeding the move limit doesn't count.
+        if self.board.has_won() && self.defeat().is_none() {
+            self.audio.play_effect(SoundEffect::Won);
+            // Watching a replay or playtesting isn't solving the level.
+            if self.replay.is_none() && self.playtest.is_none() {
+                self.level_won();
+            }
+        }
+        if self.replay.is_none() && self.playtest.is_none() {
+            self.update_tutorial();
+        }
+        res.is_some()
+    }
+
+    /// Shows the hints of the tutorial triggered by the board for the first time, until the
+    /// next ones.
+    fn update_tutorial(&mut self) {
+        let messages = self.tutorial.update(&self.board);
+        if !messages.is_empty() {
+            self.tip = Some(messages.join(" "));
+        }
+    }
+
+    /// If the level being played is the one the speedrun is on, so its timer runs while it is
+    /// played.
+    fn on_speedrun_level(&self) -> bool {
+        self.speedrun.as_ref().is_some_and(|(run, _)| {
+            run.current() == self.level_index
+                && self.endless.is_none()
+                && self.replay.is_none()
+                && self.editor.is_none()
+                && self.playtest.is_none()
+        })
+    }
+
+    /// If the level just won was split by the speedrun, which goes on with the next one.
+    fn speedrun_goes_on(&self) -> bool {
+        self.speedrun.as_ref().is_some_and(|(run, _)| {
+            !run.is_finished() && run.current() == self.level_index + 1 && self.endless.is_none()
+        })
+    }
+
+    /// Runs the timer of the speedrun only while its level is played, not in the menus nor
+    /// while `selecting` a level.
+    fn update_speedrun(&mut self, selecting: bool) {
+        let running = !selecting
+            && self.paused.is_none()
+            && self.on_speedrun_level()
+            && !self.board.has_won();
+        if let Some((run, _)) = &mut self.speedrun {
+            if running {
+                run.start();
+            } else {
+                run.pause();
+            }
+        }
+    }
+
+    /// Saves the score of the won level in the library, or counts it in the endless streak.
+    /// The speedrun is split if it was on this level.
+    fn level_won(&mut self) {
+        let time = Duration::from_secs_f64(get_time() - self.start_time);
+        self.won_time = Some(time);
+        if self.on_speedrun_level() {
+            if let Some((run, path)) = &mut self.speedrun {
+                run.split();
+                if let Err(err) = run.save(path) {
+                    error!("Couldn't write the splits : {}", err);
+                }
+            }
+        }
+        if let Some((endless, _)) = &mut self.endless {
+            endless.won();
+            return;
+        }
+        let score = Score::new(&self.board, time);
+        if let Err(err) = self
+            .library
+            .set_solved(self.level_index, score, self.board.replay())
+        {
+            error!("Couldn't save the score : {}", err);
+        }
+    }
+
+    /// Time spent on the level, without the pauses.
+    fn elapsed(&self) -> Duration {
+        let now = self.paused.map_or_else(get_time, |(_, since)| since);
+        self.won_time
+            .unwrap_or_else(|| Duration::from_secs_f64(now - self.start_time))
+    }
+
+    /// Time limit of the level being played, if any. Watching a replay, editing or
+    /// playtesting isn't timed.
+    fn countdown(&self) -> Option<TimeLimit> {
+        self.time_limit
+            .filter(|_| self.replay.is_none() && self.editor.is_none() && self.playtest.is_none())
+    }
+
+    /// Moves and pushes allowed in the level being played, if any. Watching a replay, editing or
+    /// playtesting isn't limited.
+    fn challenge(&self) -> Option<MoveLimit> {
+        self.move_limit
+            .filter(|_| self.replay.is_none() && self.editor.is_none() && self.playtest.is_none())
+    }
+
+    /// Why the level was lost, if a limit was exceeded before it was won.
+    fn defeat(&self) -> Option<Defeat> {
+        if let Some(limit) = self.challenge().filter(|l| l.is_exceeded(&self.board)) {
+            return Some(Defeat::OutOfMoves(limit));
+        }
+        if self.board.has_won() {
+            return None;
+        }
+        self.countdown()
+            .filter(|limit| limit.is_over(self.elapsed()))
+            .map(Defeat::TimeOut)
+    }
+
+    /// Cancels the last move, animating it backward.
+    fn undo(&mut self) {
+        self.queued_moves.clear();
+        if let Some(step) = self.board.undo() {
+            let (i, j) = self.board.player();
+            self.last_move_time = get_time();
+            self.moved_crate = step.push.then(|| step.dir.to_coords(i, j));
+            // Still facing the same way, but walking backward.
+            self.direction = step.dir;
+            self.move_direction = step.dir.opposite();
+            self.audio.play_effect(if step.push {
+                SoundEffect::Push
+            } else {
+                SoundEffect::Step
+            });
+        }
+    }
+
+    /// A swipe moves the player in its direction, and a tap walks to the cell tapped on.
+    fn manage_touches(&mut self) {
+        for touch in touches() {
+            match touch.phase {
+                TouchPhase::Started if self.touch_start.is_none() => {
+                    self.touch_start = Some((touch.id, touch.position));
+                }
+                TouchPhase::Ended => {
+                    let Some((_, start)) = self.touch_start.filter(|(id, _)| *id == touch.id)
+                    else {
+                        continue;
+                    };
+                    self.touch_start = None;
+                    self.queued_moves.clear();
+
+                    let swipe = touch.position - start;
+                    if swipe.length() < SWIPE_MIN_DISTANCE {
+                        self.walk_to(touch.position);
+                    } else if swipe.x.abs() > swipe.y.abs() {
+                        self.do_move_player(if swipe.x > 0. {
+                            Direction::Right
+                        } else {
+                            Direction::Left
+                        });
+                    } else {
+                        self.do_move_player(if swipe.y > 0. {
+                            Direction::Down
+                        } else {
+                            Direction::Up
+                        });
+                    }
+                }
+                TouchPhase::Cancelled if self.touch_start.is_some_and(|(id, _)| id == touch.id) => {
+                    self.touch_start = None;
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Walks to the board cell under the window position `pos`, if it can be reached.
+    fn walk_to(&mut self, pos: Vec2) {
+        if let Some(path) = self
+            .get_screen_scale()
+            .cell_at(&self.board, pos)
+            .and_then(|(i, j)| self.board.path_to(i, j))
+        {
+            self.queued_moves = path.into();
+        }
     }
 
     /// Calculates scale based on new window size.
@@ -139,58 +982,55 @@ impl State {
 
     pub fn draw(&self) -> Result<(), Box<dyn Error>> {
         let scale_infos = self.get_screen_scale();
+        let scale = scale_infos.scale();
 
-        clear_background(BLACK);
-
-        let scale = f32::min(scale_infos.scale_w, scale_infos.scale_h);
-
-        let (mario, offset) = {
-            let millis_since_last_move = Instant::now()
-                .duration_since(self.last_move_instant)
-                .as_millis() as f32;
-            let ratio_move = scale
-                * (1.
-                    - f32::min(
-                        1.,
-                        millis_since_last_move / (ANIMATION_DURATION_MILIS as f32),
-                    ));
-
-            match self.direction {
-                Direction::Up => (
-                    &self.images.mario_haut,
-                    (0., ratio_move * self.images.mur.height()),
-                ),
-                Direction::Down => (
-                    &self.images.mario_bas,
-                    (0., -ratio_move * self.images.mur.height()),
-                ),
-                Direction::Left => (
-                    &self.images.mario_gauche,
-                    (ratio_move * self.images.mur.width(), 0.),
-                ),
-                Direction::Right => (
-                    &self.images.mario_droite,
-                    (-ratio_move * self.images.mur.width(), 0.),
-                ),
-            }
-        };
+        // The board is drawn at the size of the images, then scaled up as a whole.
+        let mut board_camera =
+            Camera2D::from_display_rect(Rect::new(0., 0., scale_infos.tot_w, scale_infos.tot_h));
+        board_camera.render_target = Some(self.board_target.clone());
+        set_camera(&board_camera);
+        clear_background(THEMES[self.theme].background);
 
-        // Apparently can't set it per draw (whole image has same texture parameter).
-        {
-            let filter = match self.direction {
-                // Best for pixel art as it doesn't make things blurry.
-                Direction::Up | Direction::Down => FilterMode::Nearest,
-                Direction::Left | Direction::Right => FilterMode::Linear,
+        let (mario, offset, player_offset) = {
+            let millis_since_last_move = ((get_time() - self.last_move_time) * 1000.) as f32;
+            let ratio_move = 1.
+                - f32::min(
+                    1.,
+                    millis_since_last_move / self.animation_duration() as f32,
+                );
+
+            let mario = self.images.player(self.direction);
+            let offset = match self.move_direction {
+                Direction::Up => (0., ratio_move * self.images.mur.height()),
+                Direction::Down => (0., -ratio_move * self.images.mur.height()),
+                Direction::Left => (ratio_move * self.images.mur.width(), 0.),
+                Direction::Right => (-ratio_move * self.images.mur.width(), 0.),
             };
-            self.images.caisse.set_filter(filter);
-            self.images.caisse_ok.set_filter(filter);
-            self.images.mario_bas.set_filter(filter);
-            self.images.mario_droite.set_filter(filter);
-            self.images.mario_gauche.set_filter(filter);
-            self.images.mario_haut.set_filter(filter);
-            self.images.mur.set_filter(filter);
-            self.images.objectif.set_filter(filter);
-        }
+
+            // The player goes a bit toward what blocked it, and comes back.
+            let since_bump = ((get_time() - self.bump_time) / BUMP_DURATION) as f32;
+            let bump = if (0. ..1.).contains(&since_bump) {
+                (since_bump * std::f32::consts::PI).sin() * BUMP_DISTANCE
+            } else {
+                0.
+            };
+            let player_offset = match self.direction {
+                Direction::Up => (offset.0, offset.1 - bump * self.images.mur.height()),
+                Direction::Down => (offset.0, offset.1 + bump * self.images.mur.height()),
+                Direction::Left => (offset.0 - bump * self.images.mur.width(), offset.1),
+                Direction::Right => (offset.0 + bump * self.images.mur.width(), offset.1),
+            };
+            (mario, offset, player_offset)
+        };
+
+        // The item dragged in the editor follows the mouse, in pixels of the board.
+        let dragged = self
+            .editor
+            .as_ref()
+            .and_then(LevelEditor::dragged)
+            .map(|(cell, _)| cell);
+        let drag_pos = (Vec2::from(mouse_position()) - scale_infos.origin()) / scale
+            - self.images.mur.size() / 2.;
 
         let mut foreground = [None, None];
 
@@ -204,53 +1044,30 @@ impl State {
             for i in 0..self.board.width() {
                 use CellKind::*;
 
-                let (x, y) = (
-                    i as f32 * scale_infos.img_w * scale,
-                    j as f32 * scale_infos.img_h * scale,
-                );
+                let (x, y) = (i as f32 * scale_infos.img_w, j as f32 * scale_infos.img_h);
 
                 let params = DrawTextureParams {
-                    dest_size: Some(self.images.mur.size() * scale),
+                    dest_size: Some(self.images.mur.size()),
                     ..Default::default()
                 };
 
                 match self.board.get(i, j) {
                     BoardElem(_, Void) => (),
                     BoardElem(_, Wall) => draw_texture_ex(&self.images.mur, x, y, WHITE, params),
-                    BoardElem(None, Floor) => draw_rectangle(
-                        x,
-                        y,
-                        scale_infos.img_w * scale,
-                        scale_infos.img_h * scale,
-                        WHITE,
-                    ),
+                    BoardElem(None, Floor) => {
+                        draw_rectangle(x, y, scale_infos.img_w, scale_infos.img_h, WHITE)
+                    }
                     BoardElem(None, Target) => {
-                        draw_rectangle(
-                            x,
-                            y,
-                            scale_infos.img_w * scale,
-                            scale_infos.img_h * scale,
-                            WHITE,
-                        );
+                        draw_rectangle(x, y, scale_infos.img_w, scale_infos.img_h, WHITE);
                         draw_texture_ex(&self.images.objectif, x, y, WHITE, params);
                     }
                     BoardElem(Some(movable), under) => {
                         match under {
-                            Floor => draw_rectangle(
-                                x,
-                                y,
-                                scale_infos.img_w * scale,
-                                scale_infos.img_h * scale,
-                                WHITE,
-                            ),
+                            Floor => {
+                                draw_rectangle(x, y, scale_infos.img_w, scale_infos.img_h, WHITE)
+                            }
                             Target => {
-                                draw_rectangle(
-                                    x,
-                                    y,
-                                    scale_infos.img_w * scale,
-                                    scale_infos.img_h * scale,
-                                    WHITE,
-                                );
+                                draw_rectangle(x, y, scale_infos.img_w, scale_infos.img_h, WHITE);
                                 draw_texture_ex(&self.images.objectif, x, y, WHITE, params.clone());
                             }
                             Void | Wall => {
@@ -265,7 +1082,7 @@ impl State {
                         };
 
                         let (offset_x, offset_y) = match movable {
-                            MovableItem::Player => offset,
+                            MovableItem::Player => player_offset,
                             MovableItem::Crate(_) => self
                                 .moved_crate
                                 .filter(|(a, b)| (*a, *b) == (i, j))
@@ -277,7 +1094,9 @@ impl State {
                             MovableItem::Crate(_) => 1,
                         };
 
-                        if (offset_x, offset_y) != (0., 0.) {
+                        if dragged == Some((i, j)) {
+                            foreground[index] = Some((image, drag_pos.x, drag_pos.y, params));
+                        } else if (offset_x, offset_y) != (0., 0.) {
                             foreground[index] = Some((image, x + offset_x, y + offset_y, params));
                         } else {
                             draw_texture_ex(image, x + offset_x, y + offset_y, WHITE, params);
@@ -294,83 +1113,456 @@ impl State {
             draw_texture_ex(image, x, y, WHITE, params);
         }
 
+        set_default_camera();
+        clear_background(THEMES[self.theme].background);
+        let origin = scale_infos.origin();
+        draw_texture_ex(
+            &self.board_target.texture,
+            origin.x,
+            origin.y,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(vec2(scale_infos.tot_w, scale_infos.tot_h) * scale),
+                // Render targets are upside down.
+                flip_y: true,
+                ..Default::default()
+            },
+        );
+
         // gl_use_default_material();
 
         {
-            let fps_msg = format!("fps : {}", get_fps() as i32);
-            let fps_dim = measure_text(&fps_msg[..], None, 21, 1.);
-            draw_text(&fps_msg[..], 0., fps_dim.offset_y, 21., WHITE);
+            let elapsed = self.elapsed();
+            let time = match self.countdown() {
+                Some(limit) => format!("Time left : {}", format_duration(limit.remaining(elapsed))),
+                None => format!("Time : {}", format_duration(elapsed)),
+            };
+            let time = match &self.speedrun {
+                Some((run, _)) => format!(
+                    "{}  Run : {} ({}/{})",
+                    time,
+                    format_duration_millis(run.elapsed()),
+                    run.current(),
+                    run.len()
+                ),
+                None => time,
+            };
+            // The counters are shown against the par in the move-limit challenge.
+            let counters = match self.challenge() {
+                Some(limit) => format!(
+                    "Moves : {}/{}  Pushes : {}/{}",
+                    self.board.moves(),
+                    limit.moves,
+                    self.board.pushes(),
+                    limit.pushes
+                ),
+                None => format!(
+                    "Moves : {}  Pushes : {}",
+                    self.board.moves(),
+                    self.board.pushes()
+                ),
+            };
+            let hud = if let Some(editor) = &self.editor {
+                editor.hud(&self.board).to_vec()
+            } else {
+                let title = match &self.endless {
+                    Some((endless, _)) => {
+                        format!("{}  Streak : {}", self.title(), endless.streak())
+                    }
+                    None => self.title().to_string(),
+                };
+                vec![
+                    title,
+                    counters,
+                    format!(
+                        "Crates : {}/{}",
+                        self.board.placed_crates(),
+                        self.board.crate_count()
+                    ),
+                    time,
+                    format!("fps : {}", get_fps() as i32),
+                ]
+            };
+            for (line, text) in (0..).zip(hud) {
+                let dim = measure_text(&text, None, HUD_TEXT_SIZE as u16, 1.);
+                draw_text(
+                    &text,
+                    0.,
+                    line as f32 * HUD_TEXT_SIZE + dim.offset_y,
+                    HUD_TEXT_SIZE,
+                    WHITE,
+                );
+            }
         }
 
-        if self.board.has_won() {
-            let won_msg_1 = "You won!";
-            let won_msg_2 = "(Press Escape key to quit...)";
-
-            let won_msg_1_measure = measure_text(won_msg_1, None, 21, 1.);
-            let won_msg_2_measure = measure_text(won_msg_2, None, 21, 1.);
-            let won_msg_w = f32::max(won_msg_1_measure.width, won_msg_2_measure.width);
-            let won_msg_h = won_msg_1_measure.height + won_msg_2_measure.height;
-
-            let margin = won_msg_h * 0.2;
-
-            draw_rectangle(
-                (scale_infos.win_w - won_msg_w) / 2. - margin * 2.,
-                (scale_infos.win_h - won_msg_h) / 2. - margin * 4.,
-                won_msg_w + margin * 4.,
-                won_msg_h + margin * 8.,
-                Color::from_rgba(150, 150, 0, 200),
-            );
+        if let Some((selected, _)) = self.paused {
+            let win = vec2(scale_infos.win_w, scale_infos.win_h);
+            draw_rectangle(0., 0., win.x, win.y, Color::new(0., 0., 0., 0.6));
+            for (index, entry) in PAUSE_ENTRIES.iter().enumerate() {
+                let area = pause_entry_area(index, win);
+                let color = if index == selected {
+                    draw_rectangle(area.x, area.y, area.w, area.h, YELLOW);
+                    BLACK
+                } else {
+                    WHITE
+                };
+                let dim = measure_text(entry.label(), None, MENU_TEXT_SIZE as u16, 1.);
+                draw_text(
+                    entry.label(),
+                    area.x + (area.w - dim.width) / 2.,
+                    area.y + (area.h - dim.height) / 2. + dim.offset_y,
+                    MENU_TEXT_SIZE,
+                    color,
+                );
+            }
+        }
 
-            draw_text(
-                won_msg_1,
-                (scale_infos.win_w - won_msg_1_measure.width) / 2.,
-                scale_infos.win_h / 2. - margin - won_msg_1_measure.height
-                    + won_msg_1_measure.offset_y,
-                21.,
-                BLACK,
+        if let Some(player) = &self.replay {
+            player.draw();
+        } else if let Some(editor) = &self.editor {
+            editor.draw_palette();
+        } else if let Some(defeat) = self.defeat() {
+            draw_banner(
+                defeat.title(),
+                "(Press R to retry, or Escape to choose another level...)",
+                Color::from_rgba(170, 40, 40, 200),
+                &scale_infos,
             );
-            draw_text(
-                won_msg_2,
-                (scale_infos.win_w - won_msg_2_measure.width) / 2.,
-                scale_infos.win_h / 2. + margin + won_msg_2_measure.offset_y,
-                21.,
-                BLACK,
+        } else if self.board.has_won() {
+            let hint = if self.playtest.is_some() {
+                "(Press Escape to go back to the editor...)"
+            } else if self.endless.is_some() || self.speedrun_goes_on() {
+                "(Press Enter for the next level, or Escape to stop...)"
+            } else {
+                "(Press Escape to choose another level...)"
+            };
+            draw_banner(
+                "You won!",
+                hint,
+                Color::from_rgba(150, 150, 0, 200),
+                &scale_infos,
             );
+        } else if let Some(tip) = self.tip.as_deref().filter(|_| self.paused.is_none()) {
+            draw_tip(tip, &scale_infos);
         }
 
         Ok(())
     }
 
-    /// Returns `true` if it should quit.
-    pub fn manage_input_and_should_quit(&mut self) -> bool {
-        if self.board.has_won() {
-            is_key_pressed(KeyCode::Escape)
+    /// Returns where to go if the level is left.
+    fn manage_input(&mut self) -> Option<Leave> {
+        if self.replay.is_some() {
+            self.manage_replay()
+        } else if self.editor.is_some() {
+            self.manage_editor();
+            None
+        } else if is_key_pressed(KeyCode::Q) {
+            Some(Leave::Quit)
+        } else if self.playtest.is_some()
+            && (is_key_pressed(KeyCode::Escape) || is_key_pressed(KeyCode::P))
+        {
+            self.stop_playtest();
+            None
+        } else if self.paused.is_some() {
+            self.manage_pause_menu()
+        } else if self.defeat().is_some() {
+            let tapped = touches().iter().any(|t| t.phase == TouchPhase::Ended);
+            if is_key_pressed(KeyCode::R)
+                || is_key_pressed(KeyCode::Enter)
+                || is_mouse_button_pressed(MouseButton::Left)
+                || tapped
+            {
+                // Trying again ends the streak.
+                if let Some((endless, _)) = &mut self.endless {
+                    endless.lost();
+                }
+                self.reset();
+            }
+            is_key_pressed(KeyCode::Escape).then_some(Leave::LevelSelect)
+        } else if self.board.has_won() {
+            let tapped = touches().iter().any(|t| t.phase == TouchPhase::Ended);
+            let next = is_key_pressed(KeyCode::Enter)
+                || is_mouse_button_pressed(MouseButton::Left)
+                || tapped;
+            match self.endless.take() {
+                // The run goes on until the player leaves it.
+                Some((endless, _)) if next => {
+                    self.next_endless_level(endless);
+                    None
+                }
+                Some(endless) => {
+                    self.endless = Some(endless);
+                    is_key_pressed(KeyCode::Escape).then_some(Leave::LevelSelect)
+                }
+                // The speedrun goes on with its next level.
+                None if next && self.speedrun_goes_on() => {
+                    self.load_level(self.level_index + 1);
+                    None
+                }
+                None => (is_key_pressed(KeyCode::Escape) || next).then_some(Leave::LevelSelect),
+            }
         } else {
+            // Any key stops the walk to a tapped cell.
+            if get_last_key_pressed().is_some() {
+                self.queued_moves.clear();
+            }
+            self.manage_touches();
+            // Clicks on the settings panel are for it.
+            let on_panel = self.panel_open && root_ui().is_mouse_over(mouse_position().into());
+            if is_mouse_button_pressed(MouseButton::Left) && !on_panel {
+                self.walk_to(mouse_position().into());
+            }
+            // The steps are done once the previous one is drawn.
+            if (get_time() - self.last_move_time) * 1000. >= self.animation_duration() {
+                if let Some(dir) = self.queued_moves.pop_front() {
+                    self.do_move_player(dir);
+                }
+            }
+
             if is_key_pressed(KeyCode::R) {
                 self.reset();
             }
-            if is_key_pressed(KeyCode::Left) {
-                self.do_move_player(Direction::Left);
+            if is_key_pressed(KeyCode::F1) {
+                self.panel_open = !self.panel_open;
+            }
+            if is_key_pressed(KeyCode::F) {
+                self.toggle_fullscreen();
+            }
+            if is_key_pressed(KeyCode::M) {
+                self.audio.toggle_mute();
+            }
+            if is_key_pressed(KeyCode::U) || is_key_pressed(KeyCode::Z) {
+                self.undo();
             }
-            if is_key_pressed(KeyCode::Right) {
-                self.do_move_player(Direction::Right);
+            self.manage_arrow_keys();
+            if is_key_pressed(KeyCode::Escape) {
+                self.queued_moves.clear();
+                self.paused = Some((0, get_time()));
+            }
+            None
+        }
+    }
+
+    /// Plays the steps of the replay watched, as asked by its buttons.
+    fn manage_replay(&mut self) -> Option<Leave> {
+        match self.replay.as_mut()?.update()? {
+            Transport::StepForward(dir) => {
+                let moved = self.do_move_player(dir);
+                if let Some(player) = &mut self.replay {
+                    player.stepped(moved);
+                }
             }
+            Transport::StepBack => self.undo(),
+            Transport::Restart => self.reset(),
+            Transport::Quit => return Some(Leave::Quit),
+        }
+        None
+    }
+
+    fn manage_editor(&mut self) {
+        let cell = self
+            .get_screen_scale()
+            .cell_at(&self.board, mouse_position().into());
+        let Some(editor) = &mut self.editor else {
+            return;
+        };
+        match editor.update(&mut self.board, cell) {
+            Some(EditorAction::Playtest) => self.start_playtest(),
+            Some(EditorAction::Leave) => self.close_editor(),
+            None => (),
+        }
+    }
+
+    /// Edits the starting position of the level, with some room around it to extend it.
+    fn open_editor(&mut self) {
+        let title = match self.title() {
+            "" => "level",
+            title => title,
+        };
+        self.editor = Some(LevelEditor::new(title));
+        self.set_board(self.board.with_margin(EDITOR_MARGIN));
+    }
+
+    /// Goes back to playing the edited level, without the empty rows and columns left around
+    /// it.
+    fn close_editor(&mut self) {
+        self.editor = None;
+        self.set_board(cropped(&self.board));
+    }
+
+    /// Plays the edited level, keeping the editor to come back to it afterwards.
+    fn start_playtest(&mut self) {
+        if let Some(editor) = self.editor.take() {
+            let board = cropped(&self.board);
+            self.playtest = Some((std::mem::replace(&mut self.board, board.clone()), editor));
+            self.set_board(board);
+        }
+    }
+
+    fn stop_playtest(&mut self) {
+        if let Some((board, editor)) = self.playtest.take() {
+            self.editor = Some(editor);
+            self.set_board(board);
+        }
+    }
+
+    /// Moves the selection of the pause menu, and does what the chosen entry says.
+    fn manage_pause_menu(&mut self) -> Option<Leave> {
+        let (selected, since) = self.paused?;
+        let win = vec2(screen_width(), screen_height());
+        let last = PAUSE_ENTRIES.len() - 1;
+
+        let clicked = if is_mouse_button_pressed(MouseButton::Left) {
+            Some(Vec2::from(mouse_position()))
+        } else {
+            touches()
+                .iter()
+                .find(|t| t.phase == TouchPhase::Ended)
+                .map(|t| t.position)
+        }
+        .and_then(|pos| (0..=last).find(|i| pause_entry_area(*i, win).contains(pos)));
+
+        let chosen = if let Some(index) = clicked {
+            Some(PAUSE_ENTRIES[index])
+        } else if is_key_pressed(KeyCode::Escape) {
+            Some(PauseEntry::Resume)
+        } else if is_key_pressed(KeyCode::Enter) || is_key_pressed(KeyCode::Space) {
+            Some(PAUSE_ENTRIES[selected])
+        } else {
             if is_key_pressed(KeyCode::Up) {
-                self.do_move_player(Direction::Up);
+                self.paused = Some((selected.saturating_sub(1), since));
             }
             if is_key_pressed(KeyCode::Down) {
-                self.do_move_player(Direction::Down);
+                self.paused = Some(((selected + 1).min(last), since));
+            }
+            None
+        };
+
+        match chosen? {
+            PauseEntry::Resume => {
+                // The time spent in the menu isn't counted.
+                self.start_time += get_time() - since;
+                self.paused = None;
+                None
+            }
+            PauseEntry::Restart => {
+                self.paused = None;
+                self.reset();
+                None
             }
-            is_key_pressed(KeyCode::Escape) || is_key_pressed(KeyCode::Q)
+            PauseEntry::LevelSelect => {
+                self.paused = None;
+                Some(Leave::LevelSelect)
+            }
+            PauseEntry::Edit => {
+                self.paused = None;
+                self.open_editor();
+                None
+            }
+            PauseEntry::Quit => Some(Leave::Quit),
         }
     }
 
+    /// Moves the player when an arrow key is pressed, and again while it is held.
+    fn manage_arrow_keys(&mut self) {
+        let now = get_time();
+        for (key, dir) in ARROW_KEYS {
+            if is_key_pressed(key) {
+                self.do_move_player(dir);
+                self.held_key = Some((key, now + self.key_repeat.0));
+            }
+        }
+
+        match self.held_key {
+            Some((key, _)) if !is_key_down(key) => self.held_key = None,
+            Some((key, next)) if now >= next => {
+                if let Some((_, dir)) = ARROW_KEYS.iter().find(|(k, _)| *k == key) {
+                    self.do_move_player(*dir);
+                }
+                self.held_key = Some((key, now + self.key_repeat.1));
+            }
+            _ => (),
+        }
+    }
+
+    /// Duration of the movement animation in milliseconds, at the chosen speed.
+    fn animation_duration(&self) -> f64 {
+        ANIMATION_DURATION_MILIS as f64 / self.animation_speed as f64
+    }
+
+    /// Draws the settings and the state of the board, if the panel is open.
+    fn settings_panel(&mut self) {
+        if !self.panel_open {
+            return;
+        }
+
+        let mut volume = self.audio.music_volume();
+        let mut muted = self.audio.muted;
+        let mut theme = self.theme;
+        let mut smooth = self.smooth;
+        let board = &self.board;
+        let replay = board.replay().to_string();
+        let inspector = [
+            format!("Size : {}x{}", board.width(), board.height()),
+            format!("Player : {:?}", board.player()),
+            format!("Moves : {}  Pushes : {}", board.moves(), board.pushes()),
+            format!("Crates : {}/{}", board.placed_crates(), board.crate_count()),
+            format!("Won : {}", board.has_won()),
+            lint_summary(board),
+            // Only the end of long replays.
+            format!("Replay : {}", &replay[replay.len().saturating_sub(24)..]),
+        ];
+
+        widgets::Window::new(hash!(), vec2(screen_width() - PANEL_SIZE.x, 0.), PANEL_SIZE)
+            .label("Settings (F1)")
+            .ui(&mut root_ui(), |ui| {
+                ui.slider(hash!(), "Animation", 0.25..4., &mut self.animation_speed);
+                ui.slider(hash!(), "Music", 0. ..1., &mut volume);
+                ui.checkbox(hash!(), "Mute", &mut muted);
+                let names = THEMES.map(|theme| theme.name);
+                ui.combo_box(hash!(), "Theme", &names, &mut theme);
+                ui.checkbox(hash!(), "Smooth pixels", &mut smooth);
+
+                ui.separator();
+                ui.tree_node(hash!(), "Board", |ui| {
+                    for line in &inspector {
+                        ui.label(None, line);
+                    }
+                });
+            });
+
+        if volume != self.audio.music_volume() {
+            self.audio.set_music_volume(volume);
+        }
+        if muted != self.audio.muted {
+            self.audio.toggle_mute();
+        }
+        // The filter is only changed with the settings, the theme bringing its own.
+        if theme != self.theme {
+            self.theme = theme;
+            smooth = THEMES[theme].smooth;
+        }
+        if smooth != self.smooth {
+            self.smooth = smooth;
+            set_smooth(&self.board_target, smooth);
+        }
+    }
+
+    fn toggle_fullscreen(&mut self) {
+        self.fullscreen = !self.fullscreen;
+        set_fullscreen(self.fullscreen);
+    }
+
     fn resize_window_if_needed(&mut self) {
+        // The screen size can't change in fullscreen.
+        if self.fullscreen {
+            return;
+        }
         let scale_infos = self.get_screen_scale();
 
         // To avoid unstable resize, we accept a small difference between w and h scales.
         if (scale_infos.scale_w * 10.).floor() != (scale_infos.scale_h * 10.).floor() {
-            let scale = f32::min(scale_infos.scale_w, scale_infos.scale_h);
+            let scale = scale_infos.scale();
             let (new_width, new_height) = (scale_infos.tot_w * scale, scale_infos.tot_h * scale);
 
             if (new_width, new_height) != (scale_infos.win_w, scale_infos.win_h) {
@@ -0,0 +1,189 @@
+//! Level editor of the [macroquad interface](`super::macroquad`), on top of the shared
+//! [`Editor`]: a palette of brushes is shown at the bottom of the window, the left button
+//! paints or drags the items and the right one erases.
+
+use std::fs;
+
+use macroquad::{miniquad::window::clipboard_set, prelude::*};
+
+use super::{
+    editor::{Brush, Editor, BRUSHES},
+    lint_summary, Board,
+};
+
+/// Where the levels made with the editor are saved, relative to the working directory.
+const EDITED_LEVELS_DIR: &str = "levels";
+const PALETTE_BUTTON_SIZE: f32 = 40.;
+const TEXT_SIZE: f32 = 21.;
+const HELP: &str = "1-6/click: brush, right click: erase, P: playtest, S: save, C: copy, Esc: quit";
+const BRUSH_KEYS: [(KeyCode, KeyCode); 6] = [
+    (KeyCode::Key1, KeyCode::Kp1),
+    (KeyCode::Key2, KeyCode::Kp2),
+    (KeyCode::Key3, KeyCode::Kp3),
+    (KeyCode::Key4, KeyCode::Kp4),
+    (KeyCode::Key5, KeyCode::Kp5),
+    (KeyCode::Key6, KeyCode::Kp6),
+];
+
+/// What the game must do after an update of the editor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditorAction {
+    /// Plays the edited level, coming back to the editor afterwards.
+    Playtest,
+    /// Stops editing.
+    Leave,
+}
+
+pub struct LevelEditor {
+    editor: Editor,
+    /// Name of the file the level is saved to, without its extension
+    name: String,
+    /// Result of the last save or copy, shown instead of the issues of the level
+    status: Option<String>,
+}
+
+impl LevelEditor {
+    pub fn new(title: &str) -> Self {
+        LevelEditor {
+            editor: Editor::new(),
+            name: title
+                .chars()
+                .map(|c| if c.is_alphanumeric() { c } else { '_' })
+                .collect(),
+            status: None,
+        }
+    }
+
+    /// Handles the keyboard and the mouse, `cell` being the cell of the `board` under the
+    /// mouse if any, and returns what the game must do if anything.
+    pub fn update(&mut self, board: &mut Board, cell: Option<(u32, u32)>) -> Option<EditorAction> {
+        if let Some(index) = BRUSH_KEYS
+            .iter()
+            .position(|(key, kp_key)| is_key_pressed(*key) || is_key_pressed(*kp_key))
+        {
+            self.editor.brush = BRUSHES[index];
+        }
+
+        if is_key_pressed(KeyCode::Escape) {
+            if !self.editor.unsaved {
+                return Some(EditorAction::Leave);
+            }
+            self.editor.unsaved = false;
+            self.status = Some("Unsaved changes : press Escape again to leave.".to_string());
+        }
+        if is_key_pressed(KeyCode::P) {
+            return Some(EditorAction::Playtest);
+        }
+        if is_key_pressed(KeyCode::S) {
+            self.save(board);
+        }
+        if is_key_pressed(KeyCode::C) {
+            clipboard_set(&board.to_xsb());
+            self.status = Some("Level copied to the clipboard.".to_string());
+        }
+
+        let mouse = Vec2::from(mouse_position());
+        let mut changed = false;
+        if is_mouse_button_pressed(MouseButton::Left) {
+            if let Some(index) = (0..BRUSHES.len()).find(|i| palette_area(*i).contains(mouse)) {
+                self.editor.brush = BRUSHES[index];
+            } else if let Some(cell) = cell {
+                changed |= self.editor.press(board, cell);
+            }
+        } else if is_mouse_button_pressed(MouseButton::Right) {
+            if let Some(cell) = cell {
+                changed |= self.editor.press_erase(board, cell);
+            }
+        } else if is_mouse_button_released(MouseButton::Left)
+            || is_mouse_button_released(MouseButton::Right)
+        {
+            changed |= self.editor.release(board, cell);
+        } else if let Some(cell) = cell {
+            changed |= self.editor.hover(board, cell);
+        }
+        if changed {
+            self.status = None;
+        }
+
+        None
+    }
+
+    /// Cell of the item being dragged, and if it is the player.
+    pub fn dragged(&self) -> Option<((u32, u32), bool)> {
+        self.editor.dragged()
+    }
+
+    /// Lines of the HUD while editing the `board`.
+    pub fn hud(&self, board: &Board) -> [String; 4] {
+        [
+            format!("Editor : {}", self.name),
+            format!("Brush : {}", self.editor.brush.name()),
+            HELP.to_string(),
+            self.status.clone().unwrap_or_else(|| lint_summary(board)),
+        ]
+    }
+
+    /// Draws the brushes at the bottom of the window, the current one highlighted.
+    pub fn draw_palette(&self) {
+        for (index, brush) in BRUSHES.iter().enumerate() {
+            let area = palette_area(index);
+            draw_rectangle(area.x, area.y, area.w, area.h, brush_color(*brush));
+            let (thickness, color) = if *brush == self.editor.brush {
+                (4., YELLOW)
+            } else {
+                (1., BLACK)
+            };
+            draw_rectangle_lines(area.x, area.y, area.w, area.h, thickness, color);
+
+            let key = (index + 1).to_string();
+            draw_text(
+                &key,
+                area.x + 3.,
+                area.y + TEXT_SIZE * 0.8,
+                TEXT_SIZE,
+                BLACK,
+            );
+        }
+    }
+
+    /// Writes the edited level in an XSB file named after its title, in
+    /// [`EDITED_LEVELS_DIR`].
+    fn save(&mut self, board: &Board) {
+        let path = format!("{EDITED_LEVELS_DIR}/{}.xsb", self.name);
+        let res =
+            fs::create_dir_all(EDITED_LEVELS_DIR).and_then(|()| fs::write(&path, board.to_xsb()));
+
+        // A failed save isn't worth stopping the game.
+        self.status = Some(match res {
+            Err(err) => format!("Couldn't save the level : {err}"),
+            Ok(()) => {
+                self.editor.unsaved = false;
+                match board.lint().len() {
+                    0 => format!("Level saved to {path}"),
+                    issues => format!("Level saved to {path}, with {issues} issue(s) left"),
+                }
+            }
+        });
+    }
+}
+
+/// Where the brush at `index` of [`BRUSHES`] is drawn.
+fn palette_area(index: usize) -> Rect {
+    Rect::new(
+        index as f32 * PALETTE_BUTTON_SIZE,
+        screen_height() - PALETTE_BUTTON_SIZE,
+        PALETTE_BUTTON_SIZE,
+        PALETTE_BUTTON_SIZE,
+    )
+}
+
+fn brush_color(brush: Brush) -> Color {
+    match brush {
+        Brush::Wall => Color::from_rgba(110, 90, 80, 255),
+        Brush::Floor => Color::from_rgba(220, 220, 210, 255),
+        Brush::Target => Color::from_rgba(220, 70, 60, 255),
+        Brush::Void => Color::from_rgba(40, 40, 40, 255),
+        Brush::Crate => Color::from_rgba(200, 140, 40, 255),
+        Brush::Player => Color::from_rgba(50, 90, 220, 255),
+    }
+}
@@ -0,0 +1,237 @@
+//! Replay viewer of the [macroquad interface](`super::macroquad`): the steps of a replay are
+//! played on the board, with buttons at the bottom of the window to control them.
+
+use macroquad::prelude::*;
+
+use super::{Direction, Replay};
+
+/// Delays between two steps of a replay, from slowest to fastest.
+const SPEEDS_SECS: [f64; 6] = [1., 0.5, 0.25, 0.125, 0.06, 0.03];
+const DEFAULT_SPEED: usize = 2;
+const BUTTON_SIZE: f32 = 40.;
+const BUTTON_TEXT_SIZE: f32 = 24.;
+const STATUS_TEXT_SIZE: f32 = 21.;
+const BUTTONS: [Button; 6] = [
+    Button::Restart,
+    Button::StepBack,
+    Button::PlayPause,
+    Button::StepForward,
+    Button::Slower,
+    Button::Faster,
+];
+
+/// Buttons of the transport bar.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Button {
+    Restart,
+    StepBack,
+    PlayPause,
+    StepForward,
+    Slower,
+    Faster,
+}
+
+impl Button {
+    fn label(self, playing: bool) -> &'static str {
+        match self {
+            Button::Restart => "|<",
+            Button::StepBack => "<",
+            Button::PlayPause if playing => "||",
+            Button::PlayPause => "|>",
+            Button::StepForward => ">",
+            Button::Slower => "-",
+            Button::Faster => "+",
+        }
+    }
+}
+
+/// What must be done to the board being watched.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transport {
+    /// Plays the step in this direction, then tell the result with [`ReplayPlayer::stepped`].
+    StepForward(Direction),
+    /// Undoes the last step.
+    StepBack,
+    /// Puts the board back at its start.
+    Restart,
+    Quit,
+}
+
+pub struct ReplayPlayer {
+    replay: Replay,
+    /// Number of steps played on the board
+    position: usize,
+    playing: bool,
+    /// Index in [`SPEEDS_SECS`]
+    speed: usize,
+    /// When the next step is played, in seconds since the start of the game
+    next_step: f64,
+    /// If the step at `position` couldn't be done
+    invalid: bool,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> Self {
+        ReplayPlayer {
+            replay,
+            position: 0,
+            playing: true,
+            speed: DEFAULT_SPEED,
+            next_step: get_time() + SPEEDS_SECS[DEFAULT_SPEED],
+            invalid: false,
+        }
+    }
+
+    /// Handles the keyboard, the clicks and taps on the buttons and the timer of the steps,
+    /// returning what must be done to the board if anything.
+    pub fn update(&mut self) -> Option<Transport> {
+        if is_key_pressed(KeyCode::Escape) || is_key_pressed(KeyCode::Q) {
+            return Some(Transport::Quit);
+        }
+
+        let clicked = if is_mouse_button_pressed(MouseButton::Left) {
+            Some(Vec2::from(mouse_position()))
+        } else {
+            touches()
+                .iter()
+                .find(|t| t.phase == TouchPhase::Ended)
+                .map(|t| t.position)
+        }
+        .and_then(|pos| (0..BUTTONS.len()).find(|i| button_area(*i).contains(pos)))
+        .map(|i| BUTTONS[i]);
+
+        let button = clicked.or_else(|| {
+            [
+                (KeyCode::Home, Button::Restart),
+                (KeyCode::Left, Button::StepBack),
+                (KeyCode::Space, Button::PlayPause),
+                (KeyCode::Right, Button::StepForward),
+                (KeyCode::Minus, Button::Slower),
+                (KeyCode::KpSubtract, Button::Slower),
+                (KeyCode::Equal, Button::Faster),
+                (KeyCode::KpAdd, Button::Faster),
+            ]
+            .into_iter()
+            .find(|(key, _)| is_key_pressed(*key))
+            .map(|(_, button)| button)
+        });
+
+        let now = get_time();
+        match button {
+            Some(Button::Restart) => {
+                self.position = 0;
+                self.invalid = false;
+                Some(Transport::Restart)
+            }
+            Some(Button::StepBack) => {
+                self.playing = false;
+                self.invalid = false;
+                (self.position > 0).then(|| {
+                    self.position -= 1;
+                    Transport::StepBack
+                })
+            }
+            Some(Button::PlayPause) => {
+                // Playing again from the end starts over.
+                if !self.playing && self.position == self.replay.len() {
+                    self.position = 0;
+                    self.playing = true;
+                    self.next_step = now + SPEEDS_SECS[self.speed];
+                    return Some(Transport::Restart);
+                }
+                self.playing = !self.playing;
+                self.next_step = now;
+                None
+            }
+            Some(Button::StepForward) => {
+                self.playing = false;
+                self.next_dir().map(Transport::StepForward)
+            }
+            Some(Button::Slower) => {
+                self.speed = self.speed.saturating_sub(1);
+                None
+            }
+            Some(Button::Faster) => {
+                self.speed = (self.speed + 1).min(SPEEDS_SECS.len() - 1);
+                None
+            }
+            None if self.playing && now >= self.next_step => {
+                self.next_step = now + SPEEDS_SECS[self.speed];
+                self.next_dir().map(Transport::StepForward)
+            }
+            None => None,
+        }
+    }
+
+    /// The step asked by [`Transport::StepForward`] was done if `moved`, or else the replay
+    /// stops there.
+    pub fn stepped(&mut self, moved: bool) {
+        if moved {
+            self.position += 1;
+        } else {
+            self.invalid = true;
+        }
+        if self.invalid || self.position == self.replay.len() {
+            self.playing = false;
+        }
+    }
+
+    /// Draws the buttons and the progress at the bottom of the window.
+    pub fn draw(&self) {
+        let top = screen_height() - BUTTON_SIZE;
+        draw_rectangle(
+            0.,
+            top,
+            screen_width(),
+            BUTTON_SIZE,
+            Color::new(0., 0., 0., 0.6),
+        );
+
+        for (index, button) in BUTTONS.iter().enumerate() {
+            let area = button_area(index);
+            draw_rectangle_lines(area.x, area.y, area.w, area.h, 2., WHITE);
+            let label = button.label(self.playing);
+            let dim = measure_text(label, None, BUTTON_TEXT_SIZE as u16, 1.);
+            draw_text(
+                label,
+                area.x + (area.w - dim.width) / 2.,
+                area.y + (area.h - dim.height) / 2. + dim.offset_y,
+                BUTTON_TEXT_SIZE,
+                WHITE,
+            );
+        }
+
+        let status = format!(
+            "Step {}/{}  x{}{}",
+            self.position,
+            self.replay.len(),
+            1 << self.speed,
+            if self.invalid { "  Invalid move!" } else { "" }
+        );
+        let dim = measure_text(&status, None, STATUS_TEXT_SIZE as u16, 1.);
+        draw_text(
+            &status,
+            BUTTONS.len() as f32 * BUTTON_SIZE + dim.height,
+            top + (BUTTON_SIZE - dim.height) / 2. + dim.offset_y,
+            STATUS_TEXT_SIZE,
+            if self.invalid { RED } else { WHITE },
+        );
+    }
+
+    fn next_dir(&self) -> Option<Direction> {
+        if self.invalid {
+            return None;
+        }
+        self.replay.steps().get(self.position).map(|step| step.dir)
+    }
+}
+
+/// Where the button at `index` of [`BUTTONS`] is drawn.
+fn button_area(index: usize) -> Rect {
+    Rect::new(
+        index as f32 * BUTTON_SIZE,
+        screen_height() - BUTTON_SIZE,
+        BUTTON_SIZE,
+        BUTTON_SIZE,
+    )
+}
@@ -0,0 +1,285 @@
+//! Level select screen of the [macroquad interface](`super::macroquad`): a grid of thumbnails of
+//! the levels, scrolled with the mouse wheel or by dragging it on a touch screen. The `M` key
+//! switches the move-limit challenge of the highlighted level, and the `E` key starts the
+//! endless mode.
+
+use macroquad::prelude::*;
+
+use super::{thumbnail::Thumbnail, LevelLibrary};
+
+/// Size of a cell in the thumbnails, in pixels.
+const THUMBNAIL_CELL_SIZE: u32 = 8;
+/// Size of a level box in the grid, its title included.
+const BOX_SIZE: f32 = 180.;
+/// Space between the boxes and around the grid.
+const BOX_MARGIN: f32 = 16.;
+const TITLE_SIZE: f32 = 20.;
+const BADGE_RADIUS: f32 = 12.;
+/// Distance scrolled for each step of the mouse wheel, in pixels.
+const WHEEL_SCROLL: f32 = 40.;
+/// Distance a touch can travel and still be a tap rather than a scroll, in pixels.
+const TAP_MAX_DISTANCE: f32 = 30.;
+const HELP: &str = "Enter : play   M : move-limit challenge   E : endless mode   Escape : quit";
+
+/// What was chosen on the level select screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SelectChoice {
+    /// Play the level at this index in the library.
+    Play(usize),
+    /// Switch the move-limit challenge of the level at this index on or off.
+    ToggleChallenge(usize),
+    /// Play random levels one after the other, see [`Endless`](`crate::Endless`).
+    Endless,
+    Quit,
+}
+
+pub struct LevelSelect {
+    /// Thumbnail of each level of the library
+    thumbnails: Vec<Texture2D>,
+    /// Index of the highlighted level
+    selected: usize,
+    /// How far down the grid is scrolled, in pixels
+    scroll: f32,
+    /// Touch being followed, where it started and where it was last
+    touch: Option<(u64, Vec2, Vec2)>,
+}
+
+impl LevelSelect {
+    pub fn new(library: &LevelLibrary, selected: usize) -> Self {
+        let thumbnails = library
+            .levels()
+            .iter()
+            .map(|level| {
+                let thumbnail = Thumbnail::render(level.board(), THUMBNAIL_CELL_SIZE);
+                let texture = Texture2D::from_rgba8(
+                    thumbnail.width as u16,
+                    thumbnail.height as u16,
+                    &thumbnail.pixels,
+                );
+                texture.set_filter(FilterMode::Nearest);
+                texture
+            })
+            .collect();
+
+        let mut select = LevelSelect {
+            thumbnails,
+            selected: 0,
+            scroll: 0.,
+            touch: None,
+        };
+        select.select(selected);
+        select
+    }
+
+    /// Highlights the level at `index`, scrolling to show it.
+    pub fn select(&mut self, index: usize) {
+        self.selected = index.min(self.thumbnails.len().saturating_sub(1));
+
+        let area = self.box_area(self.selected);
+        if area.y < BOX_MARGIN {
+            self.scroll -= BOX_MARGIN - area.y;
+        } else if area.bottom() > screen_height() - BOX_MARGIN {
+            self.scroll += area.bottom() - (screen_height() - BOX_MARGIN);
+        }
+        self.clamp_scroll();
+    }
+
+    /// Handles the keyboard, the mouse and the touches, returning the choice made if any.
+    pub fn update(&mut self) -> Option<SelectChoice> {
+        let columns = columns();
+        let last = self.thumbnails.len().saturating_sub(1);
+
+        if is_key_pressed(KeyCode::Escape) || is_key_pressed(KeyCode::Q) {
+            return Some(SelectChoice::Quit);
+        }
+        if is_key_pressed(KeyCode::Enter) || is_key_pressed(KeyCode::Space) {
+            return Some(SelectChoice::Play(self.selected));
+        }
+        if is_key_pressed(KeyCode::M) {
+            return Some(SelectChoice::ToggleChallenge(self.selected));
+        }
+        if is_key_pressed(KeyCode::E) {
+            return Some(SelectChoice::Endless);
+        }
+        if is_key_pressed(KeyCode::Left) {
+            self.select(self.selected.saturating_sub(1));
+        }
+        if is_key_pressed(KeyCode::Right) {
+            self.select((self.selected + 1).min(last));
+        }
+        if is_key_pressed(KeyCode::Up) && self.selected >= columns {
+            self.select(self.selected - columns);
+        }
+        if is_key_pressed(KeyCode::Down) && self.selected + columns <= last {
+            self.select(self.selected + columns);
+        }
+
+        let (_, wheel) = mouse_wheel();
+        if wheel != 0. {
+            self.scroll -= wheel.signum() * WHEEL_SCROLL;
+            self.clamp_scroll();
+        }
+        if is_mouse_button_pressed(MouseButton::Left) {
+            if let Some(index) = self.index_at(mouse_position().into()) {
+                return Some(SelectChoice::Play(index));
+            }
+        }
+
+        for touch in touches() {
+            match (touch.phase, self.touch) {
+                (TouchPhase::Started, None) => {
+                    self.touch = Some((touch.id, touch.position, touch.position));
+                }
+                (TouchPhase::Moved, Some((id, start, last))) if id == touch.id => {
+                    self.scroll -= touch.position.y - last.y;
+                    self.clamp_scroll();
+                    self.touch = Some((id, start, touch.position));
+                }
+                (TouchPhase::Ended, Some((id, start, _))) if id == touch.id => {
+                    self.touch = None;
+                    if (touch.position - start).length() < TAP_MAX_DISTANCE {
+                        if let Some(index) = self.index_at(touch.position) {
+                            return Some(SelectChoice::Play(index));
+                        }
+                    }
+                }
+                (TouchPhase::Cancelled, Some((id, _, _))) if id == touch.id => self.touch = None,
+                _ => (),
+            }
+        }
+
+        None
+    }
+
+    pub fn draw(&self, library: &LevelLibrary) {
+        clear_background(BLACK);
+
+        for (index, (level, thumbnail)) in library.levels().iter().zip(&self.thumbnails).enumerate()
+        {
+            let area = self.box_area(index);
+            if area.bottom() < 0. || area.y > screen_height() {
+                continue;
+            }
+
+            draw_rectangle(area.x, area.y, area.w, area.h, DARKGRAY);
+            if index == self.selected {
+                draw_rectangle_lines(area.x, area.y, area.w, area.h, 4., YELLOW);
+            }
+
+            // The thumbnail is fitted above the title, keeping its proportions.
+            let room = vec2(area.w, area.h - TITLE_SIZE * 1.5) - BOX_MARGIN;
+            let scale = f32::min(room.x / thumbnail.width(), room.y / thumbnail.height());
+            let size = thumbnail.size() * scale;
+            let corner = area.point() + (room + BOX_MARGIN - size) / 2.;
+            draw_texture_ex(
+                thumbnail,
+                corner.x,
+                corner.y,
+                WHITE,
+                DrawTextureParams {
+                    dest_size: Some(size),
+                    ..Default::default()
+                },
+            );
+
+            let title = measure_text(level.title(), None, TITLE_SIZE as u16, 1.);
+            draw_text(
+                level.title(),
+                area.x + (area.w - title.width) / 2.,
+                area.bottom() - TITLE_SIZE / 2.,
+                TITLE_SIZE,
+                WHITE,
+            );
+
+            if level.is_solved() {
+                draw_solved_badge(vec2(area.right(), area.y) + vec2(-1., 1.) * BADGE_RADIUS);
+            }
+            if level.is_challenge() {
+                draw_challenge_badge(area.point() + BADGE_RADIUS);
+            }
+        }
+
+        // The keys which aren't obvious, in the bottom margin.
+        let help = measure_text(HELP, None, TITLE_SIZE as u16, 1.);
+        draw_rectangle(
+            0.,
+            screen_height() - BOX_MARGIN,
+            screen_width(),
+            BOX_MARGIN,
+            BLACK,
+        );
+        draw_text(
+            HELP,
+            (screen_width() - help.width) / 2.,
+            screen_height() - BOX_MARGIN + help.offset_y,
+            TITLE_SIZE * 0.75,
+            GRAY,
+        );
+    }
+
+    /// Where the box of the level at `index` is drawn.
+    fn box_area(&self, index: usize) -> Rect {
+        let columns = columns();
+        let (column, row) = ((index % columns) as f32, (index / columns) as f32);
+        Rect::new(
+            BOX_MARGIN + column * (BOX_SIZE + BOX_MARGIN),
+            BOX_MARGIN + row * (BOX_SIZE + BOX_MARGIN) - self.scroll,
+            BOX_SIZE,
+            BOX_SIZE,
+        )
+    }
+
+    /// Index of the level whose box is under the window position `pos`, if any.
+    fn index_at(&self, pos: Vec2) -> Option<usize> {
+        (0..self.thumbnails.len()).find(|index| self.box_area(*index).contains(pos))
+    }
+
+    /// Keeps the scroll between the top and the bottom of the grid.
+    fn clamp_scroll(&mut self) {
+        let rows = self.thumbnails.len().div_ceil(columns()) as f32;
+        let grid_height = BOX_MARGIN + rows * (BOX_SIZE + BOX_MARGIN);
+        self.scroll = self
+            .scroll
+            .clamp(0., (grid_height - screen_height()).max(0.));
+    }
+}
+
+/// Number of boxes fitting in a row of the window.
+fn columns() -> usize {
+    (((screen_width() - BOX_MARGIN) / (BOX_SIZE + BOX_MARGIN)) as usize).max(1)
+}
+
+/// Orange disk with a `P`, for par, centered on `center`.
+fn draw_challenge_badge(center: Vec2) {
+    draw_circle(center.x, center.y, BADGE_RADIUS, ORANGE);
+    let dim = measure_text("P", None, TITLE_SIZE as u16, 1.);
+    draw_text(
+        "P",
+        center.x - dim.width / 2.,
+        center.y - dim.height / 2. + dim.offset_y,
+        TITLE_SIZE,
+        WHITE,
+    );
+}
+
+/// Green disk with a check mark, centered on `center`.
+fn draw_solved_badge(center: Vec2) {
+    draw_circle(center.x, center.y, BADGE_RADIUS, GREEN);
+    let r = BADGE_RADIUS;
+    draw_line(
+        center.x - r * 0.5,
+        center.y,
+        center.x - r * 0.1,
+        center.y + r * 0.4,
+        3.,
+        WHITE,
+    );
+    draw_line(
+        center.x - r * 0.1,
+        center.y + r * 0.4,
+        center.x + r * 0.5,
+        center.y - r * 0.4,
+        3.,
+        WHITE,
+    );
+}
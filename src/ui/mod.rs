@@ -1,25 +1,75 @@
-use std::error::Error;
+use std::{error::Error, fmt, time::Duration};
 
-use super::data::{Board, BoardElem, CellKind, Direction, MovableItem};
+#[cfg(feature = "tui")]
+use super::data::SolverLimits;
+use super::data::{
+    Board, BoardElem, CellKind, Direction, LevelLibrary, MovableItem, MoveLimit, Replay, Score,
+    TimeLimit,
+};
+use super::{Hotseat, Stopwatch};
 
+#[cfg(any(feature = "ggez", test))]
+mod animation;
+#[cfg(any(feature = "ggez", feature = "term_graphics", test))]
+mod atlas;
+mod audio;
+pub use audio::{AudioSink, SoundEffect};
+#[cfg(feature = "chat")]
+mod chat_plays;
+#[cfg(feature = "chat")]
+pub use chat_plays::ChatPlays;
+#[cfg(any(feature = "chat", test))]
+mod chat_votes;
 mod cli;
 mod terminal;
 use cli::Cli;
+#[cfg(any(feature = "term_graphics", test))]
+mod term_graphics;
 #[cfg(feature = "tui")]
 mod tui;
 #[cfg(feature = "tui")]
+mod tui_editor;
+#[cfg(feature = "tui")]
+pub use tui_editor::edit_level;
+#[cfg(feature = "ratatui")]
+mod ratatui;
+#[cfg(feature = "term_graphics")]
+mod tui_graphics;
+#[cfg(feature = "tui")]
+mod tui_layout;
+#[cfg(all(feature = "tui", feature = "repo"))]
+mod tui_packs;
+#[cfg(feature = "tui")]
+mod tui_theme;
+#[cfg(feature = "tui")]
 use tui::Tui;
 #[cfg(feature = "ggez")]
 mod ggez;
 #[cfg(feature = "ggez")]
 pub use ggez::game_ggez;
+#[cfg(any(feature = "ggez", feature = "macroquad", test))]
+mod editor;
 #[cfg(feature = "macroquad")]
 mod macroquad;
 #[cfg(feature = "macroquad")]
-pub use macroquad::game_macroquad;
+pub use macroquad::{game_macroquad, game_macroquad_web, watch_replay_macroquad};
+#[cfg(feature = "macroquad")]
+mod macroquad_editor;
+#[cfg(feature = "macroquad")]
+mod macroquad_replay;
+#[cfg(feature = "macroquad")]
+mod macroquad_select;
+#[cfg(any(feature = "macroquad", feature = "thumbnail", test))]
+mod thumbnail;
+#[cfg(feature = "thumbnail")]
+pub use thumbnail::Thumbnail;
 // mod gui;
 // use gui::Gui;
 
+/// Multiplier of the speed the replays start at in the terminal interfaces, from 1 (a step per
+/// second) to 32, when they aren't given one.
+pub const REPLAY_SPEED_ENV_VAR: &str = "SOKOBAN_REPLAY_SPEED";
+
 /// How the game should be played.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum DisplayKind {
@@ -28,6 +78,13 @@ pub enum DisplayKind {
     #[cfg(feature = "tui")]
     /// Dynamic terminal display.
     TUI,
+    #[cfg(feature = "term_graphics")]
+    /// Dynamic terminal display with the tiles drawn as pictures, if the terminal supports
+    /// the sixel or kitty graphics, or else the same as [`DisplayKind::TUI`].
+    TermGraphics,
+    #[cfg(feature = "ratatui")]
+    /// Terminal display with a sidebar and a log.
+    RichTUI,
     // /// 2D graphics.
     // GUI,
 }
@@ -41,9 +98,76 @@ pub enum Action {
     ResetLevel,
     /// Quit game
     Quit,
+    /// The time given to win the level ran out, see [`Ui::start_countdown`].
+    TimeOut,
     // TODO: LoadLevel(String path)
 }
 
+/// What the player picked on the level selection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Selection {
+    /// The level at this index in the library.
+    Level(usize),
+    /// Random levels one after the other, see [`Endless`](`crate::Endless`).
+    Endless,
+}
+
+/// What to do once a level is won.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WonChoice {
+    /// Play the same level again.
+    Restart,
+    /// Go on with the next level.
+    Next,
+    /// Quit game
+    Quit,
+}
+
+/// What to do once a level is lost, see [`Defeat`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LostChoice {
+    /// Play the same level again, with the whole time.
+    Retry,
+    /// Quit game
+    Quit,
+}
+
+/// Why a level was lost.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Defeat {
+    /// The time given ran out, see [`Ui::start_countdown`].
+    TimeOut(TimeLimit),
+    /// More moves or pushes than allowed were done, see [`Ui::start_challenge`].
+    OutOfMoves(MoveLimit),
+}
+
+impl Defeat {
+    /// Short title announcing the defeat.
+    pub fn title(&self) -> &'static str {
+        match self {
+            Defeat::TimeOut(_) => "Time's up!",
+            Defeat::OutOfMoves(_) => "Out of moves!",
+        }
+    }
+}
+
+impl fmt::Display for Defeat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Defeat::TimeOut(limit) => write!(
+                f,
+                "The level had to be won in {}.",
+                format_duration(limit.par_time())
+            ),
+            Defeat::OutOfMoves(limit) => write!(
+                f,
+                "The level had to be won in {} moves and {} pushes.",
+                limit.moves, limit.pushes
+            ),
+        }
+    }
+}
+
 /// Describes a generic interface to play the game.
 pub trait Ui {
     /// All the setup needed for the UI : opening window, ...
@@ -57,8 +181,20 @@ pub trait Ui {
         Ok(())
     }
 
+    /// Lets the user pick which level of the `library` to play next, or the endless mode, or
+    /// `None` to quit. The user may also replace the `library`, for instance with a pack they
+    /// downloaded.
+    ///
+    /// By default, the levels are played in order, skipping the solved ones.
+    fn select_level(
+        &mut self,
+        library: &mut LevelLibrary,
+    ) -> Result<Option<Selection>, Box<dyn Error>> {
+        Ok(library.first_unsolved().map(Selection::Level))
+    }
+
     /// Get last input from user. This is usually blocking.
-    fn get_action(&self, board: &Board) -> Result<Action, Box<dyn Error>>;
+    fn get_action(&mut self, board: &Board) -> Result<Action, Box<dyn Error>>;
 
     /// Updates the display based on the board provided and the result of the last move and if it
     /// pushed a crate.
@@ -68,13 +204,101 @@ pub trait Ui {
     ///
     /// It can directly check and react on [`Board::has_won`].
     fn display(
-        &self,
+        &mut self,
         board: &Board,
         last_move_result: Option<Option<(u32, u32)>>,
     ) -> Result<(), Box<dyn Error>>;
 
-    /// The game is won and will quit the game when this function returns.
-    fn won(&self) -> Result<(), Box<dyn Error>>;
+    /// Shows the steps of the `replay` played from the `board`, starting at the `speed`
+    /// multiplier if given and the interface can change it (see [`REPLAY_SPEED_ENV_VAR`]).
+    ///
+    /// By default, the board is simply displayed after each step until the end of the replay
+    /// or the first step which can't be done.
+    fn watch_replay(
+        &mut self,
+        board: &Board,
+        replay: &Replay,
+        _speed: Option<u32>,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut board = board.clone();
+        self.display(&board, None)?;
+        for step in replay.steps() {
+            let res = board.do_move_player(step.dir);
+            self.display(&board, res)?;
+            if res.is_none() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// The level is won with the given `score`, `best` being the personal best before that.
+    /// Returns what the player wants to do next.
+    fn won(&mut self, score: &Score, best: Option<&Score>) -> Result<WonChoice, Box<dyn Error>>;
+
+    /// The level being started must be won before the `limit` runs out, or has no limit if
+    /// `None`. The interfaces refreshing on their own show the time left, and return
+    /// [`Action::TimeOut`] from [`Ui::get_action`] once it ran out.
+    ///
+    /// By default, the time left isn't shown and it running out is only noticed at the next
+    /// action.
+    fn start_countdown(&mut self, _limit: Option<TimeLimit>) {}
+
+    /// The level being started must be won within the moves and pushes of the `limit`, or has
+    /// no limit if `None`. The interfaces may show how many are left next to their counters.
+    fn start_challenge(&mut self, _limit: Option<MoveLimit>) {}
+
+    /// The level being started is part of an [endless](`crate::Endless`) run, `streak` levels
+    /// having been won in a row before it, or the run ended if `None`.
+    fn show_streak(&mut self, _streak: Option<u32>) {}
+
+    /// Shows a `message` to the player during the level, such as the hints of a
+    /// [tutorial](`crate::Tutorial`), until the next one or the end of the level.
+    fn show_message(&mut self, _message: &str) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    /// The level is played in the [hotseat mode](`crate::Hotseat`), with the turns and counters
+    /// of the players in `hotseat`, or the level ended if `None`. It is called before each
+    /// display of the board.
+    fn show_turn(&mut self, _hotseat: Option<&Hotseat>) {}
+
+    /// The level is played in the [speedrun mode](`crate::Speedrun`), with the timer of the run
+    /// in `stopwatch`, or the run ended if `None`. It is called before each display of the
+    /// board, and the interfaces may show the timer running in between.
+    fn show_speedrun(&mut self, _stopwatch: Option<Stopwatch>) {}
+
+    /// The level was lost before it was won, for the reason given in `defeat`.
+    /// Returns what the player wants to do next.
+    fn lost(&mut self, defeat: &Defeat) -> Result<LostChoice, Box<dyn Error>>;
+}
+
+/// Formats a duration as `m:ss.d`, precise enough for a game.
+fn format_duration(duration: Duration) -> String {
+    let tenths = duration.as_millis() / 100;
+    format!("{}:{:02}.{}", tenths / 600, (tenths / 10) % 60, tenths % 10)
+}
+
+/// Formats a duration as `m:ss.mmm`, precise enough for a speedrun.
+fn format_duration_millis(duration: Duration) -> String {
+    let millis = duration.as_millis();
+    format!(
+        "{}:{:02}.{:03}",
+        millis / 60_000,
+        (millis / 1000) % 60,
+        millis % 1000
+    )
+}
+
+/// First issue found by the linter in the level being edited, and how many others there are.
+#[cfg(any(feature = "tui", feature = "ggez", feature = "macroquad"))]
+fn lint_summary(board: &Board) -> String {
+    let issues = board.lint();
+    match issues.first() {
+        None => "No issue found.".to_string(),
+        Some(issue) if issues.len() == 1 => issue.to_string(),
+        Some(issue) => format!("{} (+{} other issues)", issue, issues.len() - 1),
+    }
 }
 
 pub fn new(kind: DisplayKind) -> Result<Box<dyn Ui>, Box<dyn Error>> {
@@ -84,14 +308,31 @@ pub fn new(kind: DisplayKind) -> Result<Box<dyn Ui>, Box<dyn Error>> {
         CLI => Box::new(Cli::initialize()?),
         #[cfg(feature = "tui")]
         TUI => Box::new(Tui::initialize()?),
+        #[cfg(feature = "term_graphics")]
+        TermGraphics => match term_graphics::GraphicsProtocol::detect() {
+            Some(protocol) => Box::new(tui_graphics::TuiGraphics::new(protocol)?),
+            None => Box::new(Tui::initialize()?),
+        },
+        #[cfg(feature = "ratatui")]
+        RichTUI => Box::new(ratatui::RichTui::initialize()?),
         // GUI -> Box::new(Gui::new()),
     })
 }
 
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
     #[test]
-    fn it_works() {
-        assert_eq!(2 + 2, 4);
+    fn it_formats_durations() {
+        assert_eq!(super::format_duration(Duration::from_millis(0)), "0:00.0");
+        assert_eq!(
+            super::format_duration(Duration::from_millis(83_456)),
+            "1:23.4"
+        );
+        assert_eq!(
+            super::format_duration_millis(Duration::from_millis(83_456)),
+            "1:23.456"
+        );
     }
 }
@@ -0,0 +1,944 @@
+//! Richer terminal interface built with ratatui: the board is shown in a pane next to a
+//! sidebar with the stats of the game and the level list, above a log of what happened.
+//!
+//! The glyphs, the themes and the scrolling of the board are the ones of the
+//! [terminal interface](`super::tui`).
+
+use std::{
+    collections::VecDeque,
+    env,
+    error::Error,
+    time::{Duration, Instant},
+};
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Flex, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Clear, List, ListState, Padding, Paragraph},
+    DefaultTerminal, Frame,
+};
+
+use super::{
+    format_duration, format_duration_millis,
+    tui::{initial_replay_speed, TuiError},
+    tui_layout::BoardLayout,
+    tui_theme::{Glyph, TuiTheme, THEMES, THEME_ENV_VAR},
+    Action, Board, CellKind, Defeat, Direction, Hotseat, LevelLibrary, LostChoice, MoveLimit,
+    Replay, Score, Selection, SolverLimits, Stopwatch, TimeLimit, Ui, WonChoice,
+};
+
+const SIDEBAR_COLS: u16 = 32;
+/// Rows of the stats in the sidebar, borders included.
+const STATS_ROWS: u16 = 8;
+/// Rows of the log under the board, borders included.
+const LOG_ROWS: u16 = 7;
+/// Messages kept in the log, the older ones being dropped.
+const LOG_LENGTH: usize = 100;
+/// Delay between two refreshes of the time while waiting for the player.
+const TICK: Duration = Duration::from_millis(500);
+
+const GAME_HELP: &str = " Arrows: move, r: reset, h: hint, t: theme, q: quit ";
+#[cfg(not(feature = "repo"))]
+const BROWSER_HELP: &str =
+    " Up/Down: move, Enter: play, p: watch best replay, m: move-limit challenge, e: endless, q: quit ";
+#[cfg(feature = "repo")]
+const BROWSER_HELP: &str = " Up/Down: move, Enter: play, p: watch best replay, m: move-limit challenge, e: endless, c: community packs, q: quit ";
+const REPLAY_HELP: &str = " Space: play/pause, Left/Right: step, +/-: speed, q: quit ";
+const SOLVED_MARK: char = '✓';
+/// Shown after the levels picked for the move-limit challenge.
+const CHALLENGE_MARK: &str = " [par]";
+
+const WON_CHOICES: [(WonChoice, &str); 3] = [
+    (WonChoice::Restart, "Restart"),
+    (WonChoice::Next, "Next"),
+    (WonChoice::Quit, "Quit"),
+];
+const LOST_CHOICES: [(LostChoice, &str); 2] =
+    [(LostChoice::Retry, "Retry"), (LostChoice::Quit, "Quit")];
+
+/// Delays between two steps of a replay, from slowest to fastest, the same as in the TUI.
+const REPLAY_SPEEDS_MILLIS: [u64; 6] = [1000, 500, 250, 125, 60, 30];
+
+const HINT_PATH_COLOR: Color = Color::Blue;
+const HINT_PUSH_COLOR: Color = Color::Yellow;
+const HINT_ARROWS: [(Direction, char); 4] = [
+    (Direction::Left, '←'),
+    (Direction::Right, '→'),
+    (Direction::Up, '↑'),
+    (Direction::Down, '↓'),
+];
+
+/// Cell of the board drawn differently until the next move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Highlight {
+    cell: (u32, u32),
+    /// Replaces the symbol of the cell.
+    symbol: Option<char>,
+    bg: Color,
+}
+
+/// What the sidebar shows above the level list.
+enum Stats<'a> {
+    Game(&'a Board),
+    Replay(Vec<String>),
+}
+
+/// Box shown over the game, with choices under its lines.
+struct Dialog<'a> {
+    title: &'a str,
+    lines: &'a [String],
+    choices: &'a [&'a str],
+    selected: usize,
+}
+
+/// Terminal interface with panes
+pub struct RichTui {
+    terminal: DefaultTerminal,
+    /// Index of the current theme in [`THEMES`].
+    theme: usize,
+    /// Titles of the levels and if they are solved, as of the last level selection.
+    levels: Vec<(String, bool)>,
+    /// Index in `levels` of the level being played.
+    current: Option<usize>,
+    /// When the current level was first displayed.
+    started: Option<Instant>,
+    /// Time limit of the current level, counted from `started`.
+    countdown: Option<TimeLimit>,
+    /// Moves and pushes allowed to win the current level.
+    challenge: Option<MoveLimit>,
+    /// Levels won in a row, in the endless mode.
+    streak: Option<u32>,
+    /// Turns and counters of the players, in the hotseat mode.
+    hotseat: Option<Hotseat>,
+    /// Timer of the run, in the speedrun mode.
+    speedrun: Option<Stopwatch>,
+    /// Last messages, the most recent at the end.
+    log: VecDeque<String>,
+    /// Where the board was last drawn, kept so it only scrolls when the player gets close to
+    /// the side.
+    layout: Option<BoardLayout>,
+    highlighted: Vec<Highlight>,
+    /// Last board displayed, drawn under the dialogs.
+    last_board: Option<Board>,
+}
+
+impl RichTui {
+    fn theme(&self) -> &TuiTheme {
+        &THEMES[self.theme]
+    }
+
+    fn log(&mut self, message: impl Into<String>) {
+        if self.log.len() == LOG_LENGTH {
+            self.log.pop_front();
+        }
+        self.log.push_back(message.into());
+    }
+
+    fn current_title(&self) -> Option<&str> {
+        self.current
+            .and_then(|c| self.levels.get(c))
+            .map(|(title, _)| &title[..])
+    }
+
+    /// Draws the game screen with the `board`, the sidebar showing the `stats`, and the
+    /// `dialog` over them if any.
+    fn draw(
+        &mut self,
+        board: &Board,
+        stats: Stats,
+        help: &str,
+        dialog: Option<Dialog>,
+    ) -> Result<(), TuiError> {
+        let theme = *self.theme();
+        let title = self.current_title().unwrap_or("Sokoban").to_string();
+        let stats = match stats {
+            Stats::Game(board) => self.game_stats(board),
+            Stats::Replay(status) => status.into_iter().map(Line::from).collect(),
+        };
+        let levels: Vec<Line> = self
+            .levels
+            .iter()
+            .map(|(title, solved)| {
+                let mark = if *solved { SOLVED_MARK } else { ' ' };
+                Line::from(format!("{} {}", mark, title))
+            })
+            .collect();
+
+        let previous = self.layout;
+        let mut layout = None;
+        self.terminal
+            .draw(|frame| {
+                let [main, log_area] =
+                    Layout::vertical([Constraint::Min(0), Constraint::Length(LOG_ROWS)])
+                        .areas(frame.area());
+                let [board_area, sidebar] =
+                    Layout::horizontal([Constraint::Min(0), Constraint::Length(SIDEBAR_COLS)])
+                        .areas(main);
+                let [stats_area, levels_area] =
+                    Layout::vertical([Constraint::Length(STATS_ROWS), Constraint::Min(0)])
+                        .areas(sidebar);
+
+                let block = Block::bordered().title(format!(" {} ", title));
+                let inner = block.inner(board_area);
+                frame.render_widget(block, board_area);
+                layout = Some(render_board(
+                    frame.buffer_mut(),
+                    inner,
+                    &theme,
+                    board,
+                    previous.as_ref(),
+                    &self.highlighted,
+                ));
+
+                frame.render_widget(
+                    Paragraph::new(stats).block(Block::bordered().title(" Stats ")),
+                    stats_area,
+                );
+                let mut list_state = ListState::default().with_selected(self.current);
+                frame.render_stateful_widget(
+                    List::new(levels)
+                        .block(Block::bordered().title(" Levels "))
+                        .highlight_style(Style::new().add_modifier(Modifier::REVERSED)),
+                    levels_area,
+                    &mut list_state,
+                );
+
+                // Only the last messages which fit are shown.
+                let log_lines = usize::from(log_area.height.saturating_sub(2));
+                let log: Vec<Line> = self
+                    .log
+                    .iter()
+                    .skip(self.log.len().saturating_sub(log_lines))
+                    .map(|m| Line::from(&m[..]))
+                    .collect();
+                frame.render_widget(
+                    Paragraph::new(log).block(
+                        Block::bordered()
+                            .title(" Log ")
+                            .title_bottom(Line::from(help).centered()),
+                    ),
+                    log_area,
+                );
+
+                if let Some(dialog) = dialog {
+                    render_dialog(frame, &dialog);
+                }
+            })
+            .map_err(TuiError::IO)?;
+
+        self.layout = layout;
+        Ok(())
+    }
+
+    /// Time left to win the level, if it has a time limit.
+    fn time_left(&self) -> Option<Duration> {
+        let elapsed = self.started.map_or(Duration::ZERO, |s| s.elapsed());
+        self.countdown.map(|limit| limit.remaining(elapsed))
+    }
+
+    fn game_stats(&self, board: &Board) -> Vec<Line<'static>> {
+        let time = match self.time_left() {
+            Some(left) => format!("Left:   {}", format_duration(left)),
+            None => {
+                let time = self.started.map_or(Duration::ZERO, |s| s.elapsed());
+                format!("Time:   {}", format_duration(time))
+            }
+        };
+        // The counters are shown against the par in the move-limit challenge.
+        let (mut moves, mut pushes) = match self.challenge {
+            Some(limit) => (
+                format!("Moves:  {}/{}", board.moves(), limit.moves),
+                format!("Pushes: {}/{}", board.pushes(), limit.pushes),
+            ),
+            None => (
+                format!("Moves:  {}", board.moves()),
+                format!("Pushes: {}", board.pushes()),
+            ),
+        };
+        // Followed by the ones of each player in the hotseat mode.
+        if let Some(hotseat) = self.hotseat {
+            let (first, second) = (hotseat.counters(0), hotseat.counters(1));
+            moves.push_str(&format!(" (P1 {}, P2 {})", first.moves, second.moves));
+            pushes.push_str(&format!(" (P1 {}, P2 {})", first.pushes, second.pushes));
+        }
+        vec![
+            Line::from(moves),
+            Line::from(pushes),
+            Line::from(format!(
+                "Crates: {}/{}",
+                board.placed_crates(),
+                board.crate_count()
+            )),
+            Line::from(time),
+            Line::from(match (self.streak, self.hotseat, self.speedrun) {
+                (_, Some(hotseat), _) => format!("Turn:   Player {}", hotseat.turn() + 1),
+                (_, None, Some(stopwatch)) => {
+                    format!("Run:    {}", format_duration_millis(stopwatch.elapsed()))
+                }
+                (Some(streak), None, None) => format!("Streak: {}", streak),
+                (None, None, None) => String::new(),
+            }),
+            Line::from(format!("Theme:  {}", self.theme().name)),
+        ]
+    }
+
+    /// Draws the level list with the one in `state` selected, and its preview on the right.
+    fn draw_browser(
+        &mut self,
+        library: &LevelLibrary,
+        state: &mut ListState,
+    ) -> Result<(), TuiError> {
+        let theme = *self.theme();
+        let entries: Vec<Line> = library
+            .levels()
+            .iter()
+            .map(|l| {
+                let mark = if l.is_solved() { SOLVED_MARK } else { ' ' };
+                let challenge = if l.is_challenge() { CHALLENGE_MARK } else { "" };
+                Line::from(format!(
+                    "{} {} ({}){}",
+                    mark,
+                    l.title(),
+                    l.difficulty(),
+                    challenge
+                ))
+            })
+            .collect();
+        let list_cols = entries.iter().map(Line::width).max().unwrap_or(0) as u16 + 2;
+
+        self.terminal
+            .draw(|frame| {
+                let [list_area, preview_area] = Layout::horizontal([
+                    Constraint::Max(list_cols.max(SIDEBAR_COLS)),
+                    Constraint::Min(0),
+                ])
+                .areas(frame.area());
+
+                frame.render_stateful_widget(
+                    List::new(entries)
+                        .block(
+                            Block::bordered()
+                                .title(" Levels ")
+                                .title_bottom(Line::from(BROWSER_HELP).centered()),
+                        )
+                        .highlight_style(Style::new().add_modifier(Modifier::REVERSED)),
+                    list_area,
+                    state,
+                );
+
+                let block = Block::bordered().title(" Preview ");
+                let inner = block.inner(preview_area);
+                frame.render_widget(block, preview_area);
+                if let Some(level) = state.selected().and_then(|s| library.get(s)) {
+                    render_board(frame.buffer_mut(), inner, &theme, level.board(), None, &[]);
+                }
+            })
+            .map_err(TuiError::IO)?;
+
+        Ok(())
+    }
+
+    /// Looks for the next push to do, and highlights the path to walk to the crate and where to
+    /// push it.
+    fn show_hint(&mut self, board: &Board) -> Result<(), TuiError> {
+        self.log("Looking for a hint...");
+        self.draw(board, Stats::Game(board), GAME_HELP, None)?;
+
+        match board.hint(&SolverLimits::default()) {
+            Ok(Some(hint)) => {
+                let arrow = HINT_ARROWS
+                    .iter()
+                    .find(|(dir, _)| *dir == hint.push)
+                    .map(|(_, arrow)| *arrow);
+                let (i, j) = hint.crate_pos;
+                self.highlighted = hint
+                    .path
+                    .iter()
+                    .map(|cell| Highlight {
+                        cell: *cell,
+                        symbol: None,
+                        bg: HINT_PATH_COLOR,
+                    })
+                    .chain([
+                        Highlight {
+                            cell: hint.crate_pos,
+                            symbol: None,
+                            bg: HINT_PUSH_COLOR,
+                        },
+                        Highlight {
+                            cell: hint.push.to_coords(i, j),
+                            symbol: arrow,
+                            bg: HINT_PUSH_COLOR,
+                        },
+                    ])
+                    .collect();
+                self.log("Hint: walk along the blue path, then push the crate along the arrow.");
+            }
+            Ok(None) => self.log("No hint: the level is already solved."),
+            Err(err) => self.log(err.to_string()),
+        }
+        self.draw(board, Stats::Game(board), GAME_HELP, None)
+    }
+}
+
+impl Ui for RichTui {
+    fn initialize() -> Result<Self, Box<dyn Error>> {
+        // This also restores the terminal on panic.
+        let terminal = ratatui::try_init().map_err(TuiError::IO)?;
+
+        let theme = env::var(THEME_ENV_VAR)
+            .ok()
+            .and_then(|name| TuiTheme::by_name(&name))
+            .unwrap_or(0);
+
+        Ok(RichTui {
+            terminal,
+            theme,
+            levels: Vec::new(),
+            current: None,
+            started: None,
+            countdown: None,
+            challenge: None,
+            streak: None,
+            hotseat: None,
+            speedrun: None,
+            log: VecDeque::new(),
+            layout: None,
+            highlighted: Vec::new(),
+            last_board: None,
+        })
+    }
+
+    fn cleanup(self: Box<Self>) -> Result<(), Box<dyn Error>> {
+        ratatui::try_restore().map_err(TuiError::IO)?;
+        Ok(())
+    }
+
+    fn select_level(
+        &mut self,
+        library: &mut LevelLibrary,
+    ) -> Result<Option<Selection>, Box<dyn Error>> {
+        self.levels = library
+            .levels()
+            .iter()
+            .map(|l| (l.title().to_string(), l.is_solved()))
+            .collect();
+        self.started = None;
+        self.layout = None;
+
+        // No need to choose if there is only one level, unless packs can be downloaded.
+        if library.len() <= 1 && cfg!(not(feature = "repo")) {
+            self.current = library.first_unsolved();
+            return Ok(self.current.map(Selection::Level));
+        }
+
+        let mut state = ListState::default().with_selected(library.first_unsolved().or(Some(0)));
+        let selected = loop {
+            self.draw_browser(library, &mut state)?;
+
+            match event::read().map_err(TuiError::IO)? {
+                Event::Key(KeyEvent {
+                    modifiers: KeyModifiers::NONE,
+                    code,
+                    ..
+                }) => match code {
+                    KeyCode::Esc | KeyCode::Char('q') => break None,
+                    KeyCode::Enter => break state.selected().map(Selection::Level),
+                    KeyCode::Char('e') => break Some(Selection::Endless),
+                    KeyCode::Up => state.select_previous(),
+                    KeyCode::Down => {
+                        // The list doesn't know how many levels there are.
+                        if state.selected() < Some(library.len() - 1) {
+                            state.select_next();
+                        }
+                    }
+                    #[cfg(feature = "repo")]
+                    KeyCode::Char('c') => {
+                        let pack = super::tui_packs::browse_packs()?;
+                        // The browser was drawn over the whole terminal.
+                        self.terminal.clear().map_err(TuiError::IO)?;
+                        if let Some(pack) = pack {
+                            *library = pack;
+                            return self.select_level(library);
+                        }
+                    }
+                    KeyCode::Char('m') => {
+                        if let Some(selected) = state.selected() {
+                            library.toggle_challenge(selected);
+                        }
+                    }
+                    KeyCode::Char('p') => {
+                        let level = state.selected().and_then(|s| library.get(s));
+                        if let Some(level) = level.filter(|l| l.best_replay().is_some()) {
+                            self.current = state.selected();
+                            let replay = level.best_replay().expect("Checked just before.");
+                            self.watch_replay(level.board(), replay, None)?;
+                        }
+                    }
+                    _ => (),
+                },
+                Event::Key(KeyEvent {
+                    modifiers: KeyModifiers::CONTROL,
+                    code: KeyCode::Char('c'),
+                    ..
+                }) => break None,
+                _ => (),
+            }
+        };
+
+        self.current = match selected {
+            Some(Selection::Level(index)) => Some(index),
+            _ => None,
+        };
+        Ok(selected)
+    }
+
+    fn get_action(&mut self, board: &Board) -> Result<Action, Box<dyn Error>> {
+        let action = loop {
+            // While waiting for the player, the time is refreshed every tick, and checked as
+            // soon as it runs out.
+            let tick = self.time_left().map_or(TICK, |left| left.min(TICK));
+            if !event::poll(tick).map_err(TuiError::IO)? {
+                if self.time_left() == Some(Duration::ZERO) {
+                    self.log("Time's up!");
+                    break Action::TimeOut;
+                }
+                self.draw(board, Stats::Game(board), GAME_HELP, None)?;
+                continue;
+            }
+
+            match event::read().map_err(TuiError::IO)? {
+                Event::Resize(_, _) => self.draw(board, Stats::Game(board), GAME_HELP, None)?,
+                Event::Key(KeyEvent {
+                    modifiers: KeyModifiers::NONE,
+                    code,
+                    ..
+                }) => match code {
+                    KeyCode::Esc | KeyCode::Char('q') => break Action::Quit,
+                    KeyCode::Char('r') => {
+                        self.log("Level reset.");
+                        break Action::ResetLevel;
+                    }
+                    KeyCode::Char('h') => self.show_hint(board)?,
+                    KeyCode::Char('t') => {
+                        self.theme = (self.theme + 1) % THEMES.len();
+                        self.log(format!("Theme: {}", self.theme().name));
+                        self.draw(board, Stats::Game(board), GAME_HELP, None)?;
+                    }
+                    KeyCode::Left => break Action::Movement(Direction::Left),
+                    KeyCode::Right => break Action::Movement(Direction::Right),
+                    KeyCode::Up => break Action::Movement(Direction::Up),
+                    KeyCode::Down => break Action::Movement(Direction::Down),
+                    _ => (),
+                },
+                Event::Key(KeyEvent {
+                    modifiers: KeyModifiers::CONTROL,
+                    code: KeyCode::Char('c'),
+                    ..
+                }) => break Action::Quit,
+                _ => (),
+            }
+        };
+        Ok(action)
+    }
+
+    fn display(
+        &mut self,
+        board: &Board,
+        last_move_result: Option<Option<(u32, u32)>>,
+    ) -> Result<(), Box<dyn Error>> {
+        if self.started.is_none() {
+            self.started = Some(Instant::now());
+            let message = match self.current_title() {
+                Some(title) => format!("Playing {}.", title),
+                None => "Playing.".to_string(),
+            };
+            self.log(message);
+        }
+
+        if let Some(Some((i, j))) = last_move_result {
+            if board.get(i, j).1 == CellKind::Target {
+                self.log(format!(
+                    "Crate placed, {}/{}.",
+                    board.placed_crates(),
+                    board.crate_count()
+                ));
+            }
+        }
+        if last_move_result.is_some() {
+            self.highlighted.clear();
+        }
+
+        self.draw(board, Stats::Game(board), GAME_HELP, None)?;
+        self.last_board = Some(board.clone());
+        Ok(())
+    }
+
+    fn watch_replay(
+        &mut self,
+        board: &Board,
+        replay: &Replay,
+        speed: Option<u32>,
+    ) -> Result<(), Box<dyn Error>> {
+        let steps = replay.steps();
+        let mut current = board.clone();
+        let mut position = 0;
+        let mut invalid = false;
+        let mut playing = true;
+        let mut speed = initial_replay_speed(speed);
+        self.layout = None;
+        self.highlighted.clear();
+        self.log(format!("Watching a replay of {} steps.", steps.len()));
+
+        loop {
+            let status = vec![
+                format!("Step:  {}/{}", position, steps.len()),
+                format!("Speed: x{}", 1 << speed),
+                if invalid {
+                    "Invalid move!"
+                } else if playing {
+                    "Playing"
+                } else {
+                    "Paused"
+                }
+                .to_string(),
+            ];
+            self.draw(&current, Stats::Replay(status), REPLAY_HELP, None)?;
+
+            let mut step_forward = false;
+            let mut step_back = false;
+
+            let delay = Duration::from_millis(REPLAY_SPEEDS_MILLIS[speed]);
+            if playing && !event::poll(delay).map_err(TuiError::IO)? {
+                step_forward = true;
+            } else {
+                match event::read().map_err(TuiError::IO)? {
+                    Event::Key(KeyEvent {
+                        modifiers: KeyModifiers::NONE,
+                        code,
+                        ..
+                    }) => match code {
+                        KeyCode::Esc | KeyCode::Char('q') => break,
+                        KeyCode::Char(' ') => playing = !playing,
+                        KeyCode::Right => {
+                            playing = false;
+                            step_forward = true;
+                        }
+                        KeyCode::Left => {
+                            playing = false;
+                            step_back = true;
+                        }
+                        KeyCode::Char('+') => {
+                            speed = (speed + 1).min(REPLAY_SPEEDS_MILLIS.len() - 1)
+                        }
+                        KeyCode::Char('-') => speed = speed.saturating_sub(1),
+                        _ => (),
+                    },
+                    Event::Key(KeyEvent {
+                        modifiers: KeyModifiers::CONTROL,
+                        code: KeyCode::Char('c'),
+                        ..
+                    }) => break,
+                    _ => (),
+                }
+            }
+
+            if step_forward && !invalid && position < steps.len() {
+                match current.do_move_player(steps[position].dir) {
+                    Some(_) => position += 1,
+                    None => {
+                        invalid = true;
+                        self.log(format!("Step {} can't be played.", position + 1));
+                    }
+                }
+            } else if step_back && position > 0 {
+                // Plays everything again from the start, as moves can't be undone.
+                position -= 1;
+                invalid = false;
+                current = board.clone();
+                for step in &steps[..position] {
+                    current.do_move_player(step.dir);
+                }
+            }
+
+            if position == steps.len() || invalid {
+                playing = false;
+            }
+        }
+
+        self.layout = None;
+        Ok(())
+    }
+
+    fn won(&mut self, score: &Score, best: Option<&Score>) -> Result<WonChoice, Box<dyn Error>> {
+        let mut lines = vec![
+            format!("Moves:  {}", score.moves),
+            format!("Pushes: {}", score.pushes),
+            format!("Time:   {}", format_duration(score.time)),
+        ];
+        match best {
+            Some(best) if !score.is_better_than(best) => lines.push(format!(
+                "Best:   {} moves, {} pushes, {}",
+                best.moves,
+                best.pushes,
+                format_duration(best.time)
+            )),
+            _ => lines.push("New personal best!".to_string()),
+        }
+
+        let message = format!(
+            "Solved in {} moves and {} pushes.",
+            score.moves, score.pushes
+        );
+        self.log(message);
+        if let Some(level) = self.current.and_then(|c| self.levels.get_mut(c)) {
+            level.1 = true;
+        }
+
+        let board = self.last_board.take().unwrap_or_else(|| Board::empty(0, 0));
+        let labels = WON_CHOICES.map(|(_, label)| label);
+        let mut selected = WON_CHOICES
+            .iter()
+            .position(|(c, _)| *c == WonChoice::Next)
+            .unwrap_or(0);
+        let choice = loop {
+            let dialog = Dialog {
+                title: " You won! ",
+                lines: &lines,
+                choices: &labels,
+                selected,
+            };
+            self.draw(&board, Stats::Game(&board), GAME_HELP, Some(dialog))?;
+
+            match event::read().map_err(TuiError::IO)? {
+                Event::Key(KeyEvent {
+                    modifiers: KeyModifiers::NONE,
+                    code,
+                    ..
+                }) => match code {
+                    KeyCode::Esc | KeyCode::Char('q') => break WonChoice::Quit,
+                    KeyCode::Char('r') => break WonChoice::Restart,
+                    KeyCode::Char('n') => break WonChoice::Next,
+                    KeyCode::Enter => break WON_CHOICES[selected].0,
+                    KeyCode::Left => selected = selected.saturating_sub(1),
+                    KeyCode::Right | KeyCode::Tab => selected = (selected + 1) % WON_CHOICES.len(),
+                    _ => (),
+                },
+                Event::Key(KeyEvent {
+                    modifiers: KeyModifiers::CONTROL,
+                    code: KeyCode::Char('c'),
+                    ..
+                }) => break WonChoice::Quit,
+                _ => (),
+            }
+        };
+
+        // The game goes on with the same level or the next one, unless a new one is selected.
+        self.started = None;
+        self.countdown = None;
+        self.challenge = None;
+        self.layout = None;
+        if choice == WonChoice::Next {
+            self.current = self
+                .current
+                .map(|c| c + 1)
+                .filter(|c| *c < self.levels.len());
+        }
+        Ok(choice)
+    }
+
+    fn start_countdown(&mut self, limit: Option<TimeLimit>) {
+        // The time is counted from the first display of the level.
+        self.started = None;
+        self.countdown = limit;
+    }
+
+    fn start_challenge(&mut self, limit: Option<MoveLimit>) {
+        self.challenge = limit;
+    }
+
+    fn show_streak(&mut self, streak: Option<u32>) {
+        self.streak = streak;
+    }
+
+    fn show_turn(&mut self, hotseat: Option<&Hotseat>) {
+        self.hotseat = hotseat.copied();
+    }
+
+    fn show_speedrun(&mut self, stopwatch: Option<Stopwatch>) {
+        self.speedrun = stopwatch;
+    }
+
+    fn show_message(&mut self, message: &str) -> Result<(), Box<dyn Error>> {
+        self.log(message);
+        if let Some(board) = self.last_board.clone() {
+            self.draw(&board, Stats::Game(&board), GAME_HELP, None)?;
+        }
+        Ok(())
+    }
+
+    fn lost(&mut self, defeat: &Defeat) -> Result<LostChoice, Box<dyn Error>> {
+        let lines = vec![defeat.to_string()];
+        let title = format!(" {} ", defeat.title());
+
+        let board = self.last_board.take().unwrap_or_else(|| Board::empty(0, 0));
+        let labels = LOST_CHOICES.map(|(_, label)| label);
+        let mut selected = 0;
+        let choice = loop {
+            let dialog = Dialog {
+                title: &title,
+                lines: &lines,
+                choices: &labels,
+                selected,
+            };
+            self.draw(&board, Stats::Game(&board), GAME_HELP, Some(dialog))?;
+
+            match event::read().map_err(TuiError::IO)? {
+                Event::Key(KeyEvent {
+                    modifiers: KeyModifiers::NONE,
+                    code,
+                    ..
+                }) => match code {
+                    KeyCode::Esc | KeyCode::Char('q') => break LostChoice::Quit,
+                    KeyCode::Char('r') => break LostChoice::Retry,
+                    KeyCode::Enter => break LOST_CHOICES[selected].0,
+                    KeyCode::Left => selected = selected.saturating_sub(1),
+                    KeyCode::Right | KeyCode::Tab => selected = (selected + 1) % LOST_CHOICES.len(),
+                    _ => (),
+                },
+                Event::Key(KeyEvent {
+                    modifiers: KeyModifiers::CONTROL,
+                    code: KeyCode::Char('c'),
+                    ..
+                }) => break LostChoice::Quit,
+                _ => (),
+            }
+        };
+
+        self.started = None;
+        self.countdown = None;
+        self.challenge = None;
+        self.layout = None;
+        Ok(choice)
+    }
+}
+
+fn glyph_style(glyph: Glyph) -> Style {
+    Style::new().fg(glyph.fg.into()).bg(glyph.bg.into())
+}
+
+/// Draws the part of the `board` fitting in the `area`, scrolled from the `previous` layout,
+/// and returns where it was drawn.
+fn render_board(
+    buf: &mut Buffer,
+    area: Rect,
+    theme: &TuiTheme,
+    board: &Board,
+    previous: Option<&BoardLayout>,
+    highlighted: &[Highlight],
+) -> BoardLayout {
+    let layout = BoardLayout::new(
+        (board.width(), board.height()),
+        ((area.x, area.y), (area.width, area.height)),
+        board.player(),
+        previous,
+    );
+
+    for (i, j) in layout.visible_cells() {
+        let Some((col, row)) = layout.cell_pos(i, j) else {
+            continue;
+        };
+        let mut glyph = theme.glyph(board.get(i, j));
+        let mut style = glyph_style(glyph);
+        if let Some(highlight) = highlighted.iter().find(|h| h.cell == (i, j)) {
+            glyph.symbol = highlight.symbol.unwrap_or(glyph.symbol);
+            style = style.bg(highlight.bg);
+        }
+
+        let fill = layout.wide.then(|| glyph.wide_fill());
+        for (col, glyph) in [(col, Some(glyph)), (col + 1, fill)] {
+            if let (Some(cell), Some(glyph)) = (buf.cell_mut((col, row)), glyph) {
+                cell.set_char(glyph.symbol).set_style(style);
+            }
+        }
+    }
+    layout
+}
+
+/// Draws the `dialog` in the middle of the screen, its selected choice being highlighted.
+fn render_dialog(frame: &mut Frame, dialog: &Dialog) {
+    let choices: Vec<Span> = dialog
+        .choices
+        .iter()
+        .enumerate()
+        .flat_map(|(index, label)| {
+            let style = if index == dialog.selected {
+                Style::new().add_modifier(Modifier::REVERSED)
+            } else {
+                Style::new()
+            };
+            [Span::raw(" "), Span::styled(format!("[{}]", label), style)]
+        })
+        .skip(1)
+        .collect();
+    let choices = Line::from(choices);
+
+    let mut text: Vec<Line> = dialog.lines.iter().map(|l| Line::from(&l[..])).collect();
+    text.push(Line::from(""));
+    text.push(choices.centered());
+
+    // Text, padding and borders.
+    let cols = text.iter().map(Line::width).max().unwrap_or(0) as u16 + 6;
+    let rows = text.len() as u16 + 2;
+    let [area] = Layout::horizontal([Constraint::Length(cols)])
+        .flex(Flex::Center)
+        .areas(frame.area());
+    let [area] = Layout::vertical([Constraint::Length(rows)])
+        .flex(Flex::Center)
+        .areas(area);
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(text).block(
+            Block::bordered()
+                .title(dialog.title)
+                .padding(Padding::horizontal(2)),
+        ),
+        area,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::{buffer::Buffer, layout::Rect, style::Color};
+
+    use super::{render_board, Board, Highlight, THEMES};
+
+    const TEST_XSB: &str = "#####
+#@$.#
+#####
+";
+
+    #[test]
+    fn it_renders_the_board_in_the_middle_of_the_area() {
+        let board = Board::from_xsb(TEST_XSB).unwrap();
+        let area = Rect::new(0, 0, 12, 5);
+        let mut buf = Buffer::empty(area);
+        // The classic theme only uses ASCII.
+        let highlighted = [Highlight {
+            cell: (2, 1),
+            symbol: Some('>'),
+            bg: Color::Yellow,
+        }];
+        let layout = render_board(&mut buf, area, &THEMES[1], &board, None, &highlighted);
+
+        assert!(layout.wide);
+        assert_eq!(layout.origin, (1, 1));
+        let row: String = (0..12).map(|x| buf[(x, 2)].symbol()).collect();
+        assert_eq!(row, " ##P > x ## ");
+        assert_eq!(buf[(5, 2)].bg, Color::Yellow);
+    }
+}
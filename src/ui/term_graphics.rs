@@ -0,0 +1,327 @@
+//! Pictures drawn in the terminal through the graphics protocols some terminals support, for
+//! the [terminal graphics interface](`super::tui_graphics`).
+//!
+//! The pictures are first scaled to the pixels of the cells they cover. The kitty protocol
+//! then sends the raw pixels, while the sixel one, older and more widespread, reduces their
+//! colors to a palette.
+
+#[cfg(feature = "term_graphics")]
+use std::env;
+use std::fmt::Write;
+
+/// Environment variable forcing the protocol used: `sixel`, `kitty`, or anything else to draw
+/// the board with characters. The protocol is guessed from the terminal if it is empty.
+#[cfg(feature = "term_graphics")]
+pub const GRAPHICS_ENV_VAR: &str = "SOKOBAN_TERM_GRAPHICS";
+/// Largest payload of a kitty escape sequence, the picture being split in several ones.
+const KITTY_CHUNK_SIZE: usize = 4096;
+/// Levels of each color component in the sixel palette, so `6 * 6 * 6` colors.
+const SIXEL_LEVELS: u32 = 6;
+/// Under this alpha, a pixel is left transparent.
+const ALPHA_THRESHOLD: u8 = 128;
+const BASE64_CHARS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Picture in RGBA pixels.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Picture {
+    pub width: u32,
+    pub height: u32,
+    /// Rows of pixels from the top, 4 bytes per pixel.
+    pub pixels: Vec<u8>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GraphicsProtocol {
+    Sixel,
+    Kitty,
+}
+
+impl GraphicsProtocol {
+    /// Protocol given by [`GRAPHICS_ENV_VAR`], or else guessed from the terminal. `None` if
+    /// the terminal can't show pictures.
+    #[cfg(feature = "term_graphics")]
+    pub fn detect() -> Option<Self> {
+        if let Some(forced) = env::var(GRAPHICS_ENV_VAR)
+            .ok()
+            .and_then(|name| GraphicsProtocol::parse(&name))
+        {
+            return forced;
+        }
+
+        let term = env::var("TERM").unwrap_or_default();
+        let program = env::var("TERM_PROGRAM").unwrap_or_default();
+        if term.contains("kitty") || ["WezTerm", "ghostty"].contains(&&program[..]) {
+            Some(GraphicsProtocol::Kitty)
+        } else if term.contains("sixel")
+            || ["foot", "mlterm", "contour"]
+                .iter()
+                .any(|t| term.starts_with(t))
+        {
+            Some(GraphicsProtocol::Sixel)
+        } else {
+            None
+        }
+    }
+
+    /// Protocol forced by the `name` given in [`GRAPHICS_ENV_VAR`], `Some(None)` to draw with
+    /// characters, or `None` if it is empty so the protocol is guessed from the terminal.
+    fn parse(name: &str) -> Option<Option<Self>> {
+        match &name.trim().to_lowercase()[..] {
+            "" => None,
+            "sixel" => Some(Some(GraphicsProtocol::Sixel)),
+            "kitty" => Some(Some(GraphicsProtocol::Kitty)),
+            _ => Some(None),
+        }
+    }
+
+    /// Escape sequences drawing the `picture` from the cursor, over `cells` columns and rows
+    /// of `cell_size` pixels. The cursor is left where it was with kitty, and after the
+    /// picture with sixel.
+    pub fn encode(self, picture: &Picture, cells: (u16, u16), cell_size: (u32, u32)) -> String {
+        let picture = scaled(
+            picture,
+            u32::from(cells.0) * cell_size.0,
+            u32::from(cells.1) * cell_size.1,
+        );
+        match self {
+            GraphicsProtocol::Kitty => kitty(&picture, cells),
+            GraphicsProtocol::Sixel => sixel(&picture),
+        }
+    }
+
+    /// Escape sequence removing the pictures drawn before, when text printed over them
+    /// doesn't.
+    pub fn clear(self) -> &'static str {
+        match self {
+            GraphicsProtocol::Kitty => "\x1b_Ga=d,d=a,q=2\x1b\\",
+            GraphicsProtocol::Sixel => "",
+        }
+    }
+}
+
+/// Sends the RGBA pixels of the `picture`, placed over the `cells`.
+fn kitty(picture: &Picture, (cols, rows): (u16, u16)) -> String {
+    let data = base64(&picture.pixels);
+    let chunks: Vec<&[u8]> = data.as_bytes().chunks(KITTY_CHUNK_SIZE).collect();
+
+    let mut res = String::new();
+    for (index, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(index + 1 < chunks.len());
+        // `q=2` keeps the terminal from answering, which would be read as keys.
+        if index == 0 {
+            let _ = write!(
+                res,
+                "\x1b_Ga=T,f=32,s={},v={},c={cols},r={rows},C=1,q=2,m={more};",
+                picture.width, picture.height
+            );
+        } else {
+            let _ = write!(res, "\x1b_Gm={more};");
+        }
+        // Base 64 is only made of ASCII.
+        res.extend(chunk.iter().map(|b| *b as char));
+        res.push_str("\x1b\\");
+    }
+    res
+}
+
+/// Draws the `picture` pixel for pixel, its transparent pixels leaving what was under.
+fn sixel(picture: &Picture) -> String {
+    let (width, height) = (picture.width as usize, picture.height as usize);
+    let colors: Vec<Option<usize>> = picture.pixels.chunks(4).map(palette_index).collect();
+
+    // The second parameter keeps the pixels which aren't drawn transparent.
+    let mut res = format!("\x1bP0;1;0q\"1;1;{width};{height}");
+    let palette_size = SIXEL_LEVELS.pow(3) as usize;
+    let mut used = vec![false; palette_size];
+    for color in colors.iter().flatten() {
+        used[*color] = true;
+    }
+    for color in (0..palette_size).filter(|c| used[*c]) {
+        let [r, g, b] = palette_color(color);
+        let _ = write!(res, "#{color};2;{r};{g};{b}");
+    }
+
+    // Each character gives 6 pixels of a column, drawn in a color at a time.
+    for band in (0..height).step_by(6) {
+        let rows = band..(band + 6).min(height);
+        let mut band_colors: Vec<usize> = rows
+            .clone()
+            .flat_map(|y| colors[y * width..(y + 1) * width].iter().flatten().copied())
+            .collect();
+        band_colors.sort_unstable();
+        band_colors.dedup();
+
+        for color in band_colors {
+            let _ = write!(res, "#{color}");
+            let mut run = (0, '?');
+            for x in 0..width {
+                let bits = rows
+                    .clone()
+                    .filter(|y| colors[y * width + x] == Some(color))
+                    .fold(0, |bits, y| bits | 1 << (y - band));
+                let c = char::from(63 + bits as u8);
+                if c == run.1 {
+                    run.0 += 1;
+                } else {
+                    push_run(&mut res, run);
+                    run = (1, c);
+                }
+            }
+            push_run(&mut res, run);
+            res.push('$');
+        }
+        res.push('-');
+    }
+
+    res.push_str("\x1b\\");
+    res
+}
+
+/// Prints the sixel character `c` repeated `count` times.
+fn push_run(res: &mut String, (count, c): (usize, char)) {
+    if count > 3 {
+        let _ = write!(res, "!{count}{c}");
+    } else {
+        res.extend(std::iter::repeat_n(c, count));
+    }
+}
+
+/// Color of the sixel palette closest to the RGBA `pixel`, `None` if it is transparent.
+fn palette_index(pixel: &[u8]) -> Option<usize> {
+    if pixel[3] < ALPHA_THRESHOLD {
+        return None;
+    }
+    let level = |v: u8| (u32::from(v) * (SIXEL_LEVELS - 1) + 127) / 255;
+    Some(
+        (level(pixel[0]) * SIXEL_LEVELS * SIXEL_LEVELS
+            + level(pixel[1]) * SIXEL_LEVELS
+            + level(pixel[2])) as usize,
+    )
+}
+
+/// Components of a color of the sixel palette, in percents.
+fn palette_color(index: usize) -> [u32; 3] {
+    let index = index as u32;
+    let percent = |level: u32| level * 100 / (SIXEL_LEVELS - 1);
+    [
+        percent(index / (SIXEL_LEVELS * SIXEL_LEVELS)),
+        percent(index / SIXEL_LEVELS % SIXEL_LEVELS),
+        percent(index % SIXEL_LEVELS),
+    ]
+}
+
+/// Copy of the `picture` stretched to `width` and `height`, keeping crisp pixels.
+fn scaled(picture: &Picture, width: u32, height: u32) -> Picture {
+    let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+    for y in 0..height {
+        let src_y = y * picture.height / height;
+        for x in 0..width {
+            let start = ((src_y * picture.width + x * picture.width / width) * 4) as usize;
+            pixels.extend_from_slice(&picture.pixels[start..start + 4]);
+        }
+    }
+    Picture {
+        width,
+        height,
+        pixels,
+    }
+}
+
+fn base64(data: &[u8]) -> String {
+    let mut res = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for index in 0..4 {
+            if index <= chunk.len() {
+                res.push(BASE64_CHARS[(n >> (18 - 6 * index) & 0x3f) as usize] as char);
+            } else {
+                res.push('=');
+            }
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{base64, kitty, scaled, sixel, GraphicsProtocol, Picture, KITTY_CHUNK_SIZE};
+
+    fn picture(width: u32, height: u32, pixel: [u8; 4]) -> Picture {
+        Picture {
+            width,
+            height,
+            pixels: pixel.repeat((width * height) as usize),
+        }
+    }
+
+    #[test]
+    fn it_encodes_base64() {
+        assert_eq!(base64(b"Man"), "TWFu");
+        assert_eq!(base64(b"Ma"), "TWE=");
+        assert_eq!(base64(b"M"), "TQ==");
+        assert_eq!(base64(b""), "");
+    }
+
+    #[test]
+    fn it_splits_kitty_pictures_in_chunks() {
+        let res = kitty(&picture(64, 64, [255, 0, 0, 255]), (4, 2));
+        assert!(res.starts_with("\x1b_Ga=T,f=32,s=64,v=64,c=4,r=2,C=1,q=2,m=1;"));
+        assert!(res.ends_with("\x1b\\"));
+        // 64 * 64 * 4 bytes take 21848 characters in base 64.
+        assert_eq!(
+            res.matches("\x1b_G").count(),
+            21848usize.div_ceil(KITTY_CHUNK_SIZE)
+        );
+        assert_eq!(res.matches("m=0;").count(), 1);
+    }
+
+    #[test]
+    fn it_encodes_sixels_with_runs_and_transparency() {
+        let mut red = picture(8, 2, [255, 0, 0, 255]);
+        // The last column is transparent.
+        red.pixels[7 * 4 + 3] = 0;
+        red.pixels[15 * 4 + 3] = 0;
+        // Pure red is the color 5 * 36 of the palette, and each column but the last one has
+        // its 2 pixels set.
+        assert_eq!(
+            sixel(&red),
+            "\x1bP0;1;0q\"1;1;8;2#180;2;100;0;0#180!7B?$-\x1b\\"
+        );
+    }
+
+    #[test]
+    fn it_scales_pictures_to_the_cells() {
+        let mut two = picture(2, 1, [0, 0, 0, 255]);
+        two.pixels[4..8].copy_from_slice(&[255, 255, 255, 255]);
+        let res = scaled(&two, 4, 2);
+        assert_eq!((res.width, res.height), (4, 2));
+        assert_eq!(&res.pixels[4..8], [0, 0, 0, 255]);
+        assert_eq!(&res.pixels[8..12], [255, 255, 255, 255]);
+
+        let sixel_res = GraphicsProtocol::Sixel.encode(&two, (2, 1), (2, 6));
+        assert!(sixel_res.contains("\"1;1;4;6"));
+    }
+
+    #[test]
+    fn it_picks_the_protocol_given() {
+        assert_eq!(
+            GraphicsProtocol::parse("Kitty"),
+            Some(Some(GraphicsProtocol::Kitty))
+        );
+        assert_eq!(
+            GraphicsProtocol::parse("sixel"),
+            Some(Some(GraphicsProtocol::Sixel))
+        );
+        assert_eq!(GraphicsProtocol::parse("none"), Some(None));
+        assert_eq!(GraphicsProtocol::parse(" "), None);
+
+        // Sixels are simply overwritten.
+        assert_eq!(GraphicsProtocol::Sixel.clear(), "");
+        assert!(GraphicsProtocol::Kitty.clear().starts_with("\x1b_Ga=d"));
+    }
+}
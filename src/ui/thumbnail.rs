@@ -0,0 +1,148 @@
+//! Small pictures of the levels, each cell being a square of plain color, to show them in the
+//! level lists.
+
+use super::{Board, BoardElem, CellKind, MovableItem};
+
+/// RGBA colors of the cells.
+const VOID_COLOR: [u8; 4] = [0, 0, 0, 0];
+const WALL_COLOR: [u8; 4] = [110, 90, 80, 255];
+const FLOOR_COLOR: [u8; 4] = [220, 220, 210, 255];
+const TARGET_COLOR: [u8; 4] = [220, 70, 60, 255];
+const CRATE_COLOR: [u8; 4] = [200, 140, 40, 255];
+const PLACED_CRATE_COLOR: [u8; 4] = [70, 170, 60, 255];
+const PLAYER_COLOR: [u8; 4] = [50, 90, 220, 255];
+
+/// Picture of a board, in RGBA pixels.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Thumbnail {
+    pub width: u32,
+    pub height: u32,
+    /// Rows of pixels from the top, 4 bytes per pixel.
+    pub pixels: Vec<u8>,
+}
+
+impl Thumbnail {
+    /// Draws each cell of the `board` as a square of `cell_size` pixels.
+    pub fn render(board: &Board, cell_size: u32) -> Self {
+        let cell_size = cell_size.max(1);
+        let (width, height) = (board.width() * cell_size, board.height() * cell_size);
+
+        let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                pixels.extend(cell_color(board.get(x / cell_size, y / cell_size)));
+            }
+        }
+
+        Thumbnail {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Puts the `thumbnails` in a grid of `columns`, each centered in a cell of the size of the
+    /// largest ones, with `padding` pixels around the cells.
+    #[cfg(any(feature = "thumbnail", test))]
+    pub fn sheet(thumbnails: &[Thumbnail], columns: usize, padding: u32) -> Self {
+        let columns = columns.clamp(1, thumbnails.len().max(1));
+        let rows = thumbnails.len().div_ceil(columns);
+        let cell_width = thumbnails.iter().map(|t| t.width).max().unwrap_or(0) + padding;
+        let cell_height = thumbnails.iter().map(|t| t.height).max().unwrap_or(0) + padding;
+        let width = cell_width * columns as u32 + padding;
+        let height = cell_height * rows as u32 + padding;
+
+        let mut pixels: Vec<u8> = VOID_COLOR
+            .into_iter()
+            .cycle()
+            .take((width * height * 4) as usize)
+            .collect();
+        for (index, thumbnail) in thumbnails.iter().enumerate() {
+            let (column, row) = ((index % columns) as u32, (index / columns) as u32);
+            let left = padding + column * cell_width + (cell_width - padding - thumbnail.width) / 2;
+            let top = padding + row * cell_height + (cell_height - padding - thumbnail.height) / 2;
+            let row_bytes = (thumbnail.width * 4) as usize;
+            for y in 0..thumbnail.height {
+                let start = (((top + y) * width + left) * 4) as usize;
+                let src = (y * thumbnail.width * 4) as usize;
+                pixels[start..start + row_bytes]
+                    .copy_from_slice(&thumbnail.pixels[src..src + row_bytes]);
+            }
+        }
+
+        Thumbnail {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Writes the picture in a PNG file.
+    #[cfg(feature = "thumbnail")]
+    pub fn save_png(&self, path: &std::path::Path) -> Result<(), image::ImageError> {
+        image::save_buffer_with_format(
+            path,
+            &self.pixels,
+            self.width,
+            self.height,
+            image::ColorType::Rgba8,
+            image::ImageFormat::Png,
+        )
+    }
+}
+
+fn cell_color(elem: BoardElem) -> [u8; 4] {
+    match elem {
+        BoardElem(Some(MovableItem::Player), _) => PLAYER_COLOR,
+        BoardElem(Some(MovableItem::Crate(_)), CellKind::Target) => PLACED_CRATE_COLOR,
+        BoardElem(Some(MovableItem::Crate(_)), _) => CRATE_COLOR,
+        BoardElem(None, CellKind::Void) => VOID_COLOR,
+        BoardElem(None, CellKind::Wall) => WALL_COLOR,
+        BoardElem(None, CellKind::Floor) => FLOOR_COLOR,
+        BoardElem(None, CellKind::Target) => TARGET_COLOR,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Board, Thumbnail, CRATE_COLOR, PLAYER_COLOR, VOID_COLOR, WALL_COLOR};
+
+    #[test]
+    fn it_draws_a_square_per_cell() {
+        let board = Board::from_xsb("####\n#@$.#\n#####\n").unwrap();
+        let thumbnail = Thumbnail::render(&board, 2);
+        assert_eq!((thumbnail.width, thumbnail.height), (10, 6));
+        assert_eq!(thumbnail.pixels.len(), 10 * 6 * 4);
+
+        let pixel = |x: usize, y: usize| {
+            let start = (y * 10 + x) * 4;
+            &thumbnail.pixels[start..start + 4]
+        };
+        assert_eq!(pixel(0, 0), WALL_COLOR);
+        assert_eq!(pixel(3, 3), PLAYER_COLOR);
+        assert_eq!(pixel(4, 2), CRATE_COLOR);
+    }
+
+    #[test]
+    fn it_puts_thumbnails_in_a_grid() {
+        let small = Thumbnail::render(&Board::from_xsb("####\n#@.#\n####\n").unwrap(), 1);
+        let large = Thumbnail::render(&Board::from_xsb("#####\n#@$.#\n#####\n").unwrap(), 1);
+        let sheet = Thumbnail::sheet(&[large.clone(), small.clone(), small], 2, 1);
+        // 2 columns of 5 pixels and 2 rows of 3, with the padding.
+        assert_eq!((sheet.width, sheet.height), (13, 9));
+        assert_eq!(sheet.pixels.len(), 13 * 9 * 4);
+
+        let pixel = |x: usize, y: usize| {
+            let start = (y * 13 + x) * 4;
+            &sheet.pixels[start..start + 4]
+        };
+        assert_eq!(pixel(0, 0), VOID_COLOR);
+        assert_eq!(pixel(1, 1), WALL_COLOR);
+        assert_eq!(pixel(3, 2), CRATE_COLOR);
+        // The smaller ones leave the end of their cell empty.
+        assert_eq!(pixel(7, 1), WALL_COLOR);
+        assert_eq!(pixel(11, 1), VOID_COLOR);
+        assert_eq!(pixel(8, 2), PLAYER_COLOR);
+        assert_eq!(pixel(2, 6), PLAYER_COLOR);
+    }
+}
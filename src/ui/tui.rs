@@ -1,26 +1,91 @@
 //! Interactive terminal interface.
 use std::{
+    env,
     error::Error,
     fmt,
     io::{self, Write},
     panic,
+    time::{Duration, Instant},
 };
 
-use super::{terminal::*, Action, Board, BoardElem, CellKind, Direction, MovableItem, Ui};
+use super::{
+    format_duration, format_duration_millis,
+    tui_layout::BoardLayout,
+    tui_theme::{Glyph, TuiTheme, THEMES, THEME_ENV_VAR},
+    Action, Board, BoardElem, CellKind, Defeat, Direction, Hotseat, LevelLibrary, LostChoice,
+    MovableItem, MoveLimit, Replay, Score, Selection, SolverLimits, Stopwatch, TimeLimit, Ui,
+    WonChoice, REPLAY_SPEED_ENV_VAR,
+};
 use crossterm::{
     cursor,
     event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
     style, terminal, QueueableCommand,
 };
 
-const WON_MESSAGE_PADDING: u16 = 3;
-const WON_MESSAGE_LN_1: &str = "You won!";
-const WON_MESSAGE_LN_2: &str = "(Press any key to quit...)";
+const WON_TITLE: &str = "You won!";
+const WON_CHOICES: [(WonChoice, &str); 3] = [
+    (WonChoice::Restart, "Restart"),
+    (WonChoice::Next, "Next"),
+    (WonChoice::Quit, "Quit"),
+];
+const LOST_CHOICES: [(LostChoice, &str); 2] =
+    [(LostChoice::Retry, "Retry"), (LostChoice::Quit, "Quit")];
+/// Spaces between the dialog border and its content.
+const DIALOG_PADDING: u16 = 2;
+
+/// Delays between two steps of a replay, from slowest to fastest.
+const REPLAY_SPEEDS_MILLIS: [u64; 6] = [1000, 500, 250, 125, 60, 30];
+const REPLAY_DEFAULT_SPEED: usize = 2;
+const REPLAY_HELP: &str = "Space: play/pause, Left/Right: step, +/-: speed, q: quit";
+
+const SYMBOL_ARROW_LEFT: char = '←';
+const SYMBOL_ARROW_RIGHT: char = '→';
+const SYMBOL_ARROW_UP: char = '↑';
+const SYMBOL_ARROW_DOWN: char = '↓';
+const HINT_PATH_COLOR: style::Color = style::Color::DarkBlue;
+const HINT_PUSH_COLOR: style::Color = style::Color::DarkYellow;
+const HINT_SEARCHING: &str = "Looking for a hint...";
+
+/// Delay between two frames of the effects shown while waiting for the player.
+const IDLE_TICK: Duration = Duration::from_millis(500);
+/// The player cell is highlighted once every this number of ticks.
+const IDLE_PLAYER_PULSE_TICKS: u32 = 4;
+
+#[cfg(not(feature = "repo"))]
+const BROWSER_TITLE: &str =
+    "Choose a level (Up/Down to move, Enter to play, p to watch best replay, m for the move-limit challenge, e for endless mode, q to quit):";
+#[cfg(feature = "repo")]
+const BROWSER_TITLE: &str =
+    "Choose a level (Up/Down to move, Enter to play, p to watch best replay, m for the move-limit challenge, e for endless mode, c for community packs, q to quit):";
+const BROWSER_SOLVED_MARK: char = '✓';
+/// Shown after the levels picked for the move-limit challenge.
+const BROWSER_CHALLENGE_MARK: &str = " [par]";
+/// Rows used above the level list.
+const BROWSER_HEADER_ROWS: u16 = 2;
+/// Columns between the level list and the preview.
+const BROWSER_PADDING: u16 = 2;
+
+/// Rows kept under the board for the messages.
+const MESSAGE_ROWS: u16 = 1;
+
+/// Index in [`REPLAY_SPEEDS_MILLIS`] of the `speed` multiplier the replays start at, or else of
+/// the one of [`REPLAY_SPEED_ENV_VAR`], the multipliers in between being rounded down.
+pub(super) fn initial_replay_speed(speed: Option<u32>) -> usize {
+    speed
+        .or_else(|| {
+            env::var(REPLAY_SPEED_ENV_VAR)
+                .ok()
+                .and_then(|s| s.trim().parse::<u32>().ok())
+        })
+        .filter(|x| *x > 0)
+        .map_or(REPLAY_DEFAULT_SPEED, |x| {
+            (x.ilog2() as usize).min(REPLAY_SPEEDS_MILLIS.len() - 1)
+        })
+}
 
 #[derive(Debug)]
 pub enum TuiError {
     IO(io::Error),
-    MapTooLarge,
 }
 
 impl fmt::Display for TuiError {
@@ -28,15 +93,44 @@ impl fmt::Display for TuiError {
         use TuiError::*;
         match self {
             IO(other) => write!(f, "IO error with terminal : {}", other),
-            MapTooLarge => write!(f, "Board is bigger than screen."),
         }
     }
 }
 
 impl Error for TuiError {}
 
+/// What is currently shown on the terminal, so only the changed cells are redrawn.
+struct BackBuffer {
+    board: Board,
+    term_size: (u16, u16),
+    layout: BoardLayout,
+}
+
 /// Interactive terminal interface
-pub struct Tui;
+pub struct Tui {
+    /// Last displayed state, `None` if the whole screen must be redrawn.
+    back_buffer: Option<BackBuffer>,
+    /// Cells drawn with a highlight, which must be drawn again normally on next display.
+    highlighted: Vec<(u32, u32)>,
+    /// Row where a message was printed under the board, to be cleared on next display.
+    message_row: Option<u16>,
+    /// Index of the current theme in [`THEMES`].
+    theme: usize,
+    /// Number of ticks elapsed while waiting for an input, to animate the idle effects.
+    idle_ticks: u32,
+    /// Time limit of the level and when it started, to show the time left.
+    countdown: Option<(TimeLimit, Instant)>,
+    /// Moves and pushes allowed to win the level, to show how many are left.
+    challenge: Option<MoveLimit>,
+    /// Levels won in a row, in the endless mode.
+    streak: Option<u32>,
+    /// Turns and counters of the players, in the hotseat mode.
+    hotseat: Option<Hotseat>,
+    /// Timer of the run, in the speedrun mode.
+    speedrun: Option<Stopwatch>,
+    /// Last message of the level, printed again under the board at each display.
+    tip: Option<String>,
+}
 
 impl Tui {
     fn cleanup_terminal() -> Result<(), Box<dyn Error>> {
@@ -53,6 +147,482 @@ impl Tui {
 
         Ok(())
     }
+
+    pub(super) fn theme(&self) -> &TuiTheme {
+        &THEMES[self.theme]
+    }
+
+    /// Prints a cell with its colors, leaving the cursor just after it.
+    pub(super) fn queue_glyph(stdout: &mut io::Stdout, glyph: Glyph) -> Result<(), io::Error> {
+        if (glyph.fg, glyph.bg) == (style::Color::Reset, style::Color::Reset) {
+            stdout.queue(style::Print(glyph.symbol))?;
+        } else {
+            stdout
+                .queue(style::SetColors(style::Colors::new(glyph.fg, glyph.bg)))?
+                .queue(style::Print(glyph.symbol))?
+                .queue(style::ResetColor)?;
+        }
+        Ok(())
+    }
+
+    /// Prints a cell at its place in the `layout` if it is visible, leaving the cursor just
+    /// after it.
+    pub(super) fn queue_cell(
+        stdout: &mut io::Stdout,
+        layout: &BoardLayout,
+        (i, j): (u32, u32),
+        glyph: Glyph,
+    ) -> Result<(), io::Error> {
+        if let Some((col, row)) = layout.cell_pos(i, j) {
+            stdout.queue(cursor::MoveTo(col, row))?;
+            Tui::queue_glyph(stdout, glyph)?;
+            if layout.wide {
+                Tui::queue_glyph(stdout, glyph.wide_fill())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Prints the visible part of the board.
+    pub(super) fn queue_board(
+        stdout: &mut io::Stdout,
+        theme: &TuiTheme,
+        board: &Board,
+        layout: &BoardLayout,
+    ) -> Result<(), io::Error> {
+        for (i, j) in layout.visible_cells() {
+            Tui::queue_cell(stdout, layout, (i, j), theme.glyph(board.get(i, j)))?;
+        }
+        Ok(())
+    }
+
+    /// Layout of the `board` on the whole terminal but the message rows, following the player
+    /// from the last display.
+    fn board_layout(&self, board: &Board, term_size: (u16, u16)) -> BoardLayout {
+        let (term_cols, term_rows) = term_size;
+        let previous = self
+            .back_buffer
+            .as_ref()
+            .filter(|b| b.term_size == term_size)
+            .map(|b| &b.layout);
+
+        BoardLayout::new(
+            (board.width(), board.height()),
+            ((0, 0), (term_cols, term_rows.saturating_sub(MESSAGE_ROWS))),
+            board.player(),
+            previous,
+        )
+    }
+
+    /// Draws the level list with the `selected` one highlighted, and its preview on the right.
+    /// If `clear` is `false`, only the preview area is cleared to avoid flickering.
+    fn draw_browser(
+        &self,
+        library: &LevelLibrary,
+        selected: usize,
+        clear: bool,
+    ) -> Result<(), io::Error> {
+        let mut stdout = io::stdout();
+        let (term_cols, term_rows) = terminal::size()?;
+
+        if clear {
+            stdout
+                .queue(terminal::Clear(terminal::ClearType::All))?
+                .queue(cursor::MoveTo(0, 0))?
+                .queue(style::Print(BROWSER_TITLE))?;
+        }
+
+        let entries: Vec<String> = library
+            .levels()
+            .iter()
+            .map(|l| {
+                let mark = if l.is_solved() {
+                    BROWSER_SOLVED_MARK
+                } else {
+                    ' '
+                };
+                let challenge = if l.is_challenge() {
+                    BROWSER_CHALLENGE_MARK
+                } else {
+                    ""
+                };
+                format!(" {} {} ({}){} ", mark, l.title(), l.difficulty(), challenge)
+            })
+            .collect();
+        let list_cols = entries
+            .iter()
+            .map(|e| e.chars().count())
+            .max()
+            .unwrap_or(0)
+            .min(usize::from(term_cols / 2));
+
+        // Scrolls so the selected level is always visible.
+        let list_rows = usize::from(term_rows.saturating_sub(BROWSER_HEADER_ROWS));
+        let first = selected.saturating_sub(list_rows.saturating_sub(1));
+
+        for (row, (index, entry)) in entries
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(list_rows)
+            .enumerate()
+        {
+            let entry: String = entry.chars().take(list_cols).collect();
+
+            // There are less rows than `term_rows`.
+            stdout.queue(cursor::MoveTo(0, BROWSER_HEADER_ROWS + row as u16))?;
+            if index == selected {
+                stdout.queue(style::SetAttribute(style::Attribute::Reverse))?;
+            }
+            stdout.queue(style::Print(format!("{:<list_cols$}", entry)))?;
+            if index == selected {
+                stdout.queue(style::SetAttribute(style::Attribute::Reset))?;
+            }
+        }
+
+        // `list_cols` is smaller than `term_cols`.
+        let preview_col = list_cols as u16 + BROWSER_PADDING;
+        for row in BROWSER_HEADER_ROWS..term_rows {
+            stdout
+                .queue(cursor::MoveTo(preview_col, row))?
+                .queue(terminal::Clear(terminal::ClearType::UntilNewLine))?;
+        }
+
+        if let Some(level) = library.get(selected) {
+            let board = level.board();
+            let preview_cols = term_cols.saturating_sub(preview_col);
+            let preview_rows = term_rows.saturating_sub(BROWSER_HEADER_ROWS);
+
+            let layout = BoardLayout::new(
+                (board.width(), board.height()),
+                (
+                    (preview_col, BROWSER_HEADER_ROWS),
+                    (preview_cols, preview_rows),
+                ),
+                board.player(),
+                None,
+            );
+            Tui::queue_board(&mut stdout, self.theme(), board, &layout)?;
+        }
+
+        stdout.flush()
+    }
+
+    /// Draws a bordered box in the middle of the screen with the `lines` and the `choices`
+    /// under them, the `selected` one being highlighted.
+    fn draw_dialog(lines: &[String], choices: &[&str], selected: usize) -> Result<(), io::Error> {
+        let mut stdout = io::stdout();
+        let (term_cols, term_rows) = terminal::size()?;
+
+        let choices_len = choices
+            .iter()
+            .map(|label| label.chars().count() + 3)
+            .sum::<usize>()
+            - 1;
+        let content_cols = lines
+            .iter()
+            .map(|l| l.chars().count())
+            .chain([choices_len])
+            .max()
+            .unwrap_or(0);
+        // The content is small enough to fit in a u16.
+        let inner_cols = content_cols as u16 + 2 * DIALOG_PADDING;
+        let inner_rows = lines.len() as u16 + 2;
+
+        let start_col = (term_cols / 2).saturating_sub(inner_cols / 2 + 1);
+        let start_row = (term_rows / 2).saturating_sub(inner_rows / 2 + 1);
+        let border = "─".repeat(usize::from(inner_cols));
+        let blank = " ".repeat(usize::from(inner_cols));
+
+        stdout
+            .queue(cursor::MoveTo(start_col, start_row))?
+            .queue(style::Print(format!("┌{}┐", border)))?;
+        for row in 1..=inner_rows {
+            stdout
+                .queue(cursor::MoveTo(start_col, start_row + row))?
+                .queue(style::Print(format!("│{}│", blank)))?;
+        }
+        stdout
+            .queue(cursor::MoveTo(start_col, start_row + inner_rows + 1))?
+            .queue(style::Print(format!("└{}┘", border)))?;
+
+        let content_col = start_col + 1 + DIALOG_PADDING;
+        for (row, line) in lines.iter().enumerate() {
+            // Only a few lines.
+            stdout
+                .queue(cursor::MoveTo(content_col, start_row + 1 + row as u16))?
+                .queue(style::Print(line))?;
+        }
+
+        stdout.queue(cursor::MoveTo(content_col, start_row + inner_rows))?;
+        for (index, label) in choices.iter().enumerate() {
+            if index > 0 {
+                stdout.queue(style::Print(' '))?;
+            }
+            if index == selected {
+                stdout.queue(style::SetAttribute(style::Attribute::Reverse))?;
+            }
+            stdout.queue(style::Print(format!("[{}]", label)))?;
+            if index == selected {
+                stdout.queue(style::SetAttribute(style::Attribute::Reset))?;
+            }
+        }
+
+        stdout.flush()
+    }
+
+    /// Draws the board of a replay being watched, with the crate pushed by the last step
+    /// highlighted, and the `status` under it.
+    fn draw_replay(
+        &self,
+        board: &Board,
+        pushed_crate: Option<(u32, u32)>,
+        status: &str,
+        clear: bool,
+    ) -> Result<(), Box<dyn Error>> {
+        let (term_cols, term_rows) = terminal::size().map_err(|e| Box::new(TuiError::IO(e)))?;
+        let layout = self.board_layout(board, (term_cols, term_rows));
+
+        let res: Result<(), io::Error> = try {
+            let mut stdout = io::stdout();
+
+            if clear {
+                stdout.queue(terminal::Clear(terminal::ClearType::All))?;
+            }
+            Tui::queue_board(&mut stdout, self.theme(), board, &layout)?;
+
+            if let Some((i, j)) = pushed_crate {
+                stdout.queue(style::SetAttribute(style::Attribute::Reverse))?;
+                Tui::queue_cell(
+                    &mut stdout,
+                    &layout,
+                    (i, j),
+                    self.theme().glyph(board.get(i, j)),
+                )?;
+                stdout.queue(style::SetAttribute(style::Attribute::Reset))?;
+            }
+
+            let status_row = Tui::message_row(&layout, term_rows);
+            stdout
+                .queue(cursor::MoveTo(0, status_row))?
+                .queue(terminal::Clear(terminal::ClearType::CurrentLine))?
+                .queue(style::Print(status))?;
+
+            stdout.flush()?;
+        };
+        res.map_err(|e| Box::new(TuiError::IO(e)))?;
+
+        Ok(())
+    }
+
+    /// Row of the messages: one line under the board if there is room left, otherwise the last
+    /// line.
+    fn message_row(layout: &BoardLayout, term_rows: u16) -> u16 {
+        (layout.bottom() + 1).min(term_rows.saturating_sub(1))
+    }
+
+    /// Prints a message under the board.
+    fn print_message(&mut self, board: &Board, message: &str) -> Result<(), Box<dyn Error>> {
+        let term_size = terminal::size().map_err(|e| Box::new(TuiError::IO(e)))?;
+        let layout = self.board_layout(board, term_size);
+        self.show_message_under(&layout, message)
+    }
+
+    /// Prints a message under the board drawn with `layout`.
+    pub(super) fn show_message_under(
+        &mut self,
+        layout: &BoardLayout,
+        message: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let (term_cols, term_rows) = terminal::size().map_err(|e| Box::new(TuiError::IO(e)))?;
+        let row = Tui::message_row(layout, term_rows);
+        let col = (term_cols / 2).saturating_sub(message.chars().count() as u16 / 2);
+
+        let res: Result<(), io::Error> = try {
+            let mut stdout = io::stdout();
+            stdout
+                .queue(cursor::MoveTo(0, row))?
+                .queue(terminal::Clear(terminal::ClearType::CurrentLine))?
+                .queue(cursor::MoveTo(col, row))?
+                .queue(style::Print(message))?;
+            stdout.flush()?;
+        };
+        res.map_err(|e| Box::new(TuiError::IO(e)))?;
+
+        self.message_row = Some(row);
+        Ok(())
+    }
+
+    /// Looks for the next push to do, and highlights the path to walk to the crate and where to
+    /// push it.
+    fn show_hint(&mut self, board: &Board) -> Result<(), Box<dyn Error>> {
+        self.print_message(board, HINT_SEARCHING)?;
+
+        let hint = match board.hint(&SolverLimits::default()) {
+            Ok(Some(hint)) => hint,
+            Ok(None) => return Ok(()),
+            Err(err) => return self.print_message(board, &err.to_string()),
+        };
+
+        let term_size = terminal::size().map_err(|e| Box::new(TuiError::IO(e)))?;
+        let layout = self.board_layout(board, term_size);
+
+        let (dest, arrow) = {
+            let (i, j) = hint.crate_pos;
+            let arrow = match hint.push {
+                Direction::Left => SYMBOL_ARROW_LEFT,
+                Direction::Right => SYMBOL_ARROW_RIGHT,
+                Direction::Up => SYMBOL_ARROW_UP,
+                Direction::Down => SYMBOL_ARROW_DOWN,
+            };
+            (hint.push.to_coords(i, j), arrow)
+        };
+
+        let theme = self.theme();
+
+        let res: Result<(), io::Error> = try {
+            let mut stdout = io::stdout();
+
+            let mut queue_cell = |(i, j): (u32, u32), symbol: Option<char>, bg: style::Color| {
+                let glyph = theme.glyph(board.get(i, j));
+                Tui::queue_cell(
+                    &mut stdout,
+                    &layout,
+                    (i, j),
+                    Glyph {
+                        symbol: symbol.unwrap_or(glyph.symbol),
+                        bg,
+                        ..glyph
+                    },
+                )
+            };
+
+            for &(i, j) in hint.path.iter() {
+                queue_cell((i, j), None, HINT_PATH_COLOR)?;
+            }
+            queue_cell(hint.crate_pos, None, HINT_PUSH_COLOR)?;
+            queue_cell(dest, Some(arrow), HINT_PUSH_COLOR)?;
+
+            stdout.flush()?;
+        };
+        res.map_err(|e| Box::new(TuiError::IO(e)))?;
+
+        self.highlighted.extend(hint.path);
+        self.highlighted.push(hint.crate_pos);
+        self.highlighted.push(dest);
+
+        self.print_message(
+            board,
+            "Hint: walk along the blue path, then push the crate along the arrow.",
+        )
+    }
+
+    /// Draws the next frame of the idle effects: the uncovered targets blink and the player
+    /// pulses, so they are easy to spot.
+    /// The highlighted cells are left untouched.
+    fn draw_idle_effects(&mut self, board: &Board) -> Result<(), Box<dyn Error>> {
+        self.idle_ticks = self.idle_ticks.wrapping_add(1);
+        let dim_targets = self.idle_ticks % 2 == 1;
+        let pulse_player = self.idle_ticks.is_multiple_of(IDLE_PLAYER_PULSE_TICKS);
+
+        let term_size = terminal::size().map_err(|e| Box::new(TuiError::IO(e)))?;
+        let layout = self.board_layout(board, term_size);
+
+        let res: Result<(), io::Error> = try {
+            let mut stdout = io::stdout();
+
+            for (i, j) in layout.visible_cells() {
+                if self.highlighted.contains(&(i, j)) {
+                    continue;
+                }
+                let elem = board.get(i, j);
+                let attribute = match elem {
+                    BoardElem(None, CellKind::Target) if dim_targets => style::Attribute::Dim,
+                    BoardElem(Some(MovableItem::Player), _) if pulse_player => {
+                        style::Attribute::Reverse
+                    }
+                    BoardElem(None, CellKind::Target) | BoardElem(Some(MovableItem::Player), _) => {
+                        style::Attribute::Reset
+                    }
+                    _ => continue,
+                };
+
+                stdout.queue(style::SetAttribute(attribute))?;
+                Tui::queue_cell(&mut stdout, &layout, (i, j), self.theme().glyph(elem))?;
+                stdout.queue(style::SetAttribute(style::Attribute::Reset))?;
+            }
+
+            stdout.flush()?;
+        };
+        res.map_err(|e| Box::new(TuiError::IO(e)))?;
+
+        Ok(())
+    }
+
+    /// Time left to win the level, if it has a time limit.
+    fn time_left(&self) -> Option<Duration> {
+        self.countdown
+            .map(|(limit, start)| limit.remaining(start.elapsed()))
+    }
+
+    /// Prints the streak in the endless mode, the turn in the hotseat mode, the timer in the
+    /// speedrun mode, and the moves and pushes left and the time left if the level has such
+    /// limits, at the end of the message row.
+    fn draw_limits(&self, board: &Board) -> Result<(), Box<dyn Error>> {
+        let mut text = String::new();
+        if let Some(streak) = self.streak {
+            text.push_str(&format!(" Streak: {}", streak));
+        }
+        if let Some(hotseat) = self.hotseat {
+            text.push_str(&format!(" {}", hotseat));
+        }
+        if let Some(stopwatch) = self.speedrun {
+            text.push_str(&format!(
+                " Run: {}",
+                format_duration_millis(stopwatch.elapsed())
+            ));
+        }
+        if let Some(limit) = self.challenge {
+            text.push_str(&format!(
+                " Moves left: {} Pushes left: {}",
+                limit.moves_left(board),
+                limit.pushes_left(board)
+            ));
+        }
+        if let Some(left) = self.time_left() {
+            text.push_str(&format!(" Time left: {}", format_duration(left)));
+        }
+        if text.is_empty() {
+            return Ok(());
+        }
+        let (term_cols, term_rows) = terminal::size().map_err(|e| Box::new(TuiError::IO(e)))?;
+        let layout = self.board_layout(board, (term_cols, term_rows));
+
+        let res: Result<(), io::Error> = try {
+            let mut stdout = io::stdout();
+            stdout
+                .queue(cursor::MoveTo(
+                    term_cols.saturating_sub(text.chars().count() as u16),
+                    Tui::message_row(&layout, term_rows),
+                ))?
+                .queue(style::Print(text))?;
+            stdout.flush()?;
+        };
+        res.map_err(|e| Box::new(TuiError::IO(e)))?;
+
+        Ok(())
+    }
+
+    /// Where the board was last displayed, if it still is.
+    #[cfg(feature = "term_graphics")]
+    pub(super) fn layout(&self) -> Option<BoardLayout> {
+        self.back_buffer.as_ref().map(|b| b.layout)
+    }
+
+    /// Forgets what is on screen, so the next [`Ui::display`] redraws everything.
+    fn invalidate(&mut self) {
+        self.back_buffer = None;
+    }
 }
 
 impl Ui for Tui {
@@ -84,15 +654,219 @@ impl Ui for Tui {
             }
         }));
 
-        Ok(Tui)
+        let theme = env::var(THEME_ENV_VAR)
+            .ok()
+            .and_then(|name| TuiTheme::by_name(&name))
+            .unwrap_or(0);
+
+        Ok(Tui {
+            back_buffer: None,
+            highlighted: Vec::new(),
+            message_row: None,
+            theme,
+            idle_ticks: 0,
+            countdown: None,
+            challenge: None,
+            streak: None,
+            hotseat: None,
+            speedrun: None,
+            tip: None,
+        })
     }
 
     fn cleanup(self: Box<Self>) -> Result<(), Box<dyn Error>> {
         Tui::cleanup_terminal()
     }
 
-    fn get_action(&self, board: &Board) -> Result<Action, Box<dyn Error>> {
+    fn select_level(
+        &mut self,
+        library: &mut LevelLibrary,
+    ) -> Result<Option<Selection>, Box<dyn Error>> {
+        // No need to choose if there is only one level, unless packs can be downloaded.
+        if library.len() <= 1 && cfg!(not(feature = "repo")) {
+            return Ok(library.first_unsolved().map(Selection::Level));
+        }
+
+        // The browser is drawn over the board.
+        self.invalidate();
+
+        let mut selected = library.first_unsolved().unwrap_or(0);
+        self.draw_browser(library, selected, true)
+            .map_err(TuiError::IO)?;
+
+        let selected = loop {
+            let clear = match event::read().map_err(TuiError::IO)? {
+                Event::Resize(_, _) => true,
+                Event::Key(KeyEvent {
+                    modifiers: KeyModifiers::NONE,
+                    code,
+                    ..
+                }) => match code {
+                    KeyCode::Esc | KeyCode::Char('q') => break None,
+                    KeyCode::Enter => break Some(Selection::Level(selected)),
+                    KeyCode::Char('e') => break Some(Selection::Endless),
+                    KeyCode::Up => {
+                        selected = selected.saturating_sub(1);
+                        false
+                    }
+                    KeyCode::Down => {
+                        selected = (selected + 1).min(library.len() - 1);
+                        false
+                    }
+                    #[cfg(feature = "repo")]
+                    KeyCode::Char('c') => {
+                        if let Some(pack) = super::tui_packs::browse_packs()? {
+                            *library = pack;
+                            return self.select_level(library);
+                        }
+                        true
+                    }
+                    KeyCode::Char('m') => {
+                        library.toggle_challenge(selected);
+                        true
+                    }
+                    KeyCode::Char('p') => match library.get(selected) {
+                        Some(level) if level.best_replay().is_some() => {
+                            let replay = level.best_replay().expect("Checked just before.");
+                            self.watch_replay(level.board(), replay, None)?;
+                            true
+                        }
+                        _ => continue,
+                    },
+                    _ => continue,
+                },
+                Event::Key(KeyEvent {
+                    modifiers: KeyModifiers::CONTROL,
+                    code: KeyCode::Char('c'),
+                    ..
+                }) => break None,
+                _ => continue,
+            };
+            self.draw_browser(library, selected, clear)
+                .map_err(TuiError::IO)?;
+        };
+
+        Ok(selected)
+    }
+
+    fn watch_replay(
+        &mut self,
+        board: &Board,
+        replay: &Replay,
+        speed: Option<u32>,
+    ) -> Result<(), Box<dyn Error>> {
+        // The replay is drawn over anything else.
+        self.invalidate();
+
+        let steps = replay.steps();
+        let mut current = board.clone();
+        let mut position = 0;
+        let mut pushed_crate = None;
+        let mut invalid = false;
+        let mut playing = true;
+        let mut speed = initial_replay_speed(speed);
+        let mut clear = true;
+
+        loop {
+            let status = format!(
+                "Step {}/{} | x{} | {} | {}",
+                position,
+                steps.len(),
+                1 << speed,
+                if invalid {
+                    "Invalid move!"
+                } else if playing {
+                    "Playing"
+                } else {
+                    "Paused"
+                },
+                REPLAY_HELP
+            );
+            self.draw_replay(&current, pushed_crate, &status, clear)?;
+            clear = false;
+
+            let mut step_forward = false;
+            let mut step_back = false;
+
+            let delay = Duration::from_millis(REPLAY_SPEEDS_MILLIS[speed]);
+            if playing && !event::poll(delay).map_err(TuiError::IO)? {
+                step_forward = true;
+            } else {
+                match event::read().map_err(TuiError::IO)? {
+                    Event::Resize(_, _) => clear = true,
+                    Event::Key(KeyEvent {
+                        modifiers: KeyModifiers::NONE,
+                        code,
+                        ..
+                    }) => match code {
+                        KeyCode::Esc | KeyCode::Char('q') => break,
+                        KeyCode::Char(' ') => playing = !playing,
+                        KeyCode::Right => {
+                            playing = false;
+                            step_forward = true;
+                        }
+                        KeyCode::Left => {
+                            playing = false;
+                            step_back = true;
+                        }
+                        KeyCode::Char('+') => {
+                            speed = (speed + 1).min(REPLAY_SPEEDS_MILLIS.len() - 1)
+                        }
+                        KeyCode::Char('-') => speed = speed.saturating_sub(1),
+                        _ => (),
+                    },
+                    Event::Key(KeyEvent {
+                        modifiers: KeyModifiers::CONTROL,
+                        code: KeyCode::Char('c'),
+                        ..
+                    }) => break,
+                    _ => (),
+                }
+            }
+
+            if step_forward && !invalid && position < steps.len() {
+                match current.do_move_player(steps[position].dir) {
+                    Some(moved) => {
+                        position += 1;
+                        pushed_crate = moved;
+                    }
+                    None => invalid = true,
+                }
+            } else if step_back && position > 0 {
+                // Plays everything again from the start, as moves can't be undone.
+                position -= 1;
+                invalid = false;
+                current = board.clone();
+                pushed_crate = None;
+                for step in &steps[..position] {
+                    pushed_crate = current.do_move_player(step.dir).flatten();
+                }
+            }
+
+            if position == steps.len() || invalid {
+                playing = false;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get_action(&mut self, board: &Board) -> Result<Action, Box<dyn Error>> {
         let action = loop {
+            // While waiting for the player, the screen is animated every tick, and the time
+            // left is checked as soon as it runs out.
+            let tick = self
+                .time_left()
+                .map_or(IDLE_TICK, |left| left.min(IDLE_TICK));
+            if !event::poll(tick).map_err(|e| Box::new(TuiError::IO(e)))? {
+                if self.time_left() == Some(Duration::ZERO) {
+                    break Action::TimeOut;
+                }
+                self.draw_idle_effects(board)?;
+                self.draw_limits(board)?;
+                continue;
+            }
+
             let ev = event::read().map_err(|e| Box::new(TuiError::IO(e)))?;
             // io::stderr().execute(Print(format!("{:?}\n", ev)))?;
             match ev {
@@ -104,7 +878,17 @@ impl Ui for Tui {
                 }) => match code {
                     KeyCode::Esc | KeyCode::Char('q') => break Action::Quit,
                     KeyCode::Char('r') => break Action::ResetLevel,
-                    KeyCode::Char('d') => self.display(board, None)?,
+                    KeyCode::Char('d') => {
+                        self.invalidate();
+                        self.display(board, None)?
+                    }
+                    KeyCode::Char('h') => self.show_hint(board)?,
+                    KeyCode::Char('t') => {
+                        self.theme = (self.theme + 1) % THEMES.len();
+                        self.invalidate();
+                        self.display(board, None)?;
+                        self.print_message(board, &format!("Theme: {}", self.theme().name))?;
+                    }
                     KeyCode::Left => break Action::Movement(Direction::Left),
                     KeyCode::Right => break Action::Movement(Direction::Right),
                     KeyCode::Up => break Action::Movement(Direction::Up),
@@ -123,79 +907,220 @@ impl Ui for Tui {
     }
 
     fn display(
-        &self,
+        &mut self,
         board: &Board,
         _last_move_result: Option<Option<(u32, u32)>>,
     ) -> Result<(), Box<dyn Error>> {
-        let cols = u16::try_from(board.width()).map_err(|_| TuiError::MapTooLarge)?;
-        let rows = u16::try_from(board.height()).map_err(|_| TuiError::MapTooLarge)?;
+        let term_size = terminal::size().map_err(|e| Box::new(TuiError::IO(e)))?;
+        let layout = self.board_layout(board, term_size);
+
+        // Only the cells which changed since last time are printed, unless the screen layout
+        // changed and everything has to be redrawn.
+        let previous = self
+            .back_buffer
+            .as_ref()
+            .filter(|b| b.term_size == term_size);
+        let changed_cells = previous
+            .filter(|b| b.layout == layout)
+            .and_then(|b| board.diff(&b.board));
+        // When it only scrolled, all the cells are drawn again at the same place so there is
+        // no need to clear the screen and make it flicker.
+        let same_area = previous.is_some_and(|b| {
+            (b.layout.origin, b.layout.visible, b.layout.wide)
+                == (layout.origin, layout.visible, layout.wide)
+        });
 
         let res: Result<(), io::Error> = try {
             let mut stdout = io::stdout();
 
-            stdout.queue(terminal::Clear(terminal::ClearType::All))?;
-
-            let (term_cols, term_rows) = terminal::size()?;
+            // Highlights and messages from last time are removed.
+            if let Some(row) = self.message_row.take() {
+                stdout
+                    .queue(cursor::MoveTo(0, row))?
+                    .queue(terminal::Clear(terminal::ClearType::CurrentLine))?;
+            }
+            let highlighted = std::mem::take(&mut self.highlighted);
 
-            if term_cols < cols || term_rows < rows {
-                return Err(Box::new(TuiError::MapTooLarge));
+            match changed_cells {
+                Some(cells) => {
+                    for (i, j) in cells.into_iter().chain(highlighted) {
+                        Tui::queue_cell(
+                            &mut stdout,
+                            &layout,
+                            (i, j),
+                            self.theme().glyph(board.get(i, j)),
+                        )?;
+                    }
+                }
+                None => {
+                    if !same_area {
+                        stdout.queue(terminal::Clear(terminal::ClearType::All))?;
+                    }
+                    Tui::queue_board(&mut stdout, self.theme(), board, &layout)?;
+                }
             }
 
-            let start_col = term_cols / 2 - cols / 2;
-            let start_row = term_rows / 2 - rows / 2;
+            stdout.flush()?;
+
+            self.back_buffer = Some(BackBuffer {
+                board: board.clone(),
+                term_size,
+                layout,
+            });
+        };
+        res.map_err(|e| Box::new(TuiError::IO(e)))?;
+
+        if let Some(tip) = self.tip.clone() {
+            self.show_message_under(&layout, &tip)?;
+        }
+        self.draw_limits(board)
+    }
+
+    fn won(&mut self, score: &Score, best: Option<&Score>) -> Result<WonChoice, Box<dyn Error>> {
+        self.countdown = None;
+        self.challenge = None;
+        self.tip = None;
+        let mut lines = vec![
+            WON_TITLE.to_string(),
+            String::new(),
+            format!("Moves:  {}", score.moves),
+            format!("Pushes: {}", score.pushes),
+            format!("Time:   {}", format_duration(score.time)),
+        ];
+        match best {
+            Some(best) if !score.is_better_than(best) => lines.push(format!(
+                "Best:   {} moves, {} pushes, {}",
+                best.moves,
+                best.pushes,
+                format_duration(best.time)
+            )),
+            _ => lines.push("New personal best!".to_string()),
+        }
 
-            for j in 0..rows {
-                for i in 0..cols {
-                    use CellKind::*;
-                    use MovableItem::*;
+        // The dialog is drawn over the board.
+        self.invalidate();
 
-                    let symbol = match board.get(i.into(), j.into()) {
-                        BoardElem(_, Void) => SYMBOL_VOID,
-                        BoardElem(_, Wall) => SYMBOL_WALL,
-                        BoardElem(None, Floor) => SYMBOL_FLOOR,
-                        BoardElem(None, Target) => SYMBOL_TARGET,
-                        BoardElem(Some(Player), Floor) => SYMBOL_PLAYER,
-                        BoardElem(Some(Crate(_)), Floor) => SYMBOL_CRATE,
-                        BoardElem(Some(Player), Target) => SYMBOL_PLAYER_ON_TARGET,
-                        BoardElem(Some(Crate(_)), Target) => SYMBOL_PLACED_CRATE,
-                    };
+        let labels = WON_CHOICES.map(|(_, label)| label);
+        let res: Result<WonChoice, io::Error> = try {
+            let mut selected = WON_CHOICES
+                .iter()
+                .position(|(c, _)| *c == WonChoice::Next)
+                .unwrap_or(0);
+            Tui::draw_dialog(&lines, &labels, selected)?;
 
-                    stdout
-                        .queue(cursor::MoveTo(start_col + i, start_row + j))?
-                        .queue(style::Print(symbol))?;
+            loop {
+                match event::read()? {
+                    Event::Resize(_, _) => {
+                        io::stdout().queue(terminal::Clear(terminal::ClearType::All))?;
+                    }
+                    Event::Key(KeyEvent {
+                        modifiers: KeyModifiers::NONE,
+                        code,
+                        ..
+                    }) => match code {
+                        KeyCode::Esc | KeyCode::Char('q') => break WonChoice::Quit,
+                        KeyCode::Char('r') => break WonChoice::Restart,
+                        KeyCode::Char('n') => break WonChoice::Next,
+                        KeyCode::Enter => break WON_CHOICES[selected].0,
+                        KeyCode::Left => selected = selected.saturating_sub(1),
+                        KeyCode::Right | KeyCode::Tab => {
+                            selected = (selected + 1) % WON_CHOICES.len()
+                        }
+                        _ => continue,
+                    },
+                    Event::Key(KeyEvent {
+                        modifiers: KeyModifiers::CONTROL,
+                        code: KeyCode::Char('c'),
+                        ..
+                    }) => break WonChoice::Quit,
+                    _ => continue,
                 }
+                Tui::draw_dialog(&lines, &labels, selected)?;
             }
+        };
 
-            if board.has_won() {
-                let start_right = start_col + cols + WON_MESSAGE_PADDING;
-                /*
-                let won_message_max_len = u16::try_from(
-                    WON_MESSAGE_LN_1
-                        .chars()
-                        .count()
-                        .max(WON_MESSAGE_LN_2.chars().count()),
-                )
-                .expect("Won message should fit in a u16.");
-                */
+        Ok(res.map_err(|e| Box::new(TuiError::IO(e)))?)
+    }
 
-                stdout
-                    .queue(cursor::MoveTo(start_right, term_rows / 2 - 1))?
-                    .queue(style::Print(WON_MESSAGE_LN_1))?
-                    .queue(cursor::MoveToNextLine(2))?
-                    .queue(cursor::MoveRight(start_right))?
-                    .queue(style::Print(WON_MESSAGE_LN_2))?;
-            }
+    fn start_countdown(&mut self, limit: Option<TimeLimit>) {
+        self.countdown = limit.map(|l| (l, Instant::now()));
+        // The level may have been quit while a message was shown.
+        self.tip = None;
+    }
 
-            stdout.flush()?;
-        };
-        res.map_err(|e| Box::new(TuiError::IO(e)))?;
+    fn start_challenge(&mut self, limit: Option<MoveLimit>) {
+        self.challenge = limit;
+    }
 
-        Ok(())
+    fn show_streak(&mut self, streak: Option<u32>) {
+        self.streak = streak;
     }
 
-    fn won(&self) -> Result<(), Box<dyn Error>> {
-        event::read().map_err(|e| Box::new(TuiError::IO(e)))?;
-        Ok(())
+    fn show_turn(&mut self, hotseat: Option<&Hotseat>) {
+        self.hotseat = hotseat.copied();
+    }
+
+    fn show_speedrun(&mut self, stopwatch: Option<Stopwatch>) {
+        self.speedrun = stopwatch;
+    }
+
+    fn show_message(&mut self, message: &str) -> Result<(), Box<dyn Error>> {
+        self.tip = Some(message.to_string());
+        match self.back_buffer.as_ref().map(|b| b.board.clone()) {
+            Some(board) => self.display(&board, None),
+            None => Ok(()),
+        }
+    }
+
+    fn lost(&mut self, defeat: &Defeat) -> Result<LostChoice, Box<dyn Error>> {
+        self.countdown = None;
+        self.challenge = None;
+        self.tip = None;
+        let lines = vec![
+            defeat.title().to_string(),
+            String::new(),
+            defeat.to_string(),
+        ];
+
+        // The dialog is drawn over the board.
+        self.invalidate();
+
+        let labels = LOST_CHOICES.map(|(_, label)| label);
+        let res: Result<LostChoice, io::Error> = try {
+            let mut selected = 0;
+            Tui::draw_dialog(&lines, &labels, selected)?;
+
+            loop {
+                match event::read()? {
+                    Event::Resize(_, _) => {
+                        io::stdout().queue(terminal::Clear(terminal::ClearType::All))?;
+                    }
+                    Event::Key(KeyEvent {
+                        modifiers: KeyModifiers::NONE,
+                        code,
+                        ..
+                    }) => match code {
+                        KeyCode::Esc | KeyCode::Char('q') => break LostChoice::Quit,
+                        KeyCode::Char('r') => break LostChoice::Retry,
+                        KeyCode::Enter => break LOST_CHOICES[selected].0,
+                        KeyCode::Left => selected = selected.saturating_sub(1),
+                        KeyCode::Right | KeyCode::Tab => {
+                            selected = (selected + 1) % LOST_CHOICES.len()
+                        }
+                        _ => continue,
+                    },
+                    Event::Key(KeyEvent {
+                        modifiers: KeyModifiers::CONTROL,
+                        code: KeyCode::Char('c'),
+                        ..
+                    }) => break LostChoice::Quit,
+                    _ => continue,
+                }
+                Tui::draw_dialog(&lines, &labels, selected)?;
+            }
+        };
+
+        Ok(res.map_err(|e| Box::new(TuiError::IO(e)))?)
     }
 }
 
@@ -0,0 +1,193 @@
+//! Level editor in the [terminal interface](`super::tui`), saving the levels in the XSB format.
+
+use std::{
+    error::Error,
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    style, terminal, QueueableCommand,
+};
+
+use super::{
+    lint_summary,
+    tui::{Tui, TuiError},
+    tui_layout::BoardLayout,
+    Board, CellKind, Ui,
+};
+
+/// Size of the canvas when creating a new level.
+const NEW_LEVEL_SIZE: (u32, u32) = (20, 12);
+/// Empty cells added around an existing level, so it can be extended.
+const MARGIN: u32 = 2;
+/// Rows kept under the board for the messages.
+const MESSAGE_ROWS: u16 = 1;
+const HELP: [&str; 2] = [
+    "Level editor - Arrows: move, s: save, q: quit",
+    "#: wall, Space: floor, .: target, $: crate, @: player, Del: void",
+];
+
+/// Lets the user draw a level in the terminal, starting from `board` or from an empty canvas,
+/// and save it to `path`.
+pub fn edit_level(board: Option<&Board>, path: &Path) -> Result<(), Box<dyn Error>> {
+    let board = match board {
+        Some(board) => board.with_margin(MARGIN),
+        None => Board::empty(NEW_LEVEL_SIZE.0, NEW_LEVEL_SIZE.1),
+    };
+
+    let mut tui = Tui::initialize()?;
+    let res = Editor::new(board).run(&mut tui, path);
+    Box::new(tui).cleanup()?;
+
+    res
+}
+
+struct Editor {
+    board: Board,
+    cursor: (u32, u32),
+    /// Where the board was last drawn, to scroll only when needed.
+    layout: Option<BoardLayout>,
+    /// If the level changed since it was last saved.
+    unsaved: bool,
+}
+
+impl Editor {
+    fn new(board: Board) -> Self {
+        Editor {
+            cursor: (board.width() / 2, board.height() / 2),
+            board,
+            layout: None,
+            unsaved: false,
+        }
+    }
+
+    fn run(&mut self, tui: &mut Tui, path: &Path) -> Result<(), Box<dyn Error>> {
+        let mut clear = true;
+        let mut status = None;
+        loop {
+            self.draw(tui, clear, status.take())?;
+            clear = false;
+
+            let (code, modifiers) = match event::read().map_err(TuiError::IO)? {
+                Event::Resize(_, _) => {
+                    clear = true;
+                    continue;
+                }
+                Event::Key(KeyEvent {
+                    code, modifiers, ..
+                }) => (code, modifiers),
+                _ => continue,
+            };
+            if modifiers.contains(KeyModifiers::CONTROL) {
+                if code == KeyCode::Char('c') {
+                    break;
+                }
+                continue;
+            }
+
+            let (i, j) = self.cursor;
+            match code {
+                KeyCode::Esc | KeyCode::Char('q') => {
+                    if !self.unsaved {
+                        break;
+                    }
+                    self.unsaved = false;
+                    status = Some("Unsaved changes: press q again to quit.".to_string());
+                }
+                KeyCode::Char('s') => {
+                    let issues = self.board.lint().len();
+                    status = Some(match fs::write(path, self.board.to_xsb()) {
+                        Ok(()) if issues == 0 => format!("Saved to `{}`.", path.display()),
+                        Ok(()) => format!(
+                            "Saved to `{}`, with {} issue(s) left.",
+                            path.display(),
+                            issues
+                        ),
+                        Err(err) => format!("Could not save `{}`: {}", path.display(), err),
+                    });
+                    self.unsaved = false;
+                }
+                KeyCode::Left => self.cursor.0 = i.saturating_sub(1),
+                KeyCode::Right => self.cursor.0 = (i + 1).min(self.board.width() - 1),
+                KeyCode::Up => self.cursor.1 = j.saturating_sub(1),
+                KeyCode::Down => self.cursor.1 = (j + 1).min(self.board.height() - 1),
+                KeyCode::Char('#') => self.edit(|b| b.set_cell(i, j, CellKind::Wall)),
+                KeyCode::Char(' ') => self.edit(|b| b.set_cell(i, j, CellKind::Floor)),
+                KeyCode::Char('.') => self.edit(|b| b.set_cell(i, j, CellKind::Target)),
+                KeyCode::Delete | KeyCode::Backspace => {
+                    self.edit(|b| b.set_cell(i, j, CellKind::Void))
+                }
+                KeyCode::Char('$') => self.edit(|b| b.toggle_crate(i, j)),
+                KeyCode::Char('@') => self.edit(|b| b.set_player(i, j)),
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn edit<F: FnOnce(&mut Board)>(&mut self, f: F) {
+        f(&mut self.board);
+        self.unsaved = true;
+    }
+
+    /// Draws the help, the level and under it either the `status` or the first issue found
+    /// by the linter, and places the terminal cursor on the edited cell.
+    fn draw(
+        &mut self,
+        tui: &mut Tui,
+        clear: bool,
+        status: Option<String>,
+    ) -> Result<(), Box<dyn Error>> {
+        let (term_cols, term_rows) = terminal::size().map_err(TuiError::IO)?;
+        let help_rows = HELP.len() as u16;
+        let layout = BoardLayout::new(
+            (self.board.width(), self.board.height()),
+            (
+                (0, help_rows),
+                (
+                    term_cols,
+                    term_rows.saturating_sub(help_rows + MESSAGE_ROWS),
+                ),
+            ),
+            self.cursor,
+            self.layout.as_ref().filter(|_| !clear),
+        );
+        self.layout = Some(layout);
+
+        let res: Result<(), io::Error> = try {
+            let mut stdout = io::stdout();
+            if clear {
+                stdout.queue(terminal::Clear(terminal::ClearType::All))?;
+            }
+            for (row, line) in (0..).zip(HELP) {
+                stdout
+                    .queue(cursor::MoveTo(0, row))?
+                    .queue(style::Print(line))?;
+            }
+            Tui::queue_board(&mut stdout, tui.theme(), &self.board, &layout)?;
+        };
+        res.map_err(TuiError::IO)?;
+
+        let message = status.unwrap_or_else(|| lint_summary(&self.board));
+        tui.show_message_under(&layout, &message)?;
+
+        let res: Result<(), io::Error> = try {
+            let mut stdout = io::stdout();
+            // The layout keeps the cursor visible, unless the terminal is way too small.
+            if let Some((col, row)) = layout.cell_pos(self.cursor.0, self.cursor.1) {
+                stdout
+                    .queue(cursor::MoveTo(col, row))?
+                    .queue(cursor::Show)?;
+            }
+            stdout.flush()?;
+        };
+        res.map_err(TuiError::IO)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,290 @@
+//! Terminal interface drawing the board with the tiles of the graphical interfaces, sent as
+//! pictures through the [graphics protocol](`super::term_graphics`) of the terminal. Everything
+//! else is left to the [terminal interface](`super::tui`), whose characters are covered by the
+//! pictures.
+//!
+//! The board is drawn again as a picture after each move, so when the terminal is resized or
+//! the theme changed while waiting for a move, the characters show until then.
+
+use std::{
+    error::Error,
+    fmt, fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use crossterm::{cursor, style, terminal, QueueableCommand};
+use image::RgbaImage;
+
+use super::{
+    atlas::{AtlasManifest, AtlasParseError},
+    term_graphics::{GraphicsProtocol, Picture},
+    tui::{Tui, TuiError},
+    tui_layout::BoardLayout,
+    Action, Board, BoardElem, CellKind, Defeat, Hotseat, LevelLibrary, LostChoice, MovableItem,
+    MoveLimit, Replay, Score, Selection, Stopwatch, TimeLimit, Ui, WonChoice,
+};
+
+/// Directory of the images, as for the graphical interfaces.
+const RESOURCES_DIR: &str = "images";
+/// Manifest of the tile atlas, in the resources.
+const ATLAS_MANIFEST_PATH: &str = "atlas.txt";
+/// Size of a terminal cell in pixels, when the terminal doesn't tell it.
+const DEFAULT_CELL_SIZE: (u32, u32) = (8, 16);
+
+#[derive(Debug)]
+pub enum TilesError {
+    IO(io::Error),
+    Manifest(AtlasParseError),
+    Image(image::ImageError),
+}
+
+impl fmt::Display for TilesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use TilesError::*;
+        match self {
+            IO(e) => write!(f, "Couldn't read the tile atlas : {}", e),
+            Manifest(e) => write!(f, "Invalid tile atlas manifest : {}", e),
+            Image(e) => write!(f, "Couldn't decode the tile atlas : {}", e),
+        }
+    }
+}
+
+impl Error for TilesError {}
+
+/// Tiles of the game, all cut from the same atlas image.
+struct Tiles {
+    atlas: RgbaImage,
+    manifest: AtlasManifest,
+    /// Size of a cell in the pictures, the one of the wall tile
+    size: (u32, u32),
+}
+
+impl Tiles {
+    /// Loads the atlas described by the manifest at [`ATLAS_MANIFEST_PATH`].
+    fn load() -> Result<Self, TilesError> {
+        let resources = Path::new(RESOURCES_DIR);
+        let manifest: AtlasManifest = fs::read_to_string(resources.join(ATLAS_MANIFEST_PATH))
+            .map_err(TilesError::IO)?
+            .parse()
+            .map_err(TilesError::Manifest)?;
+        let atlas = image::open(resources.join(manifest.image.trim_start_matches('/')))
+            .map_err(TilesError::Image)?
+            .into_rgba8();
+
+        let wall = manifest
+            .region("wall")
+            .expect("The atlas should have a wall");
+        Ok(Tiles {
+            atlas,
+            manifest,
+            size: (wall.w, wall.h),
+        })
+    }
+
+    /// Picture of the visible part of the `board` in the `layout`.
+    fn render(&self, board: &Board, layout: &BoardLayout) -> Picture {
+        let (tile_w, tile_h) = self.size;
+        let (width, height) = (layout.visible.0 * tile_w, layout.visible.1 * tile_h);
+        let mut picture = Picture {
+            width,
+            height,
+            pixels: vec![0; (width * height * 4) as usize],
+        };
+
+        for (i, j) in layout.visible_cells() {
+            let BoardElem(item, kind) = board.get(i, j);
+            let ground = match kind {
+                CellKind::Void => None,
+                CellKind::Wall => Some("wall"),
+                CellKind::Floor => Some("floor"),
+                CellKind::Target => Some("target"),
+            };
+            // The interface isn't told where the player faces.
+            let top = match item {
+                Some(MovableItem::Player) => Some("player_down"),
+                Some(MovableItem::Crate(_)) if kind == CellKind::Target => Some("placed_crate"),
+                Some(MovableItem::Crate(_)) => Some("crate"),
+                None => None,
+            };
+
+            let corner = (
+                (i - layout.scroll.0) * tile_w,
+                (j - layout.scroll.1) * tile_h,
+            );
+            for name in [ground, top].into_iter().flatten() {
+                self.blend(&mut picture, name, corner);
+            }
+        }
+        picture
+    }
+
+    /// Draws the tile `name` over the `picture` at `corner`, stretched to a cell.
+    fn blend(&self, picture: &mut Picture, name: &str, corner: (u32, u32)) {
+        let region = self
+            .manifest
+            .region(name)
+            .expect("The atlas should have all the tiles");
+        let (tile_w, tile_h) = self.size;
+
+        for y in 0..tile_h {
+            for x in 0..tile_w {
+                let src = self.atlas.get_pixel(
+                    region.x + x * region.w / tile_w,
+                    region.y + y * region.h / tile_h,
+                );
+                let start = (((corner.1 + y) * picture.width + corner.0 + x) * 4) as usize;
+                let dst = &mut picture.pixels[start..start + 4];
+
+                let alpha = u32::from(src[3]);
+                for c in 0..3 {
+                    dst[c] = ((u32::from(src[c]) * alpha + u32::from(dst[c]) * (255 - alpha)) / 255)
+                        as u8;
+                }
+                dst[3] = dst[3].max(src[3]);
+            }
+        }
+    }
+}
+
+/// Interactive terminal interface with pictures
+pub struct TuiGraphics {
+    tui: Tui,
+    protocol: GraphicsProtocol,
+    tiles: Tiles,
+}
+
+impl TuiGraphics {
+    pub fn new(protocol: GraphicsProtocol) -> Result<Self, Box<dyn Error>> {
+        let tiles = Tiles::load()?;
+        Ok(TuiGraphics {
+            tui: Tui::initialize()?,
+            protocol,
+            tiles,
+        })
+    }
+
+    /// Draws the picture of the `board` over the characters of the last display.
+    fn draw_board(&self, board: &Board) -> Result<(), Box<dyn Error>> {
+        let Some(layout) = self.tui.layout() else {
+            return Ok(());
+        };
+        let size = terminal::window_size().map_err(TuiError::IO)?;
+        let cell_size = if size.width > 0 && size.height > 0 && size.columns > 0 && size.rows > 0 {
+            (
+                u32::from(size.width / size.columns),
+                u32::from(size.height / size.rows),
+            )
+        } else {
+            DEFAULT_CELL_SIZE
+        };
+
+        let picture = self.tiles.render(board, &layout);
+        let cells = (
+            layout.visible.0 as u16 * layout.cell_width(),
+            layout.visible.1 as u16,
+        );
+        let res: Result<(), io::Error> = try {
+            let mut stdout = io::stdout();
+            stdout
+                .queue(style::Print(self.protocol.clear()))?
+                .queue(cursor::MoveTo(layout.origin.0, layout.origin.1))?
+                .queue(style::Print(
+                    self.protocol.encode(&picture, cells, cell_size),
+                ))?;
+            stdout.flush()?;
+        };
+        res.map_err(TuiError::IO)?;
+
+        Ok(())
+    }
+
+    /// Removes the pictures before the terminal interface draws over the board.
+    fn clear_pictures(&self) -> Result<(), Box<dyn Error>> {
+        let res: Result<(), io::Error> = try {
+            let mut stdout = io::stdout();
+            stdout.queue(style::Print(self.protocol.clear()))?;
+            stdout.flush()?;
+        };
+        res.map_err(TuiError::IO)?;
+        Ok(())
+    }
+}
+
+impl Ui for TuiGraphics {
+    fn initialize() -> Result<Self, Box<dyn Error>> {
+        let protocol =
+            GraphicsProtocol::detect().ok_or("The terminal doesn't support pictures.")?;
+        TuiGraphics::new(protocol)
+    }
+
+    fn cleanup(self: Box<Self>) -> Result<(), Box<dyn Error>> {
+        self.clear_pictures()?;
+        Box::new(self.tui).cleanup()
+    }
+
+    fn select_level(
+        &mut self,
+        library: &mut LevelLibrary,
+    ) -> Result<Option<Selection>, Box<dyn Error>> {
+        self.clear_pictures()?;
+        self.tui.select_level(library)
+    }
+
+    fn get_action(&mut self, board: &Board) -> Result<Action, Box<dyn Error>> {
+        self.tui.get_action(board)
+    }
+
+    fn display(
+        &mut self,
+        board: &Board,
+        last_move_result: Option<Option<(u32, u32)>>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.tui.display(board, last_move_result)?;
+        self.draw_board(board)
+    }
+
+    fn watch_replay(
+        &mut self,
+        board: &Board,
+        replay: &Replay,
+        speed: Option<u32>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.clear_pictures()?;
+        self.tui.watch_replay(board, replay, speed)
+    }
+
+    fn won(&mut self, score: &Score, best: Option<&Score>) -> Result<WonChoice, Box<dyn Error>> {
+        self.clear_pictures()?;
+        self.tui.won(score, best)
+    }
+
+    fn start_countdown(&mut self, limit: Option<TimeLimit>) {
+        self.tui.start_countdown(limit)
+    }
+
+    fn start_challenge(&mut self, limit: Option<MoveLimit>) {
+        self.tui.start_challenge(limit)
+    }
+
+    fn show_streak(&mut self, streak: Option<u32>) {
+        self.tui.show_streak(streak)
+    }
+
+    fn show_turn(&mut self, hotseat: Option<&Hotseat>) {
+        self.tui.show_turn(hotseat)
+    }
+
+    fn show_speedrun(&mut self, stopwatch: Option<Stopwatch>) {
+        self.tui.show_speedrun(stopwatch)
+    }
+
+    fn show_message(&mut self, message: &str) -> Result<(), Box<dyn Error>> {
+        self.tui.show_message(message)
+    }
+
+    fn lost(&mut self, defeat: &Defeat) -> Result<LostChoice, Box<dyn Error>> {
+        self.clear_pictures()?;
+        self.tui.lost(defeat)
+    }
+}
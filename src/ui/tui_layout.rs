@@ -0,0 +1,148 @@
+//! Where the board is drawn in the [terminal interface](`super::tui`), depending on the room
+//! available.
+//!
+//! Each cell takes two columns when possible, so the board looks about as high as it is wide.
+//! On smaller terminals, the compact glyphs of one column are used instead, and if the board
+//! still doesn't fit, only the part around the player is shown and it scrolls as they move.
+
+/// Number of cells kept visible between the player and the side of the screen before scrolling.
+const SCROLL_MARGIN: u32 = 2;
+
+/// Position of the visible part of the board on the terminal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BoardLayout {
+    /// Terminal column and row of the top-left visible cell.
+    pub origin: (u16, u16),
+    /// Board coordinates of the top-left visible cell.
+    pub scroll: (u32, u32),
+    /// Number of visible columns and rows of cells.
+    pub visible: (u32, u32),
+    /// If each cell takes two columns.
+    pub wide: bool,
+}
+
+impl BoardLayout {
+    /// Fits a board of `board_size` cells in the `area` of the terminal given as its top-left
+    /// corner and its size, keeping `focus` visible if it must scroll.
+    ///
+    /// The scrolling of the `previous` layout is kept as long as the focus doesn't get too
+    /// close to the side, so the whole screen doesn't have to be redrawn at each move.
+    pub fn new(
+        board_size: (u32, u32),
+        area: ((u16, u16), (u16, u16)),
+        focus: (u32, u32),
+        previous: Option<&BoardLayout>,
+    ) -> Self {
+        let ((area_col, area_row), (area_cols, area_rows)) = area;
+        let (width, height) = board_size;
+
+        let wide = width.saturating_mul(2) <= u32::from(area_cols);
+        let cell_width = if wide { 2 } else { 1 };
+        let visible = (
+            width.min(u32::from(area_cols) / cell_width),
+            height.min(u32::from(area_rows)),
+        );
+
+        let previous = previous.filter(|p| p.wide == wide && p.visible == visible);
+        let scroll = (
+            scroll_axis(focus.0, visible.0, width, previous.map(|p| p.scroll.0)),
+            scroll_axis(focus.1, visible.1, height, previous.map(|p| p.scroll.1)),
+        );
+
+        // The visible cells fit in the area.
+        let used_cols = (visible.0 * cell_width) as u16;
+        let used_rows = visible.1 as u16;
+        BoardLayout {
+            origin: (
+                area_col + (area_cols - used_cols) / 2,
+                area_row + (area_rows - used_rows) / 2,
+            ),
+            scroll,
+            visible,
+            wide,
+        }
+    }
+
+    /// Number of terminal columns used by each cell.
+    pub fn cell_width(&self) -> u16 {
+        if self.wide {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// Terminal column and row of the cell `(i, j)`, if it is visible.
+    pub fn cell_pos(&self, i: u32, j: u32) -> Option<(u16, u16)> {
+        let (col, row) = (i.checked_sub(self.scroll.0)?, j.checked_sub(self.scroll.1)?);
+        if col < self.visible.0 && row < self.visible.1 {
+            // The visible cells fit on screen.
+            Some((
+                self.origin.0 + col as u16 * self.cell_width(),
+                self.origin.1 + row as u16,
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// First terminal row under the board.
+    pub fn bottom(&self) -> u16 {
+        self.origin.1 + self.visible.1 as u16
+    }
+
+    /// Board coordinates of the visible cells, row by row.
+    pub fn visible_cells(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        let (first_i, first_j) = self.scroll;
+        (first_j..first_j + self.visible.1)
+            .flat_map(move |j| (first_i..first_i + self.visible.0).map(move |i| (i, j)))
+    }
+}
+
+/// First visible cell along an axis of `size` cells with `visible` ones shown.
+fn scroll_axis(focus: u32, visible: u32, size: u32, previous: Option<u32>) -> u32 {
+    let max = size - visible;
+    let margin = SCROLL_MARGIN.min(visible.saturating_sub(1) / 2);
+
+    match previous {
+        Some(p) if p <= max && focus >= p + margin && focus + margin < p + visible => p,
+        Some(p) if p <= max && focus < p + margin => focus.saturating_sub(margin),
+        Some(p) if p <= max => (focus + margin + 1).saturating_sub(visible).min(max),
+        _ => focus.saturating_sub(visible / 2).min(max),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BoardLayout;
+
+    #[test]
+    fn it_centers_wide_cells_when_there_is_room() {
+        let layout = BoardLayout::new((10, 5), ((0, 0), (80, 24)), (0, 0), None);
+        assert!(layout.wide);
+        assert_eq!(layout.origin, (30, 9));
+        assert_eq!(layout.cell_pos(1, 1), Some((32, 10)));
+    }
+
+    #[test]
+    fn it_switches_to_compact_cells() {
+        let layout = BoardLayout::new((50, 5), ((0, 0), (80, 24)), (0, 0), None);
+        assert!(!layout.wide);
+        assert_eq!(layout.visible, (50, 5));
+        assert_eq!(layout.origin, (15, 9));
+    }
+
+    #[test]
+    fn it_scrolls_around_the_focus() {
+        let layout = BoardLayout::new((100, 10), ((0, 0), (20, 24)), (50, 5), None);
+        assert_eq!((layout.visible, layout.scroll), ((20, 10), (40, 0)));
+        assert_eq!(layout.cell_pos(39, 0), None);
+        assert_eq!(layout.cell_pos(40, 0), Some((0, 7)));
+
+        // Moving a bit doesn't scroll, but getting close to the side does.
+        let moved = BoardLayout::new((100, 10), ((0, 0), (20, 24)), (55, 5), Some(&layout));
+        assert_eq!(moved.scroll, (40, 0));
+        let moved = BoardLayout::new((100, 10), ((0, 0), (20, 24)), (59, 5), Some(&moved));
+        assert_eq!(moved.scroll, (42, 0));
+    }
+}
@@ -0,0 +1,193 @@
+//! Browser of the community packs of a [level repository](`RepoClient`), opened from the level
+//! browsers of the terminal interfaces.
+
+use std::{
+    error::Error,
+    io::{self, Write},
+};
+
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    style, terminal, QueueableCommand,
+};
+
+use super::{tui::TuiError, LevelLibrary};
+use crate::repo::{PackInfo, RepoClient, MAX_RATING, REPO_URL_ENV_VAR};
+
+const TITLE: &str =
+    "Community packs (Up/Down to move, Enter to install and play, / to search, 1-5 to rate, q to go back):";
+/// Rows used above the pack list.
+const HEADER_ROWS: u16 = 3;
+
+struct PackBrowser {
+    packs: Vec<PackInfo>,
+    selected: usize,
+    /// Words looked for, being typed if `searching`.
+    query: String,
+    searching: bool,
+    status: String,
+}
+
+/// Lets the user browse the packs of the [repository set up](`RepoClient::from_env`), and
+/// returns the library of the one they installed, if any.
+pub fn browse_packs() -> Result<Option<LevelLibrary>, Box<dyn Error>> {
+    let client = RepoClient::from_env();
+    let mut browser = PackBrowser {
+        packs: Vec::new(),
+        selected: 0,
+        query: String::new(),
+        searching: false,
+        status: match &client {
+            Some(client) => format!("Loading the packs of {}...", client.url()),
+            None => format!(
+                "Set {} to the address of a level repository.",
+                REPO_URL_ENV_VAR
+            ),
+        },
+    };
+    browser.draw().map_err(TuiError::IO)?;
+    if let Some(client) = &client {
+        browser.search(client);
+    }
+
+    loop {
+        browser.draw().map_err(TuiError::IO)?;
+
+        let code = match event::read().map_err(TuiError::IO)? {
+            Event::Key(KeyEvent {
+                modifiers: KeyModifiers::CONTROL,
+                code: KeyCode::Char('c'),
+                ..
+            }) => return Ok(None),
+            Event::Key(KeyEvent {
+                modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                code,
+                ..
+            }) => code,
+            _ => continue,
+        };
+
+        let Some(client) = &client else {
+            if matches!(code, KeyCode::Esc | KeyCode::Char('q')) {
+                return Ok(None);
+            }
+            continue;
+        };
+
+        if browser.searching {
+            match code {
+                KeyCode::Char(c) => browser.query.push(c),
+                KeyCode::Backspace => {
+                    browser.query.pop();
+                }
+                KeyCode::Enter => {
+                    browser.searching = false;
+                    browser.status = format!("Looking for `{}`...", browser.query);
+                    browser.draw().map_err(TuiError::IO)?;
+                    browser.search(client);
+                }
+                KeyCode::Esc => browser.searching = false,
+                _ => (),
+            }
+            continue;
+        }
+
+        match code {
+            KeyCode::Esc | KeyCode::Char('q') => return Ok(None),
+            KeyCode::Up => browser.selected = browser.selected.saturating_sub(1),
+            KeyCode::Down => {
+                browser.selected = (browser.selected + 1).min(browser.packs.len().saturating_sub(1))
+            }
+            KeyCode::Char('/') => browser.searching = true,
+            KeyCode::Enter => {
+                let Some(pack) = browser.packs.get(browser.selected) else {
+                    continue;
+                };
+                browser.status = format!("Installing {}...", pack.name);
+                browser.draw().map_err(TuiError::IO)?;
+                match client.install(&pack.id) {
+                    Ok(library) => return Ok(Some(library)),
+                    Err(err) => browser.status = err.to_string(),
+                }
+            }
+            KeyCode::Char(c @ '1'..='5') => {
+                let rating = c as u8 - b'0';
+                let Some(pack) = browser.packs.get_mut(browser.selected) else {
+                    continue;
+                };
+                browser.status = match client.rate(&pack.id, rating) {
+                    Ok(info) => {
+                        *pack = info;
+                        format!("You rated {} {}/{}.", pack.name, rating, MAX_RATING)
+                    }
+                    Err(err) => err.to_string(),
+                };
+            }
+            _ => (),
+        }
+    }
+}
+
+impl PackBrowser {
+    /// Fetches the packs matching the query, and tells the result in the status.
+    fn search(&mut self, client: &RepoClient) {
+        match client.search(&self.query) {
+            Ok(packs) => {
+                self.status = match packs.len() {
+                    0 => "No pack found.".to_string(),
+                    1 => "1 pack found.".to_string(),
+                    n => format!("{} packs found.", n),
+                };
+                self.packs = packs;
+                self.selected = 0;
+            }
+            Err(err) => self.status = err.to_string(),
+        }
+    }
+
+    fn draw(&self) -> Result<(), io::Error> {
+        let mut stdout = io::stdout();
+        let (term_cols, term_rows) = terminal::size()?;
+        let cols = usize::from(term_cols);
+
+        let status = if self.searching {
+            format!("Search: {}_", self.query)
+        } else {
+            self.status.clone()
+        };
+        stdout
+            .queue(terminal::Clear(terminal::ClearType::All))?
+            .queue(cursor::MoveTo(0, 0))?
+            .queue(style::Print(TITLE.chars().take(cols).collect::<String>()))?
+            .queue(cursor::MoveTo(0, 1))?
+            .queue(style::Print(status.chars().take(cols).collect::<String>()))?;
+
+        // Scrolls so the selected pack is always visible.
+        let list_rows = usize::from(term_rows.saturating_sub(HEADER_ROWS));
+        let first = self.selected.saturating_sub(list_rows.saturating_sub(1));
+
+        for (row, (index, pack)) in self
+            .packs
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(list_rows)
+            .enumerate()
+        {
+            let entry: String = format!(" {} ", pack).chars().take(cols).collect();
+
+            // There are less rows than `term_rows`.
+            stdout.queue(cursor::MoveTo(0, HEADER_ROWS + row as u16))?;
+            if index == self.selected {
+                stdout.queue(style::SetAttribute(style::Attribute::Reverse))?;
+            }
+            stdout.queue(style::Print(entry))?;
+            if index == self.selected {
+                stdout.queue(style::SetAttribute(style::Attribute::Reset))?;
+            }
+        }
+
+        stdout.flush()
+    }
+}
@@ -0,0 +1,162 @@
+//! Glyphs and colors used by the [terminal interface](`super::tui`) to draw each kind of cell.
+
+use crossterm::style::Color;
+
+use super::{terminal::*, BoardElem, CellKind, MovableItem};
+
+/// Environment variable containing the name of the theme to start with.
+pub const THEME_ENV_VAR: &str = "SOKOBAN_TUI_THEME";
+
+/// Symbols which are repeated when a cell takes two columns.
+const WIDE_REPEATED_SYMBOLS: [char; 2] = ['█', '#'];
+
+/// How to print a cell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Glyph {
+    pub symbol: char,
+    pub fg: Color,
+    pub bg: Color,
+}
+
+impl Glyph {
+    const fn plain(symbol: char) -> Self {
+        Glyph {
+            symbol,
+            fg: Color::Reset,
+            bg: Color::Reset,
+        }
+    }
+
+    const fn colored(symbol: char, fg: Color, bg: Color) -> Self {
+        Glyph { symbol, fg, bg }
+    }
+
+    /// Second half of the glyph when cells take two columns: blocks are repeated so the walls
+    /// are joined, and the other symbols are followed by a space.
+    pub fn wide_fill(&self) -> Glyph {
+        let symbol = if WIDE_REPEATED_SYMBOLS.contains(&self.symbol) {
+            self.symbol
+        } else {
+            ' '
+        };
+        Glyph { symbol, ..*self }
+    }
+}
+
+/// Glyph for each kind of cell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TuiTheme {
+    pub name: &'static str,
+    pub void: Glyph,
+    pub floor: Glyph,
+    pub wall: Glyph,
+    pub target: Glyph,
+    pub player: Glyph,
+    pub player_on_target: Glyph,
+    pub crate_: Glyph,
+    pub placed_crate: Glyph,
+}
+
+impl TuiTheme {
+    pub fn glyph(&self, elem: BoardElem) -> Glyph {
+        use CellKind::*;
+        use MovableItem::*;
+
+        // Items can only be out of the floor while editing a level.
+        match elem {
+            BoardElem(None, Void) => self.void,
+            BoardElem(_, Wall) => self.wall,
+            BoardElem(None, Floor) => self.floor,
+            BoardElem(None, Target) => self.target,
+            BoardElem(Some(Player), Floor | Void) => self.player,
+            BoardElem(Some(Crate(_)), Floor | Void) => self.crate_,
+            BoardElem(Some(Player), Target) => self.player_on_target,
+            BoardElem(Some(Crate(_)), Target) => self.placed_crate,
+        }
+    }
+
+    /// Finds a theme by name, ignoring case.
+    pub fn by_name(name: &str) -> Option<usize> {
+        THEMES
+            .iter()
+            .position(|t| t.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Only ASCII characters, for terminals with poor Unicode support.
+const CLASSIC: TuiTheme = TuiTheme {
+    name: "classic",
+    void: Glyph::plain(SYMBOL_VOID),
+    floor: Glyph::plain(SYMBOL_FLOOR),
+    wall: Glyph::plain('#'),
+    target: Glyph::plain(SYMBOL_TARGET),
+    player: Glyph::plain(SYMBOL_PLAYER),
+    player_on_target: Glyph::plain(SYMBOL_PLAYER_ON_TARGET),
+    crate_: Glyph::plain(SYMBOL_CRATE),
+    placed_crate: Glyph::plain(SYMBOL_PLACED_CRATE),
+};
+
+/// The same symbols as the command-line interface, with a few colors.
+const UNICODE: TuiTheme = TuiTheme {
+    name: "unicode",
+    void: Glyph::plain(SYMBOL_VOID),
+    floor: Glyph::colored(SYMBOL_FLOOR, Color::DarkGrey, Color::Reset),
+    wall: Glyph::plain(SYMBOL_WALL),
+    target: Glyph::colored(SYMBOL_TARGET, Color::Red, Color::Reset),
+    player: Glyph::colored(SYMBOL_PLAYER, Color::Cyan, Color::Reset),
+    player_on_target: Glyph::colored(SYMBOL_PLAYER_ON_TARGET, Color::Cyan, Color::Reset),
+    crate_: Glyph::colored(SYMBOL_CRATE, Color::Yellow, Color::Reset),
+    placed_crate: Glyph::colored(SYMBOL_PLACED_CRATE, Color::Green, Color::Reset),
+};
+
+/// Black and white with bright backgrounds for the items.
+const HIGH_CONTRAST: TuiTheme = TuiTheme {
+    name: "high-contrast",
+    void: Glyph::colored(' ', Color::White, Color::Black),
+    floor: Glyph::colored(' ', Color::White, Color::Black),
+    wall: Glyph::colored('█', Color::White, Color::Black),
+    target: Glyph::colored('◎', Color::Yellow, Color::Black),
+    player: Glyph::colored('☺', Color::Black, Color::White),
+    player_on_target: Glyph::colored('☻', Color::Black, Color::Yellow),
+    crate_: Glyph::colored('■', Color::White, Color::Black),
+    placed_crate: Glyph::colored('■', Color::Black, Color::Yellow),
+};
+
+// Colors of the Okabe-Ito palette, which stay distinguishable with the common color
+// blindnesses.
+const SKY_BLUE: Color = Color::Rgb {
+    r: 0x56,
+    g: 0xb4,
+    b: 0xe9,
+};
+const VERMILLION: Color = Color::Rgb {
+    r: 0xd5,
+    g: 0x5e,
+    b: 0x00,
+};
+const ORANGE: Color = Color::Rgb {
+    r: 0xe6,
+    g: 0x9f,
+    b: 0x00,
+};
+const BLUE: Color = Color::Rgb {
+    r: 0x00,
+    g: 0x72,
+    b: 0xb2,
+};
+
+/// Colors safe for color blindness, and different shapes for placed and unplaced crates.
+const COLORBLIND: TuiTheme = TuiTheme {
+    name: "colorblind",
+    void: Glyph::plain(' '),
+    floor: Glyph::colored('·', Color::Grey, Color::Reset),
+    wall: Glyph::colored('█', Color::Grey, Color::Reset),
+    target: Glyph::colored('×', SKY_BLUE, Color::Reset),
+    player: Glyph::colored('●', VERMILLION, Color::Reset),
+    player_on_target: Glyph::colored('◉', VERMILLION, Color::Reset),
+    crate_: Glyph::colored('□', ORANGE, Color::Reset),
+    placed_crate: Glyph::colored('▣', BLUE, Color::Reset),
+};
+
+/// All available themes, the first being the default one.
+pub const THEMES: [TuiTheme; 4] = [UNICODE, CLASSIC, HIGH_CONTRAST, COLORBLIND];